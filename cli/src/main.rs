@@ -0,0 +1,391 @@
+use blister::{
+    write_atomic, BeatSaverResolver, Beatmap, FormatOptions, HttpDownloader, HttpResponse,
+    Playlist, ReadOptions, Resolution, Resolver, ZipCache,
+};
+use clap::{Parser, Subcommand};
+use std::{
+    env,
+    error::Error,
+    fs::File,
+    io::{self, BufReader},
+    path::{Path, PathBuf},
+    process,
+    time::Duration,
+};
+
+type Result<T> = std::result::Result<T, Box<dyn Error>>;
+
+#[derive(Debug, Parser)]
+#[command(name = "blist", version, about)]
+struct Cli {
+    /// Accept unknown beatmap types when reading `.blist` playlists
+    #[arg(long, global = true)]
+    lenient: bool,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Print a playlist's metadata and beatmaps
+    Inspect { input: PathBuf },
+    /// Convert between `.blist` and `.bplist`/`.json` playlists
+    Convert {
+        input: PathBuf,
+        output: PathBuf,
+        /// Convert every playlist in the input directory and its subdirectories
+        #[arg(short, long)]
+        recursive: bool,
+    },
+    /// Merge playlists, keeping the metadata of the first one
+    Merge {
+        #[arg(short, long)]
+        output: PathBuf,
+        #[arg(required = true)]
+        inputs: Vec<PathBuf>,
+    },
+    /// Remove duplicate beatmaps, keeping the first occurrence
+    Dedupe {
+        input: PathBuf,
+        /// Defaults to overwriting the input
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Embed the zips of hash and key beatmaps, downloading the ones that aren't cached. Key
+    /// beatmaps are looked up on BeatSaver for their hash first.
+    Freeze {
+        input: PathBuf,
+        /// Defaults to overwriting the input
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        /// Directory the downloaded zips are cached in
+        #[arg(long)]
+        cache: Option<PathBuf>,
+    },
+    /// Replace embedded zips with hash or key references
+    Thin {
+        input: PathBuf,
+        /// Defaults to overwriting the input
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        /// Store the removed zips in this directory so freezing again doesn't download them
+        #[arg(long)]
+        cache: Option<PathBuf>,
+    },
+    /// Check that a playlist can be read
+    Verify { input: PathBuf },
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum Format {
+    Blist,
+    Bplist,
+}
+
+impl Format {
+    fn from_path(path: &Path) -> Result<Self> {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("blist") => Ok(Self::Blist),
+            Some("bplist") | Some("json") => Ok(Self::Bplist),
+            _ => Err(format!("unrecognized playlist extension for `{}`", path.display()).into()),
+        }
+    }
+}
+
+fn main() {
+    let cli = Cli::parse();
+    if let Err(e) = run(cli.command, !cli.lenient) {
+        eprintln!("error: {}", e);
+        process::exit(1);
+    }
+}
+
+fn run(command: Command, strict: bool) -> Result<()> {
+    match command {
+        Command::Inspect { input } => inspect(&read(&input, strict)?),
+        Command::Convert {
+            input,
+            output,
+            recursive,
+        } => {
+            if input.is_dir() {
+                convert_dir(&input, &output, recursive, strict)
+            } else {
+                write(&read(&input, strict)?, &output)
+            }
+        }
+        Command::Merge { output, inputs } => {
+            let mut inputs = inputs.iter();
+            let mut merged = read(inputs.next().unwrap(), strict)?;
+            for input in inputs {
+                merged.maps.extend(read(input, strict)?.maps);
+            }
+            write(&merged, &output)
+        }
+        Command::Dedupe { input, output } => {
+            let mut playlist = read(&input, strict)?;
+            let removed = playlist.remove_duplicates();
+            println!("removed {} duplicates", removed.len());
+            write(&playlist, output.as_ref().unwrap_or(&input))
+        }
+        Command::Freeze {
+            input,
+            output,
+            cache,
+        } => {
+            let mut playlist = read(&input, strict)?;
+            freeze(&mut playlist, cache)?;
+            write(&playlist, output.as_ref().unwrap_or(&input))
+        }
+        Command::Thin {
+            input,
+            output,
+            cache,
+        } => {
+            let mut playlist = read(&input, strict)?;
+            let cache = cache.map(ZipCache::new).transpose()?;
+            let report = playlist.thin(cache.as_ref(), &Offline)?;
+            println!("thinned {} beatmaps", report.thinned.len());
+            for i in &report.kept {
+                eprintln!("kept: beatmap {} has neither a hash nor a key", i);
+            }
+            for i in &report.mismatched {
+                eprintln!("kept: beatmap {} doesn't match its hash", i);
+            }
+            write(&playlist, output.as_ref().unwrap_or(&input))
+        }
+        Command::Verify { input } => verify(&input, strict),
+    }
+}
+
+fn read(path: &Path, strict: bool) -> Result<Playlist> {
+    let reader = BufReader::new(File::open(path)?);
+    let playlist = match Format::from_path(path)? {
+        Format::Blist => Playlist::read(reader, strict)?,
+        Format::Bplist => Playlist::read_bplist(reader)?,
+    };
+    Ok(playlist)
+}
+
+// Written atomically, overwriting the input never leaves it half written
+fn write(playlist: &Playlist, path: &Path) -> Result<()> {
+    let format = Format::from_path(path)?;
+    write_atomic(path, |w| match format {
+        Format::Blist => playlist.write(w),
+        Format::Bplist => playlist.write_bplist(w),
+    })?;
+    Ok(())
+}
+
+fn convert_dir(input: &Path, output: &Path, recursive: bool, strict: bool) -> Result<()> {
+    let options = FormatOptions {
+        recursive,
+        read: ReadOptions {
+            strict,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let report = blister::convert_dir(input, output, &options)?;
+    for (input, output) in &report.converted {
+        println!("{} -> {}", input.display(), output.display());
+    }
+    for (input, e) in &report.failed {
+        eprintln!("failed: {}: {}", input.display(), e);
+    }
+    if !report.is_complete() {
+        return Err(format!("{} playlists failed to convert", report.failed.len()).into());
+    }
+    Ok(())
+}
+
+fn inspect(playlist: &Playlist) -> Result<()> {
+    println!("title:       {}", playlist.title);
+    println!("author:      {}", playlist.author);
+    if let Some(description) = &playlist.description {
+        println!("description: {}", description);
+    }
+    if let Some(cover) = &playlist.cover {
+        println!("cover:       {} bytes", cover.len());
+    }
+    println!("custom data: {} entries", playlist.custom_data.len());
+    println!("beatmaps:    {}", playlist.maps.len());
+
+    for (i, m) in playlist.maps.iter().enumerate() {
+        println!("  {:>4}  {}", i, m);
+    }
+    Ok(())
+}
+
+fn freeze(playlist: &mut Playlist, cache: Option<PathBuf>) -> Result<()> {
+    let cache = ZipCache::new(cache.unwrap_or_else(|| env::temp_dir().join("blist-zips")))?;
+    let agent = ureq::Agent::config_builder()
+        .http_status_as_error(false)
+        .build()
+        .into();
+    let downloader = HttpDownloader::new(move |url: &str, offset| get(&agent, url, offset));
+    // Key beatmaps are looked up on BeatSaver for the hash their zip is cached under
+    let resolver = BeatSaverResolver::new(downloader);
+
+    let report = playlist.freeze(&cache, resolver.downloader(), &resolver);
+    println!(
+        "froze {} beatmaps, downloaded {}",
+        report.frozen.len(),
+        report.downloaded.len()
+    );
+    for (i, e) in &report.failed {
+        eprintln!("failed: beatmap {}: {}", i, e);
+    }
+    if !report.is_complete() {
+        return Err(format!("{} beatmaps failed to freeze", report.failed.len()).into());
+    }
+    Ok(())
+}
+
+fn get(agent: &ureq::Agent, url: &str, offset: u64) -> io::Result<HttpResponse> {
+    let mut request = agent.get(url);
+    if offset > 0 {
+        request = request.header("Range", &format!("bytes={}-", offset));
+    }
+    let response = request.call().map_err(ureq::Error::into_io)?;
+
+    let header = |name| {
+        response
+            .headers()
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_owned)
+    };
+    let retry_after = header("Retry-After")
+        .and_then(|v| v.trim().parse().ok())
+        .map(Duration::from_secs);
+    let content_range = header("Content-Range");
+    let status = response.status().as_u16();
+    Ok(HttpResponse {
+        retry_after,
+        content_range,
+        ..HttpResponse::new(status, response.into_body().into_reader())
+    })
+}
+
+// BeatSaver can only look maps up by key or hash, which are exactly what the maps left to resolve
+// are missing, so thinning never goes online
+struct Offline;
+
+impl Resolver for Offline {
+    #[inline]
+    fn resolve(&self, _map: &Beatmap) -> Resolution {
+        Resolution::Unknown
+    }
+}
+
+fn verify(path: &Path, strict: bool) -> Result<()> {
+    match Format::from_path(path)? {
+        Format::Blist => {
+            let options = ReadOptions {
+                strict,
+                ..Default::default()
+            };
+            let reader = BufReader::new(File::open(path)?);
+            let (playlist, warnings) = Playlist::read_with_warnings(reader, &options)?;
+            for warning in &warnings {
+                println!("warning: {:?}", warning);
+            }
+            println!("ok: {} beatmaps", playlist.maps.len());
+        }
+        Format::Bplist => {
+            let playlist = read(path, strict)?;
+            println!("ok: {} beatmaps", playlist.maps.len());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{run, Cli};
+    use blister::{Beatmap, BeatmapType, Playlist, ZipCache};
+    use blister_format::values::Sha1;
+    use clap::Parser;
+    use std::{fs, path::Path};
+
+    fn blist(args: &[&Path]) {
+        let cli = Cli::try_parse_from(["blist".as_ref()].iter().chain(args).map(|a| a.as_os_str()))
+            .unwrap();
+        run(cli.command, !cli.lenient).unwrap();
+    }
+
+    fn playlist() -> Playlist {
+        let mut playlist = Playlist::new("test playlist".to_owned(), "me".to_owned());
+        playlist.maps.push(Beatmap::new_hash([1; 20].into()));
+        playlist.maps.push(Beatmap::new_hash([2; 20].into()));
+        playlist.maps.push(Beatmap::new_hash([1; 20].into()));
+        playlist
+    }
+
+    fn hashes(playlist: &Playlist) -> Vec<Option<Sha1>> {
+        playlist.maps.iter().map(|m| m.hash).collect()
+    }
+
+    #[test]
+    fn convert() {
+        let temp = tempfile::tempdir().unwrap();
+        let blist_path = temp.path().join("test.blist");
+        let bplist_path = temp.path().join("test.bplist");
+        let back_path = temp.path().join("back.blist");
+        playlist()
+            .write(fs::File::create(&blist_path).unwrap())
+            .unwrap();
+
+        blist(&["convert".as_ref(), &blist_path, &bplist_path]);
+        blist(&["convert".as_ref(), &bplist_path, &back_path]);
+        let back = super::read(&back_path, true).unwrap();
+        assert_eq!(back.title, "test playlist");
+        assert_eq!(back.author, "me");
+        assert_eq!(hashes(&back), hashes(&playlist()));
+
+        // Whole directories are converted by extension
+        let out = temp.path().join("out");
+        blist(&["convert".as_ref(), temp.path(), &out]);
+        let converted = super::read(&out.join("test.bplist"), true).unwrap();
+        assert_eq!(hashes(&converted), hashes(&playlist()));
+    }
+
+    #[test]
+    fn dedupe() {
+        let temp = tempfile::tempdir().unwrap();
+        let path = temp.path().join("test.blist");
+        playlist().write(fs::File::create(&path).unwrap()).unwrap();
+
+        blist(&["dedupe".as_ref(), &path]);
+        let deduped = super::read(&path, true).unwrap();
+        assert_eq!(
+            hashes(&deduped),
+            [Some([1; 20].into()), Some([2; 20].into())]
+        );
+        // The input is replaced without leaving a temporary file behind
+        assert_eq!(fs::read_dir(temp.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn thin() {
+        let temp = tempfile::tempdir().unwrap();
+        let path = temp.path().join("test.blist");
+        let cache_dir = temp.path().join("cache");
+        let zip = b"embedded zip".to_vec();
+        let (_, hash) = blister::copy_hashed(zip.as_slice(), &mut std::io::sink()).unwrap();
+        let mut playlist = Playlist::new("test playlist".to_owned(), "me".to_owned());
+        let mut map = Beatmap::new_zip(zip.clone());
+        map.hash = Some(hash);
+        playlist.maps.push(map);
+        playlist.write(fs::File::create(&path).unwrap()).unwrap();
+
+        blist(&["thin".as_ref(), &path, "--cache".as_ref(), &cache_dir]);
+        let thinned = super::read(&path, true).unwrap();
+        assert_eq!(thinned.maps[0].ty, BeatmapType::Hash);
+        assert_eq!(thinned.maps[0].hash, Some(hash));
+        assert_eq!(thinned.maps[0].zip, None);
+        let cache = ZipCache::new(&cache_dir).unwrap();
+        assert_eq!(cache.get(&hash).unwrap(), Some(zip));
+    }
+}
@@ -0,0 +1,152 @@
+#ifndef BLISTER_H
+#define BLISTER_H
+
+/* Generated with cbindgen, do not edit by hand */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+typedef enum BlisterStatus {
+  BLISTER_STATUS_OK = 0,
+  BLISTER_STATUS_ERROR = 1,
+  BLISTER_STATUS_NULL_POINTER = 2,
+  BLISTER_STATUS_INVALID_UTF8 = 3,
+  BLISTER_STATUS_OUT_OF_BOUNDS = 4,
+  BLISTER_STATUS_MISSING_FIELD = 5,
+  BLISTER_STATUS_PANIC = 6,
+} BlisterStatus;
+
+typedef enum BlisterBeatmapType {
+  BLISTER_BEATMAP_TYPE_KEY = 0,
+  BLISTER_BEATMAP_TYPE_HASH = 1,
+  BLISTER_BEATMAP_TYPE_ZIP = 2,
+  BLISTER_BEATMAP_TYPE_LEVEL_ID = 3,
+  BLISTER_BEATMAP_TYPE_UNKNOWN = 255,
+} BlisterBeatmapType;
+
+/**
+ * Opaque playlist handle, free with `blister_playlist_free`
+ */
+typedef struct BlisterPlaylist BlisterPlaylist;
+
+/**
+ * Message describing the last error on this thread, or null, valid until the next failing call
+ */
+const char *blister_last_error(void);
+
+/**
+ * Stable code of the last library error on this thread, or 0 for other failures
+ */
+uint16_t blister_last_error_code(void);
+
+struct BlisterPlaylist *blister_playlist_new(const char *title, const char *author);
+
+/**
+ * Returns null on failure, see `blister_last_error`
+ */
+struct BlisterPlaylist *blister_playlist_read(const uint8_t *data, size_t len, bool strict);
+
+/**
+ * Serializes the playlist into a new buffer, free with `blister_bytes_free`
+ */
+enum BlisterStatus blister_playlist_write(const struct BlisterPlaylist *playlist,
+                                          uint8_t **out_data,
+                                          size_t *out_len);
+
+void blister_playlist_free(struct BlisterPlaylist *playlist);
+
+void blister_bytes_free(uint8_t *data, size_t len);
+
+void blister_string_free(char *s);
+
+/**
+ * Returns a new string, free with `blister_string_free`
+ */
+char *blister_playlist_title(const struct BlisterPlaylist *playlist);
+
+/**
+ * Returns a new string, free with `blister_string_free`
+ */
+char *blister_playlist_author(const struct BlisterPlaylist *playlist);
+
+/**
+ * Returns a new string or null if unset, free with `blister_string_free`
+ */
+char *blister_playlist_description(const struct BlisterPlaylist *playlist);
+
+/**
+ * Returns a borrowed buffer or null if unset, valid until the playlist is modified
+ */
+const uint8_t *blister_playlist_cover(const struct BlisterPlaylist *playlist, size_t *out_len);
+
+enum BlisterStatus blister_playlist_set_title(struct BlisterPlaylist *playlist, const char *title);
+
+enum BlisterStatus blister_playlist_set_author(struct BlisterPlaylist *playlist,
+                                               const char *author);
+
+/**
+ * Passing null clears the description
+ */
+enum BlisterStatus blister_playlist_set_description(struct BlisterPlaylist *playlist,
+                                                    const char *description);
+
+/**
+ * Passing null clears the cover
+ */
+enum BlisterStatus blister_playlist_set_cover(struct BlisterPlaylist *playlist,
+                                              const uint8_t *data,
+                                              size_t len);
+
+size_t blister_playlist_map_count(const struct BlisterPlaylist *playlist);
+
+enum BlisterStatus blister_playlist_add_key(struct BlisterPlaylist *playlist, uint32_t key);
+
+/**
+ * `hash` must point to 20 bytes
+ */
+enum BlisterStatus blister_playlist_add_hash(struct BlisterPlaylist *playlist, const uint8_t *hash);
+
+enum BlisterStatus blister_playlist_add_zip(struct BlisterPlaylist *playlist,
+                                            const uint8_t *data,
+                                            size_t len);
+
+enum BlisterStatus blister_playlist_add_level_id(struct BlisterPlaylist *playlist,
+                                                 const char *level_id);
+
+enum BlisterStatus blister_playlist_remove_map(struct BlisterPlaylist *playlist, size_t index);
+
+enum BlisterBeatmapType blister_map_type(const struct BlisterPlaylist *playlist, size_t index);
+
+/**
+ * Seconds since the Unix epoch
+ */
+int64_t blister_map_date_added(const struct BlisterPlaylist *playlist, size_t index);
+
+enum BlisterStatus blister_map_key(const struct BlisterPlaylist *playlist,
+                                   size_t index,
+                                   uint32_t *out_key);
+
+/**
+ * `out_hash` must point to 20 writable bytes
+ */
+enum BlisterStatus blister_map_hash(const struct BlisterPlaylist *playlist,
+                                    size_t index,
+                                    uint8_t *out_hash);
+
+/**
+ * Returns a borrowed buffer or null if unset, valid until the playlist is modified. Zips that live
+ * on disk instead of in memory can't be borrowed and also return null, with an error set.
+ */
+const uint8_t *blister_map_zip(const struct BlisterPlaylist *playlist,
+                               size_t index,
+                               size_t *out_len);
+
+/**
+ * Returns a new string or null if unset, free with `blister_string_free`
+ */
+char *blister_map_level_id(const struct BlisterPlaylist *playlist, size_t index);
+
+#endif  /* BLISTER_H */
@@ -0,0 +1,645 @@
+#![allow(clippy::missing_safety_doc)]
+
+use blister::{error::Error, Beatmap, BeatmapType, Playlist};
+use blister_format::values::{LongString, Sha1, ShortString};
+use std::{
+    cell::{Cell, RefCell},
+    convert::TryInto,
+    ffi::{CStr, CString},
+    fmt::Display,
+    os::raw::c_char,
+    panic::{self, AssertUnwindSafe},
+    ptr, slice,
+};
+
+/// Opaque playlist handle, free with `blister_playlist_free`
+pub struct BlisterPlaylist(Playlist);
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum BlisterStatus {
+    Ok = 0,
+    Error = 1,
+    NullPointer = 2,
+    InvalidUtf8 = 3,
+    OutOfBounds = 4,
+    MissingField = 5,
+    Panic = 6,
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum BlisterBeatmapType {
+    Key = 0,
+    Hash = 1,
+    Zip = 2,
+    LevelId = 3,
+
+    Unknown = 255,
+}
+
+impl From<BeatmapType> for BlisterBeatmapType {
+    fn from(ty: BeatmapType) -> Self {
+        match ty {
+            BeatmapType::Key => Self::Key,
+            BeatmapType::Hash => Self::Hash,
+            BeatmapType::Zip => Self::Zip,
+            BeatmapType::LevelId => Self::LevelId,
+            BeatmapType::Unknown => Self::Unknown,
+        }
+    }
+}
+
+type Result<T> = std::result::Result<T, BlisterStatus>;
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+    static LAST_ERROR_CODE: Cell<u16> = const { Cell::new(0) };
+}
+
+fn fail<E>(status: BlisterStatus, error: E) -> BlisterStatus
+where
+    E: Display,
+{
+    let message = CString::new(error.to_string()).unwrap_or_default();
+    LAST_ERROR.with(|e| *e.borrow_mut() = Some(message));
+    LAST_ERROR_CODE.with(|c| c.set(0));
+    status
+}
+
+fn fail_with(error: Error) -> BlisterStatus {
+    let status = fail(BlisterStatus::Error, &error);
+    LAST_ERROR_CODE.with(|c| c.set(error.code()));
+    status
+}
+
+// Unwinding into the caller is undefined behaviour, so every exported function runs behind this
+fn guard<T, F>(default: T, f: F) -> T
+where
+    F: FnOnce() -> T,
+{
+    panic::catch_unwind(AssertUnwindSafe(f)).unwrap_or_else(|payload| {
+        let message = match payload.downcast_ref::<&str>() {
+            Some(s) => s,
+            None => payload
+                .downcast_ref::<String>()
+                .map_or("panic", String::as_str),
+        };
+        fail(BlisterStatus::Panic, message);
+        default
+    })
+}
+
+#[inline]
+fn guard_status<F>(f: F) -> BlisterStatus
+where
+    F: FnOnce() -> Result<()>,
+{
+    guard(BlisterStatus::Panic, || match f() {
+        Ok(()) => BlisterStatus::Ok,
+        Err(status) => status,
+    })
+}
+
+fn check_len(field: &'static str, s: &str, max: usize) -> Result<()> {
+    if s.len() > max {
+        return Err(fail_with(Error::FieldTooLong {
+            field,
+            len: s.len(),
+            max,
+        }));
+    }
+    Ok(())
+}
+
+unsafe fn str<'a>(s: *const c_char) -> Result<&'a str> {
+    if s.is_null() {
+        return Err(fail(BlisterStatus::NullPointer, "unexpected null string"));
+    }
+    CStr::from_ptr(s)
+        .to_str()
+        .map_err(|e| fail(BlisterStatus::InvalidUtf8, e))
+}
+
+unsafe fn bytes<'a>(data: *const u8, len: usize) -> Result<&'a [u8]> {
+    if data.is_null() {
+        return Err(fail(BlisterStatus::NullPointer, "unexpected null buffer"));
+    }
+    Ok(slice::from_raw_parts(data, len))
+}
+
+unsafe fn playlist<'a>(playlist: *const BlisterPlaylist) -> Result<&'a Playlist> {
+    match playlist.as_ref() {
+        Some(p) => Ok(&p.0),
+        None => Err(fail(BlisterStatus::NullPointer, "unexpected null playlist")),
+    }
+}
+
+unsafe fn playlist_mut<'a>(playlist: *mut BlisterPlaylist) -> Result<&'a mut Playlist> {
+    match playlist.as_mut() {
+        Some(p) => Ok(&mut p.0),
+        None => Err(fail(BlisterStatus::NullPointer, "unexpected null playlist")),
+    }
+}
+
+unsafe fn map<'a>(playlist: *const BlisterPlaylist, index: usize) -> Result<&'a Beatmap> {
+    let playlist = self::playlist(playlist)?;
+    playlist.maps.get(index).ok_or_else(|| {
+        fail(
+            BlisterStatus::OutOfBounds,
+            format_args!("beatmap index {} out of bounds", index),
+        )
+    })
+}
+
+fn string(s: &str) -> *mut c_char {
+    match CString::new(s) {
+        Ok(s) => s.into_raw(),
+        Err(e) => {
+            fail(BlisterStatus::Error, e);
+            ptr::null_mut()
+        }
+    }
+}
+
+fn handle(playlist: Playlist) -> *mut BlisterPlaylist {
+    Box::into_raw(Box::new(BlisterPlaylist(playlist)))
+}
+
+/// Message describing the last error on this thread, or null, valid until the next failing call
+#[no_mangle]
+pub extern "C" fn blister_last_error() -> *const c_char {
+    guard(ptr::null(), || {
+        LAST_ERROR.with(|e| e.borrow().as_ref().map_or(ptr::null(), |e| e.as_ptr()))
+    })
+}
+
+/// Stable code of the last library error on this thread, or 0 for other failures
+#[no_mangle]
+pub extern "C" fn blister_last_error_code() -> u16 {
+    guard(0, || LAST_ERROR_CODE.with(Cell::get))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn blister_playlist_new(
+    title: *const c_char,
+    author: *const c_char,
+) -> *mut BlisterPlaylist {
+    guard(ptr::null_mut(), || match (str(title), str(author)) {
+        (Ok(title), Ok(author)) => handle(Playlist::new(title.to_owned(), author.to_owned())),
+        _ => ptr::null_mut(),
+    })
+}
+
+/// Returns null on failure, see `blister_last_error`
+#[no_mangle]
+pub unsafe extern "C" fn blister_playlist_read(
+    data: *const u8,
+    len: usize,
+    strict: bool,
+) -> *mut BlisterPlaylist {
+    guard(ptr::null_mut(), || {
+        let data = match bytes(data, len) {
+            Ok(data) => data,
+            Err(_) => return ptr::null_mut(),
+        };
+        match Playlist::read(data, strict) {
+            Ok(playlist) => handle(playlist),
+            Err(e) => {
+                fail_with(e);
+                ptr::null_mut()
+            }
+        }
+    })
+}
+
+/// Serializes the playlist into a new buffer, free with `blister_bytes_free`
+#[no_mangle]
+pub unsafe extern "C" fn blister_playlist_write(
+    playlist: *const BlisterPlaylist,
+    out_data: *mut *mut u8,
+    out_len: *mut usize,
+) -> BlisterStatus {
+    guard_status(|| {
+        let playlist = self::playlist(playlist)?;
+        if out_data.is_null() || out_len.is_null() {
+            return Err(fail(BlisterStatus::NullPointer, "unexpected null output"));
+        }
+
+        let mut buffer = Vec::new();
+        playlist.write(&mut buffer).map_err(fail_with)?;
+
+        let buffer = buffer.into_boxed_slice();
+        *out_len = buffer.len();
+        *out_data = Box::into_raw(buffer) as *mut u8;
+        Ok(())
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn blister_playlist_free(playlist: *mut BlisterPlaylist) {
+    guard((), || {
+        if !playlist.is_null() {
+            drop(Box::from_raw(playlist));
+        }
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn blister_bytes_free(data: *mut u8, len: usize) {
+    guard((), || {
+        if !data.is_null() {
+            drop(Box::from_raw(ptr::slice_from_raw_parts_mut(data, len)));
+        }
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn blister_string_free(s: *mut c_char) {
+    guard((), || {
+        if !s.is_null() {
+            drop(CString::from_raw(s));
+        }
+    })
+}
+
+/// Returns a new string, free with `blister_string_free`
+#[no_mangle]
+pub unsafe extern "C" fn blister_playlist_title(playlist: *const BlisterPlaylist) -> *mut c_char {
+    guard(ptr::null_mut(), || {
+        self::playlist(playlist).map_or(ptr::null_mut(), |p| string(&p.title))
+    })
+}
+
+/// Returns a new string, free with `blister_string_free`
+#[no_mangle]
+pub unsafe extern "C" fn blister_playlist_author(playlist: *const BlisterPlaylist) -> *mut c_char {
+    guard(ptr::null_mut(), || {
+        self::playlist(playlist).map_or(ptr::null_mut(), |p| string(&p.author))
+    })
+}
+
+/// Returns a new string or null if unset, free with `blister_string_free`
+#[no_mangle]
+pub unsafe extern "C" fn blister_playlist_description(
+    playlist: *const BlisterPlaylist,
+) -> *mut c_char {
+    guard(ptr::null_mut(), || match self::playlist(playlist) {
+        Ok(Playlist {
+            description: Some(s),
+            ..
+        }) => string(s),
+        _ => ptr::null_mut(),
+    })
+}
+
+/// Returns a borrowed buffer or null if unset, valid until the playlist is modified
+#[no_mangle]
+pub unsafe extern "C" fn blister_playlist_cover(
+    playlist: *const BlisterPlaylist,
+    out_len: *mut usize,
+) -> *const u8 {
+    guard(ptr::null(), || {
+        match (self::playlist(playlist), out_len.as_mut()) {
+            (
+                Ok(Playlist {
+                    cover: Some(cover), ..
+                }),
+                Some(out_len),
+            ) => {
+                *out_len = cover.len();
+                cover.as_ptr()
+            }
+            _ => ptr::null(),
+        }
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn blister_playlist_set_title(
+    playlist: *mut BlisterPlaylist,
+    title: *const c_char,
+) -> BlisterStatus {
+    guard_status(|| {
+        let title = str(title)?;
+        check_len("title", title, ShortString::MAX_LEN)?;
+        let title = title.to_owned();
+        playlist_mut(playlist)?.title = title;
+        Ok(())
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn blister_playlist_set_author(
+    playlist: *mut BlisterPlaylist,
+    author: *const c_char,
+) -> BlisterStatus {
+    guard_status(|| {
+        let author = str(author)?;
+        check_len("author", author, ShortString::MAX_LEN)?;
+        let author = author.to_owned();
+        playlist_mut(playlist)?.author = author;
+        Ok(())
+    })
+}
+
+/// Passing null clears the description
+#[no_mangle]
+pub unsafe extern "C" fn blister_playlist_set_description(
+    playlist: *mut BlisterPlaylist,
+    description: *const c_char,
+) -> BlisterStatus {
+    guard_status(|| {
+        let description = match description.is_null() {
+            true => None,
+            false => {
+                let description = str(description)?;
+                check_len("description", description, LongString::MAX_LEN)?;
+                Some(description.to_owned())
+            }
+        };
+        playlist_mut(playlist)?.description = description;
+        Ok(())
+    })
+}
+
+/// Passing null clears the cover
+#[no_mangle]
+pub unsafe extern "C" fn blister_playlist_set_cover(
+    playlist: *mut BlisterPlaylist,
+    data: *const u8,
+    len: usize,
+) -> BlisterStatus {
+    guard_status(|| {
+        let cover = match data.is_null() {
+            true => None,
+            false => Some(bytes(data, len)?.into()),
+        };
+        playlist_mut(playlist)?.cover = cover;
+        Ok(())
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn blister_playlist_map_count(playlist: *const BlisterPlaylist) -> usize {
+    guard(0, || self::playlist(playlist).map_or(0, |p| p.maps.len()))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn blister_playlist_add_key(
+    playlist: *mut BlisterPlaylist,
+    key: u32,
+) -> BlisterStatus {
+    guard_status(|| playlist_mut(playlist).map(|p| p.maps.push(Beatmap::new_key(key))))
+}
+
+/// `hash` must point to 20 bytes
+#[no_mangle]
+pub unsafe extern "C" fn blister_playlist_add_hash(
+    playlist: *mut BlisterPlaylist,
+    hash: *const u8,
+) -> BlisterStatus {
+    guard_status(|| {
+        let hash = Sha1(bytes(hash, 20)?.try_into().unwrap());
+        playlist_mut(playlist)?.maps.push(Beatmap::new_hash(hash));
+        Ok(())
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn blister_playlist_add_zip(
+    playlist: *mut BlisterPlaylist,
+    data: *const u8,
+    len: usize,
+) -> BlisterStatus {
+    guard_status(|| {
+        let zip = bytes(data, len)?;
+        playlist_mut(playlist)?.maps.push(Beatmap::new_zip(zip));
+        Ok(())
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn blister_playlist_add_level_id(
+    playlist: *mut BlisterPlaylist,
+    level_id: *const c_char,
+) -> BlisterStatus {
+    guard_status(|| {
+        let level_id = str(level_id)?.to_owned();
+        playlist_mut(playlist)?
+            .maps
+            .push(Beatmap::new_level_id(level_id));
+        Ok(())
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn blister_playlist_remove_map(
+    playlist: *mut BlisterPlaylist,
+    index: usize,
+) -> BlisterStatus {
+    guard_status(|| {
+        map(playlist, index)?;
+        playlist_mut(playlist)?.maps.remove(index);
+        Ok(())
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn blister_map_type(
+    playlist: *const BlisterPlaylist,
+    index: usize,
+) -> BlisterBeatmapType {
+    guard(BlisterBeatmapType::Unknown, || {
+        map(playlist, index).map_or(BlisterBeatmapType::Unknown, |m| m.ty.into())
+    })
+}
+
+/// Seconds since the Unix epoch
+#[no_mangle]
+pub unsafe extern "C" fn blister_map_date_added(
+    playlist: *const BlisterPlaylist,
+    index: usize,
+) -> i64 {
+    guard(0, || {
+        map(playlist, index).map_or(0, |m| m.date_added.timestamp())
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn blister_map_key(
+    playlist: *const BlisterPlaylist,
+    index: usize,
+    out_key: *mut u32,
+) -> BlisterStatus {
+    guard_status(|| match (map(playlist, index)?.key, out_key.as_mut()) {
+        (Some(key), Some(out_key)) => {
+            *out_key = key;
+            Ok(())
+        }
+        (None, _) => Err(fail(BlisterStatus::MissingField, "beatmap has no key")),
+        (_, None) => Err(fail(BlisterStatus::NullPointer, "unexpected null output")),
+    })
+}
+
+/// `out_hash` must point to 20 writable bytes
+#[no_mangle]
+pub unsafe extern "C" fn blister_map_hash(
+    playlist: *const BlisterPlaylist,
+    index: usize,
+    out_hash: *mut u8,
+) -> BlisterStatus {
+    guard_status(|| match (map(playlist, index)?.hash, out_hash.is_null()) {
+        (Some(hash), false) => {
+            ptr::copy_nonoverlapping(hash.as_ptr(), out_hash, hash.len());
+            Ok(())
+        }
+        (None, _) => Err(fail(BlisterStatus::MissingField, "beatmap has no hash")),
+        (_, true) => Err(fail(BlisterStatus::NullPointer, "unexpected null output")),
+    })
+}
+
+/// Returns a borrowed buffer or null if unset, valid until the playlist is modified. Zips that live
+/// on disk instead of in memory can't be borrowed and also return null, with an error set.
+#[no_mangle]
+pub unsafe extern "C" fn blister_map_zip(
+    playlist: *const BlisterPlaylist,
+    index: usize,
+    out_len: *mut usize,
+) -> *const u8 {
+    guard(ptr::null(), || {
+        match (map(playlist, index), out_len.as_mut()) {
+            (Ok(Beatmap { zip: Some(zip), .. }), Some(out_len)) => match zip.as_bytes() {
+                Some(zip) => {
+                    *out_len = zip.len();
+                    zip.as_ptr()
+                }
+                None => {
+                    fail(BlisterStatus::Error, "beatmap zip was spilled to disk");
+                    ptr::null()
+                }
+            },
+            _ => ptr::null(),
+        }
+    })
+}
+
+/// Returns a new string or null if unset, free with `blister_string_free`
+#[no_mangle]
+pub unsafe extern "C" fn blister_map_level_id(
+    playlist: *const BlisterPlaylist,
+    index: usize,
+) -> *mut c_char {
+    guard(ptr::null_mut(), || match map(playlist, index) {
+        Ok(Beatmap {
+            level_id: Some(s), ..
+        }) => string(s),
+        _ => ptr::null_mut(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    #[test]
+    fn write_and_read() {
+        unsafe {
+            let playlist = blister_playlist_new(
+                b"test playlist\0".as_ptr() as *const c_char,
+                b"me\0".as_ptr() as *const c_char,
+            );
+            assert_eq!(blister_playlist_add_key(playlist, 2112), BlisterStatus::Ok);
+
+            let mut data = ptr::null_mut();
+            let mut len = 0;
+            assert_eq!(
+                blister_playlist_write(playlist, &mut data, &mut len),
+                BlisterStatus::Ok
+            );
+            blister_playlist_free(playlist);
+
+            let playlist = blister_playlist_read(data, len, true);
+            blister_bytes_free(data, len);
+            assert!(!playlist.is_null());
+
+            let title = blister_playlist_title(playlist);
+            assert_eq!(CStr::from_ptr(title).to_str(), Ok("test playlist"));
+            blister_string_free(title);
+
+            assert_eq!(blister_playlist_map_count(playlist), 1);
+            let mut key = 0;
+            assert_eq!(blister_map_key(playlist, 0, &mut key), BlisterStatus::Ok);
+            assert_eq!(key, 2112);
+            assert_eq!(
+                blister_map_key(playlist, 1, &mut key),
+                BlisterStatus::OutOfBounds
+            );
+            assert!(!blister_last_error().is_null());
+            assert_eq!(blister_last_error_code(), 0);
+
+            let invalid = blister_playlist_read(b"Blist.v2".as_ptr(), 8, true);
+            assert!(invalid.is_null());
+            assert_eq!(
+                blister_last_error_code(),
+                blister::error::ErrorKind::InvalidMagicNumber.code()
+            );
+
+            blister_playlist_free(playlist);
+        }
+    }
+
+    #[test]
+    fn guarded() {
+        unsafe {
+            let playlist = blister_playlist_new(
+                b"test playlist\0".as_ptr() as *const c_char,
+                b"me\0".as_ptr() as *const c_char,
+            );
+            let long = CString::new("a".repeat(256)).unwrap();
+            assert_eq!(
+                blister_playlist_set_title(playlist, long.as_ptr()),
+                BlisterStatus::Error
+            );
+            assert_eq!(
+                blister_last_error_code(),
+                blister::error::ErrorKind::FieldTooLong.code()
+            );
+            assert_eq!(
+                blister_playlist_set_author(playlist, long.as_ptr()),
+                BlisterStatus::Error
+            );
+            let title = blister_playlist_title(playlist);
+            assert_eq!(CStr::from_ptr(title).to_str(), Ok("test playlist"));
+            blister_string_free(title);
+            blister_playlist_free(playlist);
+
+            // Zips spilled to disk can't be borrowed
+            let temp = tempfile::tempdir().unwrap();
+            let mut spilled = Playlist::new("test playlist".to_owned(), "me".to_owned());
+            spilled.maps.push(Beatmap::new_zip(vec![1; 4096]));
+            let mut buffer = Vec::new();
+            spilled.write(&mut buffer).unwrap();
+            let options = blister::ReadOptions {
+                spill: Some(blister_format::Spill::new(1024, temp.path())),
+                ..Default::default()
+            };
+            let playlist =
+                handle(Playlist::read_with_options(buffer.as_slice(), &options).unwrap());
+            let mut len = 0;
+            assert!(blister_map_zip(playlist, 0, &mut len).is_null());
+            assert!(!blister_last_error().is_null());
+            blister_playlist_free(playlist);
+        }
+
+        assert_eq!(
+            guard(BlisterStatus::Ok, || panic!("boom")),
+            BlisterStatus::Ok
+        );
+        let message = unsafe { CStr::from_ptr(blister_last_error()) };
+        assert_eq!(message.to_str(), Ok("boom"));
+        assert_eq!(guard_status(|| panic!("{}", 2112)), BlisterStatus::Panic);
+        let message = unsafe { CStr::from_ptr(blister_last_error()) };
+        assert_eq!(message.to_str(), Ok("2112"));
+    }
+}
@@ -0,0 +1,82 @@
+use crate::{Map, MapEncoding, ReadOptions, Result, Value, Warning};
+use byteorder::{ReadBytesExt, WriteBytesExt, LE};
+use std::{
+    convert::TryInto,
+    io::{Read, Write},
+};
+
+// Same as the playlist crate, maps are much larger than the format crate's byte buffers
+const MAPS_PREALLOCATION_LIMIT: usize = 1024;
+
+const TITLE_KEY: u32 = 0;
+const AUTHOR_KEY: u32 = 1;
+const DATE_ADDED_KEY: u32 = 1;
+
+// What's left of a playlist once the magic number is checked and the body decompressed, which is
+// enough to work with playlists without pulling in a gzip implementation or a date library
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RawBody {
+    pub header: Map,
+    pub maps: Vec<Map>,
+}
+
+impl RawBody {
+    pub fn read<R>(
+        mut reader: R,
+        options: &ReadOptions,
+        warnings: &mut Vec<Warning>,
+    ) -> Result<Self>
+    where
+        R: Read,
+    {
+        let spill = options.spill.as_ref();
+        if let Some(spill) = spill {
+            spill.set_scope(None);
+        }
+
+        let mut header = Map::new();
+        header.read_with_options(&mut reader, options, warnings)?;
+
+        let count = reader.read_u32::<LE>()? as usize;
+        let mut maps = Vec::with_capacity(count.min(MAPS_PREALLOCATION_LIMIT));
+        for i in 0..count {
+            if let Some(spill) = spill {
+                spill.set_scope(Some(i));
+            }
+            let mut map = Map::new();
+            map.read_with_options(&mut reader, options, warnings)?;
+            maps.push(map);
+        }
+        Ok(Self { header, maps })
+    }
+
+    pub fn write<W>(&self, mut writer: W, encoding: MapEncoding) -> Result<()>
+    where
+        W: Write,
+    {
+        self.header.write_with_encoding(&mut writer, encoding)?;
+        writer.write_u32::<LE>(self.maps.len().try_into()?)?;
+        for map in &self.maps {
+            map.write_with_encoding(&mut writer, encoding)?;
+        }
+        Ok(())
+    }
+
+    #[inline]
+    pub fn title(&self) -> Option<&str> {
+        self.header.get(TITLE_KEY).and_then(Value::as_str)
+    }
+
+    #[inline]
+    pub fn author(&self) -> Option<&str> {
+        self.header.get(AUTHOR_KEY).and_then(Value::as_str)
+    }
+
+    // Seconds since the Unix epoch
+    #[inline]
+    pub fn date_added(&self, map: usize) -> Option<u64> {
+        self.maps
+            .get(map)
+            .and_then(|m| m.get_u64(DATE_ADDED_KEY, false))
+    }
+}
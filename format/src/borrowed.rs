@@ -0,0 +1,301 @@
+use crate::{
+    error::Error,
+    map::{checksum, CHECKSUM_LEN, ENTRY_MARKER},
+    values::{Binary, LongString, Sha1, ShortString},
+    Key, Map, MapEncoding, Result, Value,
+};
+use byteorder::{ByteOrder, LE};
+use std::{convert::TryInto, io, str};
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ValueRef<'a> {
+    U8(u8),
+    U16(u16),
+    U32(u32),
+    U64(u64),
+    ShortString(&'a str),
+    LongString(&'a str),
+    Binary(&'a [u8]),
+    Bool(bool),
+    Float(f32),
+    Sha1(Sha1),
+    LongBinary(&'a [u8]),
+    // Binary values that only live in a spill file, written by streaming it back out
+    SpilledBinary(&'a Binary),
+}
+
+impl<'a> ValueRef<'a> {
+    // Same limits as `ShortString::new` and `LongString::new`
+    pub fn short_string(s: &'a str) -> Result<Self> {
+        check_str_len(s, ShortString::MAX_LEN).map(Self::ShortString)
+    }
+
+    pub fn long_string(s: &'a str) -> Result<Self> {
+        check_str_len(s, LongString::MAX_LEN).map(Self::LongString)
+    }
+
+    // Values too long for a 32 bit length are written as long binaries
+    #[inline]
+    pub fn binary(b: &'a Binary) -> Self {
+        match b.as_bytes() {
+            None => Self::SpilledBinary(b),
+            Some(bytes) if is_long(bytes.len()) => Self::LongBinary(bytes),
+            Some(bytes) => Self::Binary(bytes),
+        }
+    }
+
+    pub fn encoded_len(&self) -> usize {
+        4 + 1
+            + match self {
+                ValueRef::U8(_) => 1,
+                ValueRef::U16(_) => 2,
+                ValueRef::U32(_) => 4,
+                ValueRef::U64(_) => 8,
+                ValueRef::ShortString(v) => 1 + v.len(),
+                ValueRef::LongString(v) => 2 + v.len(),
+                ValueRef::Binary(v) => 4 + v.len(),
+                ValueRef::Bool(_) => 1,
+                ValueRef::Float(_) => 4,
+                ValueRef::Sha1(_) => 20,
+                ValueRef::LongBinary(v) => 8 + v.len(),
+                ValueRef::SpilledBinary(v) if is_long(v.len()) => 8 + v.len(),
+                ValueRef::SpilledBinary(v) => 4 + v.len(),
+            }
+    }
+
+    pub(crate) fn data_type(&self) -> u8 {
+        match self {
+            ValueRef::U8(_) => 0,
+            ValueRef::U16(_) => 1,
+            ValueRef::U32(_) => 2,
+            ValueRef::U64(_) => 3,
+            ValueRef::ShortString(_) => 4,
+            ValueRef::LongString(_) => 5,
+            ValueRef::Binary(_) => 6,
+            ValueRef::Bool(_) => 7,
+            ValueRef::Float(_) => 8,
+            ValueRef::Sha1(_) => 9,
+            ValueRef::LongBinary(_) => 10,
+            ValueRef::SpilledBinary(v) if is_long(v.len()) => 10,
+            ValueRef::SpilledBinary(_) => 6,
+        }
+    }
+
+    pub fn to_owned(&self) -> Value {
+        match *self {
+            ValueRef::U8(v) => Value::U8(v),
+            ValueRef::U16(v) => Value::U16(v),
+            ValueRef::U32(v) => Value::U32(v),
+            ValueRef::U64(v) => Value::U64(v),
+            ValueRef::ShortString(v) => Value::ShortString(ShortString::new_unchecked(v.into())),
+            ValueRef::LongString(v) => Value::LongString(LongString::new_unchecked(v.into())),
+            ValueRef::Binary(v) => Value::Binary(v.into()),
+            ValueRef::Bool(v) => Value::Bool(v),
+            ValueRef::Float(v) => Value::Float(v),
+            ValueRef::Sha1(v) => Value::Sha1(v),
+            ValueRef::LongBinary(v) => Value::LongBinary(v.into()),
+            ValueRef::SpilledBinary(v) if is_long(v.len()) => Value::LongBinary(v.clone().into()),
+            ValueRef::SpilledBinary(v) => Value::Binary(v.clone()),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct MapRef<'a> {
+    data: &'a [u8],
+    encoding: MapEncoding,
+}
+
+impl<'a> MapRef<'a> {
+    #[inline]
+    pub fn parse(input: &mut &'a [u8]) -> Result<Self> {
+        Self::parse_with_encoding(input, MapEncoding::Length)
+    }
+
+    pub fn parse_with_encoding(input: &mut &'a [u8], encoding: MapEncoding) -> Result<Self> {
+        let (data, count) = split_map(input, encoding)?;
+
+        let mut entries = data;
+        let mut actual = 0;
+        while !entries.is_empty() {
+            if encoding == MapEncoding::Checksummed {
+                let offset = data.len() - entries.len();
+                if take(&mut entries, ENTRY_MARKER.len())? != ENTRY_MARKER {
+                    return Err(Error::MissingEntryMarker(offset));
+                }
+                let start = entries;
+                let len = LE::read_u32(take(&mut entries, 4)?) as usize;
+                let mut entry = take(&mut entries, len)?;
+                let stored = LE::read_u32(take(&mut entries, CHECKSUM_LEN)?);
+                let key = Key(entry.get(..4).map_or(0, LE::read_u32));
+                if stored != checksum(&start[..4 + len]) {
+                    return Err(Error::ChecksumMismatch(key));
+                }
+                read_kv_ref(&mut entry)?;
+                if !entry.is_empty() {
+                    return Err(Error::ChecksumMismatch(key));
+                }
+            } else {
+                read_kv_ref(&mut entries)?;
+            }
+            actual += 1;
+        }
+        match count {
+            Some(expected) if expected != actual => {
+                Err(Error::EntryCountMismatch { expected, actual })
+            }
+            _ => Ok(Self { data, encoding }),
+        }
+    }
+
+    // Moves past a whole map using its length prefix alone, without decoding any entry
+    #[inline]
+    pub fn skip(input: &mut &'a [u8], encoding: MapEncoding) -> Result<()> {
+        split_map(input, encoding).map(|_| ())
+    }
+
+    #[inline]
+    pub fn iter(&self) -> MapRefIter<'a> {
+        MapRefIter {
+            data: self.data,
+            encoding: self.encoding,
+        }
+    }
+
+    pub fn get<K>(&self, key: K) -> Option<ValueRef<'a>>
+    where
+        K: Into<Key>,
+    {
+        let key = key.into();
+        self.iter()
+            .filter(|(k, _)| *k == key)
+            .map(|(_, v)| v)
+            .last()
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.iter().count()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    pub fn to_owned(&self) -> Map {
+        let mut map = Map::new();
+        for (k, v) in self.iter() {
+            map.insert(k, v.to_owned());
+        }
+        map
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MapRefIter<'a> {
+    data: &'a [u8],
+    encoding: MapEncoding,
+}
+
+impl<'a> Iterator for MapRefIter<'a> {
+    type Item = (Key, ValueRef<'a>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.data.is_empty() {
+            return None;
+        }
+        // Entries are validated when the map is parsed
+        if self.encoding == MapEncoding::Checksummed {
+            self.data = &self.data[ENTRY_MARKER.len() + 4..];
+        }
+        let entry = read_kv_ref(&mut self.data).ok()?;
+        if self.encoding == MapEncoding::Checksummed {
+            self.data = &self.data[CHECKSUM_LEN..];
+        }
+        Some(entry)
+    }
+}
+
+impl<'a> IntoIterator for &MapRef<'a> {
+    type Item = (Key, ValueRef<'a>);
+    type IntoIter = MapRefIter<'a>;
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+#[inline]
+fn is_long(len: usize) -> bool {
+    len as u64 > u32::MAX as u64
+}
+
+fn check_str_len(s: &str, max: usize) -> Result<&str> {
+    if s.len() > max {
+        return Err(Error::StringTooLong { len: s.len(), max });
+    }
+    Ok(s)
+}
+
+// The entries of the map at the start of the input, and the count counted maps declare
+fn split_map<'a>(input: &mut &'a [u8], encoding: MapEncoding) -> Result<(&'a [u8], Option<usize>)> {
+    let len = LE::read_u32(take(input, 4)?) as usize;
+    let count = match encoding {
+        MapEncoding::Counted => Some(LE::read_u32(take(input, 4)?) as usize),
+        MapEncoding::Length | MapEncoding::Checksummed => None,
+    };
+    Ok((take(input, len)?, count))
+}
+
+fn take<'a>(input: &mut &'a [u8], len: usize) -> Result<&'a [u8]> {
+    if input.len() < len {
+        return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+    }
+    let (taken, rest) = input.split_at(len);
+    *input = rest;
+    Ok(taken)
+}
+
+fn read_kv_ref<'a>(input: &mut &'a [u8]) -> Result<(Key, ValueRef<'a>)> {
+    let key = Key(LE::read_u32(take(input, 4)?));
+
+    let data_type = take(input, 1)?[0];
+    let value = match data_type {
+        0 => ValueRef::U8(take(input, 1)?[0]),
+        1 => ValueRef::U16(LE::read_u16(take(input, 2)?)),
+        2 => ValueRef::U32(LE::read_u32(take(input, 4)?)),
+        3 => ValueRef::U64(LE::read_u64(take(input, 8)?)),
+        4 => {
+            let len = take(input, 1)?[0] as usize;
+            ValueRef::ShortString(read_str(input, len)?)
+        }
+        5 => {
+            let len = LE::read_u16(take(input, 2)?) as usize;
+            ValueRef::LongString(read_str(input, len)?)
+        }
+        6 => {
+            let len = LE::read_u32(take(input, 4)?) as usize;
+            ValueRef::Binary(take(input, len)?)
+        }
+        7 => match take(input, 1)?[0] {
+            0 => ValueRef::Bool(false),
+            1 => ValueRef::Bool(true),
+            value => return Err(Error::InvalidBoolean(value)),
+        },
+        8 => ValueRef::Float(LE::read_f32(take(input, 4)?)),
+        9 => ValueRef::Sha1(Sha1(take(input, 20)?.try_into().unwrap())),
+        10 => {
+            let len = LE::read_u64(take(input, 8)?).try_into()?;
+            ValueRef::LongBinary(take(input, len)?)
+        }
+        _ => return Err(Error::InvalidDataType(data_type)),
+    };
+
+    Ok((key, value))
+}
+
+fn read_str<'a>(input: &mut &'a [u8], len: usize) -> Result<&'a str> {
+    Ok(str::from_utf8(take(input, len)?)?)
+}
@@ -0,0 +1,112 @@
+use crate::{
+    map::{CHECKSUM_LEN, ENTRY_MARKER},
+    Map, MapEncoding, Value,
+};
+use std::fmt::Write;
+
+const ROW_LEN: usize = 16;
+// Wide enough for a full row of bytes
+const HEX_WIDTH: usize = ROW_LEN * 3;
+
+// Renders the bytes `Map::write_sorted` would produce, one line per field with its offset and what
+// it holds. Key, type and length prefix are separated by `|`, payloads follow on their own rows.
+#[inline]
+pub fn hexdump(map: &Map) -> String {
+    hexdump_with_encoding(map, MapEncoding::Length)
+}
+
+pub fn hexdump_with_encoding(map: &Map, encoding: MapEncoding) -> String {
+    let mut bytes = Vec::with_capacity(map.encoded_len_with_encoding(encoding));
+    if let Err(e) = map.write_sorted_with_encoding(&mut bytes, encoding) {
+        return format!("map can't be encoded: {}\n", e);
+    }
+
+    let mut dump = String::new();
+    let header_len = match encoding {
+        MapEncoding::Counted => 8,
+        _ => 4,
+    };
+    let mut annotation = format!("map, {} bytes", bytes.len() - header_len);
+    if encoding == MapEncoding::Counted {
+        let _ = write!(annotation, ", {} entries", map.len());
+    }
+    line(&mut dump, 0, &[&bytes[..header_len]], &annotation);
+
+    let mut offset = header_len;
+    for (k, v) in map.iter_sorted() {
+        if encoding == MapEncoding::Checksummed {
+            let framing = &bytes[offset..offset + ENTRY_MARKER.len() + 4];
+            let (marker, len) = framing.split_at(ENTRY_MARKER.len());
+            line(
+                &mut dump,
+                offset,
+                &[marker, len],
+                &format!("entry, {} bytes", v.encoded_len()),
+            );
+            offset += framing.len();
+        }
+        let entry = &bytes[offset..offset + v.encoded_len()];
+        let prefix_len = prefix_len(v);
+        let (header, payload) = entry.split_at(4 + 1 + prefix_len);
+        line(
+            &mut dump,
+            offset,
+            &[&header[..4], &header[4..5], &header[5..]],
+            &describe(**k, v),
+        );
+        for (i, row) in payload.chunks(ROW_LEN).enumerate() {
+            line(&mut dump, offset + header.len() + i * ROW_LEN, &[row], "");
+        }
+        offset += entry.len();
+
+        if encoding == MapEncoding::Checksummed {
+            let checksum = &bytes[offset..offset + CHECKSUM_LEN];
+            line(&mut dump, offset, &[checksum], "checksum");
+            offset += CHECKSUM_LEN;
+        }
+    }
+    dump
+}
+
+fn prefix_len(value: &Value) -> usize {
+    match value {
+        Value::ShortString(_) => 1,
+        Value::LongString(_) => 2,
+        Value::Binary(_) => 4,
+        Value::LongBinary(_) => 8,
+        _ => 0,
+    }
+}
+
+fn describe(key: u32, value: &Value) -> String {
+    let name = value.type_name();
+    match value {
+        Value::ShortString(v) => format!("key {}, {}, {} bytes", key, name, v.len()),
+        Value::LongString(v) => format!("key {}, {}, {} bytes", key, name, v.len()),
+        Value::Binary(v) => format!("key {}, {}, {} bytes", key, name, v.len()),
+        Value::LongBinary(v) => format!("key {}, {}, {} bytes", key, name, v.len()),
+        v => format!("key {}, {} = {}", key, name, v),
+    }
+}
+
+fn line(dump: &mut String, offset: usize, groups: &[&[u8]], annotation: &str) {
+    let mut hex = String::new();
+    for group in groups.iter().filter(|g| !g.is_empty()) {
+        if !hex.is_empty() {
+            hex.push_str("| ");
+        }
+        for b in group.iter() {
+            let _ = write!(hex, "{:02x} ", b);
+        }
+    }
+    let _ = write!(
+        dump,
+        "{:08x}  {:<width$} {}",
+        offset,
+        hex,
+        annotation,
+        width = HEX_WIDTH
+    );
+    dump.truncate(dump.trim_end().len());
+    dump.push('\n');
+}
@@ -0,0 +1,73 @@
+use crate::{Key, Map, Value};
+use std::collections::hash_map::Entry;
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MapDiff {
+    pub added: Vec<(Key, Value)>,
+    pub removed: Vec<(Key, Value)>,
+    pub changed: Vec<(Key, Value, Value)>,
+}
+
+impl MapDiff {
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum MergePolicy {
+    KeepOurs,
+    KeepTheirs,
+}
+
+impl Default for MergePolicy {
+    #[inline]
+    fn default() -> Self {
+        Self::KeepOurs
+    }
+}
+
+impl Map {
+    pub fn diff(&self, other: &Map) -> MapDiff {
+        let mut diff = MapDiff::default();
+        for (k, v) in self.iter() {
+            match other.get(*k) {
+                None => diff.removed.push((*k, v.clone())),
+                Some(o) if o != v => diff.changed.push((*k, v.clone(), o.clone())),
+                Some(_) => (),
+            }
+        }
+        for (k, v) in other.iter() {
+            if !self.contains_key(*k) {
+                diff.added.push((*k, v.clone()));
+            }
+        }
+
+        diff.added.sort_unstable_by_key(|(k, _)| *k);
+        diff.removed.sort_unstable_by_key(|(k, _)| *k);
+        diff.changed.sort_unstable_by_key(|(k, _, _)| *k);
+        diff
+    }
+
+    // Returns the keys present in both maps with different values
+    pub fn merge(&mut self, mut other: Map, policy: MergePolicy) -> Vec<Key> {
+        let mut conflicts = Vec::new();
+        for (k, v) in other.drain() {
+            match self.entry(k) {
+                Entry::Vacant(e) => {
+                    e.insert(v);
+                }
+                Entry::Occupied(e) if *e.get() == v => (),
+                Entry::Occupied(mut e) => {
+                    conflicts.push(k);
+                    if policy == MergePolicy::KeepTheirs {
+                        e.insert(v);
+                    }
+                }
+            }
+        }
+        conflicts.sort_unstable();
+        conflicts
+    }
+}
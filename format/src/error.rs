@@ -1,3 +1,4 @@
+use crate::Key;
 use thiserror::Error;
 
 #[derive(Debug, Error)]
@@ -7,10 +8,83 @@ pub enum Error {
     #[error(transparent)]
     InvalidUtf8(#[from] std::string::FromUtf8Error),
     #[error(transparent)]
+    InvalidUtf8Str(#[from] std::str::Utf8Error),
+    #[error(transparent)]
     IntegerOverflow(#[from] std::num::TryFromIntError),
 
     #[error("`{0} isn't a valid data type`")]
     InvalidDataType(u8),
+    #[error("binary value of {0} bytes exceeds the configured limit")]
+    BinaryTooLarge(usize),
+    #[error("binary value of {len} bytes under key `{key:?}` exceeds the configured limit")]
+    FieldTooLarge { key: Key, len: usize },
     #[error("`{0} isn't a valid boolean, should be `0` for false or `1` for true`")]
     InvalidBoolean(u8),
+    #[error("map header declares {expected} entries but {actual} were read")]
+    EntryCountMismatch { expected: usize, actual: usize },
+    #[error("checksum mismatch for entry `{0:?}`")]
+    ChecksumMismatch(Key),
+    #[error("no entry marker at offset {0} of the map")]
+    MissingEntryMarker(usize),
+    #[error("string of {len} bytes exceeds the maximum of {max}")]
+    StringTooLong { len: usize, max: usize },
+    #[error("`{0}` isn't a valid SHA-1 hash")]
+    InvalidSha1(String),
+    #[error("expected a value of type `{expected}`, found `{found}`")]
+    TypeMismatch {
+        expected: &'static str,
+        found: &'static str,
+    },
+}
+
+// Discriminants are stable and must never be reused
+#[non_exhaustive]
+#[repr(u16)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum ErrorKind {
+    IO = 1,
+    InvalidUtf8 = 2,
+    IntegerOverflow = 3,
+    InvalidDataType = 4,
+    BinaryTooLarge = 5,
+    InvalidBoolean = 6,
+    EntryCountMismatch = 7,
+    ChecksumMismatch = 8,
+    TypeMismatch = 9,
+    StringTooLong = 10,
+    InvalidSha1 = 11,
+    FieldTooLarge = 12,
+    MissingEntryMarker = 13,
+}
+
+impl ErrorKind {
+    #[inline]
+    pub fn code(self) -> u16 {
+        self as u16
+    }
+}
+
+impl Error {
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Error::IO(_) => ErrorKind::IO,
+            Error::InvalidUtf8(_) | Error::InvalidUtf8Str(_) => ErrorKind::InvalidUtf8,
+            Error::IntegerOverflow(_) => ErrorKind::IntegerOverflow,
+            Error::InvalidDataType(_) => ErrorKind::InvalidDataType,
+            Error::BinaryTooLarge(_) => ErrorKind::BinaryTooLarge,
+            Error::InvalidBoolean(_) => ErrorKind::InvalidBoolean,
+            Error::EntryCountMismatch { .. } => ErrorKind::EntryCountMismatch,
+            Error::ChecksumMismatch(_) => ErrorKind::ChecksumMismatch,
+            Error::TypeMismatch { .. } => ErrorKind::TypeMismatch,
+            Error::StringTooLong { .. } => ErrorKind::StringTooLong,
+            Error::InvalidSha1(_) => ErrorKind::InvalidSha1,
+            Error::FieldTooLarge { .. } => ErrorKind::FieldTooLarge,
+            Error::MissingEntryMarker(_) => ErrorKind::MissingEntryMarker,
+        }
+    }
+
+    #[inline]
+    pub fn code(&self) -> u16 {
+        self.kind().code()
+    }
 }
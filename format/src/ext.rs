@@ -1,12 +1,28 @@
-use crate::{error::Error, values::Sha1, Key, Result, Value};
+#[cfg(feature = "smallvec")]
+use crate::values::INLINE_LEN;
+use crate::{
+    error::Error,
+    values::{Binary, LongBinary, LongString, Sha1, ShortString, Str},
+    Key, ReadOptions, Result, Value, ValueRef, Warning, PREALLOCATION_LIMIT,
+};
 use byteorder::{ReadBytesExt, WriteBytesExt, LE};
 use std::{
-    convert::TryInto,
-    io::{Read, Write},
+    convert::{TryFrom, TryInto},
+    io::{self, IoSlice, Read, Write},
+    sync::Arc,
 };
 
 pub trait ReadExt: Read {
+    #[inline]
     fn read_kv(&mut self) -> Result<(usize, (Key, Value))> {
+        self.read_kv_with_options(&Default::default(), &mut Vec::new())
+    }
+
+    fn read_kv_with_options(
+        &mut self,
+        options: &ReadOptions,
+        warnings: &mut Vec<Warning>,
+    ) -> Result<(usize, (Key, Value))> {
         let read;
 
         let key = Key(self.read_u32::<LE>()?);
@@ -31,24 +47,50 @@ pub trait ReadExt: Read {
             }
             4 => {
                 let len = self.read_u8()? as usize;
-                let mut utf8 = vec![0; len];
-                self.read_exact(&mut utf8)?;
+                let utf8 = read_bytes(self, len)?;
 
                 read = 4 + 1 + 1 + len;
-                Value::ShortString(String::from_utf8(utf8)?)
+                Value::ShortString(ShortString::new_unchecked(decode_utf8(
+                    utf8,
+                    ShortString::MAX_LEN,
+                    key,
+                    options,
+                    warnings,
+                )?))
             }
             5 => {
                 let len = self.read_u16::<LE>()? as usize;
-                let mut utf8 = vec![0; len];
-                self.read_exact(&mut utf8)?;
+                let utf8 = read_bytes(self, len)?;
 
                 read = 4 + 1 + 2 + len;
-                Value::LongString(String::from_utf8(utf8)?)
+                Value::LongString(LongString::new_unchecked(decode_utf8(
+                    utf8,
+                    LongString::MAX_LEN,
+                    key,
+                    options,
+                    warnings,
+                )?))
             }
             6 => {
                 let len = self.read_u32::<LE>()? as usize;
-                let mut bytes = vec![0; len];
-                self.read_exact(&mut bytes)?;
+                options.check_binary_len(key, len)?;
+                if let Some(spill) = options.spill.as_ref().filter(|s| len > s.threshold()) {
+                    let spilled = spill.spill_to_file(key, self, len)?;
+
+                    read = 4 + 1 + 4 + len;
+                    let value = Value::Binary(Binary::from_spilled(Arc::new(spilled)));
+                    return Ok((read, (key, value)));
+                }
+                #[cfg(feature = "smallvec")]
+                let bytes = if len <= INLINE_LEN {
+                    let mut buffer = [0; INLINE_LEN];
+                    self.read_exact(&mut buffer[..len])?;
+                    buffer[..len].into()
+                } else {
+                    read_bytes(self, len)?.into()
+                };
+                #[cfg(not(feature = "smallvec"))]
+                let bytes = read_bytes(self, len)?.into();
 
                 read = 4 + 1 + 4 + len;
                 Value::Binary(bytes)
@@ -74,6 +116,19 @@ pub trait ReadExt: Read {
                 read = 4 + 1 + 20;
                 Value::Sha1(Sha1(hash))
             }
+            10 => {
+                let len: usize = self.read_u64::<LE>()?.try_into()?;
+                options.check_binary_len(key, len)?;
+
+                read = 4 + 1 + 8 + len;
+                match options.spill.as_ref().filter(|s| len > s.threshold()) {
+                    Some(spill) => {
+                        let spilled = spill.spill_to_file(key, self, len)?;
+                        Value::LongBinary(LongBinary::spilled(Arc::new(spilled)))
+                    }
+                    None => Value::LongBinary(read_bytes(self, len)?.into()),
+                }
+            }
             _ => return Err(Error::InvalidDataType(data_type)),
         };
 
@@ -82,70 +137,175 @@ pub trait ReadExt: Read {
 }
 impl<R> ReadExt for R where R: Read + ?Sized {}
 
+fn decode_utf8(
+    utf8: Vec<u8>,
+    max: usize,
+    key: Key,
+    options: &ReadOptions,
+    warnings: &mut Vec<Warning>,
+) -> Result<Str> {
+    let s = match String::from_utf8(utf8) {
+        Ok(s) => s,
+        Err(e) if options.lossy_utf8 => {
+            let raw = options.capture_discarded.then(|| e.as_bytes().to_vec());
+            warnings.push(Warning::InvalidUtf8 { key, raw });
+            let mut s = String::from_utf8_lossy(e.as_bytes()).into_owned();
+            if s.len() > max {
+                warnings.push(Warning::TruncatedString { key, len: s.len() });
+                let mut end = max;
+                while !s.is_char_boundary(end) {
+                    end -= 1;
+                }
+                s.truncate(end);
+            }
+            s
+        }
+        Err(e) => return Err(e.into()),
+    };
+
+    Ok(match &options.interner {
+        Some(interner) => interner.intern(&s),
+        None => s.into(),
+    })
+}
+
+pub(crate) fn read_bytes<R>(reader: &mut R, len: usize) -> Result<Vec<u8>>
+where
+    R: Read + ?Sized,
+{
+    // Length prefixes can't be trusted, so only grow the buffer as data actually comes in
+    let mut bytes = Vec::with_capacity(len.min(PREALLOCATION_LIMIT));
+    reader.take(len as u64).read_to_end(&mut bytes)?;
+    if bytes.len() != len {
+        return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+    }
+    Ok(bytes)
+}
+
 pub trait WriteExt: Write {
     fn write_kv(&mut self, key: Key, value: &Value) -> Result<usize> {
-        let written;
+        match (value, value.to_ref()) {
+            (_, Some(value)) => self.write_kv_ref(key, value),
+            (Value::LongBinary(v), None) => write_streamed(self, key, 10, v.len(), v.reader()?),
+            // Long binaries are the only values that can live outside of memory
+            (_, None) => unreachable!(),
+        }
+    }
 
-        self.write_u32::<LE>(*key)?;
+    fn write_kv_ref(&mut self, key: Key, value: ValueRef<'_>) -> Result<usize> {
+        if let ValueRef::SpilledBinary(v) = value {
+            return write_streamed(self, key, value.data_type(), v.len() as u64, v.reader()?);
+        }
+
+        const HEADER_LEN: usize = 4 + 1 + 8;
+        let mut header = [0; HEADER_LEN];
+        let mut cursor = &mut header[..];
+
+        cursor.write_u32::<LE>(*key)?;
 
-        self.write_u8(value.data_type())?;
-        match value {
-            Value::U8(v) => {
-                self.write_u8(*v)?;
-                written = 4 + 1 + 1;
+        cursor.write_u8(value.data_type())?;
+        let payload: &[u8] = match value {
+            ValueRef::U8(v) => {
+                cursor.write_u8(v)?;
+                &[]
             }
-            Value::U16(v) => {
-                self.write_u16::<LE>(*v)?;
-                written = 4 + 1 + 2;
+            ValueRef::U16(v) => {
+                cursor.write_u16::<LE>(v)?;
+                &[]
             }
-            Value::U32(v) => {
-                self.write_u32::<LE>(*v)?;
-                written = 4 + 1 + 4;
+            ValueRef::U32(v) => {
+                cursor.write_u32::<LE>(v)?;
+                &[]
             }
-            Value::U64(v) => {
-                self.write_u64::<LE>(*v)?;
-                written = 4 + 1 + 8;
+            ValueRef::U64(v) => {
+                cursor.write_u64::<LE>(v)?;
+                &[]
             }
-            Value::ShortString(v) => {
-                let utf8 = v.clone().into_bytes();
-                let len = utf8.len();
-                self.write_u8(len.try_into()?)?;
-                self.write_all(&utf8)?;
-
-                written = 4 + 1 + 1 + len;
+            ValueRef::ShortString(v) => {
+                let utf8 = v.as_bytes();
+                cursor.write_u8(utf8.len().try_into()?)?;
+                utf8
             }
-            Value::LongString(v) => {
-                let utf8 = v.clone().into_bytes();
-                let len = utf8.len();
-                self.write_u16::<LE>(len.try_into()?)?;
-                self.write_all(&utf8)?;
-
-                written = 4 + 1 + 2 + len;
+            ValueRef::LongString(v) => {
+                let utf8 = v.as_bytes();
+                cursor.write_u16::<LE>(utf8.len().try_into()?)?;
+                utf8
             }
-            Value::Binary(v) => {
-                let len = v.len();
-                self.write_u32::<LE>(len.try_into()?)?;
-                self.write_all(&v)?;
-
-                written = 4 + 1 + 4 + len;
+            ValueRef::Binary(v) => {
+                cursor.write_u32::<LE>(v.len().try_into()?)?;
+                v
             }
-            Value::Bool(v) => {
-                let value = if *v { 1 } else { 0 };
-                self.write_u8(value)?;
-
-                written = 4 + 1 + 1;
+            ValueRef::Bool(v) => {
+                let value = if v { 1 } else { 0 };
+                cursor.write_u8(value)?;
+                &[]
             }
-            Value::Float(v) => {
-                self.write_f32::<LE>(*v)?;
-                written = 4 + 1 + 4;
+            ValueRef::Float(v) => {
+                cursor.write_f32::<LE>(v)?;
+                &[]
             }
-            Value::Sha1(v) => {
-                self.write_all(&v[..])?;
-                written = 4 + 1 + 20;
+            ValueRef::Sha1(ref v) => &v[..],
+            ValueRef::LongBinary(v) => {
+                cursor.write_u64::<LE>(v.len().try_into()?)?;
+                v
             }
-        }
+            ValueRef::SpilledBinary(_) => unreachable!(),
+        };
+        let header_len = HEADER_LEN - cursor.len();
 
-        Ok(written)
+        write_all_vectored(
+            self,
+            &mut [IoSlice::new(&header[..header_len]), IoSlice::new(payload)],
+        )?;
+        Ok(header_len + payload.len())
     }
 }
 impl<W> WriteExt for W where W: Write + ?Sized {}
+
+// Binaries backed by files are copied straight from them
+fn write_streamed<W, R>(
+    writer: &mut W,
+    key: Key,
+    data_type: u8,
+    len: u64,
+    reader: R,
+) -> Result<usize>
+where
+    W: Write + ?Sized,
+    R: Read,
+{
+    const HEADER_LEN: usize = 4 + 1 + 8;
+    let mut header = [0; HEADER_LEN];
+    let mut cursor = &mut header[..];
+    cursor.write_u32::<LE>(*key)?;
+    cursor.write_u8(data_type)?;
+    match data_type {
+        6 => cursor.write_u32::<LE>(len.try_into()?)?,
+        _ => cursor.write_u64::<LE>(len)?,
+    }
+    let header_len = HEADER_LEN - cursor.len();
+    writer.write_all(&header[..header_len])?;
+
+    // A file shrinking under us would leave the entry shorter than its header says
+    let copied = io::copy(&mut reader.take(len), writer)?;
+    if copied != len {
+        return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+    }
+    Ok(header_len + usize::try_from(copied)?)
+}
+
+fn write_all_vectored<W>(writer: &mut W, mut bufs: &mut [IoSlice<'_>]) -> io::Result<()>
+where
+    W: Write + ?Sized,
+{
+    IoSlice::advance_slices(&mut bufs, 0);
+    while !bufs.is_empty() {
+        match writer.write_vectored(bufs) {
+            Ok(0) => return Err(io::ErrorKind::WriteZero.into()),
+            Ok(n) => IoSlice::advance_slices(&mut bufs, n),
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => (),
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(())
+}
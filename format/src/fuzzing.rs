@@ -0,0 +1,80 @@
+use crate::{
+    values::{Binary, LongString, Sha1, ShortString, Str},
+    Key, Map, Result, Value,
+};
+use arbitrary::{Arbitrary, Unstructured};
+
+impl<'a> Arbitrary<'a> for Key {
+    fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<Self> {
+        Ok(Self(u.arbitrary()?))
+    }
+}
+
+impl<'a> Arbitrary<'a> for Sha1 {
+    fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<Self> {
+        Ok(Self(u.arbitrary()?))
+    }
+}
+
+impl<'a> Arbitrary<'a> for Binary {
+    fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<Self> {
+        Ok(<&[u8]>::arbitrary(u)?.into())
+    }
+}
+
+impl<'a> Arbitrary<'a> for Value {
+    fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<Self> {
+        Ok(match u.int_in_range(0..=10)? {
+            0 => Value::U8(u.arbitrary()?),
+            1 => Value::U16(u.arbitrary()?),
+            2 => Value::U32(u.arbitrary()?),
+            3 => Value::U64(u.arbitrary()?),
+            4 => Value::ShortString(ShortString::new_unchecked(string(u, ShortString::MAX_LEN)?)),
+            5 => Value::LongString(LongString::new_unchecked(string(u, LongString::MAX_LEN)?)),
+            6 => Value::Binary(u.arbitrary()?),
+            7 => Value::Bool(u.arbitrary()?),
+            // NaN never compares equal, which would break round trip properties
+            8 => Value::Float(match f32::arbitrary(u)? {
+                f if f.is_nan() => 0.0,
+                f => f,
+            }),
+            9 => Value::Sha1(u.arbitrary()?),
+            _ => Value::LongBinary(<&[u8]>::arbitrary(u)?.into()),
+        })
+    }
+}
+
+impl<'a> Arbitrary<'a> for Map {
+    fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<Self> {
+        let mut map = Map::new();
+        for entry in u.arbitrary_iter::<(Key, Value)>()? {
+            let (k, v) = entry?;
+            map.insert(k, v);
+        }
+        Ok(map)
+    }
+}
+
+// Strings are truncated on a character boundary to fit their length prefix
+pub fn string(u: &mut Unstructured<'_>, max_len: usize) -> arbitrary::Result<Str> {
+    let mut s = String::arbitrary(u)?;
+    if s.len() > max_len {
+        let mut len = max_len;
+        while !s.is_char_boundary(len) {
+            len -= 1;
+        }
+        s.truncate(len);
+    }
+    Ok(s.into())
+}
+
+impl Map {
+    pub fn roundtrips(&self) -> Result<bool> {
+        let mut buffer = Vec::new();
+        self.write(&mut buffer)?;
+
+        let mut map = Map::new();
+        map.read(buffer.as_slice())?;
+        Ok(map == *self)
+    }
+}
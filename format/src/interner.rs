@@ -0,0 +1,37 @@
+use crate::values::Str;
+use fnv::FnvBuildHasher;
+use std::{
+    collections::HashSet,
+    sync::{Arc, Mutex},
+};
+
+#[derive(Debug, Clone, Default)]
+pub struct Interner(Arc<Mutex<HashSet<Arc<str>, FnvBuildHasher>>>);
+
+impl Interner {
+    #[inline]
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn intern(&self, s: &str) -> Str {
+        let mut strings = self.0.lock().unwrap();
+        if let Some(interned) = strings.get(s) {
+            return interned.clone().into();
+        }
+
+        let interned: Arc<str> = s.into();
+        strings.insert(interned.clone());
+        interned.into()
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.0.lock().unwrap().len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
@@ -1,19 +1,70 @@
+mod body;
+mod borrowed;
+pub mod debug;
+mod diff;
 pub mod error;
 pub mod ext;
+#[cfg(feature = "arbitrary")]
+pub mod fuzzing;
+mod interner;
 mod map;
+mod options;
+mod spill;
+#[cfg(any(feature = "sqlx", feature = "diesel"))]
+mod sql;
+#[cfg(feature = "proptest")]
+pub mod strategy;
+mod typed;
 pub mod values;
+mod warning;
 
-pub use map::Map;
+pub use crate::{
+    body::RawBody,
+    borrowed::{MapRef, MapRefIter, ValueRef},
+    diff::{MapDiff, MergePolicy},
+    interner::Interner,
+    map::{Map, MapEncoding},
+    options::ReadOptions,
+    spill::{Spill, SpilledBinary},
+    typed::{TypedEntry, TypedValue},
+    warning::Warning,
+};
 
-use crate::{error::Error, values::Sha1};
+use crate::{
+    error::Error,
+    values::{Binary, LongBinary, LongString, Sha1, ShortString},
+};
 use derive_more::{Deref, DerefMut, From};
-use std::hash::{Hash, Hasher};
+use std::{
+    cmp::Ordering,
+    fmt,
+    hash::{Hash, Hasher},
+    ops::Range,
+};
 
 pub type Result<T> = std::result::Result<T, Error>;
 
+const PREALLOCATION_LIMIT: usize = 64 * 1024;
+
+// Core fields only use the start of these ranges, the rest is kept for future fields
+pub const PLAYLIST_RESERVED_KEYS: Range<u32> = 0..16;
+pub const BEATMAP_RESERVED_KEYS: Range<u32> = 0..16;
+
 #[derive(Debug, Copy, Clone, Deref, DerefMut, From)]
+#[cfg_attr(
+    feature = "diesel",
+    derive(diesel::expression::AsExpression, diesel::deserialize::FromSqlRow),
+    diesel(sql_type = diesel::sql_types::BigInt)
+)]
 pub struct Key(u32);
 
+impl Key {
+    #[inline]
+    pub fn is_reserved(self, reserved: &Range<u32>) -> bool {
+        reserved.contains(&self.0)
+    }
+}
+
 impl PartialEq for Key {
     #[inline]
     fn eq(&self, other: &Self) -> bool {
@@ -22,6 +73,19 @@ impl PartialEq for Key {
 }
 impl Eq for Key {}
 
+impl PartialOrd for Key {
+    #[inline]
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+impl Ord for Key {
+    #[inline]
+    fn cmp(&self, other: &Self) -> Ordering {
+        (**self).cmp(&**other)
+    }
+}
+
 impl Hash for Key {
     #[inline]
     fn hash<H: Hasher>(&self, state: &mut H) {
@@ -29,47 +93,205 @@ impl Hash for Key {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, From)]
+#[derive(Clone, PartialEq, From)]
 pub enum Value {
     U8(u8),
     U16(u16),
     U32(u32),
     U64(u64),
-    #[from(ignore)]
-    ShortString(String),
-    LongString(String),
-    Binary(Vec<u8>),
+    ShortString(ShortString),
+    LongString(LongString),
+    Binary(Binary),
     Bool(bool),
     Float(f32),
     Sha1(Sha1),
+    LongBinary(LongBinary),
+}
+
+// Plain strings become long strings, but still need to fit in one
+impl std::convert::TryFrom<&str> for Value {
+    type Error = Error;
+
+    #[inline]
+    fn try_from(s: &str) -> Result<Self> {
+        LongString::new(s).map(Self::LongString)
+    }
+}
+
+impl std::convert::TryFrom<String> for Value {
+    type Error = Error;
+
+    #[inline]
+    fn try_from(s: String) -> Result<Self> {
+        LongString::new(s).map(Self::LongString)
+    }
+}
+
+impl std::convert::From<Vec<u8>> for Value {
+    fn from(v: Vec<u8>) -> Self {
+        Self::Binary(v.into())
+    }
 }
 
-impl std::convert::From<&str> for Value {
-    fn from(s: &str) -> Self {
-        Self::LongString(s.to_owned())
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::U8(v) => fmt::Display::fmt(v, f),
+            Value::U16(v) => fmt::Display::fmt(v, f),
+            Value::U32(v) => fmt::Display::fmt(v, f),
+            Value::U64(v) => fmt::Display::fmt(v, f),
+            Value::ShortString(v) => fmt::Debug::fmt(&**v, f),
+            Value::LongString(v) => fmt::Debug::fmt(&**v, f),
+            Value::Binary(v) => fmt::Display::fmt(v, f),
+            Value::Bool(v) => fmt::Display::fmt(v, f),
+            Value::Float(v) => fmt::Display::fmt(v, f),
+            Value::Sha1(v) => fmt::Display::fmt(v, f),
+            Value::LongBinary(v) => fmt::Display::fmt(v, f),
+        }
+    }
+}
+
+// The alternate form is a single line using the display representation
+impl fmt::Debug for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = self.type_name();
+        if f.alternate() {
+            return write!(f, "{}({})", name, self);
+        }
+
+        let mut tuple = f.debug_tuple(name);
+        match self {
+            Value::U8(v) => tuple.field(v),
+            Value::U16(v) => tuple.field(v),
+            Value::U32(v) => tuple.field(v),
+            Value::U64(v) => tuple.field(v),
+            Value::ShortString(v) => tuple.field(v),
+            Value::LongString(v) => tuple.field(v),
+            Value::Binary(v) => tuple.field(v),
+            Value::Bool(v) => tuple.field(v),
+            Value::Float(v) => tuple.field(v),
+            Value::Sha1(v) => tuple.field(v),
+            Value::LongBinary(v) => tuple.field(v),
+        };
+        tuple.finish()
     }
 }
 
 impl Value {
-    fn data_type(&self) -> u8 {
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::U8(_) => "U8",
+            Value::U16(_) => "U16",
+            Value::U32(_) => "U32",
+            Value::U64(_) => "U64",
+            Value::ShortString(_) => "ShortString",
+            Value::LongString(_) => "LongString",
+            Value::Binary(_) => "Binary",
+            Value::Bool(_) => "Bool",
+            Value::Float(_) => "Float",
+            Value::Sha1(_) => "Sha1",
+            Value::LongBinary(_) => "LongBinary",
+        }
+    }
+
+    // Long binaries kept in files have nothing to borrow
+    pub fn to_ref(&self) -> Option<ValueRef<'_>> {
+        Some(match self {
+            Value::U8(v) => ValueRef::U8(*v),
+            Value::U16(v) => ValueRef::U16(*v),
+            Value::U32(v) => ValueRef::U32(*v),
+            Value::U64(v) => ValueRef::U64(*v),
+            Value::ShortString(v) => ValueRef::ShortString(v),
+            Value::LongString(v) => ValueRef::LongString(v),
+            Value::Binary(v) => ValueRef::binary(v),
+            Value::Bool(v) => ValueRef::Bool(*v),
+            Value::Float(v) => ValueRef::Float(*v),
+            Value::Sha1(v) => ValueRef::Sha1(*v),
+            Value::LongBinary(v) => ValueRef::LongBinary(v.as_slice()?),
+        })
+    }
+
+    // Either kind of string
+    #[inline]
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::ShortString(v) => Some(v),
+            Value::LongString(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    #[inline]
+    pub fn to_u8(&self) -> Option<u8> {
+        match *self {
+            Value::U8(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    // Narrower unsigned integers are only accepted when widening
+    pub fn to_u16(&self, widen: bool) -> Option<u16> {
+        match *self {
+            Value::U16(v) => Some(v),
+            Value::U8(v) if widen => Some(v.into()),
+            _ => None,
+        }
+    }
+
+    pub fn to_u32(&self, widen: bool) -> Option<u32> {
+        match *self {
+            Value::U32(v) => Some(v),
+            Value::U8(_) | Value::U16(_) if widen => self.to_u16(true).map(Into::into),
+            _ => None,
+        }
+    }
+
+    pub fn to_u64(&self, widen: bool) -> Option<u64> {
+        match *self {
+            Value::U64(v) => Some(v),
+            Value::U8(_) | Value::U16(_) | Value::U32(_) if widen => {
+                self.to_u32(true).map(Into::into)
+            }
+            _ => None,
+        }
+    }
+
+    pub fn heap_size(&self) -> usize {
         match self {
-            Value::U8(_) => 0,
-            Value::U16(_) => 1,
-            Value::U32(_) => 2,
-            Value::U64(_) => 3,
-            Value::ShortString(_) => 4,
-            Value::LongString(_) => 5,
-            Value::Binary(_) => 6,
-            Value::Bool(_) => 7,
-            Value::Float(_) => 8,
-            Value::Sha1(_) => 9,
+            Value::ShortString(v) => v.heap_size(),
+            Value::LongString(v) => v.heap_size(),
+            Value::Binary(v) => v.heap_size(),
+            Value::LongBinary(v) => v.heap_size(),
+            _ => 0,
         }
     }
+
+    pub fn encoded_len(&self) -> usize {
+        4 + 1
+            + match self {
+                Value::U8(_) => 1,
+                Value::U16(_) => 2,
+                Value::U32(_) => 4,
+                Value::U64(_) => 8,
+                Value::ShortString(v) => 1 + v.len(),
+                Value::LongString(v) => 2 + v.len(),
+                Value::Binary(v) => 4 + v.len(),
+                Value::Bool(_) => 1,
+                Value::Float(_) => 4,
+                Value::Sha1(_) => 20,
+                Value::LongBinary(v) => 8 + v.len() as usize,
+            }
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::{values::Sha1, Map, Value};
+    use crate::{
+        values::{Binary, LongBinary, LongString, Sha1, ShortString},
+        Interner, Key, Map, MapEncoding, MapRef, MergePolicy, RawBody, ReadOptions, Spill, Value,
+        Warning,
+    };
+    use std::convert::TryFrom;
 
     #[test]
     fn write_and_read() {
@@ -78,8 +300,11 @@ mod tests {
         old.insert(1, 1u16);
         old.insert(2, 2u32);
         old.insert(3, 3u64);
-        old.insert(4, Value::ShortString("short string".to_owned()));
-        old.insert(5, "long string");
+        old.insert(
+            4,
+            Value::ShortString(ShortString::new("short string").unwrap()),
+        );
+        old.insert(5, LongString::new("long string").unwrap());
         old.insert(6, vec![6, 6, 6, 6, 6, 6]);
         old.insert(7, true);
         old.insert(8, 8.8);
@@ -94,5 +319,615 @@ mod tests {
         new.read(buffer.as_slice()).unwrap();
 
         assert_eq!(old, new);
+
+        let mut input = buffer.as_slice();
+        let borrowed = MapRef::parse(&mut input).unwrap();
+        assert!(input.is_empty());
+        assert_eq!(borrowed.len(), len);
+        assert_eq!(old, borrowed.to_owned());
+    }
+
+    #[test]
+    fn counted() {
+        let mut old = Map::new();
+        old.insert(0, 0u8);
+        old.insert(1, LongString::new("one").unwrap());
+
+        let mut buffer = Vec::new();
+        old.write_with_encoding(&mut buffer, MapEncoding::Counted)
+            .unwrap();
+        assert_eq!(
+            buffer.len(),
+            old.encoded_len_with_encoding(MapEncoding::Counted)
+        );
+
+        let options = ReadOptions {
+            encoding: MapEncoding::Counted,
+            ..Default::default()
+        };
+        let mut new = Map::new();
+        new.read_with_options(buffer.as_slice(), &options, &mut Vec::new())
+            .unwrap();
+        assert_eq!(old, new);
+        let mut input = buffer.as_slice();
+        let borrowed = MapRef::parse_with_encoding(&mut input, MapEncoding::Counted).unwrap();
+        assert!(input.is_empty());
+        assert_eq!(borrowed.to_owned(), old);
+
+        // Two maps back to back, the first one skipped without being decoded
+        let mut twice = buffer.clone();
+        twice.extend_from_slice(&buffer);
+        Map::skip(twice.as_slice(), MapEncoding::Counted).unwrap();
+        let mut input = twice.as_slice();
+        MapRef::skip(&mut input, MapEncoding::Counted).unwrap();
+        assert_eq!(input, buffer.as_slice());
+
+        buffer[4] = 3;
+        assert!(matches!(
+            Map::new().read_with_options(buffer.as_slice(), &options, &mut Vec::new()),
+            Err(crate::error::Error::EntryCountMismatch {
+                expected: 3,
+                actual: 2
+            })
+        ));
+        assert!(matches!(
+            MapRef::parse_with_encoding(&mut buffer.as_slice(), MapEncoding::Counted),
+            Err(crate::error::Error::EntryCountMismatch {
+                expected: 3,
+                actual: 2
+            })
+        ));
+        assert!(Map::skip(&buffer[..buffer.len() - 1], MapEncoding::Counted).is_err());
+    }
+
+    #[test]
+    fn checksummed() {
+        let mut old = Map::new();
+        old.insert(0, 0u8);
+        old.insert(1, LongString::new("one").unwrap());
+        old.insert(16, 16u8);
+
+        let mut buffer = Vec::new();
+        old.write_sorted_with_encoding(&mut buffer, MapEncoding::Checksummed)
+            .unwrap();
+        assert_eq!(
+            buffer.len(),
+            old.encoded_len_with_encoding(MapEncoding::Checksummed)
+        );
+
+        let mut options = ReadOptions {
+            encoding: MapEncoding::Checksummed,
+            ..Default::default()
+        };
+        let mut new = Map::new();
+        new.read_with_options(buffer.as_slice(), &options, &mut Vec::new())
+            .unwrap();
+        assert_eq!(old, new);
+        let borrowed =
+            MapRef::parse_with_encoding(&mut buffer.as_slice(), MapEncoding::Checksummed).unwrap();
+        assert_eq!(borrowed.to_owned(), old);
+
+        // The value of the first entry, past its marker, length, key and type
+        buffer[4 + 4 + 4 + 5] ^= 0xff;
+        assert!(matches!(
+            Map::new().read_with_options(buffer.as_slice(), &options, &mut Vec::new()),
+            Err(crate::error::Error::ChecksumMismatch(Key(0)))
+        ));
+        assert!(matches!(
+            MapRef::parse_with_encoding(&mut buffer.as_slice(), MapEncoding::Checksummed),
+            Err(crate::error::Error::ChecksumMismatch(Key(0)))
+        ));
+
+        options.resync = true;
+        let mut new = Map::new();
+        let mut warnings = Vec::new();
+        new.read_with_options(buffer.as_slice(), &options, &mut warnings)
+            .unwrap();
+        // The entry found right after the corrupted one sets a reserved key, so it's dropped too
+        assert_eq!(new.get(0), None);
+        assert_eq!(new.get(1), None);
+        assert_eq!(new.get(16), old.get(16));
+        assert_eq!(
+            warnings,
+            vec![Warning::CorruptEntries {
+                offset: 0,
+                len: 40,
+                raw: None
+            }]
+        );
+
+        options.capture_discarded = true;
+        let mut warnings = Vec::new();
+        Map::new()
+            .read_with_options(buffer.as_slice(), &options, &mut warnings)
+            .unwrap();
+        assert_eq!(
+            warnings,
+            vec![Warning::CorruptEntries {
+                offset: 0,
+                len: 40,
+                raw: Some(buffer[4..44].to_vec())
+            }]
+        );
+
+        // Garbage between entries is skipped up to the next marker
+        let mut old = Map::new();
+        old.insert(16, 16u8);
+        old.insert(17, 17u8);
+        let mut buffer = Vec::new();
+        old.write_sorted_with_encoding(&mut buffer, MapEncoding::Checksummed)
+            .unwrap();
+        let garbage: Vec<u8> = (0..10_000).map(|i| (i % 251) as u8).collect();
+        buffer.splice(4 + 18..4 + 18, garbage.iter().copied());
+        let len = buffer.len() as u32 - 4;
+        buffer[..4].copy_from_slice(&len.to_le_bytes());
+        options.capture_discarded = false;
+        let mut new = Map::new();
+        let mut warnings = Vec::new();
+        new.read_with_options(buffer.as_slice(), &options, &mut warnings)
+            .unwrap();
+        assert_eq!(new, old);
+        assert_eq!(
+            warnings,
+            vec![Warning::CorruptEntries {
+                offset: 18,
+                len: garbage.len(),
+                raw: None
+            }]
+        );
+        options.resync = false;
+        assert!(matches!(
+            Map::new().read_with_options(buffer.as_slice(), &options, &mut Vec::new()),
+            Err(crate::error::Error::MissingEntryMarker(18))
+        ));
+
+        // Entry lengths are checked against the limits before anything is read
+        let mut buffer = u32::MAX.to_le_bytes().to_vec();
+        buffer.extend_from_slice(&crate::map::ENTRY_MARKER);
+        buffer.extend_from_slice(&0x1000_0000_u32.to_le_bytes());
+        options.max_binary_len = Some(64);
+        assert!(matches!(
+            Map::new().read_with_options(buffer.as_slice(), &options, &mut Vec::new()),
+            Err(crate::error::Error::BinaryTooLarge(0x1000_0000))
+        ));
+    }
+
+    #[test]
+    fn diff_and_merge() {
+        let mut ours = Map::new();
+        ours.insert(0, 0u8);
+        ours.insert(1, 1u8);
+        let mut theirs = Map::new();
+        theirs.insert(1, 2u8);
+        theirs.insert(2, 2u8);
+
+        let diff = ours.diff(&theirs);
+        assert_eq!(diff.removed, vec![(0.into(), Value::U8(0))]);
+        assert_eq!(diff.added, vec![(2.into(), Value::U8(2))]);
+        assert_eq!(diff.changed, vec![(1.into(), Value::U8(1), Value::U8(2))]);
+        assert!(ours.diff(&ours).is_empty());
+
+        let mut merged = ours.clone();
+        assert_eq!(
+            merged.merge(theirs.clone(), MergePolicy::KeepOurs),
+            vec![1.into()]
+        );
+        assert_eq!(merged.get(1), Some(&Value::U8(1)));
+        assert_eq!(merged.len(), 3);
+
+        ours.merge(theirs, MergePolicy::KeepTheirs);
+        assert_eq!(ours.get(1), Some(&Value::U8(2)));
+    }
+
+    #[test]
+    fn bulk_removal() {
+        let mut map = Map::new();
+        for k in 0..8u32 {
+            map.insert(k, k);
+        }
+
+        assert_eq!(map.remove_many(vec![0, 1, 16]), 2);
+        map.retain(|k, _| *k < 6);
+        assert_eq!(map.len(), 4);
+        assert_eq!(map.drain().count(), 4);
+        assert!(map.is_empty());
+    }
+
+    #[test]
+    fn display() {
+        assert_eq!(
+            Value::try_from("curator").unwrap().to_string(),
+            "\"curator\""
+        );
+        assert_eq!(Value::Sha1(Sha1([0xab; 20])).to_string(), "ab".repeat(20));
+        assert_eq!(
+            Value::from(vec![0; 1024]).to_string(),
+            format!("<1024 bytes:{} …>", " 00".repeat(16))
+        );
+        assert_eq!(format!("{:#?}", Value::U8(1)), "U8(1)");
+        assert_eq!(format!("{:?}", Value::from(vec![1, 2])), "Binary([1, 2])");
+    }
+
+    #[test]
+    fn overlaid() {
+        use crate::ValueRef;
+
+        let mut map = Map::new();
+        map.insert(0, 1u8);
+        map.insert(1, 2u16);
+        map.insert(2, LongString::new("kept").unwrap());
+        let overlay = [
+            (0.into(), Some(ValueRef::short_string("over").unwrap())),
+            (1.into(), None),
+            (3.into(), Some(ValueRef::Binary(&[1, 2, 3]))),
+        ];
+
+        let mut expected = map.clone();
+        expected.insert(0, ShortString::new("over").unwrap());
+        expected.remove(1);
+        expected.insert(3, vec![1, 2, 3]);
+        for &encoding in &[MapEncoding::Length, MapEncoding::Checksummed] {
+            let mut buffer = Vec::new();
+            map.write_overlaid(&overlay, &mut buffer, encoding, true)
+                .unwrap();
+            assert_eq!(buffer.len(), map.encoded_len_overlaid(&overlay, encoding));
+            let mut sorted = Vec::new();
+            expected
+                .write_sorted_with_encoding(&mut sorted, encoding)
+                .unwrap();
+            assert_eq!(buffer, sorted);
+        }
+        assert!(ValueRef::short_string(&"a".repeat(256)).is_err());
+    }
+
+    #[test]
+    fn sha1_hex() {
+        use crate::error::ErrorKind;
+
+        let hash = Sha1([0xab; 20]);
+        assert_eq!(Sha1::from_hex(&hash.to_string()), Some(hash));
+        assert_eq!("AB".repeat(20).parse::<Sha1>().unwrap(), hash);
+        assert_eq!(Sha1::from_hex(&"ab".repeat(19)), None);
+        assert_eq!(Sha1::from_hex(&format!("+b{}", "ab".repeat(19))), None);
+        assert_eq!(
+            "zz".repeat(20).parse::<Sha1>().unwrap_err().kind(),
+            ErrorKind::InvalidSha1
+        );
+    }
+
+    #[test]
+    fn string_limits() {
+        use crate::error::ErrorKind;
+
+        let long = "a".repeat(256);
+        assert_eq!(
+            ShortString::new(long.as_str()).unwrap_err().kind(),
+            ErrorKind::StringTooLong
+        );
+        let long = LongString::new(long).unwrap();
+        assert_eq!(long.len(), 256);
+        assert!(Value::try_from("a".repeat(LongString::MAX_LEN + 1)).is_err());
+
+        let mut buffer = Vec::new();
+        let mut map = Map::new();
+        map.insert(0, long);
+        map.write(&mut buffer).unwrap();
+        let mut input = buffer.as_slice();
+        assert_eq!(MapRef::parse(&mut input).unwrap().to_owned(), map);
+    }
+
+    #[test]
+    fn widening() {
+        let mut map = Map::new();
+        map.insert(0, 1u8);
+        map.insert(1, 2u16);
+        map.insert(2, 3u32);
+
+        assert_eq!(map.get_u8(0), Some(1));
+        assert_eq!(map.get_u32(0, false), None);
+        assert_eq!(map.get_u32(0, true), Some(1));
+        assert_eq!(map.get_u32(1, true), Some(2));
+        assert_eq!(map.get_u64(2, true), Some(3));
+        assert_eq!(map.get_u16(2, true), None);
+    }
+
+    #[test]
+    fn typed_entry() {
+        let mut map = Map::new();
+        *map.entry_typed::<u32>(0).or_insert(0).unwrap() += 1;
+        map.entry_typed::<u32>(0)
+            .and_modify(|v| *v += 1)
+            .unwrap()
+            .or_default()
+            .unwrap();
+        assert_eq!(map.get(0), Some(&Value::U32(2)));
+
+        map.insert(1, true);
+        assert!(matches!(
+            map.entry_typed::<u32>(1).or_insert(0),
+            Err(crate::error::Error::TypeMismatch {
+                expected: "U32",
+                found: "Bool"
+            })
+        ));
+    }
+
+    #[test]
+    fn iter_sorted() {
+        let mut map = Map::new();
+        for k in (0..32u32).rev() {
+            map.insert(k, k);
+        }
+
+        let keys: Vec<u32> = map.iter_sorted().map(|(k, _)| **k).collect();
+        assert_eq!(keys, (0..32).collect::<Vec<_>>());
+        assert!(format!("{:?}", map).starts_with("{Key(0): U32(0), Key(1): U32(1),"));
+    }
+
+    #[test]
+    fn lossy_utf8() {
+        let mut old = Map::new();
+        old.insert(0, Value::ShortString(ShortString::new("abc").unwrap()));
+
+        let mut buffer = Vec::new();
+        old.write(&mut buffer).unwrap();
+        let len = buffer.len();
+        buffer[len - 2] = 0xff;
+
+        let mut new = Map::new();
+        assert!(new.read(buffer.as_slice()).is_err());
+
+        let options = ReadOptions {
+            lossy_utf8: true,
+            ..Default::default()
+        };
+        let mut warnings = Vec::new();
+        new.read_with_options(buffer.as_slice(), &options, &mut warnings)
+            .unwrap();
+        assert_eq!(
+            new.get(0),
+            Some(&Value::ShortString(ShortString::new("a\u{fffd}c").unwrap()))
+        );
+        assert_eq!(
+            warnings,
+            vec![Warning::InvalidUtf8 {
+                key: 0.into(),
+                raw: None
+            }]
+        );
+
+        // Each invalid byte becomes a three byte replacement character
+        let mut buffer = Vec::new();
+        let mut old = Map::new();
+        old.insert(0, ShortString::new("a".repeat(255)).unwrap());
+        old.write(&mut buffer).unwrap();
+        let start = buffer.len() - 255;
+        for b in &mut buffer[start..start + 100] {
+            *b = 0xff;
+        }
+        let mut warnings = Vec::new();
+        new.read_with_options(buffer.as_slice(), &options, &mut warnings)
+            .unwrap();
+        let s = new.get(0).and_then(Value::as_str).unwrap();
+        assert!(s.len() <= ShortString::MAX_LEN);
+        assert!(s.chars().all(|c| c == '\u{fffd}'));
+        assert_eq!(
+            warnings[1],
+            Warning::TruncatedString {
+                key: 0.into(),
+                len: 100 * 3 + 155
+            }
+        );
+        let mut rewritten = Vec::new();
+        new.write(&mut rewritten).unwrap();
+    }
+
+    #[test]
+    fn interned() {
+        let mut old = Map::new();
+        old.insert(0, LongString::new("curator").unwrap());
+        old.insert(1, LongString::new("curator").unwrap());
+
+        let mut buffer = Vec::new();
+        old.write(&mut buffer).unwrap();
+
+        let interner = Interner::new();
+        let options = ReadOptions {
+            interner: Some(interner.clone()),
+            ..Default::default()
+        };
+        let mut new = Map::new();
+        new.read_with_options(buffer.as_slice(), &options, &mut Vec::new())
+            .unwrap();
+
+        assert_eq!(old, new);
+        assert_eq!(interner.len(), 1);
+        match (new.get(0), new.get(1)) {
+            (Some(Value::LongString(a)), Some(Value::LongString(b))) => {
+                assert_eq!(a.as_ptr(), b.as_ptr());
+                assert!(a.clone().into_str().is_shared());
+            }
+            _ => unreachable!(),
+        }
+
+        // Strings stay owned without an interner
+        let mut new = Map::new();
+        new.read(buffer.as_slice()).unwrap();
+        match new.get(0) {
+            Some(Value::LongString(s)) => assert!(!s.clone().into_str().is_shared()),
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn long_binary() {
+        let dir = tempfile::tempdir().unwrap();
+        let bytes: Vec<u8> = (0..=255).cycle().take(1000).collect();
+
+        let mut old = Map::new();
+        old.insert(0, LongBinary::from(bytes.clone()));
+        let mut buffer = Vec::new();
+        old.write(&mut buffer).unwrap();
+        assert_eq!(buffer.len(), old.encoded_len());
+
+        let options = ReadOptions {
+            spill: Some(Spill::new(100, dir.path())),
+            ..Default::default()
+        };
+        let mut new = Map::new();
+        new.read_with_options(buffer.as_slice(), &options, &mut Vec::new())
+            .unwrap();
+        let spilled = match new.get(0) {
+            Some(Value::LongBinary(v)) => v.clone(),
+            _ => unreachable!(),
+        };
+        assert!(spilled.as_slice().is_none());
+        assert_eq!(spilled.read_to_vec().unwrap(), bytes);
+        assert_eq!(old, new);
+
+        let mut rewritten = Vec::new();
+        new.write(&mut rewritten).unwrap();
+        assert_eq!(rewritten, buffer);
+        assert_eq!(
+            MapRef::parse(&mut buffer.as_slice()).unwrap().to_owned(),
+            old
+        );
+
+        drop((new, spilled));
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn field_limits() {
+        let mut map = Map::new();
+        map.insert(4, Binary::from(vec![0; 16]));
+        let mut buffer = Vec::new();
+        map.write(&mut buffer).unwrap();
+
+        let options = ReadOptions {
+            max_field_lens: vec![(Key(4), 8)],
+            ..Default::default()
+        };
+        // Rejected from the length prefix alone, the 4 GB value is never read
+        buffer[9..13].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(matches!(
+            Map::new().read_with_options(buffer.as_slice(), &options, &mut Vec::new()),
+            Err(crate::error::Error::FieldTooLarge { key: Key(4), len }) if len == u32::MAX as usize
+        ));
+
+        let options = ReadOptions {
+            max_field_lens: vec![(Key(4), 16), (Key(5), 0)],
+            ..Default::default()
+        };
+        buffer[9..13].copy_from_slice(&16u32.to_le_bytes());
+        let mut new = Map::new();
+        new.read_with_options(buffer.as_slice(), &options, &mut Vec::new())
+            .unwrap();
+        assert_eq!(new, map);
+    }
+
+    #[test]
+    fn hexdump() {
+        let mut map = Map::new();
+        map.insert(1, 2u16);
+        map.insert(0, ShortString::new("hi").unwrap());
+
+        let expected = "\
+00000000  0f 00 00 00                                      map, 15 bytes
+00000004  00 00 00 00 | 04 | 02                            key 0, ShortString, 2 bytes
+0000000a  68 69
+0000000c  01 00 00 00 | 01                                 key 1, U16 = 2
+00000011  02 00
+";
+        assert_eq!(crate::debug::hexdump(&map), expected);
+    }
+
+    #[test]
+    fn raw_body() {
+        let mut body = RawBody::default();
+        body.header
+            .insert(0, ShortString::new("test playlist").unwrap());
+        body.header.insert(1, ShortString::new("me").unwrap());
+        let mut map = Map::new();
+        map.insert(0, 1u8);
+        map.insert(1, 1_600_000_000u64);
+        body.maps.push(map);
+
+        let mut buffer = Vec::new();
+        body.write(&mut buffer, MapEncoding::Length).unwrap();
+        let read = RawBody::read(buffer.as_slice(), &Default::default(), &mut Vec::new()).unwrap();
+        assert_eq!(read, body);
+        assert_eq!(read.title(), Some("test playlist"));
+        assert_eq!(read.author(), Some("me"));
+        assert_eq!(read.date_added(0), Some(1_600_000_000));
+        assert_eq!(read.date_added(1), None);
+    }
+
+    #[cfg(feature = "sqlx")]
+    #[test]
+    fn sqlx_roundtrip() {
+        use crate::Key;
+        use sqlx::{
+            any::{AnyArguments, AnyValue},
+            encode::IsNull,
+            Any, Decode, Encode,
+        };
+
+        fn roundtrip<T>(old: T) -> T
+        where
+            T: for<'q> Encode<'q, Any> + for<'r> Decode<'r, Any>,
+        {
+            let mut arguments = AnyArguments::default();
+            assert!(matches!(
+                old.encode_by_ref(&mut arguments.values),
+                Ok(IsNull::No)
+            ));
+            let value = AnyValue {
+                kind: arguments.values.0.pop().unwrap(),
+            };
+            T::decode(sqlx::Value::as_ref(&value)).unwrap()
+        }
+
+        assert_eq!(roundtrip(Sha1([7; 20])), Sha1([7; 20]));
+        assert_eq!(roundtrip(Key(u32::MAX)), Key(u32::MAX));
+    }
+
+    #[cfg(feature = "diesel")]
+    #[test]
+    fn diesel_roundtrip() {
+        use crate::Key;
+        use diesel::{
+            deserialize::FromSql,
+            pg::{Pg, PgMetadataLookup, PgTypeMetadata, PgValue},
+            query_builder::bind_collector::{BindCollector, RawBytesBindCollector},
+            serialize::ToSql,
+            sql_types::{BigInt, Binary},
+        };
+        use std::num::NonZeroU32;
+
+        // Both types have static OIDs, so nothing is ever looked up
+        struct NoLookup;
+        impl PgMetadataLookup for NoLookup {
+            fn lookup_type(&mut self, _: &str, _: Option<&str>) -> PgTypeMetadata {
+                unreachable!()
+            }
+        }
+
+        fn roundtrip<T, ST>(old: &T, oid: u32) -> T
+        where
+            T: ToSql<ST, Pg> + FromSql<ST, Pg>,
+            Pg: diesel::sql_types::HasSqlType<ST>,
+        {
+            let mut collector = RawBytesBindCollector::<Pg>::new();
+            collector
+                .push_bound_value::<ST, _>(old, &mut NoLookup)
+                .unwrap();
+            let bytes = collector.binds.pop().unwrap().unwrap();
+            let oid = NonZeroU32::new(oid).unwrap();
+            T::from_sql(PgValue::new(&bytes, &oid)).unwrap()
+        }
+
+        assert_eq!(roundtrip::<_, Binary>(&Sha1([7; 20]), 17), Sha1([7; 20]));
+        assert_eq!(roundtrip::<_, BigInt>(&Key(u32::MAX), 20), Key(u32::MAX));
     }
 }
@@ -1,48 +1,306 @@
 use crate::{
+    error::Error,
     ext::{ReadExt, WriteExt},
-    Key, Result, Value,
+    Key, ReadOptions, Result, Value, ValueRef, Warning, BEATMAP_RESERVED_KEYS,
+    PLAYLIST_RESERVED_KEYS, PREALLOCATION_LIMIT,
 };
-use byteorder::{ReadBytesExt, WriteBytesExt, LE};
+use byteorder::{ByteOrder, ReadBytesExt, WriteBytesExt, LE};
 use derive_more::{Deref, DerefMut, From};
 use fnv::FnvBuildHasher;
 use std::{
-    collections::hash_map::{Entry, HashMap},
+    collections::hash_map::{Drain, Entry, HashMap},
     convert::TryInto,
-    io::{BufWriter, Read, Write},
+    fmt,
+    io::{self, Read, Write},
+    mem,
+    ops::Range,
+    vec,
 };
 
-#[derive(Debug, Clone, Deref, DerefMut, From)]
+const MIN_ENTRY_LEN: usize = 4 + 1 + 1;
+// The byte length only gives an upper bound on the entry count, so keep the hint small
+const CAPACITY_HINT_LIMIT: usize = 16;
+
+// Checksummed entries start with a marker a resynchronizing read can scan for, followed by the
+// entry length, the entry itself and a checksum of the length and entry
+pub(crate) const ENTRY_MARKER: [u8; 4] = [0xb1, 0x57, 0xe5, 0x17];
+pub(crate) const CHECKSUM_LEN: usize = 4;
+pub(crate) const ENTRY_OVERHEAD: usize = ENTRY_MARKER.len() + 4 + CHECKSUM_LEN;
+// Key, data type and the widest length prefix
+const MAX_KV_HEADER_LEN: usize = 4 + 1 + 8;
+const RESYNC_CHUNK_LEN: usize = 8 * 1024;
+
+// Counted maps also carry their entry count after the byte length,
+// checksummed maps frame every entry with a marker, its length and a checksum
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum MapEncoding {
+    Length,
+    Counted,
+    Checksummed,
+}
+
+impl Default for MapEncoding {
+    #[inline]
+    fn default() -> Self {
+        Self::Length
+    }
+}
+
+#[derive(Clone, Deref, DerefMut, From)]
 pub struct Map(HashMap<Key, Value, FnvBuildHasher>);
 
 impl Map {
-    pub fn read<R>(&mut self, mut reader: R) -> Result<()>
+    #[inline]
+    pub fn read<R>(&mut self, reader: R) -> Result<()>
+    where
+        R: Read,
+    {
+        self.read_with_options(reader, &Default::default(), &mut Vec::new())
+    }
+
+    pub fn read_with_options<R>(
+        &mut self,
+        mut reader: R,
+        options: &ReadOptions,
+        warnings: &mut Vec<Warning>,
+    ) -> Result<()>
     where
         R: Read,
     {
         let len = reader.read_u32::<LE>()? as usize;
+        let count = match options.encoding {
+            MapEncoding::Length => None,
+            MapEncoding::Counted => Some(reader.read_u32::<LE>()? as usize),
+            MapEncoding::Checksummed => {
+                // Entries are kept in memory until their checksum is verified and failing ones
+                // would leave stray files behind, so nothing gets spilled
+                let options = ReadOptions {
+                    spill: None,
+                    ..options.clone()
+                };
+                return self.read_checksummed(reader, len, &options, warnings);
+            }
+        };
+        match count {
+            Some(count) => self.reserve(count.min(len / MIN_ENTRY_LEN).min(PREALLOCATION_LIMIT)),
+            None => self.reserve((len / MIN_ENTRY_LEN).min(CAPACITY_HINT_LIMIT)),
+        }
+
         let mut i = 0;
+        let mut entries = 0;
         while i < len {
-            let (r, (k, v)) = reader.read_kv()?;
+            let (r, (k, v)) = reader.read_kv_with_options(options, warnings)?;
             i += r;
+            entries += 1;
             self.insert(k, v);
         }
+
+        match count {
+            Some(expected) if expected != entries => Err(Error::EntryCountMismatch {
+                expected,
+                actual: entries,
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    // Moves past a whole map using its length prefix alone, without decoding any entry
+    pub fn skip<R>(mut reader: R, encoding: MapEncoding) -> Result<()>
+    where
+        R: Read,
+    {
+        let len = reader.read_u32::<LE>()? as u64;
+        if encoding == MapEncoding::Counted {
+            reader.read_u32::<LE>()?;
+        }
+        if io::copy(&mut reader.take(len), &mut io::sink())? != len {
+            return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+        }
         Ok(())
     }
 
-    pub fn write<W>(&self, mut writer: W) -> Result<()>
+    // Corrupted entries are skipped up to the next entry marker. The first entry found that way
+    // can't be told apart from a marker that happens to appear in the corrupted data, so it's
+    // dropped as well if it would set one of the reserved keys.
+    fn read_checksummed<R>(
+        &mut self,
+        reader: R,
+        len: usize,
+        options: &ReadOptions,
+        warnings: &mut Vec<Warning>,
+    ) -> Result<()>
     where
-        W: Write,
+        R: Read,
     {
-        let mut buffer = BufWriter::new(Vec::with_capacity(self.len() * (4 + 1 + 1)));
-        let mut i = 0;
-        for (k, v) in self.iter() {
-            i += buffer.write_kv(*k, v)?;
+        self.reserve((len / (MIN_ENTRY_LEN + ENTRY_OVERHEAD)).min(CAPACITY_HINT_LIMIT));
+
+        let mut entries = Entries::new(reader, len);
+        let mut corrupt: Option<Warning> = None;
+        while entries.remaining() > 0 {
+            let recovered = corrupt.is_some();
+            match entries.read(options) {
+                Ok((k, v, entry_warnings)) if !(recovered && is_reserved(k)) => {
+                    warnings.extend(corrupt.take());
+                    warnings.extend(entry_warnings);
+                    self.insert(k, v);
+                    continue;
+                }
+                Ok(_) => (),
+                Err(e) if !options.resync => return Err(e),
+                Err(_) => (),
+            }
+
+            let offset = entries.offset;
+            let warning = corrupt.get_or_insert(Warning::CorruptEntries {
+                offset,
+                len: 0,
+                raw: options.capture_discarded.then(Vec::new),
+            });
+            if let Warning::CorruptEntries { len, raw, .. } = warning {
+                *len += entries.resync(raw.as_mut())?;
+            }
         }
-        writer.write_u32::<LE>(i.try_into()?)?;
-        writer.write_all(buffer.buffer())?;
+
+        warnings.extend(corrupt);
         Ok(())
     }
 
+    #[inline]
+    pub fn write<W>(&self, writer: W) -> Result<()>
+    where
+        W: Write,
+    {
+        self.write_with_encoding(writer, MapEncoding::Length)
+    }
+
+    #[inline]
+    pub fn write_with_encoding<W>(&self, writer: W, encoding: MapEncoding) -> Result<()>
+    where
+        W: Write,
+    {
+        write_entries(owned_entries(self.iter()), writer, encoding)
+    }
+
+    #[inline]
+    pub fn write_sorted<W>(&self, writer: W) -> Result<()>
+    where
+        W: Write,
+    {
+        self.write_sorted_with_encoding(writer, MapEncoding::Length)
+    }
+
+    #[inline]
+    pub fn write_sorted_with_encoding<W>(&self, writer: W, encoding: MapEncoding) -> Result<()>
+    where
+        W: Write,
+    {
+        write_entries(owned_entries(self.iter_sorted()), writer, encoding)
+    }
+
+    // Writes the map as if every entry of `overlay` was inserted into it, without cloning either.
+    // `None` leaves out the map's own entry for that key.
+    pub fn write_overlaid<W>(
+        &self,
+        overlay: &[(Key, Option<ValueRef<'_>>)],
+        writer: W,
+        encoding: MapEncoding,
+        sorted: bool,
+    ) -> Result<()>
+    where
+        W: Write,
+    {
+        let entries = self.overlaid(overlay);
+        if sorted {
+            let mut entries: Vec<_> = entries.collect();
+            entries.sort_unstable_by_key(|(k, _)| *k);
+            write_entries(entries.into_iter(), writer, encoding)
+        } else {
+            write_entries(entries, writer, encoding)
+        }
+    }
+
+    pub fn encoded_len_overlaid(
+        &self,
+        overlay: &[(Key, Option<ValueRef<'_>>)],
+        encoding: MapEncoding,
+    ) -> usize {
+        let (count, entries) = self
+            .overlaid(overlay)
+            .fold((0, 0), |(c, l), (_, v)| (c + 1, l + v.encoded_len()));
+        encoded_len(count, entries, encoding)
+    }
+
+    fn overlaid<'a>(
+        &'a self,
+        overlay: &'a [(Key, Option<ValueRef<'a>>)],
+    ) -> impl Iterator<Item = (Key, EntryRef<'a>)> + Clone {
+        let overlaid = move |k: &Key| overlay.iter().any(|(o, _)| o == k);
+        owned_entries(self.iter().filter(move |(k, _)| !overlaid(k))).chain(
+            overlay
+                .iter()
+                .filter_map(|(k, v)| v.map(|v| (*k, EntryRef::Borrowed(v)))),
+        )
+    }
+
+    pub fn iter_sorted(&self) -> vec::IntoIter<(&Key, &Value)> {
+        let mut entries: Vec<_> = self.iter().collect();
+        entries.sort_unstable_by_key(|(k, _)| **k);
+        entries.into_iter()
+    }
+
+    #[inline]
+    pub fn encoded_len(&self) -> usize {
+        self.encoded_len_with_encoding(MapEncoding::Length)
+    }
+
+    pub fn encoded_len_with_encoding(&self, encoding: MapEncoding) -> usize {
+        let entries = self.values().map(Value::encoded_len).sum::<usize>();
+        encoded_len(self.len(), entries, encoding)
+    }
+
+    pub fn reserved_keys<'a>(&'a self, reserved: &'a Range<u32>) -> impl Iterator<Item = Key> + 'a {
+        self.keys()
+            .copied()
+            .filter(move |k| k.is_reserved(reserved))
+    }
+
+    #[inline]
+    pub fn get_u8<K>(&self, key: K) -> Option<u8>
+    where
+        K: Into<Key>,
+    {
+        self.get(key).and_then(Value::to_u8)
+    }
+
+    #[inline]
+    pub fn get_u16<K>(&self, key: K, widen: bool) -> Option<u16>
+    where
+        K: Into<Key>,
+    {
+        self.get(key).and_then(|v| v.to_u16(widen))
+    }
+
+    #[inline]
+    pub fn get_u32<K>(&self, key: K, widen: bool) -> Option<u32>
+    where
+        K: Into<Key>,
+    {
+        self.get(key).and_then(|v| v.to_u32(widen))
+    }
+
+    #[inline]
+    pub fn get_u64<K>(&self, key: K, widen: bool) -> Option<u64>
+    where
+        K: Into<Key>,
+    {
+        self.get(key).and_then(|v| v.to_u64(widen))
+    }
+
+    pub fn heap_size(&self) -> usize {
+        self.capacity() * (mem::size_of::<(Key, Value)>() + 1)
+            + self.values().map(Value::heap_size).sum::<usize>()
+    }
+
     // HashMap overrides
 
     #[inline]
@@ -59,7 +317,7 @@ impl Map {
     }
 
     #[inline]
-    pub fn entry<K>(&mut self, key: K) -> Entry<Key, Value>
+    pub fn entry<K>(&mut self, key: K) -> Entry<'_, Key, Value>
     where
         K: Into<Key>,
     {
@@ -122,6 +380,291 @@ impl Map {
     {
         self.0.remove_entry(&key.into())
     }
+
+    #[inline]
+    pub fn retain<F>(&mut self, mut f: F)
+    where
+        F: FnMut(Key, &mut Value) -> bool,
+    {
+        self.0.retain(|k, v| f(*k, v))
+    }
+
+    #[inline]
+    pub fn drain(&mut self) -> Drain<'_, Key, Value> {
+        self.0.drain()
+    }
+
+    pub fn remove_many<I, K>(&mut self, keys: I) -> usize
+    where
+        I: IntoIterator<Item = K>,
+        K: Into<Key>,
+    {
+        keys.into_iter()
+            .filter_map(|k| self.0.remove(&k.into()))
+            .count()
+    }
+}
+
+// Entries of the map itself, or borrowed from elsewhere when writing overlaid maps
+#[derive(Copy, Clone)]
+enum EntryRef<'a> {
+    Owned(&'a Value),
+    Borrowed(ValueRef<'a>),
+}
+
+impl EntryRef<'_> {
+    #[inline]
+    fn encoded_len(&self) -> usize {
+        match self {
+            Self::Owned(v) => v.encoded_len(),
+            Self::Borrowed(v) => v.encoded_len(),
+        }
+    }
+
+    #[inline]
+    fn write<W>(&self, writer: &mut W, key: Key) -> Result<usize>
+    where
+        W: Write,
+    {
+        match *self {
+            Self::Owned(v) => writer.write_kv(key, v),
+            Self::Borrowed(v) => writer.write_kv_ref(key, v),
+        }
+    }
+}
+
+#[inline]
+fn owned_entries<'a, I>(entries: I) -> impl Iterator<Item = (Key, EntryRef<'a>)> + Clone
+where
+    I: Iterator<Item = (&'a Key, &'a Value)> + Clone,
+{
+    entries.map(|(k, v)| (*k, EntryRef::Owned(v)))
+}
+
+#[inline]
+fn encoded_len(count: usize, entries: usize, encoding: MapEncoding) -> usize {
+    match encoding {
+        MapEncoding::Length => 4 + entries,
+        MapEncoding::Counted => 8 + entries,
+        MapEncoding::Checksummed => 4 + entries + count * ENTRY_OVERHEAD,
+    }
+}
+
+fn write_entries<'a, I, W>(entries: I, mut writer: W, encoding: MapEncoding) -> Result<()>
+where
+    I: Iterator<Item = (Key, EntryRef<'a>)> + Clone,
+    W: Write,
+{
+    let overhead = match encoding {
+        MapEncoding::Checksummed => ENTRY_OVERHEAD,
+        _ => 0,
+    };
+    let len: usize = entries
+        .clone()
+        .map(|(_, v)| v.encoded_len() + overhead)
+        .sum();
+    writer.write_u32::<LE>(len.try_into()?)?;
+    if encoding == MapEncoding::Counted {
+        writer.write_u32::<LE>(entries.clone().count().try_into()?)?;
+    }
+
+    for (k, v) in entries {
+        if encoding == MapEncoding::Checksummed {
+            // The length is known upfront, so the value is checksummed as it's written
+            writer.write_all(&ENTRY_MARKER)?;
+            let mut checksummed = ChecksumWriter::new(&mut writer);
+            checksummed.write_u32::<LE>(v.encoded_len().try_into()?)?;
+            v.write(&mut checksummed, k)?;
+            let checksum = checksummed.checksum;
+            writer.write_u32::<LE>(checksum)?;
+        } else {
+            v.write(&mut writer, k)?;
+        }
+    }
+    Ok(())
+}
+
+// Both ranges, since the same map encoding is used for playlists and beatmaps
+#[inline]
+fn is_reserved(key: Key) -> bool {
+    key.is_reserved(&PLAYLIST_RESERVED_KEYS) || key.is_reserved(&BEATMAP_RESERVED_KEYS)
+}
+
+// The body of a checksummed map. The bytes of the entry being read are kept until it's verified,
+// so they can be scanned for the next marker if it turns out to be corrupted.
+struct Entries<R> {
+    inner: io::Take<R>,
+    // Bytes handed back by a resync, read before `inner`
+    pending: Vec<u8>,
+    entry: Vec<u8>,
+    // Of the current entry within the body
+    offset: usize,
+}
+
+impl<R> Entries<R>
+where
+    R: Read,
+{
+    #[inline]
+    fn new(reader: R, len: usize) -> Self {
+        Self {
+            inner: reader.take(len as u64),
+            pending: Vec::new(),
+            entry: Vec::new(),
+            offset: 0,
+        }
+    }
+
+    // Not counting the current entry
+    #[inline]
+    fn remaining(&self) -> usize {
+        self.inner.limit() as usize + self.pending.len()
+    }
+
+    // The entry is kept until the next one is read, so `resync` can skip past it
+    fn read(&mut self, options: &ReadOptions) -> Result<(Key, Value, Vec<Warning>)> {
+        self.offset += self.entry.len();
+        self.entry.clear();
+        self.fill(ENTRY_MARKER.len())?;
+        if self.entry[..] != ENTRY_MARKER {
+            return Err(Error::MissingEntryMarker(self.offset));
+        }
+        self.fill(4)?;
+        let len = LE::read_u32(&self.entry[ENTRY_MARKER.len()..]) as usize;
+        // Checked before anything is allocated for the entry
+        if len.saturating_add(CHECKSUM_LEN) > self.remaining() {
+            return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+        }
+        if matches!(options.max_binary_len, Some(max) if len > max.saturating_add(MAX_KV_HEADER_LEN))
+        {
+            return Err(Error::BinaryTooLarge(len));
+        }
+        self.fill(len + CHECKSUM_LEN)?;
+
+        let (checked, stored) = self.entry[ENTRY_MARKER.len()..].split_at(4 + len);
+        let mut kv = &checked[4..];
+        let key = Key(kv.get(..4).map_or(0, LE::read_u32));
+        if LE::read_u32(stored) != checksum(checked) {
+            return Err(Error::ChecksumMismatch(key));
+        }
+        let mut warnings = Vec::new();
+        let (_, (k, v)) = kv.read_kv_with_options(options, &mut warnings)?;
+        if !kv.is_empty() {
+            return Err(Error::ChecksumMismatch(k));
+        }
+        Ok((k, v, warnings))
+    }
+
+    // Moves the current entry `len` bytes further
+    fn fill(&mut self, len: usize) -> Result<()> {
+        let from_pending = len.min(self.pending.len());
+        self.entry.extend(self.pending.drain(..from_pending));
+        let missing = (len - from_pending) as u64;
+        if (&mut self.inner)
+            .take(missing)
+            .read_to_end(&mut self.entry)? as u64
+            != missing
+        {
+            return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+        }
+        Ok(())
+    }
+
+    // Skips past the failed entry up to the next marker, or the end of the body, scanning every
+    // byte once. Returns how many bytes were skipped.
+    fn resync(&mut self, mut raw: Option<&mut Vec<u8>>) -> Result<usize> {
+        let mut window = mem::take(&mut self.entry);
+        window.append(&mut self.pending);
+        // The failed entry started at a marker, so the next one is at least a byte further
+        let mut skipped = window.len().min(1);
+        let mut scanned = skipped;
+        loop {
+            let found = window[scanned..]
+                .windows(ENTRY_MARKER.len())
+                .position(|w| w == ENTRY_MARKER);
+            let end = match found {
+                Some(i) => scanned + i,
+                // A marker could still start in the last few bytes
+                None => window
+                    .len()
+                    .saturating_sub(ENTRY_MARKER.len() - 1)
+                    .max(scanned),
+            };
+            skipped += end - scanned;
+            if let Some(raw) = raw.as_deref_mut() {
+                raw.extend_from_slice(&window[..end]);
+            }
+            window.drain(..end);
+            scanned = 0;
+
+            if found.is_some() || self.inner.limit() == 0 {
+                break;
+            }
+            let missing = self.inner.limit().min(RESYNC_CHUNK_LEN as u64);
+            if (&mut self.inner).take(missing).read_to_end(&mut window)? == 0 {
+                return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+            }
+        }
+
+        // Nothing but a partial marker is left at the end of the body
+        if self.inner.limit() == 0 && !window.starts_with(&ENTRY_MARKER) {
+            skipped += window.len();
+            if let Some(raw) = raw {
+                raw.extend_from_slice(&window);
+            }
+            window.clear();
+        }
+        self.offset += skipped;
+        self.pending = window;
+        Ok(skipped)
+    }
+}
+
+// FNV-1a
+#[inline]
+pub(crate) fn checksum(bytes: &[u8]) -> u32 {
+    update_checksum(FNV_OFFSET_BASIS, bytes)
+}
+
+const FNV_OFFSET_BASIS: u32 = 0x811c_9dc5;
+
+#[inline]
+fn update_checksum(checksum: u32, bytes: &[u8]) -> u32 {
+    bytes.iter().fold(checksum, |h, &b| {
+        (h ^ u32::from(b)).wrapping_mul(0x0100_0193)
+    })
+}
+
+struct ChecksumWriter<W> {
+    inner: W,
+    checksum: u32,
+}
+
+impl<W> ChecksumWriter<W> {
+    #[inline]
+    fn new(inner: W) -> Self {
+        Self {
+            inner,
+            checksum: FNV_OFFSET_BASIS,
+        }
+    }
+}
+
+impl<W> Write for ChecksumWriter<W>
+where
+    W: Write,
+{
+    #[inline]
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.checksum = update_checksum(self.checksum, &buf[..written]);
+        Ok(written)
+    }
+
+    #[inline]
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
 }
 
 impl Default for Map {
@@ -131,6 +674,13 @@ impl Default for Map {
     }
 }
 
+impl fmt::Debug for Map {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter_sorted()).finish()
+    }
+}
+
 impl PartialEq for Map {
     #[inline]
     fn eq(&self, other: &Self) -> bool {
@@ -0,0 +1,32 @@
+use crate::{error::Error, map::MapEncoding, Interner, Key, Result, Spill};
+
+#[derive(Debug, Clone, Default)]
+pub struct ReadOptions {
+    pub max_binary_len: Option<usize>,
+    // Tighter limits for binary values stored under specific keys
+    pub max_field_lens: Vec<(Key, usize)>,
+    pub lossy_utf8: bool,
+    pub interner: Option<Interner>,
+    pub encoding: MapEncoding,
+    pub resync: bool,
+    pub spill: Option<Spill>,
+    // Keeps a copy of the bytes lossy and resynchronizing reads throw away in their warnings
+    pub capture_discarded: bool,
+}
+
+impl ReadOptions {
+    // Checked against the length prefix, before anything is allocated
+    pub(crate) fn check_binary_len(&self, key: Key, len: usize) -> Result<()> {
+        if matches!(self.max_binary_len, Some(max) if len > max) {
+            return Err(Error::BinaryTooLarge(len));
+        }
+        if self
+            .max_field_lens
+            .iter()
+            .any(|&(k, max)| *k == *key && len > max)
+        {
+            return Err(Error::FieldTooLarge { key, len });
+        }
+        Ok(())
+    }
+}
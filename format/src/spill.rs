@@ -0,0 +1,156 @@
+use crate::{Key, Result};
+use std::{
+    fmt, fs,
+    io::{self, Read},
+    path::{Path, PathBuf},
+    process,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+};
+
+static NEXT_FILE: AtomicUsize = AtomicUsize::new(0);
+
+// Binary values above the threshold are copied to temporary files instead of memory, and the map
+// keeps a handle to the file in their place
+#[derive(Clone)]
+pub struct Spill(Arc<SpillInner>);
+
+struct SpillInner {
+    threshold: usize,
+    dir: PathBuf,
+    state: Mutex<SpillState>,
+}
+
+#[derive(Default)]
+struct SpillState {
+    scope: Option<usize>,
+}
+
+// The file is deleted when the handle is dropped
+#[derive(Debug)]
+pub struct SpilledBinary {
+    pub scope: Option<usize>,
+    pub key: Key,
+    path: PathBuf,
+    len: usize,
+}
+
+impl Spill {
+    #[inline]
+    pub fn new<P>(threshold: usize, dir: P) -> Self
+    where
+        P: Into<PathBuf>,
+    {
+        Self(Arc::new(SpillInner {
+            threshold,
+            dir: dir.into(),
+            state: Default::default(),
+        }))
+    }
+
+    #[inline]
+    pub fn threshold(&self) -> usize {
+        self.0.threshold
+    }
+
+    #[inline]
+    pub fn dir(&self) -> &Path {
+        &self.0.dir
+    }
+
+    // Tags the values spilled from now on, e.g. with the index of the map being read
+    #[inline]
+    pub fn set_scope(&self, scope: Option<usize>) {
+        self.0.state.lock().unwrap().scope = scope;
+    }
+
+    pub(crate) fn spill_to_file<R>(
+        &self,
+        key: Key,
+        reader: &mut R,
+        len: usize,
+    ) -> Result<SpilledBinary>
+    where
+        R: Read + ?Sized,
+    {
+        let (path, mut file) = self.create()?;
+        let scope = self.0.state.lock().unwrap().scope;
+        // Owning the path right away makes sure partial files get cleaned up on error
+        let spilled = SpilledBinary {
+            scope,
+            key,
+            path,
+            len,
+        };
+
+        let copied = io::copy(&mut reader.take(len as u64), &mut file)?;
+        if copied != len as u64 {
+            return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+        }
+        Ok(spilled)
+    }
+
+    fn create(&self) -> io::Result<(PathBuf, fs::File)> {
+        loop {
+            let n = NEXT_FILE.fetch_add(1, Ordering::Relaxed);
+            let path = self
+                .0
+                .dir
+                .join(format!("blister-{}-{}.bin", process::id(), n));
+            match fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&path)
+            {
+                Ok(file) => return Ok((path, file)),
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+impl SpilledBinary {
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    #[inline]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    #[inline]
+    pub fn open(&self) -> io::Result<fs::File> {
+        fs::File::open(&self.path)
+    }
+
+    #[inline]
+    pub fn read(&self) -> io::Result<Vec<u8>> {
+        fs::read(&self.path)
+    }
+}
+
+impl Drop for SpilledBinary {
+    #[inline]
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+impl fmt::Debug for Spill {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Spill")
+            .field("threshold", &self.0.threshold)
+            .field("dir", &self.0.dir)
+            .finish()
+    }
+}
@@ -0,0 +1,142 @@
+// Hashes are stored as 20 byte blobs and keys as 64 bit integers since u32 isn't portable
+
+#[cfg(feature = "sqlx")]
+mod sqlx_impls {
+    use crate::{values::Sha1, Key};
+    use sqlx::{encode::IsNull, error::BoxDynError, Database, Decode, Encode, Type};
+    use std::convert::TryInto;
+
+    impl<DB: Database> Type<DB> for Sha1
+    where
+        Vec<u8>: Type<DB>,
+    {
+        #[inline]
+        fn type_info() -> DB::TypeInfo {
+            <Vec<u8> as Type<DB>>::type_info()
+        }
+
+        #[inline]
+        fn compatible(ty: &DB::TypeInfo) -> bool {
+            <Vec<u8> as Type<DB>>::compatible(ty)
+        }
+    }
+
+    impl<'q, DB: Database> Encode<'q, DB> for Sha1
+    where
+        Vec<u8>: Encode<'q, DB>,
+    {
+        #[inline]
+        fn encode_by_ref(
+            &self,
+            buf: &mut <DB as Database>::ArgumentBuffer<'q>,
+        ) -> Result<IsNull, BoxDynError> {
+            self.0.to_vec().encode(buf)
+        }
+    }
+
+    impl<'r, DB: Database> Decode<'r, DB> for Sha1
+    where
+        &'r [u8]: Decode<'r, DB>,
+    {
+        #[inline]
+        fn decode(value: <DB as Database>::ValueRef<'r>) -> Result<Self, BoxDynError> {
+            Ok(Sha1(<&[u8]>::decode(value)?.try_into()?))
+        }
+    }
+
+    impl<DB: Database> Type<DB> for Key
+    where
+        i64: Type<DB>,
+    {
+        #[inline]
+        fn type_info() -> DB::TypeInfo {
+            <i64 as Type<DB>>::type_info()
+        }
+
+        #[inline]
+        fn compatible(ty: &DB::TypeInfo) -> bool {
+            <i64 as Type<DB>>::compatible(ty)
+        }
+    }
+
+    impl<'q, DB: Database> Encode<'q, DB> for Key
+    where
+        i64: Encode<'q, DB>,
+    {
+        #[inline]
+        fn encode_by_ref(
+            &self,
+            buf: &mut <DB as Database>::ArgumentBuffer<'q>,
+        ) -> Result<IsNull, BoxDynError> {
+            i64::from(self.0).encode(buf)
+        }
+    }
+
+    impl<'r, DB: Database> Decode<'r, DB> for Key
+    where
+        i64: Decode<'r, DB>,
+    {
+        #[inline]
+        fn decode(value: <DB as Database>::ValueRef<'r>) -> Result<Self, BoxDynError> {
+            Ok(Key(i64::decode(value)?.try_into()?))
+        }
+    }
+}
+
+#[cfg(feature = "diesel")]
+mod diesel_impls {
+    use crate::{values::Sha1, Key};
+    use diesel::{
+        backend::Backend,
+        deserialize::{self, FromSql},
+        query_builder::bind_collector::RawBytesBindCollector,
+        serialize::{self, Output, ToSql},
+        sql_types::{BigInt, Binary},
+    };
+    use std::convert::TryInto;
+
+    impl<DB: Backend> ToSql<Binary, DB> for Sha1
+    where
+        [u8]: ToSql<Binary, DB>,
+    {
+        #[inline]
+        fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, DB>) -> serialize::Result {
+            <[u8] as ToSql<Binary, DB>>::to_sql(&self.0[..], out)
+        }
+    }
+
+    impl<DB: Backend> FromSql<Binary, DB> for Sha1
+    where
+        Vec<u8>: FromSql<Binary, DB>,
+    {
+        #[inline]
+        fn from_sql(bytes: DB::RawValue<'_>) -> deserialize::Result<Self> {
+            let bytes = <Vec<u8> as FromSql<Binary, DB>>::from_sql(bytes)?;
+            Ok(Sha1(bytes.as_slice().try_into()?))
+        }
+    }
+
+    // Only backends binding raw bytes can serialize a temporary, which leaves SQLite out
+    impl<DB> ToSql<BigInt, DB> for Key
+    where
+        DB: for<'c> Backend<BindCollector<'c> = RawBytesBindCollector<DB>>,
+        i64: ToSql<BigInt, DB>,
+    {
+        #[inline]
+        fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, DB>) -> serialize::Result {
+            <i64 as ToSql<BigInt, DB>>::to_sql(&i64::from(self.0), &mut out.reborrow())
+        }
+    }
+
+    impl<DB: Backend> FromSql<BigInt, DB> for Key
+    where
+        i64: FromSql<BigInt, DB>,
+    {
+        #[inline]
+        fn from_sql(bytes: DB::RawValue<'_>) -> deserialize::Result<Self> {
+            Ok(Key(
+                <i64 as FromSql<BigInt, DB>>::from_sql(bytes)?.try_into()?
+            ))
+        }
+    }
+}
@@ -0,0 +1,48 @@
+use crate::{
+    values::{LongString, Sha1, ShortString},
+    Map, Value,
+};
+use proptest::{collection::SizeRange, prelude::*};
+
+// At most 4 bytes per character, which fits in a short string
+const STRING_PATTERN: &str = "\\PC{0,63}";
+
+pub fn string() -> impl Strategy<Value = String> {
+    STRING_PATTERN
+}
+
+pub fn sha1() -> impl Strategy<Value = Sha1> {
+    any::<[u8; 20]>().prop_map(Sha1)
+}
+
+pub fn value() -> impl Strategy<Value = Value> {
+    prop_oneof![
+        any::<u8>().prop_map(Value::U8),
+        any::<u16>().prop_map(Value::U16),
+        any::<u32>().prop_map(Value::U32),
+        any::<u64>().prop_map(Value::U64),
+        string().prop_map(|s| Value::ShortString(ShortString::new(s).unwrap())),
+        string().prop_map(|s| Value::LongString(LongString::new(s).unwrap())),
+        proptest::collection::vec(any::<u8>(), 0..64).prop_map(Value::from),
+        any::<bool>().prop_map(Value::Bool),
+        // NaN never compares equal, which would break round trip properties
+        any::<f32>()
+            .prop_filter("NaN", |f| !f.is_nan())
+            .prop_map(Value::Float),
+        sha1().prop_map(Value::Sha1),
+        proptest::collection::vec(any::<u8>(), 0..64).prop_map(|v| Value::LongBinary(v.into())),
+    ]
+}
+
+pub fn map<S>(size: S) -> impl Strategy<Value = Map>
+where
+    S: Into<SizeRange>,
+{
+    proptest::collection::vec((any::<u32>(), value()), size).prop_map(|entries| {
+        let mut map = Map::new();
+        for (k, v) in entries {
+            map.insert(k, v);
+        }
+        map
+    })
+}
@@ -0,0 +1,134 @@
+use crate::{
+    error::Error,
+    values::{Binary, LongBinary, LongString, Sha1, ShortString},
+    Key, Map, Result, Value,
+};
+use std::{collections::hash_map::Entry, convert::TryFrom, marker::PhantomData};
+
+pub trait TypedValue: TryFrom<Value, Error = Error> + Into<Value> {
+    const NAME: &'static str;
+
+    fn as_mut(value: &mut Value) -> Option<&mut Self>;
+}
+
+macro_rules! typed_value {
+    ($ty:ty, $name:literal, $($variant:ident)|+) => {
+        impl TryFrom<Value> for $ty {
+            type Error = Error;
+
+            fn try_from(value: Value) -> Result<Self> {
+                match value {
+                    $(Value::$variant(v))|+ => Ok(v),
+                    v => Err(Error::TypeMismatch {
+                        expected: $name,
+                        found: v.type_name(),
+                    }),
+                }
+            }
+        }
+
+        impl TypedValue for $ty {
+            const NAME: &'static str = $name;
+
+            #[inline]
+            fn as_mut(value: &mut Value) -> Option<&mut Self> {
+                match value {
+                    $(Value::$variant(v))|+ => Some(v),
+                    _ => None,
+                }
+            }
+        }
+    };
+}
+
+typed_value!(u8, "U8", U8);
+typed_value!(u16, "U16", U16);
+typed_value!(u32, "U32", U32);
+typed_value!(u64, "U64", U64);
+typed_value!(ShortString, "ShortString", ShortString);
+typed_value!(LongString, "LongString", LongString);
+typed_value!(Binary, "Binary", Binary);
+typed_value!(bool, "Bool", Bool);
+typed_value!(f32, "Float", Float);
+typed_value!(Sha1, "Sha1", Sha1);
+typed_value!(LongBinary, "LongBinary", LongBinary);
+
+pub struct TypedEntry<'a, T> {
+    entry: Entry<'a, Key, Value>,
+    ty: PhantomData<T>,
+}
+
+impl<'a, T> TypedEntry<'a, T>
+where
+    T: TypedValue,
+{
+    #[inline]
+    pub fn key(&self) -> Key {
+        *self.entry.key()
+    }
+
+    #[inline]
+    pub fn or_insert(self, default: T) -> Result<&'a mut T> {
+        self.or_insert_with(|| default)
+    }
+
+    pub fn or_insert_with<F>(self, default: F) -> Result<&'a mut T>
+    where
+        F: FnOnce() -> T,
+    {
+        let value = self.entry.or_insert_with(|| default().into());
+        let found = value.type_name();
+        T::as_mut(value).ok_or(Error::TypeMismatch {
+            expected: T::NAME,
+            found,
+        })
+    }
+
+    #[inline]
+    pub fn or_default(self) -> Result<&'a mut T>
+    where
+        T: Default,
+    {
+        self.or_insert_with(T::default)
+    }
+
+    pub fn and_modify<F>(self, f: F) -> Result<Self>
+    where
+        F: FnOnce(&mut T),
+    {
+        let entry = match self.entry {
+            Entry::Occupied(mut e) => {
+                let found = e.get().type_name();
+                match T::as_mut(e.get_mut()) {
+                    Some(v) => f(v),
+                    None => {
+                        return Err(Error::TypeMismatch {
+                            expected: T::NAME,
+                            found,
+                        })
+                    }
+                }
+                Entry::Occupied(e)
+            }
+            e => e,
+        };
+        Ok(Self {
+            entry,
+            ty: PhantomData,
+        })
+    }
+}
+
+impl Map {
+    // `impl Trait` keeps the key type out of the turbofish
+    #[inline]
+    pub fn entry_typed<T>(&mut self, key: impl Into<Key>) -> TypedEntry<'_, T>
+    where
+        T: TypedValue,
+    {
+        TypedEntry {
+            entry: self.entry(key),
+            ty: PhantomData,
+        }
+    }
+}
@@ -1,12 +1,832 @@
+use crate::{error::Error, Result, SpilledBinary};
 use derive_more::{Deref, DerefMut, From};
+use std::{
+    borrow::Borrow,
+    cmp::Ordering,
+    convert::TryFrom,
+    fmt, fs,
+    hash::{Hash, Hasher},
+    io::{self, Read},
+    mem,
+    ops::Deref,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::Arc,
+};
 
 #[derive(Debug, Copy, Clone, Deref, DerefMut, From)]
+#[cfg_attr(
+    feature = "diesel",
+    derive(diesel::expression::AsExpression, diesel::deserialize::FromSqlRow),
+    diesel(sql_type = diesel::sql_types::Binary)
+)]
 pub struct Sha1(pub [u8; 20]);
 
+// Comparisons take the same time wherever the hashes differ, unless `constant-time-eq` is left out
+// of the build
 impl PartialEq for Sha1 {
+    #[cfg(feature = "constant-time-eq")]
     #[inline]
     fn eq(&self, other: &Self) -> bool {
         constant_time_eq::constant_time_eq(&self[..], &other[..])
     }
+
+    #[cfg(not(feature = "constant-time-eq"))]
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self[..] == other[..]
+    }
 }
 impl Eq for Sha1 {}
+
+impl Sha1 {
+    // Either case is accepted, tools disagree on which one to write
+    #[inline]
+    pub fn from_hex(hex: &str) -> Option<Self> {
+        let mut bytes = [0; 20];
+        if decode_hex(hex, &mut bytes) {
+            Some(Self(bytes))
+        } else {
+            None
+        }
+    }
+}
+
+impl FromStr for Sha1 {
+    type Err = Error;
+
+    #[inline]
+    fn from_str(s: &str) -> Result<Self> {
+        Self::from_hex(s).ok_or_else(|| Error::InvalidSha1(s.to_owned()))
+    }
+}
+
+// Fills `out` from exactly twice as many hex digits, without the signs `from_str_radix` would let
+// through
+pub fn decode_hex(hex: &str, out: &mut [u8]) -> bool {
+    let hex = hex.as_bytes();
+    if hex.len() != out.len() * 2 {
+        return false;
+    }
+    let digit = |c: u8| (c as char).to_digit(16);
+    for (b, pair) in out.iter_mut().zip(hex.chunks(2)) {
+        match (digit(pair[0]), digit(pair[1])) {
+            (Some(hi), Some(lo)) => *b = (hi << 4 | lo) as u8,
+            _ => return false,
+        }
+    }
+    true
+}
+
+impl fmt::Display for Sha1 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for b in self.iter() {
+            write!(f, "{:02x}", b)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "bytes")]
+type Heap = bytes::Bytes;
+#[cfg(not(feature = "bytes"))]
+type Heap = Vec<u8>;
+
+#[cfg(feature = "smallvec")]
+pub(crate) const INLINE_LEN: usize = 32;
+
+const PREVIEW_LEN: usize = 16;
+
+#[derive(Clone)]
+enum BinaryInner {
+    #[cfg(feature = "smallvec")]
+    Inline(smallvec::SmallVec<[u8; INLINE_LEN]>),
+    Heap(Heap),
+    Spilled(Arc<SpilledBinary>),
+}
+
+// Values read with a spill only live in their file, so their bytes are only available through
+// fallible accessors. Writing them streams the file back out.
+#[derive(Clone)]
+pub struct Binary(BinaryInner);
+
+// Conversions from and to the heap type are no-ops unless `bytes` is enabled
+impl Binary {
+    #[inline]
+    pub(crate) fn from_spilled(spilled: Arc<SpilledBinary>) -> Self {
+        Self(BinaryInner::Spilled(spilled))
+    }
+
+    // Spilled values are loaded from their file, which the handle keeps around
+    #[allow(clippy::useless_conversion)]
+    #[inline]
+    pub fn into_vec(self) -> io::Result<Vec<u8>> {
+        match self.0 {
+            #[cfg(feature = "smallvec")]
+            BinaryInner::Inline(v) => Ok(v.into_vec()),
+            BinaryInner::Heap(v) => Ok(v.into()),
+            BinaryInner::Spilled(v) => v.read(),
+        }
+    }
+
+    // `None` for spilled values
+    #[inline]
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match &self.0 {
+            #[cfg(feature = "smallvec")]
+            BinaryInner::Inline(v) => Some(v),
+            BinaryInner::Heap(v) => Some(v),
+            BinaryInner::Spilled(_) => None,
+        }
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        match &self.0 {
+            #[cfg(feature = "smallvec")]
+            BinaryInner::Inline(v) => v.len(),
+            BinaryInner::Heap(v) => v.len(),
+            BinaryInner::Spilled(v) => v.len(),
+        }
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    #[inline]
+    pub fn spilled(&self) -> Option<&SpilledBinary> {
+        match &self.0 {
+            BinaryInner::Spilled(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    pub fn reader(&self) -> io::Result<Box<dyn Read + '_>> {
+        Ok(match &self.0 {
+            #[cfg(feature = "smallvec")]
+            BinaryInner::Inline(v) => Box::new(&v[..]),
+            BinaryInner::Heap(v) => Box::new(&v[..]),
+            BinaryInner::Spilled(v) => Box::new(io::BufReader::new(v.open()?)),
+        })
+    }
+
+    pub fn read_to_vec(&self) -> io::Result<Vec<u8>> {
+        match &self.0 {
+            #[cfg(feature = "smallvec")]
+            BinaryInner::Inline(v) => Ok(v.to_vec()),
+            BinaryInner::Heap(v) => Ok(v.to_vec()),
+            BinaryInner::Spilled(v) => v.read(),
+        }
+    }
+
+    #[inline]
+    pub fn heap_size(&self) -> usize {
+        match &self.0 {
+            #[cfg(feature = "smallvec")]
+            BinaryInner::Inline(_) => 0,
+            #[cfg(feature = "bytes")]
+            BinaryInner::Heap(v) => v.len(),
+            #[cfg(not(feature = "bytes"))]
+            BinaryInner::Heap(v) => v.capacity(),
+            BinaryInner::Spilled(_) => 0,
+        }
+    }
+
+    #[inline]
+    pub fn is_inline(&self) -> bool {
+        match self.0 {
+            #[cfg(feature = "smallvec")]
+            BinaryInner::Inline(_) => true,
+            _ => false,
+        }
+    }
+}
+
+impl Default for Binary {
+    #[inline]
+    fn default() -> Self {
+        Self(BinaryInner::Heap(Default::default()))
+    }
+}
+
+impl PartialEq for Binary {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        match (self.as_bytes(), other.as_bytes()) {
+            (Some(a), Some(b)) => a == b,
+            _ => {
+                self.len() == other.len()
+                    && streams_eq(self.len() as u64, self.reader(), other.reader())
+            }
+        }
+    }
+}
+impl Eq for Binary {}
+
+// Fed to the hasher in fixed chunks whichever way the value is stored, so spilled values hash
+// like their contents without being loaded whole. Unreadable files only hash their length.
+impl Hash for Binary {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_usize(self.len());
+        let mut reader = match self.reader() {
+            Ok(reader) => reader,
+            Err(_) => return,
+        };
+        let mut chunk = vec![0; COMPARE_CHUNK_LEN.min(self.len())];
+        let mut remaining = self.len();
+        while remaining > 0 {
+            let len = remaining.min(chunk.len());
+            if reader.read_exact(&mut chunk[..len]).is_err() {
+                return;
+            }
+            state.write(&chunk[..len]);
+            remaining -= len;
+        }
+    }
+}
+
+// Binary values can be megabytes long so only print the start of anything longer than the preview
+impl fmt::Debug for Binary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(v) = self.spilled() {
+            return write!(f, "{:?} ({} bytes)", v.path(), v.len());
+        }
+        let bytes = self.as_bytes().unwrap_or_default();
+        if bytes.len() <= PREVIEW_LEN {
+            return fmt::Debug::fmt(bytes, f);
+        }
+        write!(f, "{:?}.. ({} bytes)", &bytes[..PREVIEW_LEN], bytes.len())
+    }
+}
+
+impl fmt::Display for Binary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(v) = self.spilled() {
+            return write!(f, "<{} bytes in {}>", v.len(), v.path().display());
+        }
+        let bytes = self.as_bytes().unwrap_or_default();
+        write!(f, "<{} bytes", bytes.len())?;
+        if !bytes.is_empty() {
+            f.write_str(":")?;
+            for b in bytes.iter().take(PREVIEW_LEN) {
+                write!(f, " {:02x}", b)?;
+            }
+            if bytes.len() > PREVIEW_LEN {
+                f.write_str(" …")?;
+            }
+        }
+        f.write_str(">")
+    }
+}
+
+impl From<Vec<u8>> for Binary {
+    #[allow(clippy::useless_conversion)]
+    #[inline]
+    fn from(v: Vec<u8>) -> Self {
+        #[cfg(feature = "smallvec")]
+        if v.len() <= INLINE_LEN {
+            return Self(BinaryInner::Inline(v.as_slice().into()));
+        }
+        Self(BinaryInner::Heap(v.into()))
+    }
+}
+
+impl From<&[u8]> for Binary {
+    #[allow(clippy::useless_conversion)]
+    #[inline]
+    fn from(s: &[u8]) -> Self {
+        #[cfg(feature = "smallvec")]
+        if s.len() <= INLINE_LEN {
+            return Self(BinaryInner::Inline(s.into()));
+        }
+        Self(BinaryInner::Heap(s.to_vec().into()))
+    }
+}
+
+impl TryFrom<Binary> for Vec<u8> {
+    type Error = io::Error;
+
+    #[inline]
+    fn try_from(b: Binary) -> io::Result<Self> {
+        b.into_vec()
+    }
+}
+
+#[cfg(feature = "bytes")]
+impl From<bytes::Bytes> for Binary {
+    #[inline]
+    fn from(b: bytes::Bytes) -> Self {
+        Self(BinaryInner::Heap(b))
+    }
+}
+
+#[cfg(feature = "bytes")]
+impl TryFrom<Binary> for bytes::Bytes {
+    type Error = io::Error;
+
+    #[inline]
+    fn try_from(b: Binary) -> io::Result<Self> {
+        match b.0 {
+            #[cfg(feature = "smallvec")]
+            BinaryInner::Inline(v) => Ok(bytes::Bytes::copy_from_slice(&v)),
+            BinaryInner::Heap(v) => Ok(v),
+            BinaryInner::Spilled(v) => v.read().map(Into::into),
+        }
+    }
+}
+
+const COMPARE_CHUNK_LEN: usize = 64 * 1024;
+
+// Binary values with a 64 bit length, which don't have to be held in memory. Values backed by a
+// file are streamed from it when written, values read with a spill are streamed to one.
+// Maps still carry a 32 bit byte length, so anything past 4 GB has to go through `WriteExt`.
+#[derive(Clone)]
+pub struct LongBinary(LongBinaryInner);
+
+#[derive(Clone)]
+enum LongBinaryInner {
+    Memory(Binary),
+    // The file must not change until the value is written
+    File { path: PathBuf, len: u64 },
+    Spilled(Arc<SpilledBinary>),
+}
+
+impl LongBinary {
+    pub fn from_file<P>(path: P) -> io::Result<Self>
+    where
+        P: Into<PathBuf>,
+    {
+        let path = path.into();
+        let len = fs::metadata(&path)?.len();
+        Ok(Self(LongBinaryInner::File { path, len }))
+    }
+
+    #[inline]
+    pub(crate) fn spilled(spilled: Arc<SpilledBinary>) -> Self {
+        Self(LongBinaryInner::Spilled(spilled))
+    }
+
+    #[inline]
+    pub fn len(&self) -> u64 {
+        match &self.0 {
+            LongBinaryInner::Memory(v) => v.len() as u64,
+            LongBinaryInner::File { len, .. } => *len,
+            LongBinaryInner::Spilled(v) => v.len() as u64,
+        }
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // `None` for values backed by a file
+    #[inline]
+    pub fn as_slice(&self) -> Option<&[u8]> {
+        match &self.0 {
+            LongBinaryInner::Memory(v) => v.as_bytes(),
+            _ => None,
+        }
+    }
+
+    #[inline]
+    pub fn path(&self) -> Option<&Path> {
+        match &self.0 {
+            LongBinaryInner::Memory(_) => None,
+            LongBinaryInner::File { path, .. } => Some(path),
+            LongBinaryInner::Spilled(v) => Some(v.path()),
+        }
+    }
+
+    pub fn reader(&self) -> io::Result<Box<dyn Read + '_>> {
+        Ok(match &self.0 {
+            LongBinaryInner::Memory(v) => v.reader()?,
+            LongBinaryInner::File { path, .. } => {
+                Box::new(io::BufReader::new(fs::File::open(path)?))
+            }
+            LongBinaryInner::Spilled(v) => Box::new(io::BufReader::new(v.open()?)),
+        })
+    }
+
+    pub fn read_to_vec(&self) -> io::Result<Vec<u8>> {
+        match &self.0 {
+            LongBinaryInner::Memory(v) => v.read_to_vec(),
+            LongBinaryInner::File { path, .. } => fs::read(path),
+            LongBinaryInner::Spilled(v) => v.read(),
+        }
+    }
+
+    // Spilled values stay in their file, values backed by any other file are loaded from it
+    pub fn into_binary(self) -> io::Result<Binary> {
+        match self.0 {
+            LongBinaryInner::Memory(v) => Ok(v),
+            LongBinaryInner::File { path, .. } => fs::read(path).map(Into::into),
+            LongBinaryInner::Spilled(v) => Ok(Binary::from_spilled(v)),
+        }
+    }
+
+    #[inline]
+    pub fn heap_size(&self) -> usize {
+        match &self.0 {
+            LongBinaryInner::Memory(v) => v.heap_size(),
+            _ => 0,
+        }
+    }
+}
+
+impl Default for LongBinary {
+    #[inline]
+    fn default() -> Self {
+        Self(LongBinaryInner::Memory(Default::default()))
+    }
+}
+
+// Values backed by files are compared chunk by chunk, and never equal anything if they can't be
+// read
+impl PartialEq for LongBinary {
+    fn eq(&self, other: &Self) -> bool {
+        if self.len() != other.len() {
+            return false;
+        }
+        if let (Some(a), Some(b)) = (self.as_slice(), other.as_slice()) {
+            return a == b;
+        }
+        streams_eq(self.len(), self.reader(), other.reader())
+    }
+}
+
+fn streams_eq<R>(len: u64, a: io::Result<R>, b: io::Result<R>) -> bool
+where
+    R: Read,
+{
+    let (mut a, mut b) = match (a, b) {
+        (Ok(a), Ok(b)) => (a, b),
+        _ => return false,
+    };
+    let mut chunk_a = vec![0; COMPARE_CHUNK_LEN];
+    let mut chunk_b = vec![0; COMPARE_CHUNK_LEN];
+    let mut remaining = len;
+    while remaining > 0 {
+        let len = remaining.min(COMPARE_CHUNK_LEN as u64) as usize;
+        if a.read_exact(&mut chunk_a[..len]).is_err()
+            || b.read_exact(&mut chunk_b[..len]).is_err()
+            || chunk_a[..len] != chunk_b[..len]
+        {
+            return false;
+        }
+        remaining -= len as u64;
+    }
+    true
+}
+
+impl fmt::Debug for LongBinary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.0 {
+            LongBinaryInner::Memory(v) => fmt::Debug::fmt(v, f),
+            _ => write!(f, "{:?} ({} bytes)", self.path().unwrap(), self.len()),
+        }
+    }
+}
+
+impl fmt::Display for LongBinary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.0 {
+            LongBinaryInner::Memory(v) => fmt::Display::fmt(v, f),
+            _ => write!(
+                f,
+                "<{} bytes in {}>",
+                self.len(),
+                self.path().unwrap().display()
+            ),
+        }
+    }
+}
+
+impl From<Binary> for LongBinary {
+    #[inline]
+    fn from(b: Binary) -> Self {
+        match b.0 {
+            BinaryInner::Spilled(v) => Self(LongBinaryInner::Spilled(v)),
+            _ => Self(LongBinaryInner::Memory(b)),
+        }
+    }
+}
+
+impl From<Vec<u8>> for LongBinary {
+    #[inline]
+    fn from(v: Vec<u8>) -> Self {
+        Self(LongBinaryInner::Memory(v.into()))
+    }
+}
+
+impl From<&[u8]> for LongBinary {
+    #[inline]
+    fn from(s: &[u8]) -> Self {
+        Self(LongBinaryInner::Memory(s.into()))
+    }
+}
+
+// Owned unless it came from an interner, so reads without one don't pay for shared strings
+#[derive(Clone)]
+pub struct Str(pub(crate) StrInner);
+
+#[derive(Clone)]
+pub(crate) enum StrInner {
+    Owned(String),
+    Shared(Arc<str>),
+}
+
+impl Str {
+    #[inline]
+    pub fn as_str(&self) -> &str {
+        self
+    }
+
+    #[inline]
+    pub fn is_shared(&self) -> bool {
+        matches!(self.0, StrInner::Shared(_))
+    }
+
+    // Shared strings are attributed to every holder
+    #[inline]
+    pub fn heap_size(&self) -> usize {
+        match &self.0 {
+            StrInner::Owned(s) => s.capacity(),
+            StrInner::Shared(s) => 2 * mem::size_of::<usize>() + s.len(),
+        }
+    }
+}
+
+impl Default for Str {
+    #[inline]
+    fn default() -> Self {
+        Self(StrInner::Owned(String::new()))
+    }
+}
+
+impl PartialEq for Str {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        **self == **other
+    }
+}
+
+impl Eq for Str {}
+
+impl Hash for Str {
+    #[inline]
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        (**self).hash(state)
+    }
+}
+
+impl PartialOrd for Str {
+    #[inline]
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Str {
+    #[inline]
+    fn cmp(&self, other: &Self) -> Ordering {
+        (**self).cmp(&**other)
+    }
+}
+
+impl Deref for Str {
+    type Target = str;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        match &self.0 {
+            StrInner::Owned(s) => s,
+            StrInner::Shared(s) => s,
+        }
+    }
+}
+
+impl AsRef<str> for Str {
+    #[inline]
+    fn as_ref(&self) -> &str {
+        self
+    }
+}
+
+impl Borrow<str> for Str {
+    #[inline]
+    fn borrow(&self) -> &str {
+        self
+    }
+}
+
+impl fmt::Debug for Str {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+impl fmt::Display for Str {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&**self, f)
+    }
+}
+
+impl PartialEq<str> for Str {
+    #[inline]
+    fn eq(&self, other: &str) -> bool {
+        **self == *other
+    }
+}
+
+impl PartialEq<&str> for Str {
+    #[inline]
+    fn eq(&self, other: &&str) -> bool {
+        **self == **other
+    }
+}
+
+impl From<String> for Str {
+    #[inline]
+    fn from(s: String) -> Self {
+        Self(StrInner::Owned(s))
+    }
+}
+
+impl From<&str> for Str {
+    #[inline]
+    fn from(s: &str) -> Self {
+        Self(StrInner::Owned(s.to_owned()))
+    }
+}
+
+impl From<Arc<str>> for Str {
+    #[inline]
+    fn from(s: Arc<str>) -> Self {
+        Self(StrInner::Shared(s))
+    }
+}
+
+impl From<Str> for String {
+    #[inline]
+    fn from(s: Str) -> Self {
+        match s.0 {
+            StrInner::Owned(s) => s,
+            StrInner::Shared(s) => s.to_string(),
+        }
+    }
+}
+
+// Strings that fit the length prefix of their value type, checked when they're built instead of
+// when they're written
+macro_rules! bounded_string {
+    ($name:ident, $max:expr) => {
+        #[derive(Clone, Default, Eq, PartialEq, Hash, PartialOrd, Ord)]
+        pub struct $name(Str);
+
+        impl $name {
+            pub const MAX_LEN: usize = $max;
+
+            pub fn new<S>(s: S) -> Result<Self>
+            where
+                S: Into<Str>,
+            {
+                let s = s.into();
+                if s.len() > Self::MAX_LEN {
+                    return Err(Error::StringTooLong {
+                        len: s.len(),
+                        max: Self::MAX_LEN,
+                    });
+                }
+                Ok(Self(s))
+            }
+
+            // Only for strings read with a length prefix of the right size
+            #[inline]
+            pub(crate) fn new_unchecked(s: Str) -> Self {
+                Self(s)
+            }
+
+            #[inline]
+            pub fn as_str(&self) -> &str {
+                self
+            }
+
+            #[inline]
+            pub fn into_str(self) -> Str {
+                self.0
+            }
+
+            #[inline]
+            pub fn heap_size(&self) -> usize {
+                self.0.heap_size()
+            }
+        }
+
+        impl Deref for $name {
+            type Target = str;
+
+            #[inline]
+            fn deref(&self) -> &Self::Target {
+                &self.0
+            }
+        }
+
+        impl AsRef<str> for $name {
+            #[inline]
+            fn as_ref(&self) -> &str {
+                self
+            }
+        }
+
+        impl Borrow<str> for $name {
+            #[inline]
+            fn borrow(&self) -> &str {
+                self
+            }
+        }
+
+        impl fmt::Debug for $name {
+            #[inline]
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                fmt::Debug::fmt(&**self, f)
+            }
+        }
+
+        impl fmt::Display for $name {
+            #[inline]
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                fmt::Display::fmt(&**self, f)
+            }
+        }
+
+        impl PartialEq<str> for $name {
+            #[inline]
+            fn eq(&self, other: &str) -> bool {
+                **self == *other
+            }
+        }
+
+        impl PartialEq<&str> for $name {
+            #[inline]
+            fn eq(&self, other: &&str) -> bool {
+                **self == **other
+            }
+        }
+
+        impl TryFrom<Str> for $name {
+            type Error = Error;
+
+            #[inline]
+            fn try_from(s: Str) -> Result<Self> {
+                Self::new(s)
+            }
+        }
+
+        impl TryFrom<String> for $name {
+            type Error = Error;
+
+            #[inline]
+            fn try_from(s: String) -> Result<Self> {
+                Self::new(s)
+            }
+        }
+
+        impl TryFrom<&str> for $name {
+            type Error = Error;
+
+            #[inline]
+            fn try_from(s: &str) -> Result<Self> {
+                Self::new(s)
+            }
+        }
+
+        impl From<$name> for Str {
+            #[inline]
+            fn from(s: $name) -> Self {
+                s.0
+            }
+        }
+
+        impl From<$name> for String {
+            #[inline]
+            fn from(s: $name) -> Self {
+                s.0.into()
+            }
+        }
+    };
+}
+
+bounded_string!(ShortString, u8::MAX as usize);
+bounded_string!(LongString, u16::MAX as usize);
+
+impl From<ShortString> for LongString {
+    #[inline]
+    fn from(s: ShortString) -> Self {
+        Self(s.0)
+    }
+}
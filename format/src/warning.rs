@@ -0,0 +1,21 @@
+use crate::Key;
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Warning {
+    // `raw` holds the discarded bytes when `ReadOptions::capture_discarded` is set
+    InvalidUtf8 {
+        key: Key,
+        raw: Option<Vec<u8>>,
+    },
+    // Replacement characters take more room than the bytes they replace, `len` is how long the
+    // lossy string was before being cut back to what its length prefix allows
+    TruncatedString {
+        key: Key,
+        len: usize,
+    },
+    CorruptEntries {
+        offset: usize,
+        len: usize,
+        raw: Option<Vec<u8>>,
+    },
+}
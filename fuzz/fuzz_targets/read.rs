@@ -0,0 +1,7 @@
+#![no_main]
+use blister::Playlist;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = Playlist::read(data, false);
+});
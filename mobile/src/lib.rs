@@ -0,0 +1,171 @@
+// The generated scaffolding doesn't pass clippy
+#![allow(clippy::empty_line_after_doc_comments)]
+
+use std::{
+    convert::TryInto,
+    sync::{Mutex, MutexGuard},
+};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum BlisterError {
+    #[error("failed to read playlist: {0}")]
+    Read(blister::error::Error),
+    #[error("failed to write playlist: {0}")]
+    Write(blister::error::Error),
+    #[error("SHA1 hashes must be 20 bytes long")]
+    InvalidHash,
+    #[error("beatmap index out of bounds")]
+    OutOfBounds,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum BeatmapType {
+    Key,
+    Hash,
+    Zip,
+    LevelId,
+    Unknown,
+}
+
+impl From<blister::BeatmapType> for BeatmapType {
+    fn from(ty: blister::BeatmapType) -> Self {
+        match ty {
+            blister::BeatmapType::Key => Self::Key,
+            blister::BeatmapType::Hash => Self::Hash,
+            blister::BeatmapType::Zip => Self::Zip,
+            blister::BeatmapType::LevelId => Self::LevelId,
+            blister::BeatmapType::Unknown => Self::Unknown,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Beatmap {
+    pub ty: BeatmapType,
+    pub date_added: i64,
+    pub key: Option<u32>,
+    pub hash: Option<Vec<u8>>,
+    pub zip: Option<Vec<u8>>,
+    pub level_id: Option<String>,
+}
+
+impl From<&blister::Beatmap> for Beatmap {
+    fn from(beatmap: &blister::Beatmap) -> Self {
+        Self {
+            ty: beatmap.ty.into(),
+            date_added: beatmap.date_added.timestamp(),
+            key: beatmap.key,
+            hash: beatmap.hash.map(|h| h.to_vec()),
+            // Playlists are never read with a spill here, so zips are always in memory
+            zip: beatmap
+                .zip
+                .as_ref()
+                .and_then(|z| z.as_bytes())
+                .map(<[u8]>::to_vec),
+            level_id: beatmap.level_id.clone(),
+        }
+    }
+}
+
+// Objects are shared across the FFI boundary so they need interior mutability
+#[derive(Debug)]
+pub struct Playlist(Mutex<blister::Playlist>);
+
+impl Playlist {
+    pub fn new(title: String, author: String) -> Self {
+        Self(Mutex::new(blister::Playlist::new(title, author)))
+    }
+
+    pub fn read(data: Vec<u8>, strict: bool) -> Result<Self, BlisterError> {
+        let playlist =
+            blister::Playlist::read(data.as_slice(), strict).map_err(BlisterError::Read)?;
+        Ok(Self(Mutex::new(playlist)))
+    }
+
+    fn lock(&self) -> MutexGuard<'_, blister::Playlist> {
+        self.0.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub fn write(&self) -> Result<Vec<u8>, BlisterError> {
+        let mut buffer = Vec::new();
+        self.lock()
+            .write(&mut buffer)
+            .map_err(BlisterError::Write)?;
+        Ok(buffer)
+    }
+
+    pub fn title(&self) -> String {
+        self.lock().title.clone()
+    }
+
+    pub fn set_title(&self, title: String) {
+        self.lock().title = title;
+    }
+
+    pub fn author(&self) -> String {
+        self.lock().author.clone()
+    }
+
+    pub fn set_author(&self, author: String) {
+        self.lock().author = author;
+    }
+
+    pub fn description(&self) -> Option<String> {
+        self.lock().description.clone()
+    }
+
+    pub fn set_description(&self, description: Option<String>) {
+        self.lock().description = description;
+    }
+
+    pub fn cover(&self) -> Option<Vec<u8>> {
+        self.lock().cover.as_deref().map(ToOwned::to_owned)
+    }
+
+    pub fn set_cover(&self, cover: Option<Vec<u8>>) {
+        self.lock().cover = cover.map(Into::into);
+    }
+
+    pub fn maps(&self) -> Vec<Beatmap> {
+        self.lock().maps.iter().map(Into::into).collect()
+    }
+
+    pub fn add_key(&self, key: u32) {
+        self.lock().maps.push(blister::Beatmap::new_key(key));
+    }
+
+    pub fn add_hash(&self, hash: Vec<u8>) -> Result<(), BlisterError> {
+        let hash: [u8; 20] = hash
+            .as_slice()
+            .try_into()
+            .map_err(|_| BlisterError::InvalidHash)?;
+        self.lock()
+            .maps
+            .push(blister::Beatmap::new_hash(hash.into()));
+        Ok(())
+    }
+
+    pub fn add_zip(&self, zip: Vec<u8>) {
+        self.lock().maps.push(blister::Beatmap::new_zip(zip));
+    }
+
+    pub fn add_level_id(&self, level_id: String) {
+        self.lock()
+            .maps
+            .push(blister::Beatmap::new_level_id(level_id));
+    }
+
+    pub fn remove_map(&self, index: u64) -> Result<(), BlisterError> {
+        let mut playlist = self.lock();
+        match index.try_into() {
+            Ok(index) if index < playlist.maps.len() => {
+                playlist.maps.remove(index);
+                Ok(())
+            }
+            _ => Err(BlisterError::OutOfBounds),
+        }
+    }
+}
+
+uniffi::include_scaffolding!("blister");
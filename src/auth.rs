@@ -0,0 +1,81 @@
+use crate::{error::Error, Playlist, ReadOptions, Result};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use std::io::{Read, Write};
+
+type HmacSha256 = Hmac<Sha256>;
+
+const MAC_LEN: usize = 32;
+
+impl Playlist {
+    pub fn read_authenticated<R>(reader: R, key: &[u8], strict: bool) -> Result<Self>
+    where
+        R: Read,
+    {
+        Self::read_authenticated_with_options(
+            reader,
+            key,
+            &ReadOptions {
+                strict,
+                ..Default::default()
+            },
+        )
+    }
+
+    // The whole file is buffered before the MAC can be checked, `max_total_len` bounds it
+    pub fn read_authenticated_with_options<R>(
+        reader: R,
+        key: &[u8],
+        options: &ReadOptions,
+    ) -> Result<Self>
+    where
+        R: Read,
+    {
+        let mut buffer = Vec::new();
+        match options.max_total_len {
+            Some(max) => {
+                reader
+                    .take((max as u64).saturating_add(1))
+                    .read_to_end(&mut buffer)?;
+                if buffer.len() > max {
+                    return Err(Error::PlaylistTooLarge(max));
+                }
+            }
+            None => {
+                let mut reader = reader;
+                reader.read_to_end(&mut buffer)?;
+            }
+        }
+        if buffer.len() < MAC_LEN {
+            return Err(Error::MissingMac);
+        }
+
+        let (data, tag) = buffer.split_at(buffer.len() - MAC_LEN);
+        mac(key, data)
+            .verify_slice(tag)
+            .map_err(|_| Error::InvalidMac)?;
+
+        Self::read_with_options(data, options)
+    }
+
+    pub fn write_authenticated<W>(&self, mut writer: W, key: &[u8]) -> Result<()>
+    where
+        W: Write,
+    {
+        let mut buffer = Vec::new();
+        self.write(&mut buffer)?;
+
+        let tag = mac(key, &buffer).finalize().into_bytes();
+        writer.write_all(&buffer)?;
+        writer.write_all(&tag)?;
+        Ok(())
+    }
+}
+
+#[inline]
+fn mac(key: &[u8], data: &[u8]) -> HmacSha256 {
+    // HMAC accepts keys of any length
+    let mut mac = HmacSha256::new_from_slice(key).unwrap();
+    mac.update(data);
+    mac
+}
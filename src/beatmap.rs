@@ -1,22 +1,36 @@
-use crate::{error::Error, Result};
-use blister_format::{values::Sha1, Map, Value};
+use crate::{
+    builder::check_len, error::Error, ReadOptions, Result, LONG_STRING_LEN, SHORT_STRING_LEN,
+};
+#[cfg(feature = "chrono")]
+use crate::{Clock, SystemClock};
+use blister_format::{
+    error::Error as FormatError,
+    values::{Binary, Sha1},
+    Key, Map, Value, ValueRef,
+};
+#[cfg(feature = "chrono")]
 use chrono::{DateTime, TimeZone, Utc};
 use num_enum::{IntoPrimitive, TryFromPrimitive};
-use std::{
-    convert::TryInto,
-    io::{Read, Write},
-};
+use std::{convert::TryInto, io::Read};
+
+pub(crate) const ZIP_KEY: u32 = 4;
+// JSON fields preserved from a bplist
+pub(crate) const UNKNOWN_FIELDS_KEY: u32 = 12;
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct Beatmap {
     pub ty: BeatmapType,
+    // Without `chrono` the date stays in the custom data as raw seconds
+    #[cfg(feature = "chrono")]
     pub date_added: DateTime<Utc>,
 
     pub key: Option<u32>,
     pub hash: Option<Sha1>,
-    pub zip: Option<Vec<u8>>,
+    pub zip: Option<Binary>,
     pub level_id: Option<String>,
 
+    pub note: Option<String>,
+
     pub custom_data: Map,
 }
 
@@ -33,132 +47,272 @@ pub enum BeatmapType {
 
 impl BeatmapType {
     #[inline]
-    fn from(u: u8) -> Self {
+    pub(crate) fn from(u: u8) -> Self {
         match u {
             0..=3 => u.try_into().unwrap(),
             _ => Self::Unknown,
         }
     }
+
+    pub(crate) fn read(u: u8, strict: bool) -> Result<Self> {
+        let ty = Self::from(u);
+        if strict && ty == Self::Unknown {
+            return Err(Error::StrictModeUnknownBeatmapType(u));
+        }
+        Ok(ty)
+    }
+
+    pub(crate) fn check_identifier(
+        self,
+        key: bool,
+        hash: bool,
+        zip: bool,
+        level_id: bool,
+    ) -> Result<()> {
+        match self {
+            Self::Key if !key => Err(Error::MissingBeatmapKey),
+            Self::Hash if !hash => Err(Error::MissingBeatmapHash),
+            Self::Zip if !zip => Err(Error::MissingBeatmapZip),
+            Self::LevelId if !level_id => Err(Error::MissingBeatmapLevelId),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(feature = "chrono")]
+pub(crate) fn date_from_timestamp(u: u64) -> Result<DateTime<Utc>> {
+    match Utc.timestamp_opt(u.try_into()?, 0).single() {
+        Some(d) => Ok(d),
+        None => Err(Error::InvalidBeatmapDateAdded(Some(Value::U64(u)))),
+    }
 }
 
 impl Beatmap {
+    #[inline]
     pub fn new_key(key: u32) -> Self {
+        #[cfg(feature = "chrono")]
+        return Self::new_key_with_clock(key, &SystemClock);
+        #[cfg(not(feature = "chrono"))]
+        return Self {
+            key: Some(key),
+            ..Self::empty(BeatmapType::Key)
+        };
+    }
+
+    #[cfg(feature = "chrono")]
+    pub fn new_key_with_clock<C>(key: u32, clock: &C) -> Self
+    where
+        C: Clock + ?Sized,
+    {
         Self {
-            ty: BeatmapType::Key,
-            date_added: Utc::now(),
             key: Some(key),
-            hash: None,
-            zip: None,
-            level_id: None,
-            custom_data: Default::default(),
+            ..Self::empty(BeatmapType::Key, clock.now())
         }
     }
 
+    #[inline]
     pub fn new_hash(hash: Sha1) -> Self {
+        #[cfg(feature = "chrono")]
+        return Self::new_hash_with_clock(hash, &SystemClock);
+        #[cfg(not(feature = "chrono"))]
+        return Self {
+            hash: Some(hash),
+            ..Self::empty(BeatmapType::Hash)
+        };
+    }
+
+    #[cfg(feature = "chrono")]
+    pub fn new_hash_with_clock<C>(hash: Sha1, clock: &C) -> Self
+    where
+        C: Clock + ?Sized,
+    {
         Self {
-            ty: BeatmapType::Hash,
-            date_added: Utc::now(),
-            key: None,
             hash: Some(hash),
-            zip: None,
-            level_id: None,
-            custom_data: Default::default(),
+            ..Self::empty(BeatmapType::Hash, clock.now())
         }
     }
 
-    pub fn new_zip(zip: Vec<u8>) -> Self {
+    #[inline]
+    pub fn new_zip<B>(zip: B) -> Self
+    where
+        B: Into<Binary>,
+    {
+        #[cfg(feature = "chrono")]
+        return Self::new_zip_with_clock(zip, &SystemClock);
+        #[cfg(not(feature = "chrono"))]
+        return Self {
+            zip: Some(zip.into()),
+            ..Self::empty(BeatmapType::Zip)
+        };
+    }
+
+    #[cfg(feature = "chrono")]
+    pub fn new_zip_with_clock<B, C>(zip: B, clock: &C) -> Self
+    where
+        B: Into<Binary>,
+        C: Clock + ?Sized,
+    {
         Self {
-            ty: BeatmapType::Zip,
-            date_added: Utc::now(),
-            key: None,
-            hash: None,
-            zip: Some(zip),
-            level_id: None,
-            custom_data: Default::default(),
+            zip: Some(zip.into()),
+            ..Self::empty(BeatmapType::Zip, clock.now())
         }
     }
 
+    #[inline]
     pub fn new_level_id(level_id: String) -> Self {
+        #[cfg(feature = "chrono")]
+        return Self::new_level_id_with_clock(level_id, &SystemClock);
+        #[cfg(not(feature = "chrono"))]
+        return Self {
+            level_id: Some(level_id),
+            ..Self::empty(BeatmapType::LevelId)
+        };
+    }
+
+    #[cfg(feature = "chrono")]
+    pub fn new_level_id_with_clock<C>(level_id: String, clock: &C) -> Self
+    where
+        C: Clock + ?Sized,
+    {
         Self {
-            ty: BeatmapType::LevelId,
-            date_added: Utc::now(),
+            level_id: Some(level_id),
+            ..Self::empty(BeatmapType::LevelId, clock.now())
+        }
+    }
+
+    // Shared by the constructors, which only differ in the identifier they set
+    pub(crate) fn empty(
+        ty: BeatmapType,
+        #[cfg(feature = "chrono")] date_added: DateTime<Utc>,
+    ) -> Self {
+        #[allow(unused_mut)]
+        let mut custom_data = Map::new();
+        #[cfg(not(feature = "chrono"))]
+        custom_data.insert(1, Value::U64(crate::clock::now_secs()));
+        Self {
+            ty,
+            #[cfg(feature = "chrono")]
+            date_added,
             key: None,
             hash: None,
             zip: None,
-            level_id: Some(level_id),
-            custom_data: Default::default(),
+            level_id: None,
+            note: None,
+            custom_data,
         }
     }
 
-    pub(crate) fn read<R>(mut reader: R, strict: bool) -> Result<Self>
+    // Seconds since the epoch, whether or not the date was parsed
+    #[inline]
+    pub fn date_added_secs(&self) -> Option<u64> {
+        #[cfg(feature = "chrono")]
+        return self.date_added.timestamp().try_into().ok();
+        #[cfg(not(feature = "chrono"))]
+        return self.custom_data.get(1).and_then(|v| v.to_u64(true));
+    }
+
+    pub fn set_level_id<S>(&mut self, level_id: S) -> Result<()>
+    where
+        S: Into<String>,
+    {
+        let level_id = level_id.into();
+        check_len("level ID", &level_id, SHORT_STRING_LEN)?;
+        self.level_id = Some(level_id);
+        Ok(())
+    }
+
+    pub fn set_note<S>(&mut self, note: S) -> Result<()>
+    where
+        S: Into<String>,
+    {
+        let note = note.into();
+        check_len("note", &note, LONG_STRING_LEN)?;
+        self.note = Some(note);
+        Ok(())
+    }
+
+    pub(crate) fn read<R>(
+        mut reader: R,
+        options: &ReadOptions,
+        format: &blister_format::ReadOptions,
+        warnings: &mut Vec<blister_format::Warning>,
+    ) -> Result<Self>
     where
         R: Read,
     {
-        let mut data = Map::with_capacity(2);
-        data.read(&mut reader)?;
+        let mut data = Map::new();
+        data.read_with_options(&mut reader, format, warnings)
+            .map_err(|e| match e {
+                FormatError::FieldTooLarge { len, .. } => Error::ZipTooLarge(len),
+                e => e.into(),
+            })?;
 
-        let ty = match data.remove(0) {
+        let (ty, extension) = match data.remove(0) {
             Some(Value::U8(u)) => {
-                let ty = BeatmapType::from(u);
-                if strict && ty == BeatmapType::Unknown {
-                    return Err(Error::StrictModeUnknownBeatmapType(u));
+                let extension = options.extensions.as_ref().and_then(|e| e.get(u));
+                let ty = BeatmapType::read(u, options.strict && extension.is_none())?;
+                if ty == BeatmapType::Unknown {
+                    data.insert(0, Value::U8(u));
                 }
-                ty
+                (ty, extension)
             }
             v => return Err(Error::InvalidBeatmapType(v)),
         };
+        #[cfg(feature = "chrono")]
         let date_added = match data.remove(1) {
-            Some(Value::U64(u)) => Utc.timestamp(u.try_into()?, 0),
-            v => return Err(Error::InvalidBeatmapDateAdded(v)),
+            Some(v) => match v.to_u64(options.widen_integers) {
+                Some(u) => date_from_timestamp(u)?,
+                None => return Err(Error::InvalidBeatmapDateAdded(Some(v))),
+            },
+            None => return Err(Error::InvalidBeatmapDateAdded(None)),
         };
+        // Still checked so the raw seconds left in the custom data are always a valid date
+        #[cfg(not(feature = "chrono"))]
+        match data.get(1) {
+            Some(v) if v.to_u64(options.widen_integers).is_some() => (),
+            v => return Err(Error::InvalidBeatmapDateAdded(v.cloned())),
+        }
 
         let key = match data.remove(2) {
-            Some(Value::U32(u)) => Some(u),
+            Some(v) => match v.to_u32(options.widen_integers) {
+                Some(u) => Some(u),
+                None => return Err(Error::InvalidBeatmapKey(Some(v))),
+            },
             None => None,
-            v => return Err(Error::InvalidBeatmapKey(v)),
         };
         let hash = match data.remove(3) {
             Some(Value::Sha1(h)) => Some(h),
             None => None,
             v => return Err(Error::InvalidBeatmapHash(v)),
         };
-        let zip = match data.remove(4) {
+        let zip = match data.remove(ZIP_KEY) {
             Some(Value::Binary(b)) => Some(b),
+            // Archives too large for a 32 bit length
+            Some(Value::LongBinary(b)) => Some(b.into_binary()?),
             None => None,
             v => return Err(Error::InvalidBeatmapZip(v)),
         };
         let level_id = match data.remove(5) {
-            Some(Value::ShortString(s)) => Some(s),
+            Some(Value::ShortString(s)) => Some(s.into()),
             None => None,
             v => return Err(Error::InvalidBeatmapLevelId(v)),
         };
+        let note = match data.remove(6) {
+            Some(Value::LongString(s)) => Some(s.into()),
+            None => None,
+            v => return Err(Error::InvalidBeatmapNote(v)),
+        };
 
-        match ty {
-            BeatmapType::Key => {
-                if key.is_none() {
-                    return Err(Error::MissingBeatmapKey);
-                }
-            }
-            BeatmapType::Hash => {
-                if hash.is_none() {
-                    return Err(Error::MissingBeatmapHash);
-                }
-            }
-            BeatmapType::Zip => {
-                if zip.is_none() {
-                    return Err(Error::MissingBeatmapZip);
-                }
-            }
-            BeatmapType::LevelId => {
-                if level_id.is_none() {
-                    return Err(Error::MissingBeatmapLevelId);
-                }
-            }
-            _ => (),
-        }
+        ty.check_identifier(
+            key.is_some(),
+            hash.is_some(),
+            zip.is_some(),
+            level_id.is_some(),
+        )?;
 
-        Ok(Self {
+        let map = Self {
             ty,
+            #[cfg(feature = "chrono")]
             date_added,
 
             key,
@@ -166,40 +320,50 @@ impl Beatmap {
             zip,
             level_id,
 
+            note,
+
             custom_data: data,
-        })
+        };
+        if let Some(extension) = extension {
+            extension.validate(&map)?;
+        }
+        Ok(map)
     }
 
-    pub(crate) fn write<W>(self, mut writer: W) -> Result<()>
-    where
-        W: Write,
-    {
-        let Self {
-            ty,
-            date_added,
-            key,
-            hash,
-            zip,
-            level_id,
-            custom_data: mut data,
-        } = self;
-
-        data.insert(0, Value::U8(ty.into()));
-        data.insert(1, Value::U64(date_added.timestamp().try_into()?));
-        if let Some(u) = key {
-            data.insert(2, Value::U32(u));
+    // Core fields written over the custom data, which keeps its entries for fields left unset.
+    // Without `custom_data` the fields are meant to be written over an empty map instead.
+    pub(crate) fn overlay(&self, custom_data: bool) -> Result<Vec<(Key, Option<ValueRef<'_>>)>> {
+        let mut overlay = Vec::with_capacity(7);
+        // Unknown types are kept as they were read
+        let raw_ty = custom_data && matches!(self.custom_data.get(0), Some(Value::U8(_)));
+        if self.ty != BeatmapType::Unknown || !raw_ty {
+            overlay.push((0.into(), Some(ValueRef::U8(self.ty.into()))));
         }
-        if let Some(h) = hash {
-            data.insert(3, Value::Sha1(h));
+        #[cfg(feature = "chrono")]
+        overlay.push((
+            1.into(),
+            Some(ValueRef::U64(self.date_added.timestamp().try_into()?)),
+        ));
+        // The raw date is part of the custom data, but still has to be written without it
+        #[cfg(not(feature = "chrono"))]
+        if let (false, Some(v)) = (custom_data, self.custom_data.get(1)) {
+            overlay.push((1.into(), v.to_ref()));
         }
-        if let Some(b) = zip {
-            data.insert(4, Value::Binary(b));
+        if let Some(u) = self.key {
+            overlay.push((2.into(), Some(ValueRef::U32(u))));
         }
-        if let Some(s) = level_id {
-            data.insert(5, Value::ShortString(s));
+        if let Some(h) = self.hash {
+            overlay.push((3.into(), Some(ValueRef::Sha1(h))));
         }
-
-        data.write(&mut writer)?;
-        Ok(())
+        if let Some(b) = &self.zip {
+            overlay.push((4.into(), Some(ValueRef::binary(b))));
+        }
+        if let Some(s) = &self.level_id {
+            overlay.push((5.into(), Some(ValueRef::short_string(s)?)));
+        }
+        if let Some(s) = &self.note {
+            overlay.push((6.into(), Some(ValueRef::long_string(s)?)));
+        }
+        Ok(overlay)
     }
 }
@@ -0,0 +1,97 @@
+use crate::{
+    error::Error, http::HttpDownloader, Beatmap, HttpClient, Resolution, Resolver, SongInfo,
+};
+use blister_format::values::Sha1;
+use serde_json::Value;
+
+pub const BEATSAVER_API_URL: &str = "https://api.beatsaver.com";
+
+// Resolves maps through the BeatSaver API, going through the downloader so lookups share its
+// retries and concurrency limit with every other request
+#[derive(Debug)]
+pub struct BeatSaverResolver<C> {
+    downloader: HttpDownloader<C>,
+    base_url: String,
+}
+
+impl<C> BeatSaverResolver<C>
+where
+    C: HttpClient,
+{
+    #[inline]
+    pub fn new(downloader: HttpDownloader<C>) -> Self {
+        Self {
+            downloader,
+            base_url: BEATSAVER_API_URL.to_owned(),
+        }
+    }
+
+    #[inline]
+    pub fn with_base_url(mut self, base_url: String) -> Self {
+        self.base_url = base_url;
+        self
+    }
+
+    #[inline]
+    pub fn downloader(&self) -> &HttpDownloader<C> {
+        &self.downloader
+    }
+
+    fn url(&self, map: &Beatmap) -> Option<String> {
+        let base = self.base_url.trim_end_matches('/');
+        match (map.key, map.hash) {
+            (Some(key), _) => Some(format!("{}/maps/id/{:x}", base, key)),
+            (None, Some(hash)) => Some(format!("{}/maps/hash/{}", base, hash)),
+            (None, None) => None,
+        }
+    }
+}
+
+impl<C> Resolver for BeatSaverResolver<C>
+where
+    C: HttpClient,
+{
+    fn resolve(&self, map: &Beatmap) -> Resolution {
+        let url = match self.url(map) {
+            Some(url) => url,
+            None => return Resolution::Unknown,
+        };
+        match self.downloader.fetch(&url) {
+            Ok(body) => match serde_json::from_slice(&body) {
+                Ok(json) => Resolution::Found(song_info(&json)),
+                Err(_) => Resolution::Unknown,
+            },
+            Err(Error::Http { status: 404, .. }) => Resolution::NotFound,
+            Err(_) => Resolution::Unknown,
+        }
+    }
+}
+
+// Only the latest version of the map is looked at
+fn song_info(json: &Value) -> SongInfo {
+    let string = |v: &Value| v.as_str().map(str::to_owned);
+    let metadata = &json["metadata"];
+    let version = &json["versions"][0];
+    SongInfo {
+        key: json["id"]
+            .as_str()
+            .and_then(|id| u32::from_str_radix(id, 16).ok()),
+        hash: version["hash"].as_str().and_then(Sha1::from_hex),
+        name: string(&metadata["songName"]),
+        author: string(&metadata["songAuthorName"]),
+        mapper: string(&metadata["levelAuthorName"]),
+        difficulties: difficulties(version),
+    }
+}
+
+// The same difficulty shows up once per characteristic
+fn difficulties(version: &Value) -> Vec<String> {
+    let mut difficulties: Vec<String> = Vec::new();
+    let diffs = version["diffs"].as_array().into_iter().flatten();
+    for difficulty in diffs.filter_map(|d| d["difficulty"].as_str()) {
+        if !difficulties.iter().any(|d| d == difficulty) {
+            difficulties.push(difficulty.to_owned());
+        }
+    }
+    difficulties
+}
@@ -0,0 +1,275 @@
+#[cfg(feature = "chrono")]
+use crate::beatmap::date_from_timestamp;
+#[cfg(feature = "gzip")]
+use crate::read_map_encoding;
+use crate::{
+    error::Error,
+    folder::{decode_children, ChildPlaylistRef, CHILDREN_KEY},
+    localized::{decode_localized_refs, LOCALIZED_DESCRIPTIONS_KEY, LOCALIZED_TITLES_KEY},
+    nested::decode_str_refs,
+    playlist::TAGS_KEY,
+    Beatmap, BeatmapType, LocalizedStrings, Playlist, Result, PREALLOCATION_LIMIT,
+};
+use blister_format::{values::Sha1, MapEncoding, MapRef, Value, ValueRef};
+use byteorder::{ReadBytesExt, LE};
+#[cfg(feature = "chrono")]
+use chrono::{DateTime, Utc};
+#[cfg(feature = "gzip")]
+use flate2::bufread::GzDecoder;
+#[cfg(feature = "gzip")]
+use std::io::{BufReader, Read};
+
+#[derive(Debug, Clone)]
+pub struct PlaylistRef<'a> {
+    pub title: &'a str,
+    pub author: &'a str,
+    pub description: Option<&'a str>,
+    pub cover: Option<&'a [u8]>,
+    // Pairs of language tag and string
+    pub localized_titles: Vec<(&'a str, &'a str)>,
+    pub localized_descriptions: Vec<(&'a str, &'a str)>,
+    pub tags: Vec<&'a str>,
+    pub children: Vec<ChildPlaylistRef<'a>>,
+
+    pub maps: Vec<BeatmapRef<'a>>,
+
+    pub data: MapRef<'a>,
+}
+
+#[derive(Debug, Clone)]
+pub struct BeatmapRef<'a> {
+    pub ty: BeatmapType,
+    // Left in `data` without `chrono`, like owned maps
+    #[cfg(feature = "chrono")]
+    pub date_added: DateTime<Utc>,
+
+    pub key: Option<u32>,
+    pub hash: Option<Sha1>,
+    pub zip: Option<&'a [u8]>,
+    pub level_id: Option<&'a str>,
+
+    pub note: Option<&'a str>,
+
+    pub data: MapRef<'a>,
+}
+
+impl<'a> PlaylistRef<'a> {
+    // Also returns the encoding the magic number selects, which the body has to be parsed with
+    #[cfg(feature = "gzip")]
+    pub fn decompress<R>(mut reader: R) -> Result<(Vec<u8>, MapEncoding)>
+    where
+        R: Read,
+    {
+        let encoding = read_map_encoding(&mut reader)?;
+
+        let mut body = Vec::new();
+        GzDecoder::new(BufReader::new(reader)).read_to_end(&mut body)?;
+        Ok((body, encoding))
+    }
+
+    #[inline]
+    pub fn parse(body: &'a [u8], strict: bool) -> Result<Self> {
+        Self::parse_with_encoding(body, MapEncoding::Length, strict)
+    }
+
+    pub fn parse_with_encoding(
+        mut body: &'a [u8],
+        encoding: MapEncoding,
+        strict: bool,
+    ) -> Result<Self> {
+        let data = MapRef::parse_with_encoding(&mut body, encoding)?;
+
+        let title = match data.get(0) {
+            Some(ValueRef::ShortString(s)) => s,
+            v => return Err(Error::InvalidPlaylistTitle(owned(v))),
+        };
+        let author = match data.get(1) {
+            Some(ValueRef::ShortString(s)) => s,
+            v => return Err(Error::InvalidPlaylistAuthor(owned(v))),
+        };
+        let description = match data.get(2) {
+            Some(ValueRef::LongString(s)) => Some(s),
+            None => None,
+            v => return Err(Error::InvalidPlaylistDescription(owned(v))),
+        };
+        let cover = match data.get(3) {
+            Some(ValueRef::Binary(b)) => Some(b),
+            None => None,
+            v => return Err(Error::InvalidPlaylistCover(owned(v))),
+        };
+        let localized_titles = match data.get(LOCALIZED_TITLES_KEY) {
+            Some(ValueRef::Binary(b)) => decode_localized_refs(b)?,
+            None => Vec::new(),
+            v => return Err(Error::InvalidPlaylistLocalizedTitles(owned(v))),
+        };
+        let localized_descriptions = match data.get(LOCALIZED_DESCRIPTIONS_KEY) {
+            Some(ValueRef::Binary(b)) => decode_localized_refs(b)?,
+            None => Vec::new(),
+            v => return Err(Error::InvalidPlaylistLocalizedDescriptions(owned(v))),
+        };
+        let tags = match data.get(TAGS_KEY) {
+            Some(ValueRef::Binary(b)) => decode_str_refs(b)?,
+            None => Vec::new(),
+            v => return Err(Error::InvalidPlaylistTags(owned(v))),
+        };
+        let children = match data.get(CHILDREN_KEY) {
+            Some(ValueRef::Binary(b)) => decode_children(b)?,
+            None => Vec::new(),
+            v => return Err(Error::InvalidPlaylistChildren(owned(v))),
+        };
+
+        let map_count = body.read_u32::<LE>()? as usize;
+        let mut maps = Vec::with_capacity(map_count.min(PREALLOCATION_LIMIT));
+        for _ in 0..map_count {
+            maps.push(BeatmapRef::parse(&mut body, encoding, strict)?);
+        }
+
+        Ok(Self {
+            title,
+            author,
+            description,
+            cover,
+            localized_titles,
+            localized_descriptions,
+            tags,
+            children,
+            maps,
+            data,
+        })
+    }
+
+    pub fn to_owned(&self) -> Playlist {
+        let mut custom_data = self.data.to_owned();
+        for key in 0..6 {
+            custom_data.remove(key);
+        }
+        custom_data.remove(TAGS_KEY);
+        custom_data.remove(CHILDREN_KEY);
+
+        Playlist {
+            title: self.title.to_owned(),
+            author: self.author.to_owned(),
+            description: self.description.map(ToOwned::to_owned),
+            cover: self.cover.map(Into::into),
+            localized_titles: localized(&self.localized_titles),
+            localized_descriptions: localized(&self.localized_descriptions),
+            tags: self.tags.iter().map(|&t| t.to_owned()).collect(),
+            children: self.children.iter().map(|&c| c.to_owned()).collect(),
+            maps: self.maps.iter().map(BeatmapRef::to_owned).collect(),
+            custom_data,
+        }
+    }
+}
+
+impl<'a> BeatmapRef<'a> {
+    fn parse(body: &mut &'a [u8], encoding: MapEncoding, strict: bool) -> Result<Self> {
+        let data = MapRef::parse_with_encoding(body, encoding)?;
+
+        let ty = match data.get(0) {
+            Some(ValueRef::U8(u)) => BeatmapType::read(u, strict)?,
+            v => return Err(Error::InvalidBeatmapType(owned(v))),
+        };
+        #[cfg(feature = "chrono")]
+        let date_added = match data.get(1) {
+            Some(ValueRef::U64(u)) => date_from_timestamp(u)?,
+            v => return Err(Error::InvalidBeatmapDateAdded(owned(v))),
+        };
+        #[cfg(not(feature = "chrono"))]
+        match data.get(1) {
+            Some(ValueRef::U64(_)) => (),
+            v => return Err(Error::InvalidBeatmapDateAdded(owned(v))),
+        }
+
+        let key = match data.get(2) {
+            Some(ValueRef::U32(u)) => Some(u),
+            None => None,
+            v => return Err(Error::InvalidBeatmapKey(owned(v))),
+        };
+        let hash = match data.get(3) {
+            Some(ValueRef::Sha1(h)) => Some(h),
+            None => None,
+            v => return Err(Error::InvalidBeatmapHash(owned(v))),
+        };
+        let zip = match data.get(4) {
+            Some(ValueRef::Binary(b) | ValueRef::LongBinary(b)) => Some(b),
+            None => None,
+            v => return Err(Error::InvalidBeatmapZip(owned(v))),
+        };
+        let level_id = match data.get(5) {
+            Some(ValueRef::ShortString(s)) => Some(s),
+            None => None,
+            v => return Err(Error::InvalidBeatmapLevelId(owned(v))),
+        };
+        let note = match data.get(6) {
+            Some(ValueRef::LongString(s)) => Some(s),
+            None => None,
+            v => return Err(Error::InvalidBeatmapNote(owned(v))),
+        };
+
+        ty.check_identifier(
+            key.is_some(),
+            hash.is_some(),
+            zip.is_some(),
+            level_id.is_some(),
+        )?;
+
+        Ok(Self {
+            ty,
+            #[cfg(feature = "chrono")]
+            date_added,
+
+            key,
+            hash,
+            zip,
+            level_id,
+
+            note,
+
+            data,
+        })
+    }
+
+    pub fn to_owned(&self) -> Beatmap {
+        let mut custom_data = self.data.to_owned();
+        // Unknown types keep their discriminant, like owned reads
+        let first = if self.ty == BeatmapType::Unknown {
+            1
+        } else {
+            0
+        };
+        for key in first..7 {
+            // The raw date stays when there is no field to move it to
+            if cfg!(feature = "chrono") || key != 1 {
+                custom_data.remove(key);
+            }
+        }
+
+        Beatmap {
+            ty: self.ty,
+            #[cfg(feature = "chrono")]
+            date_added: self.date_added,
+
+            key: self.key,
+            hash: self.hash,
+            zip: self.zip.map(Into::into),
+            level_id: self.level_id.map(ToOwned::to_owned),
+
+            note: self.note.map(ToOwned::to_owned),
+
+            custom_data,
+        }
+    }
+}
+
+#[inline]
+fn owned(value: Option<ValueRef>) -> Option<Value> {
+    value.map(|v| v.to_owned())
+}
+
+#[inline]
+fn localized(pairs: &[(&str, &str)]) -> LocalizedStrings {
+    pairs
+        .iter()
+        .map(|&(l, s)| (l.to_owned(), s.to_owned()))
+        .collect()
+}
@@ -0,0 +1,283 @@
+use crate::{
+    beatmap::UNKNOWN_FIELDS_KEY as MAP_FIELDS_KEY, error::Error,
+    playlist::UNKNOWN_FIELDS_KEY as PLAYLIST_FIELDS_KEY, Beatmap, BeatmapType, Playlist, Result,
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use blister_format::{values::Sha1, Map, Value};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map as JsonMap, Value as Json};
+use std::io::{Read, Write};
+
+const PLAYLIST_FIELDS: &[&str] = &[
+    "playlistTitle",
+    "playlistAuthor",
+    "playlistDescription",
+    "image",
+    "songs",
+];
+const SONG_FIELDS: &[&str] = &["key", "hash", "levelid", "dateAdded", "note"];
+
+// What happens to JSON fields this crate doesn't model, and on writes to custom data a bplist
+// can't hold
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum UnknownFieldPolicy {
+    // Kept as JSON under reserved custom data keys and written back out
+    Preserve,
+    Drop,
+    Error,
+}
+
+impl Default for UnknownFieldPolicy {
+    #[inline]
+    fn default() -> Self {
+        Self::Drop
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Bplist {
+    playlist_title: String,
+    #[serde(default)]
+    playlist_author: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    playlist_description: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    image: Option<String>,
+    #[serde(default)]
+    songs: Vec<Song>,
+    #[serde(flatten)]
+    unknown: JsonMap<String, Json>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Song {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    key: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    hash: Option<String>,
+    #[serde(default, rename = "levelid", skip_serializing_if = "Option::is_none")]
+    level_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    date_added: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    note: Option<String>,
+    #[serde(flatten)]
+    unknown: JsonMap<String, Json>,
+}
+
+impl Playlist {
+    #[inline]
+    pub fn read_bplist<R>(reader: R) -> Result<Self>
+    where
+        R: Read,
+    {
+        Self::read_bplist_with_policy(reader, UnknownFieldPolicy::Drop)
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip(reader), err)
+    )]
+    pub fn read_bplist_with_policy<R>(reader: R, policy: UnknownFieldPolicy) -> Result<Self>
+    where
+        R: Read,
+    {
+        let bplist: Bplist = serde_json::from_reader(reader)?;
+        let mut custom_data = Map::default();
+        read_unknown(
+            bplist.unknown,
+            policy,
+            None,
+            &mut custom_data,
+            PLAYLIST_FIELDS_KEY,
+        )?;
+
+        let cover = match bplist.image {
+            Some(image) => Some(decode_image(&image)?.into()),
+            None => None,
+        };
+        let maps = bplist
+            .songs
+            .into_iter()
+            .enumerate()
+            .map(|(i, song)| song.into_beatmap(i, policy))
+            .collect::<Result<_>>()?;
+
+        Ok(Self {
+            title: bplist.playlist_title,
+            author: bplist.playlist_author,
+            description: bplist.playlist_description,
+            cover,
+            localized_titles: Default::default(),
+            localized_descriptions: Default::default(),
+            tags: Vec::new(),
+            children: Vec::new(),
+            maps,
+            custom_data,
+        })
+    }
+
+    // Writes back the fields preserved when reading, any other custom data is lost
+    #[inline]
+    pub fn write_bplist<W>(&self, writer: W) -> Result<()>
+    where
+        W: Write,
+    {
+        self.write_bplist_with_policy(writer, UnknownFieldPolicy::Preserve)
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip(self, writer), err, fields(maps = self.maps.len()))
+    )]
+    pub fn write_bplist_with_policy<W>(&self, writer: W, policy: UnknownFieldPolicy) -> Result<()>
+    where
+        W: Write,
+    {
+        let unknown = write_unknown(
+            &self.custom_data,
+            policy,
+            None,
+            PLAYLIST_FIELDS_KEY,
+            PLAYLIST_FIELDS,
+        )?;
+        let bplist = Bplist {
+            playlist_title: self.title.clone(),
+            playlist_author: self.author.clone(),
+            playlist_description: self.description.clone(),
+            image: self.cover.as_ref().map(|c| STANDARD.encode(c)),
+            songs: self
+                .maps
+                .iter()
+                .enumerate()
+                .map(|(i, map)| Song::from_beatmap(map, i, policy))
+                .collect::<Result<_>>()?,
+            unknown,
+        };
+
+        serde_json::to_writer_pretty(writer, &bplist)?;
+        Ok(())
+    }
+}
+
+impl Song {
+    fn into_beatmap(self, index: usize, policy: UnknownFieldPolicy) -> Result<Beatmap> {
+        // Hashes are the most precise identifier so they take priority
+        let mut beatmap = match (self.hash, self.key, self.level_id) {
+            (Some(hash), _, _) => Beatmap::new_hash(decode_hash(&hash)?),
+            (None, Some(key), _) => Beatmap::new_key(decode_key(&key)?),
+            (None, None, Some(level_id)) => Beatmap::new_level_id(level_id),
+            (None, None, None) => return Err(Error::MissingBplistIdentifier),
+        };
+        if let Some(date_added) = self.date_added {
+            beatmap.date_added = date_added;
+        }
+        beatmap.note = self.note;
+        let data = &mut beatmap.custom_data;
+        read_unknown(self.unknown, policy, Some(index), data, MAP_FIELDS_KEY)?;
+        Ok(beatmap)
+    }
+
+    fn from_beatmap(beatmap: &Beatmap, index: usize, policy: UnknownFieldPolicy) -> Result<Self> {
+        let mut song = Self {
+            key: None,
+            hash: None,
+            level_id: None,
+            date_added: Some(beatmap.date_added),
+            note: beatmap.note.clone(),
+            unknown: write_unknown(
+                &beatmap.custom_data,
+                policy,
+                Some(index),
+                MAP_FIELDS_KEY,
+                SONG_FIELDS,
+            )?,
+        };
+        match beatmap.ty {
+            BeatmapType::Key => song.key = beatmap.key.map(|k| format!("{:x}", k)),
+            BeatmapType::Hash => song.hash = beatmap.hash.map(|h| encode_hash(&h)),
+            BeatmapType::LevelId => song.level_id = beatmap.level_id.clone(),
+            BeatmapType::Zip | BeatmapType::Unknown => {
+                return Err(Error::UnsupportedBplistBeatmap(beatmap.ty))
+            }
+        }
+        Ok(song)
+    }
+}
+
+// Stored as a single compact JSON object
+fn read_unknown(
+    unknown: JsonMap<String, Json>,
+    policy: UnknownFieldPolicy,
+    map: Option<usize>,
+    data: &mut Map,
+    key: u32,
+) -> Result<()> {
+    if unknown.is_empty() {
+        return Ok(());
+    }
+    match policy {
+        UnknownFieldPolicy::Preserve => {
+            let json = serde_json::to_vec(&unknown)?;
+            data.insert(key, Value::Binary(json.into()));
+        }
+        UnknownFieldPolicy::Drop => (),
+        UnknownFieldPolicy::Error => {
+            let field = unknown.keys().next().cloned().unwrap_or_default();
+            return Err(Error::UnknownBplistField { map, field });
+        }
+    }
+    Ok(())
+}
+
+// Preserved fields named like modeled ones are skipped so they can't shadow them
+fn write_unknown(
+    data: &Map,
+    policy: UnknownFieldPolicy,
+    map: Option<usize>,
+    key: u32,
+    modeled: &[&str],
+) -> Result<JsonMap<String, Json>> {
+    match policy {
+        UnknownFieldPolicy::Drop => return Ok(JsonMap::new()),
+        UnknownFieldPolicy::Error => {
+            if let Some((&other, _)) = data.iter().find(|(&k, _)| *k != key) {
+                let field = format!("custom data key {}", *other);
+                return Err(Error::UnknownBplistField { map, field });
+            }
+        }
+        UnknownFieldPolicy::Preserve => (),
+    }
+    let mut unknown: JsonMap<String, Json> = match data.get(key) {
+        Some(Value::Binary(json)) => serde_json::from_reader(json.reader()?)?,
+        _ => JsonMap::new(),
+    };
+    unknown.retain(|field, _| !modeled.contains(&field.as_str()));
+    Ok(unknown)
+}
+
+fn decode_key(key: &str) -> Result<u32> {
+    u32::from_str_radix(key, 16).map_err(|_| Error::InvalidBplistKey(key.to_owned()))
+}
+
+fn decode_hash(hash: &str) -> Result<Sha1> {
+    Sha1::from_hex(hash).ok_or_else(|| Error::InvalidBplistHash(hash.to_owned()))
+}
+
+fn encode_hash(hash: &Sha1) -> String {
+    hash.iter().map(|b| format!("{:02X}", b)).collect()
+}
+
+// Images are sometimes stored as data URIs
+fn decode_image(image: &str) -> Result<Vec<u8>> {
+    let data = match image.find("base64,") {
+        Some(i) => &image[i + "base64,".len()..],
+        None => image,
+    };
+    STANDARD
+        .decode(data.trim())
+        .map_err(|_| Error::InvalidBplistImage)
+}
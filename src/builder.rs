@@ -0,0 +1,152 @@
+use crate::{
+    error::Error, Beatmap, ChildPlaylist, Playlist, Result, LONG_STRING_LEN, SHORT_STRING_LEN,
+};
+use blister_format::{Key, Map, Value};
+use std::{convert::TryFrom, sync::Arc};
+
+#[derive(Debug, Clone, Default)]
+pub struct PlaylistBuilder {
+    title: Option<String>,
+    author: Option<String>,
+    description: Option<String>,
+    cover: Option<Arc<[u8]>>,
+    tags: Vec<String>,
+    children: Vec<ChildPlaylist>,
+
+    maps: Vec<Beatmap>,
+
+    custom_data: Map,
+}
+
+impl Playlist {
+    #[inline]
+    pub fn builder() -> PlaylistBuilder {
+        Default::default()
+    }
+}
+
+impl PlaylistBuilder {
+    pub fn title<S>(mut self, title: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.title = Some(title.into());
+        self
+    }
+
+    pub fn author<S>(mut self, author: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.author = Some(author.into());
+        self
+    }
+
+    pub fn description<S>(mut self, description: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.description = Some(description.into());
+        self
+    }
+
+    pub fn cover<B>(mut self, cover: B) -> Self
+    where
+        B: Into<Arc<[u8]>>,
+    {
+        self.cover = Some(cover.into());
+        self
+    }
+
+    pub fn tag<S>(mut self, tag: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.tags.push(tag.into());
+        self
+    }
+
+    pub fn child(mut self, child: ChildPlaylist) -> Self {
+        self.children.push(child);
+        self
+    }
+
+    pub fn map(mut self, map: Beatmap) -> Self {
+        self.maps.push(map);
+        self
+    }
+
+    pub fn maps<I>(mut self, maps: I) -> Self
+    where
+        I: IntoIterator<Item = Beatmap>,
+    {
+        self.maps.extend(maps);
+        self
+    }
+
+    pub fn custom_data<K, V>(mut self, key: K, value: V) -> Self
+    where
+        K: Into<Key>,
+        V: Into<Value>,
+    {
+        self.custom_data.insert(key, value);
+        self
+    }
+
+    pub fn build(self) -> Result<Playlist> {
+        let title = self.title.ok_or(Error::MissingField("title"))?;
+        check_len("title", &title, SHORT_STRING_LEN)?;
+        let author = self.author.ok_or(Error::MissingField("author"))?;
+        check_len("author", &author, SHORT_STRING_LEN)?;
+        if let Some(description) = &self.description {
+            check_len("description", description, LONG_STRING_LEN)?;
+        }
+        if let Some(cover) = &self.cover {
+            if cover.is_empty() {
+                return Err(Error::EmptyCover);
+            }
+            u32::try_from(cover.len())?;
+        }
+        for tag in &self.tags {
+            check_len("tag", tag, SHORT_STRING_LEN)?;
+        }
+        for map in &self.maps {
+            if let Some(level_id) = &map.level_id {
+                check_len("level ID", level_id, SHORT_STRING_LEN)?;
+            }
+            if let Some(note) = &map.note {
+                check_len("note", note, LONG_STRING_LEN)?;
+            }
+            map.ty.check_identifier(
+                map.key.is_some(),
+                map.hash.is_some(),
+                map.zip.is_some(),
+                map.level_id.is_some(),
+            )?;
+        }
+
+        Ok(Playlist {
+            title,
+            author,
+            description: self.description,
+            cover: self.cover,
+            localized_titles: Default::default(),
+            localized_descriptions: Default::default(),
+            tags: self.tags,
+            children: self.children,
+            maps: self.maps,
+            custom_data: self.custom_data,
+        })
+    }
+}
+
+pub(crate) fn check_len(field: &'static str, s: &str, max: usize) -> Result<()> {
+    if s.len() > max {
+        return Err(Error::FieldTooLong {
+            field,
+            len: s.len(),
+            max,
+        });
+    }
+    Ok(())
+}
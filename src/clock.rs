@@ -0,0 +1,66 @@
+#[cfg(feature = "chrono")]
+use chrono::{DateTime, Utc};
+use std::time::Instant;
+
+// Source of the `date_added` of new maps
+#[cfg(feature = "chrono")]
+pub trait Clock {
+    fn now(&self) -> DateTime<Utc>;
+}
+
+#[cfg(feature = "chrono")]
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq, Hash)]
+pub struct SystemClock;
+
+// Always returns the same instant, mostly useful for tests and reproducible output
+#[cfg(feature = "chrono")]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct FixedClock(pub DateTime<Utc>);
+
+#[cfg(feature = "chrono")]
+impl Clock for SystemClock {
+    #[inline]
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl Clock for FixedClock {
+    #[inline]
+    fn now(&self) -> DateTime<Utc> {
+        self.0
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl<F> Clock for F
+where
+    F: Fn() -> DateTime<Utc>,
+{
+    #[inline]
+    fn now(&self) -> DateTime<Utc> {
+        self()
+    }
+}
+
+// `Instant::now` panics on wasm32-unknown-unknown, which has no clock to read
+#[inline]
+pub(crate) fn instant_now() -> Option<Instant> {
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    return Some(Instant::now());
+    #[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+    return None;
+}
+
+// Seconds since the epoch for the raw `date_added` of new maps in builds without `chrono`.
+// wasm32-unknown-unknown has no clock to read either, so maps are dated at the epoch there.
+#[cfg(not(feature = "chrono"))]
+pub(crate) fn now_secs() -> u64 {
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    return std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+    #[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+    return 0;
+}
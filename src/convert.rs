@@ -0,0 +1,198 @@
+use crate::{
+    error::Error, file::write_atomic, Playlist, ReadOptions, Result, UnknownFieldPolicy,
+    WriteOptions,
+};
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    io::BufReader,
+    path::{Path, PathBuf},
+    sync::Mutex,
+    thread,
+};
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum PlaylistFormat {
+    Blist,
+    // JSON playlists, with either a `.bplist` or `.json` extension
+    Bplist,
+}
+
+impl PlaylistFormat {
+    #[inline]
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()? {
+            "blist" => Some(Self::Blist),
+            "bplist" | "json" => Some(Self::Bplist),
+            _ => None,
+        }
+    }
+
+    #[inline]
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Blist => "blist",
+            Self::Bplist => "bplist",
+        }
+    }
+
+    #[inline]
+    pub fn other(self) -> Self {
+        match self {
+            Self::Blist => Self::Bplist,
+            Self::Bplist => Self::Blist,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct FormatOptions {
+    pub recursive: bool,
+    // Zero uses the available parallelism
+    pub threads: usize,
+    pub read: ReadOptions,
+    pub write: WriteOptions,
+    // Applies to reads from and writes to bplists alike
+    pub unknown_fields: UnknownFieldPolicy,
+}
+
+impl Default for FormatOptions {
+    #[inline]
+    fn default() -> Self {
+        Self {
+            recursive: false,
+            threads: 0,
+            read: Default::default(),
+            write: Default::default(),
+            unknown_fields: Default::default(),
+        }
+    }
+}
+
+// Both lists are sorted by source path
+#[derive(Debug, Default)]
+pub struct ConvertReport {
+    pub converted: Vec<(PathBuf, PathBuf)>,
+    pub failed: Vec<(PathBuf, Error)>,
+}
+
+impl ConvertReport {
+    #[inline]
+    pub fn is_complete(&self) -> bool {
+        self.failed.is_empty()
+    }
+}
+
+// Every recognized playlist under `src` is converted to the other format and written to the same
+// relative path under `dst`, files with other extensions are ignored
+pub fn convert_dir<S, D>(src: S, dst: D, options: &FormatOptions) -> Result<ConvertReport>
+where
+    S: AsRef<Path>,
+    D: AsRef<Path>,
+{
+    let (src, dst) = (src.as_ref(), dst.as_ref());
+    // Collecting everything first keeps outputs from being picked up when `dst` is inside `src`
+    let mut files = Vec::new();
+    collect(src, options.recursive, &mut files)?;
+    files.sort_by(|a, b| a.0.cmp(&b.0));
+
+    // Targets written by more than one source, or that are themselves sources, would be
+    // overwritten or read mid-write by another thread
+    let sources: HashSet<_> = files.iter().map(|(path, _)| path.clone()).collect();
+    let mut targets = HashMap::<_, usize>::new();
+    for (path, format) in &files {
+        *targets.entry(target(src, dst, path, *format)).or_default() += 1;
+    }
+    let mut collisions = Vec::new();
+    files.retain(|(path, format)| {
+        let target = target(src, dst, path, *format);
+        let collides = targets[&target] > 1 || sources.contains(&target);
+        if collides {
+            collisions.push((path.clone(), Error::ConvertCollision(target)));
+        }
+        !collides
+    });
+
+    let threads = match options.threads {
+        0 => thread::available_parallelism().map_or(1, |n| n.get()),
+        n => n,
+    };
+    let queue = Mutex::new(files.into_iter());
+    let results = Mutex::new(Vec::new());
+    thread::scope(|scope| {
+        for _ in 0..threads {
+            scope.spawn(|| loop {
+                let next = queue.lock().unwrap().next();
+                let (path, format) = match next {
+                    Some(next) => next,
+                    None => break,
+                };
+                let target = target(src, dst, &path, format);
+                let result = convert_file(&path, format, &target, options);
+                results.lock().unwrap().push((path, target, result));
+            });
+        }
+    });
+
+    let mut results = results.into_inner().unwrap();
+    results.sort_by(|a, b| a.0.cmp(&b.0));
+    let mut report = ConvertReport {
+        failed: collisions,
+        ..Default::default()
+    };
+    for (path, target, result) in results {
+        match result {
+            Ok(()) => report.converted.push((path, target)),
+            Err(e) => report.failed.push((path, e)),
+        }
+    }
+    report.failed.sort_by(|a, b| a.0.cmp(&b.0));
+    Ok(report)
+}
+
+fn collect(dir: &Path, recursive: bool, files: &mut Vec<(PathBuf, PlaylistFormat)>) -> Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        let ty = entry.file_type()?;
+        if ty.is_dir() {
+            if recursive {
+                collect(&path, recursive, files)?;
+            }
+        } else if let Some(format) = PlaylistFormat::from_path(&path) {
+            files.push((path, format));
+        }
+    }
+    Ok(())
+}
+
+fn target(src: &Path, dst: &Path, path: &Path, format: PlaylistFormat) -> PathBuf {
+    let relative = path.strip_prefix(src).unwrap_or(path);
+    dst.join(relative)
+        .with_extension(format.other().extension())
+}
+
+fn convert_file(
+    path: &Path,
+    format: PlaylistFormat,
+    target: &Path,
+    options: &FormatOptions,
+) -> Result<()> {
+    let reader = BufReader::new(fs::File::open(path)?);
+    let playlist = match format {
+        PlaylistFormat::Blist => Playlist::read_with_options(reader, &options.read)?,
+        PlaylistFormat::Bplist => {
+            Playlist::read_bplist_with_policy(reader, options.unknown_fields)?
+        }
+    };
+
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    write_atomic(target, |writer| match format.other() {
+        PlaylistFormat::Blist => playlist
+            .write_with_options(writer, &options.write)
+            .map(|_| ()),
+        PlaylistFormat::Bplist => playlist.write_bplist_with_policy(writer, options.unknown_fields),
+    })
+}
@@ -0,0 +1,72 @@
+use crate::{CoverRecompression, Playlist, Result};
+use image::{codecs::jpeg::JpegEncoder, DynamicImage, ImageOutputFormat};
+use std::io::Cursor;
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct CoverOptions {
+    pub threshold: usize,
+    pub max_dimension: u32,
+    pub quality: u8,
+}
+
+impl Default for CoverOptions {
+    #[inline]
+    fn default() -> Self {
+        Self {
+            threshold: 256 * 1024,
+            max_dimension: 512,
+            quality: 85,
+        }
+    }
+}
+
+impl Playlist {
+    // Covers with transparency are kept as PNG, everything else becomes a JPEG
+    pub fn recompress_cover(
+        &mut self,
+        options: &CoverOptions,
+    ) -> Result<Option<CoverRecompression>> {
+        let (cover, encoded) = match &self.cover {
+            Some(cover) => match recompress(cover, options)? {
+                Some(encoded) => (cover, encoded),
+                None => return Ok(None),
+            },
+            None => return Ok(None),
+        };
+        let recompression = CoverRecompression {
+            original: cover.len(),
+            recompressed: encoded.len(),
+        };
+        self.cover = Some(encoded.into());
+        Ok(Some(recompression))
+    }
+}
+
+// `None` when the cover is under the threshold or wouldn't get any smaller
+pub(crate) fn recompress(cover: &[u8], options: &CoverOptions) -> Result<Option<Vec<u8>>> {
+    if cover.len() <= options.threshold {
+        return Ok(None);
+    }
+
+    let mut image = image::load_from_memory(cover)?;
+    if image.width() > options.max_dimension || image.height() > options.max_dimension {
+        image = image.thumbnail(options.max_dimension, options.max_dimension);
+    }
+    let encoded = encode(&image, options.quality)?;
+
+    // Already well compressed covers are left alone
+    if encoded.len() >= cover.len() {
+        return Ok(None);
+    }
+    Ok(Some(encoded))
+}
+
+fn encode(image: &DynamicImage, quality: u8) -> Result<Vec<u8>> {
+    let mut encoded = Vec::new();
+    if image.color().has_alpha() {
+        image.write_to(&mut Cursor::new(&mut encoded), ImageOutputFormat::Png)?;
+    } else {
+        JpegEncoder::new_with_quality(&mut encoded, quality).encode_image(&image.to_rgb8())?;
+    }
+    Ok(encoded)
+}
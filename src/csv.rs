@@ -0,0 +1,133 @@
+use crate::{BeatmapType, Playlist, Result};
+#[cfg(feature = "chrono")]
+use chrono::SecondsFormat;
+use std::io::Write;
+
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct Columns {
+    pub ty: bool,
+    pub key: bool,
+    pub hash: bool,
+    pub level_id: bool,
+    pub date_added: bool,
+    pub note: bool,
+    pub custom_data: Vec<u32>,
+}
+
+impl Default for Columns {
+    #[inline]
+    fn default() -> Self {
+        Self {
+            ty: true,
+            key: true,
+            hash: true,
+            level_id: true,
+            date_added: true,
+            note: true,
+            custom_data: Vec::new(),
+        }
+    }
+}
+
+impl Playlist {
+    // One row per map with a header, following RFC 4180
+    pub fn to_csv<W>(&self, mut writer: W, columns: &Columns) -> Result<()>
+    where
+        W: Write,
+    {
+        let mut header = Vec::new();
+        if columns.ty {
+            header.push("type".to_owned());
+        }
+        if columns.key {
+            header.push("key".to_owned());
+        }
+        if columns.hash {
+            header.push("hash".to_owned());
+        }
+        if columns.level_id {
+            header.push("level_id".to_owned());
+        }
+        if columns.date_added {
+            header.push("date_added".to_owned());
+        }
+        if columns.note {
+            header.push("note".to_owned());
+        }
+        header.extend(columns.custom_data.iter().map(|k| format!("custom_{}", k)));
+        write_row(&mut writer, &header)?;
+
+        let mut row = Vec::with_capacity(header.len());
+        for map in &self.maps {
+            row.clear();
+            if columns.ty {
+                row.push(type_name(map.ty).to_owned());
+            }
+            if columns.key {
+                row.push(map.key.map(|k| format!("{:x}", k)).unwrap_or_default());
+            }
+            if columns.hash {
+                row.push(map.hash.map(|h| h.to_string()).unwrap_or_default());
+            }
+            if columns.level_id {
+                row.push(map.level_id.clone().unwrap_or_default());
+            }
+            if columns.date_added {
+                // Raw seconds without `chrono`
+                #[cfg(feature = "chrono")]
+                row.push(map.date_added.to_rfc3339_opts(SecondsFormat::Secs, true));
+                #[cfg(not(feature = "chrono"))]
+                row.push(
+                    map.date_added_secs()
+                        .map(|s| s.to_string())
+                        .unwrap_or_default(),
+                );
+            }
+            if columns.note {
+                row.push(map.note.clone().unwrap_or_default());
+            }
+            row.extend(
+                columns
+                    .custom_data
+                    .iter()
+                    .map(|&k| match map.custom_data.get(k) {
+                        Some(v) => match v.as_str() {
+                            Some(s) => s.to_owned(),
+                            None => v.to_string(),
+                        },
+                        None => String::new(),
+                    }),
+            );
+            write_row(&mut writer, &row)?;
+        }
+        Ok(())
+    }
+}
+
+fn type_name(ty: BeatmapType) -> &'static str {
+    match ty {
+        BeatmapType::Key => "key",
+        BeatmapType::Hash => "hash",
+        BeatmapType::Zip => "zip",
+        BeatmapType::LevelId => "level_id",
+        BeatmapType::Unknown => "unknown",
+    }
+}
+
+fn write_row<W>(mut writer: W, cells: &[String]) -> Result<()>
+where
+    W: Write,
+{
+    for (i, cell) in cells.iter().enumerate() {
+        if i > 0 {
+            writer.write_all(b",")?;
+        }
+        if cell.contains(&[',', '"', '\n', '\r'][..]) {
+            write!(writer, "\"{}\"", cell.replace('"', "\"\""))?;
+        } else {
+            writer.write_all(cell.as_bytes())?;
+        }
+    }
+    writer.write_all(b"\r\n")?;
+    Ok(())
+}
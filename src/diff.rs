@@ -0,0 +1,117 @@
+use crate::{Beatmap, BeatmapType, Playlist};
+use std::fmt::Write;
+
+#[cfg(feature = "chrono")]
+const DATE_FORMAT: &str = "%Y-%m-%d";
+
+// Maps are told apart with `Beatmap::same_map`, so a map switching from a key to a hash counts as
+// removed and added again
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlaylistDiff {
+    pub title: String,
+    // `(old, new)` if the title changed
+    pub renamed: Option<(String, String)>,
+    pub added: Vec<Beatmap>,
+    pub removed: Vec<Beatmap>,
+}
+
+impl Playlist {
+    pub fn diff(&self, newer: &Playlist) -> PlaylistDiff {
+        let mut unmatched: Vec<&Beatmap> = newer.maps.iter().collect();
+        let mut removed = Vec::new();
+        for map in &self.maps {
+            match unmatched.iter().position(|m| m.same_map(map)) {
+                Some(i) => {
+                    unmatched.remove(i);
+                }
+                None => removed.push(map.clone()),
+            }
+        }
+
+        PlaylistDiff {
+            title: newer.title.clone(),
+            renamed: (self.title != newer.title).then(|| (self.title.clone(), newer.title.clone())),
+            added: unmatched.into_iter().cloned().collect(),
+            removed,
+        }
+    }
+}
+
+impl PlaylistDiff {
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.renamed.is_none() && self.added.is_empty() && self.removed.is_empty()
+    }
+
+    // Markdown meant to be pasted as is, names come from the song info cached by `enrich`
+    pub fn to_changelog(&self) -> String {
+        let mut changelog = format!("## Changes to {}\n", escape(&self.title));
+        if self.is_empty() {
+            changelog.push_str("\nNo changes\n");
+            return changelog;
+        }
+
+        if let Some((old, new)) = &self.renamed {
+            let _ = write!(
+                changelog,
+                "\nRenamed from **{}** to **{}**\n",
+                escape(old),
+                escape(new)
+            );
+        }
+        for (heading, maps) in &[("Added", &self.added), ("Removed", &self.removed)] {
+            if maps.is_empty() {
+                continue;
+            }
+            let _ = write!(changelog, "\n### {} ({})\n\n", heading, maps.len());
+            for map in maps.iter() {
+                let _ = writeln!(changelog, "- {}", entry(map));
+            }
+        }
+        changelog
+    }
+}
+
+fn entry(map: &Beatmap) -> String {
+    let mut entry = match map.song_name() {
+        Some(name) => format!("**{}**", escape(name)),
+        None => format!("*{}*", identifier(map)),
+    };
+    if let Some(author) = map.song_author() {
+        let _ = write!(entry, " by {}", escape(author));
+    }
+    if let Some(mapper) = map.mapper() {
+        let _ = write!(entry, ", mapped by {}", escape(mapper));
+    }
+    if map.song_name().is_some() {
+        let _ = write!(entry, " ({})", identifier(map));
+    }
+    #[cfg(feature = "chrono")]
+    let _ = write!(entry, ", added {}", map.date_added.format(DATE_FORMAT));
+    entry
+}
+
+fn identifier(map: &Beatmap) -> String {
+    match (map.ty, map.key, &map.hash, &map.level_id) {
+        (BeatmapType::Key, Some(key), ..) => format!("`{:x}`", key),
+        (BeatmapType::Hash, _, Some(hash), _) => format!("`{}`", hash),
+        (BeatmapType::LevelId, .., Some(level_id)) => format!("`{}`", level_id),
+        (BeatmapType::Zip, ..) => "embedded map".to_owned(),
+        _ => "unknown map".to_owned(),
+    }
+}
+
+// Keeps names from being rendered as formatting
+fn escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        if matches!(
+            c,
+            '\\' | '*' | '_' | '~' | '`' | '|' | '#' | '[' | ']' | '<' | '>'
+        ) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
@@ -0,0 +1,128 @@
+use std::{
+    env,
+    ffi::OsString,
+    fs,
+    path::{Path, PathBuf},
+};
+
+const GAME_DIR: &str = "Beat Saber";
+const OCULUS_GAME_DIR: &str = "hyperbolic-magnetism-beat-saber";
+const PLAYLISTS_DIR: &str = "Playlists";
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum InstallSource {
+    // The `BEAT_SABER_DIR` environment variable
+    Env,
+    Steam,
+    Oculus,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct PlaylistDir {
+    pub path: PathBuf,
+    pub source: InstallSource,
+    // The game creates the folder on first launch, so it can be missing on fresh installs
+    pub exists: bool,
+}
+
+// Only installs that actually exist on disk are returned, the environment override first
+#[inline]
+pub fn playlist_dirs() -> Vec<PlaylistDir> {
+    playlist_dirs_with(|var| env::var_os(var))
+}
+
+pub(crate) fn playlist_dirs_with<F>(env: F) -> Vec<PlaylistDir>
+where
+    F: Fn(&str) -> Option<OsString>,
+{
+    let mut games = Vec::new();
+    if let Some(dir) = env("BEAT_SABER_DIR") {
+        games.push((PathBuf::from(dir), InstallSource::Env));
+    }
+    for steam in steam_roots(&env) {
+        for library in steam_libraries(&steam) {
+            let game = library.join("steamapps").join("common").join(GAME_DIR);
+            games.push((game, InstallSource::Steam));
+        }
+    }
+    for oculus in oculus_roots(&env) {
+        let game = oculus.join("Software").join(OCULUS_GAME_DIR);
+        games.push((game, InstallSource::Oculus));
+    }
+
+    let mut dirs: Vec<PlaylistDir> = Vec::new();
+    for (game, source) in games {
+        if !game.is_dir() {
+            continue;
+        }
+        let path = game.join(PLAYLISTS_DIR);
+        if dirs.iter().any(|d| d.path == path) {
+            continue;
+        }
+        dirs.push(PlaylistDir {
+            exists: path.is_dir(),
+            path,
+            source,
+        });
+    }
+    dirs
+}
+
+fn steam_roots<F>(env: &F) -> Vec<PathBuf>
+where
+    F: Fn(&str) -> Option<OsString>,
+{
+    let mut roots = Vec::new();
+    if let Some(dir) = env("STEAM_DIR") {
+        roots.push(PathBuf::from(dir));
+    }
+    for var in ["ProgramFiles(x86)", "ProgramFiles"] {
+        if let Some(dir) = env(var) {
+            roots.push(PathBuf::from(dir).join("Steam"));
+        }
+    }
+    if let Some(home) = env("HOME") {
+        let home = PathBuf::from(home);
+        roots.push(home.join(".steam").join("steam"));
+        roots.push(home.join(".local").join("share").join("Steam"));
+        roots.push(home.join(".var/app/com.valvesoftware.Steam/.local/share/Steam"));
+    }
+    roots
+}
+
+// Every Steam library, including the root itself, is listed in `libraryfolders.vdf`
+fn steam_libraries(root: &Path) -> Vec<PathBuf> {
+    let mut libraries = vec![root.to_owned()];
+    let vdf = root.join("steamapps").join("libraryfolders.vdf");
+    if let Ok(vdf) = fs::read_to_string(vdf) {
+        libraries.extend(parse_library_folders(&vdf));
+    }
+    libraries
+}
+
+pub(crate) fn parse_library_folders(vdf: &str) -> Vec<PathBuf> {
+    vdf.lines()
+        .filter_map(|line| {
+            let mut fields = line.split('"').filter(|f| !f.trim().is_empty());
+            match (fields.next(), fields.next()) {
+                (Some("path"), Some(path)) => Some(PathBuf::from(path.replace("\\\\", "\\"))),
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+// Oculus libraries can be moved, `OculusBase` points at the default one
+fn oculus_roots<F>(env: &F) -> Vec<PathBuf>
+where
+    F: Fn(&str) -> Option<OsString>,
+{
+    let mut roots = Vec::new();
+    if let Some(dir) = env("OculusBase") {
+        roots.push(PathBuf::from(dir).join("Software"));
+    }
+    if let Some(dir) = env("ProgramFiles") {
+        roots.push(PathBuf::from(dir).join("Oculus").join("Software"));
+    }
+    roots
+}
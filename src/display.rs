@@ -0,0 +1,178 @@
+use crate::{Beatmap, BeatmapType, Playlist};
+use blister_format::{
+    values::{Binary, Sha1},
+    Map, Value,
+};
+use sha1::{Digest, Sha1 as Sha1Hasher};
+use std::fmt;
+
+impl fmt::Display for Playlist {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "'{}' by {} — {} map",
+            self.title,
+            self.author,
+            self.maps.len()
+        )?;
+        if self.maps.len() != 1 {
+            f.write_str("s")?;
+        }
+
+        let counts = [
+            (BeatmapType::Hash, "hash"),
+            (BeatmapType::Key, "key"),
+            (BeatmapType::Zip, "zip"),
+            (BeatmapType::LevelId, "level ID"),
+            (BeatmapType::Unknown, "unknown"),
+        ]
+        .iter()
+        .map(|&(ty, name)| (self.maps.iter().filter(|m| m.ty == ty).count(), name))
+        .filter(|&(count, _)| count > 0)
+        .collect::<Vec<_>>();
+        if !counts.is_empty() {
+            f.write_str(" (")?;
+            for (i, (count, name)) in counts.iter().enumerate() {
+                if i > 0 {
+                    f.write_str(", ")?;
+                }
+                write!(f, "{} {}", count, name)?;
+            }
+            f.write_str(")")?;
+        }
+
+        if let Some(cover) = &self.cover {
+            write!(f, ", cover {}", ByteSize(cover.len()))?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Beatmap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.ty, self.key, &self.hash, &self.zip, &self.level_id) {
+            (BeatmapType::Key, Some(key), ..) => write!(f, "key {:x}", key)?,
+            (BeatmapType::Hash, _, Some(hash), ..) => write!(f, "hash {}", hash)?,
+            (BeatmapType::Zip, _, _, Some(zip), _) => {
+                write!(f, "embedded zip ({})", ByteSize(zip.len()))?
+            }
+            (BeatmapType::LevelId, .., Some(level_id)) => write!(f, "level ID {}", level_id)?,
+            (BeatmapType::Unknown, ..) => f.write_str("unknown beatmap")?,
+            _ => write!(f, "{:?} beatmap without identifier", self.ty)?,
+        }
+        #[cfg(feature = "chrono")]
+        write!(f, ", added {}", self.date_added.format("%Y-%m-%d"))?;
+        if let Some(note) = &self.note {
+            write!(f, " ({:?})", note)?;
+        }
+        Ok(())
+    }
+}
+
+pub(crate) struct ByteSize(pub(crate) usize);
+
+impl fmt::Display for ByteSize {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        const KB: usize = 1024;
+        const MB: usize = 1024 * KB;
+
+        match self.0 {
+            b if b < KB => write!(f, "{} B", b),
+            b if b < MB => write!(f, "{:.0} KB", b as f64 / KB as f64),
+            b => write!(f, "{:.1} MB", b as f64 / MB as f64),
+        }
+    }
+}
+
+// Debug output with binary data replaced by its size and SHA-1, since covers and zips would
+// otherwise be dumped byte by byte. Values backed by files are never read.
+#[derive(Copy, Clone)]
+pub struct Redacted<'a, T: ?Sized>(pub &'a T);
+
+impl Playlist {
+    #[inline]
+    pub fn redacted(&self) -> Redacted<'_, Self> {
+        Redacted(self)
+    }
+}
+
+impl Beatmap {
+    #[inline]
+    pub fn redacted(&self) -> Redacted<'_, Self> {
+        Redacted(self)
+    }
+}
+
+impl fmt::Debug for Redacted<'_, Playlist> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let p = self.0;
+        f.debug_struct("Playlist")
+            .field("title", &p.title)
+            .field("author", &p.author)
+            .field("description", &p.description)
+            .field("cover", &p.cover.as_deref().map(Redacted))
+            .field("localized_titles", &p.localized_titles)
+            .field("localized_descriptions", &p.localized_descriptions)
+            .field("tags", &p.tags)
+            .field("children", &p.children)
+            .field("maps", &p.maps.iter().map(Redacted).collect::<Vec<_>>())
+            .field("custom_data", &Redacted(&p.custom_data))
+            .finish()
+    }
+}
+
+impl fmt::Debug for Redacted<'_, Beatmap> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let m = self.0;
+        let mut debug = f.debug_struct("Beatmap");
+        debug.field("ty", &m.ty);
+        #[cfg(feature = "chrono")]
+        debug.field("date_added", &m.date_added);
+        debug
+            .field("key", &m.key)
+            .field("hash", &m.hash)
+            .field("zip", &m.zip.as_ref().map(Redacted))
+            .field("level_id", &m.level_id)
+            .field("note", &m.note)
+            .field("custom_data", &Redacted(&m.custom_data))
+            .finish()
+    }
+}
+
+impl fmt::Debug for Redacted<'_, Map> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map()
+            .entries(self.0.iter_sorted().map(|(k, v)| (k, Redacted(v))))
+            .finish()
+    }
+}
+
+impl fmt::Debug for Redacted<'_, Value> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Value::Binary(b) => f.debug_tuple("Binary").field(&Redacted(b)).finish(),
+            Value::LongBinary(b) => match b.as_slice() {
+                Some(b) => f.debug_tuple("LongBinary").field(&Redacted(b)).finish(),
+                None => fmt::Debug::fmt(b, f),
+            },
+            v => fmt::Debug::fmt(v, f),
+        }
+    }
+}
+
+impl fmt::Debug for Redacted<'_, [u8]> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let digest = Sha1(Sha1Hasher::digest(self.0).into());
+        write!(f, "<{}, sha1 {}>", ByteSize(self.0.len()), digest)
+    }
+}
+
+// Spilled values aren't read back just to be hashed
+impl fmt::Debug for Redacted<'_, Binary> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0.as_bytes() {
+            Some(b) => fmt::Debug::fmt(&Redacted(b), f),
+            None => write!(f, "<{}, spilled>", ByteSize(self.0.len())),
+        }
+    }
+}
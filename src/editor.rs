@@ -0,0 +1,337 @@
+use crate::{
+    builder::check_len, error::Error, Beatmap, Playlist, Result, LONG_STRING_LEN, SHORT_STRING_LEN,
+};
+use std::{collections::VecDeque, fmt, sync::Arc};
+
+pub const DEFAULT_HISTORY_LEN: usize = 100;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Edit {
+    InsertMap { index: usize, map: Beatmap },
+    RemoveMap { index: usize },
+    MoveMap { from: usize, to: usize },
+    SetTitle(String),
+    SetAuthor(String),
+    SetDescription(Option<String>),
+    SetCover(Option<Arc<[u8]>>),
+    ReplacePlaylist(Box<Playlist>),
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum ChangeEvent {
+    MapInserted { index: usize },
+    MapRemoved { index: usize },
+    MapMoved { from: usize, to: usize },
+    TitleChanged,
+    AuthorChanged,
+    DescriptionChanged,
+    CoverChanged,
+    PlaylistReplaced,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct ObserverId(usize);
+
+type Observer = Arc<dyn Fn(&ChangeEvent) + Send + Sync>;
+
+// Every applied edit is stored as its inverse, so undoing is just applying it
+#[derive(Clone)]
+pub struct PlaylistEditor {
+    playlist: Playlist,
+    undo: VecDeque<Edit>,
+    redo: Vec<Edit>,
+    history_len: usize,
+    observers: Vec<(ObserverId, Observer)>,
+    next_observer: usize,
+    revision: u64,
+}
+
+impl PlaylistEditor {
+    #[inline]
+    pub fn new(playlist: Playlist) -> Self {
+        Self::with_history_len(playlist, DEFAULT_HISTORY_LEN)
+    }
+
+    #[inline]
+    pub fn with_history_len(playlist: Playlist, history_len: usize) -> Self {
+        Self {
+            playlist,
+            undo: VecDeque::new(),
+            redo: Vec::new(),
+            history_len,
+            observers: Vec::new(),
+            next_observer: 0,
+            revision: 0,
+        }
+    }
+
+    #[inline]
+    pub fn playlist(&self) -> &Playlist {
+        &self.playlist
+    }
+
+    #[inline]
+    pub fn into_playlist(self) -> Playlist {
+        self.playlist
+    }
+
+    // Observers are called after every change, including undos and redos
+    pub fn subscribe<F>(&mut self, observer: F) -> ObserverId
+    where
+        F: Fn(&ChangeEvent) + Send + Sync + 'static,
+    {
+        let id = ObserverId(self.next_observer);
+        self.next_observer += 1;
+        self.observers.push((id, Arc::new(observer)));
+        id
+    }
+
+    pub fn unsubscribe(&mut self, id: ObserverId) -> bool {
+        let len = self.observers.len();
+        self.observers.retain(|(i, _)| *i != id);
+        self.observers.len() != len
+    }
+
+    // Clears the redo history, like any editor would
+    pub fn apply(&mut self, edit: Edit) -> Result<()> {
+        let inverse = self.apply_edit(edit, true)?;
+        self.redo.clear();
+        if self.history_len == 0 {
+            return Ok(());
+        }
+        if self.undo.len() == self.history_len {
+            self.undo.pop_front();
+        }
+        self.undo.push_back(inverse);
+        Ok(())
+    }
+
+    pub fn undo(&mut self) -> bool {
+        let edit = match self.undo.pop_back() {
+            Some(edit) => edit,
+            None => return false,
+        };
+        // Inverses restore values that were already there, which don't have to pass validation
+        // themselves, and their indices always match the state they were recorded for
+        let inverse = self.apply_edit(edit, false).unwrap();
+        self.redo.push(inverse);
+        true
+    }
+
+    pub fn redo(&mut self) -> bool {
+        let edit = match self.redo.pop() {
+            Some(edit) => edit,
+            None => return false,
+        };
+        let inverse = self.apply_edit(edit, false).unwrap();
+        self.undo.push_back(inverse);
+        true
+    }
+
+    // Bumped by every change, undos and redos included
+    #[inline]
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    #[inline]
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    #[inline]
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    #[inline]
+    pub fn clear_history(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+    }
+
+    #[inline]
+    pub fn push_map(&mut self, map: Beatmap) -> Result<()> {
+        let index = self.playlist.maps.len();
+        self.apply(Edit::InsertMap { index, map })
+    }
+
+    #[inline]
+    pub fn insert_map(&mut self, index: usize, map: Beatmap) -> Result<()> {
+        self.apply(Edit::InsertMap { index, map })
+    }
+
+    #[inline]
+    pub fn remove_map(&mut self, index: usize) -> Result<()> {
+        self.apply(Edit::RemoveMap { index })
+    }
+
+    #[inline]
+    pub fn move_map(&mut self, from: usize, to: usize) -> Result<()> {
+        self.apply(Edit::MoveMap { from, to })
+    }
+
+    #[inline]
+    pub fn set_title<S>(&mut self, title: S) -> Result<()>
+    where
+        S: Into<String>,
+    {
+        self.apply(Edit::SetTitle(title.into()))
+    }
+
+    #[inline]
+    pub fn set_author<S>(&mut self, author: S) -> Result<()>
+    where
+        S: Into<String>,
+    {
+        self.apply(Edit::SetAuthor(author.into()))
+    }
+
+    #[inline]
+    pub fn set_description(&mut self, description: Option<String>) -> Result<()> {
+        self.apply(Edit::SetDescription(description))
+    }
+
+    #[inline]
+    pub fn set_cover(&mut self, cover: Option<Arc<[u8]>>) -> Result<()> {
+        self.apply(Edit::SetCover(cover))
+    }
+
+    #[inline]
+    pub fn replace_playlist(&mut self, playlist: Playlist) -> Result<()> {
+        self.apply(Edit::ReplacePlaylist(Box::new(playlist)))
+    }
+
+    fn apply_edit(&mut self, edit: Edit, validate: bool) -> Result<Edit> {
+        let event = edit.event();
+        let inverse = self.playlist.apply_edit(edit, validate)?;
+        self.revision += 1;
+        for (_, observer) in &self.observers {
+            observer(&event);
+        }
+        Ok(inverse)
+    }
+}
+
+impl Edit {
+    fn event(&self) -> ChangeEvent {
+        match *self {
+            Self::InsertMap { index, .. } => ChangeEvent::MapInserted { index },
+            Self::RemoveMap { index } => ChangeEvent::MapRemoved { index },
+            Self::MoveMap { from, to } => ChangeEvent::MapMoved { from, to },
+            Self::SetTitle(_) => ChangeEvent::TitleChanged,
+            Self::SetAuthor(_) => ChangeEvent::AuthorChanged,
+            Self::SetDescription(_) => ChangeEvent::DescriptionChanged,
+            Self::SetCover(_) => ChangeEvent::CoverChanged,
+            Self::ReplacePlaylist(_) => ChangeEvent::PlaylistReplaced,
+        }
+    }
+}
+
+impl fmt::Debug for PlaylistEditor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PlaylistEditor")
+            .field("playlist", &self.playlist)
+            .field("undo", &self.undo)
+            .field("redo", &self.redo)
+            .field("history_len", &self.history_len)
+            .field("observers", &self.observers.len())
+            .field("revision", &self.revision)
+            .finish()
+    }
+}
+
+impl Playlist {
+    // Moves the map at `from` so it ends up at `to`, shifting the ones in between
+    pub fn move_map(&mut self, from: usize, to: usize) -> Result<()> {
+        self.check_map_index(from)?;
+        self.check_map_index(to)?;
+        let map = self.maps.remove(from);
+        self.maps.insert(to, map);
+        Ok(())
+    }
+
+    pub fn swap(&mut self, a: usize, b: usize) -> Result<()> {
+        self.check_map_index(a)?;
+        self.check_map_index(b)?;
+        self.maps.swap(a, b);
+        Ok(())
+    }
+
+    // `order[i]` is the current index of the map that should end up at `i`. Nothing is moved
+    // unless every index appears exactly once.
+    pub fn sort_manual(&mut self, order: &[usize]) -> Result<()> {
+        let len = self.maps.len();
+        if order.len() != len {
+            return Err(Error::InvalidMapOrder(len));
+        }
+        let mut seen = vec![false; len];
+        for &i in order {
+            self.check_map_index(i)?;
+            if std::mem::replace(&mut seen[i], true) {
+                return Err(Error::InvalidMapOrder(len));
+            }
+        }
+
+        let mut maps: Vec<_> = self.maps.drain(..).map(Some).collect();
+        self.maps
+            .extend(order.iter().map(|&i| maps[i].take().unwrap()));
+        Ok(())
+    }
+
+    #[inline]
+    fn check_map_index(&self, index: usize) -> Result<()> {
+        if index >= self.maps.len() {
+            return Err(Error::MapIndexOutOfBounds(index));
+        }
+        Ok(())
+    }
+
+    // Returns the edit reverting this one. Only length checks are skipped without `validate`,
+    // indices are always checked.
+    fn apply_edit(&mut self, edit: Edit, validate: bool) -> Result<Edit> {
+        let len = self.maps.len();
+        Ok(match edit {
+            Edit::InsertMap { index, map } => {
+                if index > len {
+                    return Err(Error::MapIndexOutOfBounds(index));
+                }
+                self.maps.insert(index, map);
+                Edit::RemoveMap { index }
+            }
+            Edit::RemoveMap { index } => {
+                if index >= len {
+                    return Err(Error::MapIndexOutOfBounds(index));
+                }
+                let map = self.maps.remove(index);
+                Edit::InsertMap { index, map }
+            }
+            Edit::MoveMap { from, to } => {
+                self.move_map(from, to)?;
+                Edit::MoveMap { from: to, to: from }
+            }
+            Edit::SetTitle(title) => {
+                if validate {
+                    check_len("title", &title, SHORT_STRING_LEN)?;
+                }
+                Edit::SetTitle(std::mem::replace(&mut self.title, title))
+            }
+            Edit::SetAuthor(author) => {
+                if validate {
+                    check_len("author", &author, SHORT_STRING_LEN)?;
+                }
+                Edit::SetAuthor(std::mem::replace(&mut self.author, author))
+            }
+            Edit::SetDescription(description) => {
+                if let (true, Some(description)) = (validate, &description) {
+                    check_len("description", description, LONG_STRING_LEN)?;
+                }
+                Edit::SetDescription(std::mem::replace(&mut self.description, description))
+            }
+            Edit::SetCover(cover) => Edit::SetCover(std::mem::replace(&mut self.cover, cover)),
+            Edit::ReplacePlaylist(playlist) => {
+                Edit::ReplacePlaylist(Box::new(std::mem::replace(self, *playlist)))
+            }
+        })
+    }
+}
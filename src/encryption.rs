@@ -0,0 +1,97 @@
+use crate::{
+    error::Error, magic_eq, Playlist, ReadOptions, Result, MAGIC_NUMBER_ENCRYPTED, MAGIC_NUMBER_LEN,
+};
+use aes_gcm::{
+    aead::{Aead, AeadCore, KeyInit, OsRng, Payload},
+    Aes256Gcm, Nonce,
+};
+use blister_format::MapEncoding;
+use num_enum::{IntoPrimitive, TryFromPrimitive};
+use std::{
+    convert::TryFrom,
+    io::{Read, Write},
+};
+
+pub const KEY_LEN: usize = 32;
+const NONCE_LEN: usize = 12;
+// The magic number and cipher ID, bound to the ciphertext as associated data
+const HEADER_LEN: usize = MAGIC_NUMBER_LEN + 1;
+
+#[repr(u8)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, IntoPrimitive, TryFromPrimitive)]
+pub enum Cipher {
+    Aes256Gcm = 1,
+}
+
+impl Playlist {
+    pub fn read_encrypted<R>(mut reader: R, key: &[u8; KEY_LEN], strict: bool) -> Result<Self>
+    where
+        R: Read,
+    {
+        let mut header = [0; HEADER_LEN];
+        reader.read_exact(&mut header)?;
+        let mut magic_number = [0; MAGIC_NUMBER_LEN];
+        magic_number.copy_from_slice(&header[..MAGIC_NUMBER_LEN]);
+        if !magic_eq(&magic_number, MAGIC_NUMBER_ENCRYPTED) {
+            return Err(Error::InvalidMagicNumber(magic_number));
+        }
+
+        let cipher = header[MAGIC_NUMBER_LEN];
+        match Cipher::try_from(cipher) {
+            Ok(Cipher::Aes256Gcm) => (),
+            Err(_) => return Err(Error::InvalidCipher(cipher)),
+        }
+
+        let mut nonce = [0; NONCE_LEN];
+        reader.read_exact(&mut nonce)?;
+        let mut ciphertext = Vec::new();
+        reader.read_to_end(&mut ciphertext)?;
+
+        let body = Aes256Gcm::new(key.into())
+            .decrypt(
+                Nonce::from_slice(&nonce),
+                Payload {
+                    msg: &ciphertext,
+                    aad: &header,
+                },
+            )
+            .map_err(|_| Error::Decryption)?;
+        Self::read_body(
+            body.as_slice(),
+            &ReadOptions {
+                strict,
+                ..Default::default()
+            },
+            MapEncoding::Length,
+            &mut Vec::new(),
+        )
+    }
+
+    pub fn write_encrypted<W>(&self, mut writer: W, key: &[u8; KEY_LEN]) -> Result<()>
+    where
+        W: Write,
+    {
+        let mut body = Vec::new();
+        self.write_body(&mut body, Default::default(), MapEncoding::Length)?;
+
+        let mut header = [0; HEADER_LEN];
+        header[..MAGIC_NUMBER_LEN].copy_from_slice(MAGIC_NUMBER_ENCRYPTED);
+        header[MAGIC_NUMBER_LEN] = Cipher::Aes256Gcm.into();
+
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let ciphertext = Aes256Gcm::new(key.into())
+            .encrypt(
+                &nonce,
+                Payload {
+                    msg: &body,
+                    aad: &header,
+                },
+            )
+            .map_err(|_| Error::Encryption)?;
+
+        writer.write_all(&header)?;
+        writer.write_all(&nonce)?;
+        writer.write_all(&ciphertext)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,168 @@
+use crate::{Beatmap, Playlist, Resolution, Resolver, SongInfo};
+use blister_format::{values::LongString, Key, Value};
+use std::ops::RangeInclusive;
+
+// Reserved keys the cached resolver results are stored under, next to the regular beatmap fields
+const SONG_NAME_KEY: u32 = 7;
+const SONG_AUTHOR_KEY: u32 = 8;
+const MAPPER_KEY: u32 = 9;
+const DIFFICULTIES_KEY: u32 = 10;
+const DEAD_KEY: u32 = 11;
+
+const SONG_INFO_KEYS: RangeInclusive<u32> = SONG_NAME_KEY..=DIFFICULTIES_KEY;
+const CACHED_KEYS: RangeInclusive<u32> = SONG_NAME_KEY..=DEAD_KEY;
+
+const DIFFICULTY_SEPARATOR: char = ',';
+
+// Indices of the maps in each outcome
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct EnrichReport {
+    pub enriched: Vec<usize>,
+    pub not_found: Vec<usize>,
+    pub unknown: Vec<usize>,
+}
+
+impl EnrichReport {
+    #[inline]
+    pub fn is_complete(&self) -> bool {
+        self.not_found.is_empty() && self.unknown.is_empty()
+    }
+}
+
+impl Playlist {
+    // Maps that don't resolve keep whatever was cached before
+    pub fn enrich<R>(&mut self, resolver: &R) -> EnrichReport
+    where
+        R: Resolver + ?Sized,
+    {
+        let mut report = EnrichReport::default();
+        for (i, map) in self.maps.iter_mut().enumerate() {
+            match resolver.resolve(map) {
+                Resolution::Found(info) => {
+                    map.cache_song_info(&info);
+                    report.enriched.push(i);
+                }
+                Resolution::NotFound => report.not_found.push(i),
+                Resolution::Unknown => report.unknown.push(i),
+            }
+        }
+        report
+    }
+
+    // Maps the resolver positively reports as gone, maps it can't tell about are kept out
+    pub fn find_unresolvable<R>(&self, resolver: &R) -> Vec<usize>
+    where
+        R: Resolver + ?Sized,
+    {
+        self.maps
+            .iter()
+            .enumerate()
+            .filter(|(_, map)| resolver.resolve(map) == Resolution::NotFound)
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    // Flags unresolvable maps instead of removing them, clearing the flag on maps that resolve again
+    pub fn mark_unresolvable<R>(&mut self, resolver: &R) -> Vec<usize>
+    where
+        R: Resolver + ?Sized,
+    {
+        let mut dead = Vec::new();
+        for (i, map) in self.maps.iter_mut().enumerate() {
+            match resolver.resolve(map) {
+                Resolution::Found(_) => map.set_dead(false),
+                Resolution::NotFound => {
+                    map.set_dead(true);
+                    dead.push(i);
+                }
+                Resolution::Unknown => (),
+            }
+        }
+        dead
+    }
+
+    // Returns the number of maps removed
+    pub fn remove_dead(&mut self) -> usize {
+        let before = self.maps.len();
+        self.maps.retain(|m| !m.is_dead());
+        before - self.maps.len()
+    }
+}
+
+impl Beatmap {
+    #[inline]
+    pub fn song_name(&self) -> Option<&str> {
+        self.cached(SONG_NAME_KEY)
+    }
+
+    #[inline]
+    pub fn song_author(&self) -> Option<&str> {
+        self.cached(SONG_AUTHOR_KEY)
+    }
+
+    #[inline]
+    pub fn mapper(&self) -> Option<&str> {
+        self.cached(MAPPER_KEY)
+    }
+
+    pub fn difficulties(&self) -> Vec<&str> {
+        match self.cached(DIFFICULTIES_KEY) {
+            Some(d) => d.split(DIFFICULTY_SEPARATOR).collect(),
+            None => Vec::new(),
+        }
+    }
+
+    // Missing fields leave the cached ones untouched
+    pub fn cache_song_info(&mut self, info: &SongInfo) {
+        // Fields too long to be stored are left out as well
+        let data = &mut self.custom_data;
+        let mut cache = |key, s: &str| {
+            if let Ok(s) = LongString::new(s) {
+                data.insert(key, s);
+            }
+        };
+        if let Some(name) = &info.name {
+            cache(SONG_NAME_KEY, name);
+        }
+        if let Some(author) = &info.author {
+            cache(SONG_AUTHOR_KEY, author);
+        }
+        if let Some(mapper) = &info.mapper {
+            cache(MAPPER_KEY, mapper);
+        }
+        if !info.difficulties.is_empty() {
+            cache(
+                DIFFICULTIES_KEY,
+                &info.difficulties.join(&DIFFICULTY_SEPARATOR.to_string()),
+            );
+        }
+    }
+
+    pub fn clear_song_info(&mut self) {
+        self.custom_data.remove_many(SONG_INFO_KEYS);
+    }
+
+    #[inline]
+    pub fn is_dead(&self) -> bool {
+        matches!(self.custom_data.get(DEAD_KEY), Some(Value::Bool(true)))
+    }
+
+    #[inline]
+    pub fn set_dead(&mut self, dead: bool) {
+        if dead {
+            self.custom_data.insert(DEAD_KEY, Value::Bool(true));
+        } else {
+            self.custom_data.remove(DEAD_KEY);
+        }
+    }
+
+    #[inline]
+    fn cached(&self, key: u32) -> Option<&str> {
+        self.custom_data.get(key).and_then(Value::as_str)
+    }
+}
+
+#[inline]
+pub(crate) fn is_cached_key(key: Key) -> bool {
+    CACHED_KEYS.contains(&*key)
+}
@@ -0,0 +1,94 @@
+use crate::{Beatmap, BeatmapType, Playlist};
+use blister_format::{Key, Map};
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct IgnoreFields {
+    pub date_added: bool,
+    pub custom_data: bool,
+    pub custom_data_keys: Vec<Key>,
+}
+
+impl Default for IgnoreFields {
+    #[inline]
+    fn default() -> Self {
+        Self {
+            date_added: true,
+            custom_data: false,
+            custom_data_keys: Vec::new(),
+        }
+    }
+}
+
+impl Beatmap {
+    pub fn same_map(&self, other: &Self) -> bool {
+        if self.ty != other.ty {
+            return false;
+        }
+        match self.ty {
+            BeatmapType::Key => self.key == other.key,
+            BeatmapType::Hash => self.hash == other.hash,
+            BeatmapType::Zip => self.zip == other.zip,
+            BeatmapType::LevelId => self.level_id == other.level_id,
+            BeatmapType::Unknown => {
+                self.key == other.key
+                    && self.hash == other.hash
+                    && self.zip == other.zip
+                    && self.level_id == other.level_id
+            }
+        }
+    }
+
+    fn eq_ignoring(&self, other: &Self, ignore: &IgnoreFields) -> bool {
+        #[cfg(feature = "chrono")]
+        let (dates_eq, date_key) = (
+            ignore.date_added || self.date_added == other.date_added,
+            None,
+        );
+        // The raw date is compared along with the rest of the custom data
+        #[cfg(not(feature = "chrono"))]
+        let (dates_eq, date_key) = (true, Some(1.into()).filter(|_| ignore.date_added));
+
+        self.same_map(other)
+            && self.key == other.key
+            && self.hash == other.hash
+            && self.zip == other.zip
+            && self.level_id == other.level_id
+            && self.note == other.note
+            && dates_eq
+            && custom_data_eq(&self.custom_data, &other.custom_data, ignore, date_key)
+    }
+}
+
+impl Playlist {
+    pub fn eq_ignoring(&self, other: &Self, ignore: &IgnoreFields) -> bool {
+        self.title == other.title
+            && self.author == other.author
+            && self.description == other.description
+            && self.cover == other.cover
+            && self.localized_titles == other.localized_titles
+            && self.localized_descriptions == other.localized_descriptions
+            && self.tags == other.tags
+            && self.children == other.children
+            && self.maps.len() == other.maps.len()
+            && self
+                .maps
+                .iter()
+                .zip(&other.maps)
+                .all(|(a, b)| a.eq_ignoring(b, ignore))
+            && custom_data_eq(&self.custom_data, &other.custom_data, ignore, None)
+    }
+}
+
+// `also_ignored` is skipped on top of the configured keys
+fn custom_data_eq(a: &Map, b: &Map, ignore: &IgnoreFields, also_ignored: Option<Key>) -> bool {
+    if ignore.custom_data {
+        return true;
+    }
+
+    let ignored = |k: &Key| ignore.custom_data_keys.contains(k) || Some(*k) == also_ignored;
+    let compared = |m: &Map| m.keys().filter(|k| !ignored(k)).count();
+    compared(a) == compared(b)
+        && a.iter()
+            .filter(|(k, _)| !ignored(k))
+            .all(|(&k, v)| b.get(k) == Some(v))
+}
@@ -1,5 +1,5 @@
 use crate::MAGIC_NUMBER;
-use blister_format::Value;
+use blister_format::{Key, Value};
 use thiserror::Error;
 
 #[derive(Debug, Error)]
@@ -21,6 +21,50 @@ pub enum Error {
     InvalidPlaylistDescription(Option<Value>),
     #[error("invalid playlist cover, expected optional binary data, got {0:?}")]
     InvalidPlaylistCover(Option<Value>),
+    #[error("invalid playlist localized titles, expected optional string list, got {0:?}")]
+    InvalidPlaylistLocalizedTitles(Option<Value>),
+    #[error("invalid playlist localized descriptions, expected optional string list, got {0:?}")]
+    InvalidPlaylistLocalizedDescriptions(Option<Value>),
+    #[error("invalid playlist tags, expected optional string list, got {0:?}")]
+    InvalidPlaylistTags(Option<Value>),
+    #[error("invalid playlist children, expected optional child list, got {0:?}")]
+    InvalidPlaylistChildren(Option<Value>),
+
+    #[error("playlist contains {0} beatmaps, exceeding the configured limit")]
+    TooManyMaps(usize),
+    #[error("playlist cover of {0} bytes exceeds the configured limit")]
+    CoverTooLarge(usize),
+    #[error("playlist exceeds the {0} bytes limit")]
+    PlaylistTooLarge(usize),
+    #[error("beatmap {map} is a duplicate of beatmap {first}")]
+    DuplicateMap { map: usize, first: usize },
+    #[error("playlist version `{0}` isn't supported")]
+    UnsupportedVersion(u8),
+    #[error("invalid library archive magic number, got `{0:?}`")]
+    InvalidArchiveMagicNumber([u8; crate::MAGIC_NUMBER_LEN]),
+    #[error("library archive references missing cover `{0}`")]
+    InvalidArchiveCover(u32),
+    #[error("invalid string list, expected length-prefixed UTF-8 strings")]
+    InvalidStringList,
+    #[error(
+        "child playlists are nested more than {} levels deep",
+        crate::MAX_CHILD_DEPTH
+    )]
+    ChildTooDeep,
+    #[error("child playlist `{0}` is a path but no base directory was given")]
+    UnresolvedChild(String),
+    #[error("child playlist path `{0}` should only name folders below the parent's")]
+    UnsafeChildPath(String),
+    #[error("child playlist `{0}` includes itself")]
+    ChildCycle(String),
+    #[error("beatmap index {0} is out of bounds")]
+    MapIndexOutOfBounds(usize),
+    #[error("map order should list each of the {0} map indices exactly once")]
+    InvalidMapOrder(usize),
+    #[error("no snapshot with version {0} in the history")]
+    MissingSnapshot(u64),
+    #[error("conversion target `{}` is shared with another playlist", .0.display())]
+    ConvertCollision(std::path::PathBuf),
 
     #[error("invalid beatmap type, expected u8, got {0:?}")]
     InvalidBeatmapType(Option<Value>),
@@ -34,6 +78,12 @@ pub enum Error {
     InvalidBeatmapZip(Option<Value>),
     #[error("invalid beatmap level ID, expected short string, got {0:?}")]
     InvalidBeatmapLevelId(Option<Value>),
+    #[error("invalid beatmap note, expected optional long string, got {0:?}")]
+    InvalidBeatmapNote(Option<Value>),
+    #[error("beatmap type `{0}` is reserved and can't be used by an extension")]
+    InvalidExtensionType(u8),
+    #[error("beatmap zip of {0} bytes exceeds the configured limit")]
+    ZipTooLarge(usize),
     #[error("missing beatmap key for key identified beatmap")]
     MissingBeatmapKey,
     #[error("missing beatmap hash for hash identified beatmap")]
@@ -43,6 +93,346 @@ pub enum Error {
     #[error("missing beatmap level ID for level ID identified beatmap")]
     MissingBeatmapLevelId,
 
+    #[cfg(feature = "hmac")]
+    #[error("missing playlist MAC")]
+    MissingMac,
+    #[cfg(feature = "hmac")]
+    #[error("invalid playlist MAC")]
+    InvalidMac,
+
+    #[cfg(feature = "encryption")]
+    #[error("unknown playlist cipher `{0}`")]
+    InvalidCipher(u8),
+    #[cfg(feature = "encryption")]
+    #[error("failed to encrypt playlist")]
+    Encryption,
+    #[cfg(feature = "encryption")]
+    #[error("failed to decrypt playlist, wrong key or tampered data")]
+    Decryption,
+
     #[error("encountered a beatmap with unknown type `{0}` in strict mode")]
     StrictModeUnknownBeatmapType(u8),
+
+    #[error("missing required {0}")]
+    MissingField(&'static str),
+    #[error("{field} of {len} bytes exceeds the {max} bytes limit")]
+    FieldTooLong {
+        field: &'static str,
+        len: usize,
+        max: usize,
+    },
+    #[error("playlist cover is empty")]
+    EmptyCover,
+    #[error("custom data uses reserved key `{key:?}`")]
+    ReservedKey { map: Option<usize>, key: Key },
+    #[error("playlist failed validation with {} problems", .0.len())]
+    Validation(Vec<crate::Problem>),
+    #[error("gzip header {0} contains a nul byte")]
+    InvalidGzipHeader(&'static str),
+
+    #[cfg(any(feature = "bplist", feature = "zip", feature = "songcore"))]
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+    #[cfg(feature = "bplist")]
+    #[error("invalid bplist key `{0}`, expected hexadecimal")]
+    InvalidBplistKey(String),
+    #[cfg(feature = "bplist")]
+    #[error("invalid bplist hash `{0}`, expected 40 hexadecimal characters")]
+    InvalidBplistHash(String),
+    #[cfg(feature = "bplist")]
+    #[error("invalid bplist image, expected base64")]
+    InvalidBplistImage,
+    #[cfg(feature = "bplist")]
+    #[error("bplist song has no key, hash or level ID")]
+    MissingBplistIdentifier,
+    #[cfg(feature = "bplist")]
+    #[error("beatmaps of type `{0:?}` can't be represented in a bplist")]
+    UnsupportedBplistBeatmap(crate::BeatmapType),
+    #[cfg(feature = "bplist")]
+    #[error("bplist field `{field}` isn't supported")]
+    UnknownBplistField { map: Option<usize>, field: String },
+
+    #[cfg(feature = "image")]
+    #[error(transparent)]
+    Image(#[from] image::ImageError),
+
+    #[cfg(feature = "zip")]
+    #[error(transparent)]
+    Zip(#[from] zip::result::ZipError),
+    #[cfg(feature = "zip")]
+    #[error("beatmap {0} has no embedded zip")]
+    NotSelfContained(usize),
+    #[cfg(feature = "zip")]
+    #[error("level folder has no Info.dat")]
+    MissingLevelInfo,
+    #[cfg(feature = "zip")]
+    #[error("level file `{0}` is outside of the level folder")]
+    InvalidLevelFile(String),
+    #[cfg(feature = "zip")]
+    #[error("beatmap {map} has a corrupt zip")]
+    CorruptZip {
+        map: usize,
+        #[source]
+        source: zip::result::ZipError,
+    },
+    #[cfg(feature = "zip")]
+    #[error("zip entry `{0}` decompresses past its declared size or the entry limit")]
+    ZipEntryTooLarge(String),
+
+    #[cfg(feature = "stream")]
+    #[error(transparent)]
+    Stream(Box<dyn std::error::Error + Send + Sync>),
+    #[cfg(feature = "stream")]
+    #[error("playlist stream exceeds the {0} bytes limit")]
+    StreamTooLarge(usize),
+
+    #[cfg(feature = "lock")]
+    #[error("`{}` is locked by another process{}", path.display(), holder.map(|p| format!(" ({})", p)).unwrap_or_default())]
+    Locked {
+        path: std::path::PathBuf,
+        holder: Option<u32>,
+    },
+    #[cfg(feature = "gzip")]
+    #[error("edit was applied but autosaving it failed")]
+    Autosave(#[source] Box<Error>),
+
+    #[error("request to `{url}` failed with status {status}")]
+    Http {
+        url: String,
+        status: u16,
+        retry_after: Option<std::time::Duration>,
+    },
+    #[error("response from `{url}` exceeds the {max} byte limit")]
+    ResponseTooLarge { url: String, max: u64 },
+    #[error("partial response from `{url}` doesn't resume at byte {offset}")]
+    UnexpectedContentRange { url: String, offset: u64 },
+    #[error("download from `{0}` isn't a valid zip")]
+    CorruptDownload(String),
+    #[error("zip doesn't match map hash `{0}`")]
+    ZipHashMismatch(blister_format::values::Sha1),
+}
+
+// Discriminants are stable and must never be reused
+#[non_exhaustive]
+#[repr(u16)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum ErrorKind {
+    IO = 1,
+    Format = 2,
+    IntegerOverflow = 3,
+
+    InvalidMagicNumber = 100,
+    InvalidPlaylistTitle = 101,
+    InvalidPlaylistAuthor = 102,
+    InvalidPlaylistDescription = 103,
+    InvalidPlaylistCover = 104,
+    TooManyMaps = 105,
+    CoverTooLarge = 106,
+    DuplicateMap = 107,
+    UnsupportedVersion = 108,
+    InvalidArchiveMagicNumber = 109,
+    InvalidArchiveCover = 110,
+    InvalidStringList = 111,
+    InvalidPlaylistTags = 112,
+    InvalidPlaylistChildren = 113,
+    ChildTooDeep = 114,
+    UnresolvedChild = 115,
+    MapIndexOutOfBounds = 116,
+    MissingSnapshot = 117,
+    InvalidMapOrder = 118,
+    UnsafeChildPath = 119,
+    ChildCycle = 120,
+    ConvertCollision = 121,
+    InvalidPlaylistLocalizedTitles = 122,
+    InvalidPlaylistLocalizedDescriptions = 123,
+    PlaylistTooLarge = 124,
+
+    InvalidBeatmapType = 200,
+    InvalidBeatmapDateAdded = 201,
+    InvalidBeatmapKey = 202,
+    InvalidBeatmapHash = 203,
+    InvalidBeatmapZip = 204,
+    InvalidBeatmapLevelId = 205,
+    ZipTooLarge = 206,
+    MissingBeatmapKey = 207,
+    MissingBeatmapHash = 208,
+    MissingBeatmapZip = 209,
+    MissingBeatmapLevelId = 210,
+    InvalidBeatmapNote = 211,
+    InvalidExtensionType = 212,
+
+    MissingMac = 300,
+    InvalidMac = 301,
+
+    InvalidCipher = 400,
+    Encryption = 401,
+    Decryption = 402,
+
+    StrictModeUnknownBeatmapType = 500,
+    MissingField = 501,
+    FieldTooLong = 502,
+    EmptyCover = 503,
+    ReservedKey = 504,
+    Validation = 505,
+    InvalidGzipHeader = 506,
+
+    Json = 600,
+    InvalidBplistKey = 601,
+    InvalidBplistHash = 602,
+    InvalidBplistImage = 603,
+    MissingBplistIdentifier = 604,
+    UnsupportedBplistBeatmap = 605,
+    UnknownBplistField = 606,
+
+    Image = 700,
+
+    Zip = 800,
+    NotSelfContained = 801,
+    MissingLevelInfo = 802,
+    InvalidLevelFile = 803,
+    CorruptZip = 804,
+    ZipEntryTooLarge = 805,
+
+    Stream = 900,
+    StreamTooLarge = 901,
+
+    Locked = 1000,
+    Autosave = 1001,
+
+    Http = 1100,
+    ResponseTooLarge = 1101,
+    UnexpectedContentRange = 1102,
+    CorruptDownload = 1103,
+    ZipHashMismatch = 1104,
+}
+
+impl ErrorKind {
+    #[inline]
+    pub fn code(self) -> u16 {
+        self as u16
+    }
+}
+
+impl Error {
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Error::IO(_) => ErrorKind::IO,
+            Error::Format(_) => ErrorKind::Format,
+            Error::IntegerOverflow(_) => ErrorKind::IntegerOverflow,
+
+            Error::InvalidMagicNumber(_) => ErrorKind::InvalidMagicNumber,
+            Error::InvalidPlaylistTitle(_) => ErrorKind::InvalidPlaylistTitle,
+            Error::InvalidPlaylistAuthor(_) => ErrorKind::InvalidPlaylistAuthor,
+            Error::InvalidPlaylistDescription(_) => ErrorKind::InvalidPlaylistDescription,
+            Error::InvalidPlaylistCover(_) => ErrorKind::InvalidPlaylistCover,
+            Error::TooManyMaps(_) => ErrorKind::TooManyMaps,
+            Error::CoverTooLarge(_) => ErrorKind::CoverTooLarge,
+            Error::PlaylistTooLarge(_) => ErrorKind::PlaylistTooLarge,
+            Error::DuplicateMap { .. } => ErrorKind::DuplicateMap,
+            Error::UnsupportedVersion(_) => ErrorKind::UnsupportedVersion,
+            Error::InvalidArchiveMagicNumber(_) => ErrorKind::InvalidArchiveMagicNumber,
+            Error::InvalidArchiveCover(_) => ErrorKind::InvalidArchiveCover,
+            Error::InvalidStringList => ErrorKind::InvalidStringList,
+            Error::InvalidPlaylistTags(_) => ErrorKind::InvalidPlaylistTags,
+            Error::InvalidPlaylistChildren(_) => ErrorKind::InvalidPlaylistChildren,
+            Error::ChildTooDeep => ErrorKind::ChildTooDeep,
+            Error::UnresolvedChild(_) => ErrorKind::UnresolvedChild,
+            Error::UnsafeChildPath(_) => ErrorKind::UnsafeChildPath,
+            Error::ChildCycle(_) => ErrorKind::ChildCycle,
+            Error::MapIndexOutOfBounds(_) => ErrorKind::MapIndexOutOfBounds,
+            Error::MissingSnapshot(_) => ErrorKind::MissingSnapshot,
+            Error::ConvertCollision(_) => ErrorKind::ConvertCollision,
+            Error::InvalidPlaylistLocalizedTitles(_) => ErrorKind::InvalidPlaylistLocalizedTitles,
+            Error::InvalidPlaylistLocalizedDescriptions(_) => {
+                ErrorKind::InvalidPlaylistLocalizedDescriptions
+            }
+            Error::InvalidMapOrder(_) => ErrorKind::InvalidMapOrder,
+
+            Error::InvalidBeatmapType(_) => ErrorKind::InvalidBeatmapType,
+            Error::InvalidBeatmapDateAdded(_) => ErrorKind::InvalidBeatmapDateAdded,
+            Error::InvalidBeatmapKey(_) => ErrorKind::InvalidBeatmapKey,
+            Error::InvalidBeatmapHash(_) => ErrorKind::InvalidBeatmapHash,
+            Error::InvalidBeatmapZip(_) => ErrorKind::InvalidBeatmapZip,
+            Error::InvalidBeatmapLevelId(_) => ErrorKind::InvalidBeatmapLevelId,
+            Error::ZipTooLarge(_) => ErrorKind::ZipTooLarge,
+            Error::MissingBeatmapKey => ErrorKind::MissingBeatmapKey,
+            Error::MissingBeatmapHash => ErrorKind::MissingBeatmapHash,
+            Error::MissingBeatmapZip => ErrorKind::MissingBeatmapZip,
+            Error::MissingBeatmapLevelId => ErrorKind::MissingBeatmapLevelId,
+            Error::InvalidBeatmapNote(_) => ErrorKind::InvalidBeatmapNote,
+            Error::InvalidExtensionType(_) => ErrorKind::InvalidExtensionType,
+
+            #[cfg(feature = "hmac")]
+            Error::MissingMac => ErrorKind::MissingMac,
+            #[cfg(feature = "hmac")]
+            Error::InvalidMac => ErrorKind::InvalidMac,
+
+            #[cfg(feature = "encryption")]
+            Error::InvalidCipher(_) => ErrorKind::InvalidCipher,
+            #[cfg(feature = "encryption")]
+            Error::Encryption => ErrorKind::Encryption,
+            #[cfg(feature = "encryption")]
+            Error::Decryption => ErrorKind::Decryption,
+
+            Error::StrictModeUnknownBeatmapType(_) => ErrorKind::StrictModeUnknownBeatmapType,
+            Error::MissingField(_) => ErrorKind::MissingField,
+            Error::FieldTooLong { .. } => ErrorKind::FieldTooLong,
+            Error::EmptyCover => ErrorKind::EmptyCover,
+            Error::ReservedKey { .. } => ErrorKind::ReservedKey,
+            Error::Validation(_) => ErrorKind::Validation,
+            Error::InvalidGzipHeader(_) => ErrorKind::InvalidGzipHeader,
+
+            #[cfg(any(feature = "bplist", feature = "zip", feature = "songcore"))]
+            Error::Json(_) => ErrorKind::Json,
+            #[cfg(feature = "bplist")]
+            Error::InvalidBplistKey(_) => ErrorKind::InvalidBplistKey,
+            #[cfg(feature = "bplist")]
+            Error::InvalidBplistHash(_) => ErrorKind::InvalidBplistHash,
+            #[cfg(feature = "bplist")]
+            Error::InvalidBplistImage => ErrorKind::InvalidBplistImage,
+            #[cfg(feature = "bplist")]
+            Error::MissingBplistIdentifier => ErrorKind::MissingBplistIdentifier,
+            #[cfg(feature = "bplist")]
+            Error::UnsupportedBplistBeatmap(_) => ErrorKind::UnsupportedBplistBeatmap,
+            #[cfg(feature = "bplist")]
+            Error::UnknownBplistField { .. } => ErrorKind::UnknownBplistField,
+
+            #[cfg(feature = "image")]
+            Error::Image(_) => ErrorKind::Image,
+
+            #[cfg(feature = "zip")]
+            Error::Zip(_) => ErrorKind::Zip,
+            #[cfg(feature = "zip")]
+            Error::NotSelfContained(_) => ErrorKind::NotSelfContained,
+            #[cfg(feature = "zip")]
+            Error::MissingLevelInfo => ErrorKind::MissingLevelInfo,
+            #[cfg(feature = "zip")]
+            Error::InvalidLevelFile(_) => ErrorKind::InvalidLevelFile,
+            #[cfg(feature = "zip")]
+            Error::CorruptZip { .. } => ErrorKind::CorruptZip,
+            #[cfg(feature = "zip")]
+            Error::ZipEntryTooLarge(_) => ErrorKind::ZipEntryTooLarge,
+
+            #[cfg(feature = "stream")]
+            Error::Stream(_) => ErrorKind::Stream,
+            #[cfg(feature = "stream")]
+            Error::StreamTooLarge(_) => ErrorKind::StreamTooLarge,
+
+            #[cfg(feature = "lock")]
+            Error::Locked { .. } => ErrorKind::Locked,
+            #[cfg(feature = "gzip")]
+            Error::Autosave(_) => ErrorKind::Autosave,
+
+            Error::Http { .. } => ErrorKind::Http,
+            Error::ResponseTooLarge { .. } => ErrorKind::ResponseTooLarge,
+            Error::UnexpectedContentRange { .. } => ErrorKind::UnexpectedContentRange,
+            Error::CorruptDownload(_) => ErrorKind::CorruptDownload,
+            Error::ZipHashMismatch(_) => ErrorKind::ZipHashMismatch,
+        }
+    }
+
+    #[inline]
+    pub fn code(&self) -> u16 {
+        self.kind().code()
+    }
 }
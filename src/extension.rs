@@ -0,0 +1,174 @@
+use crate::{
+    beatmap::UNKNOWN_FIELDS_KEY, enrich::is_cached_key, error::Error, Beatmap, BeatmapType, Result,
+};
+#[cfg(feature = "chrono")]
+use crate::{Clock, SystemClock};
+use blister_format::{Key, Value, BEATMAP_RESERVED_KEYS};
+use std::{collections::BTreeMap, fmt, sync::Arc};
+
+// Maps of unknown types keep their discriminant under the type key so it survives round trips
+const TYPE_KEY: u32 = 0;
+
+// Handler for a beatmap type outside of the core ones, whose data lives in the custom data
+pub trait BeatmapExtension: Send + Sync {
+    fn discriminant(&self) -> u8;
+
+    fn name(&self) -> &str;
+
+    // Usually checks that the custom data keys the type relies on are present
+    #[inline]
+    fn validate(&self, map: &Beatmap) -> Result<()> {
+        let _ = map;
+        Ok(())
+    }
+
+    #[inline]
+    fn describe(&self, map: &Beatmap) -> String {
+        let _ = map;
+        format!("{} beatmap", self.name())
+    }
+
+    // Conversion to a core type for consumers that don't know about the extension
+    #[inline]
+    fn to_core(&self, map: &Beatmap) -> Option<Beatmap> {
+        let _ = map;
+        None
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct ExtensionRegistry {
+    extensions: BTreeMap<u8, Arc<dyn BeatmapExtension>>,
+}
+
+impl ExtensionRegistry {
+    #[inline]
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    // Core discriminants and the unknown marker can't be taken over, registering an extension
+    // twice replaces it
+    pub fn register<E>(&mut self, extension: E) -> Result<()>
+    where
+        E: BeatmapExtension + 'static,
+    {
+        let discriminant = extension.discriminant();
+        if !is_extension(discriminant) {
+            return Err(Error::InvalidExtensionType(discriminant));
+        }
+        self.extensions.insert(discriminant, Arc::new(extension));
+        Ok(())
+    }
+
+    #[inline]
+    pub fn get(&self, discriminant: u8) -> Option<&dyn BeatmapExtension> {
+        self.extensions.get(&discriminant).map(|e| &**e)
+    }
+
+    #[inline]
+    pub fn extension_for(&self, map: &Beatmap) -> Option<&dyn BeatmapExtension> {
+        self.get(map.extension_type()?)
+    }
+
+    // Maps that aren't handled by an extension are always valid
+    #[inline]
+    pub fn validate(&self, map: &Beatmap) -> Result<()> {
+        match self.extension_for(map) {
+            Some(extension) => extension.validate(map),
+            None => Ok(()),
+        }
+    }
+
+    #[inline]
+    pub fn describe(&self, map: &Beatmap) -> Option<String> {
+        self.extension_for(map).map(|e| e.describe(map))
+    }
+
+    #[inline]
+    pub fn to_core(&self, map: &Beatmap) -> Option<Beatmap> {
+        self.extension_for(map)?.to_core(map)
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.extensions.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.extensions.is_empty()
+    }
+}
+
+impl Beatmap {
+    #[inline]
+    pub fn new_extension(discriminant: u8) -> Self {
+        #[cfg(feature = "chrono")]
+        return Self::new_extension_with_clock(discriminant, &SystemClock);
+        #[cfg(not(feature = "chrono"))]
+        return Self::empty(BeatmapType::Unknown).with_extension_type(discriminant);
+    }
+
+    #[cfg(feature = "chrono")]
+    pub fn new_extension_with_clock<C>(discriminant: u8, clock: &C) -> Self
+    where
+        C: Clock + ?Sized,
+    {
+        Self::empty(BeatmapType::Unknown, clock.now()).with_extension_type(discriminant)
+    }
+
+    #[inline]
+    fn with_extension_type(mut self, discriminant: u8) -> Self {
+        self.custom_data.insert(TYPE_KEY, Value::U8(discriminant));
+        self
+    }
+
+    // Discriminant of maps of unknown types, as read or set by `new_extension`
+    pub fn extension_type(&self) -> Option<u8> {
+        if self.ty != BeatmapType::Unknown {
+            return None;
+        }
+        match self.custom_data.get(TYPE_KEY) {
+            Some(Value::U8(u)) => Some(*u),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn reserved_custom_keys(&self) -> impl Iterator<Item = Key> + '_ {
+        let keeps_type = self.extension_type().is_some();
+        self.custom_data
+            .reserved_keys(&BEATMAP_RESERVED_KEYS)
+            .filter(move |k| {
+                !(is_cached_key(*k) || **k == UNKNOWN_FIELDS_KEY || keeps_type && **k == TYPE_KEY)
+            })
+    }
+}
+
+// Registries are equal when they hold the very same extensions
+impl PartialEq for ExtensionRegistry {
+    fn eq(&self, other: &Self) -> bool {
+        self.extensions.len() == other.extensions.len()
+            && self
+                .extensions
+                .iter()
+                .zip(&other.extensions)
+                .all(|((a, ea), (b, eb))| a == b && Arc::ptr_eq(ea, eb))
+    }
+}
+
+impl Eq for ExtensionRegistry {}
+
+impl fmt::Debug for ExtensionRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map()
+            .entries(self.extensions.iter().map(|(d, e)| (d, e.name())))
+            .finish()
+    }
+}
+
+#[inline]
+pub(crate) fn is_extension(discriminant: u8) -> bool {
+    BeatmapType::from(discriminant) == BeatmapType::Unknown
+        && discriminant != u8::from(BeatmapType::Unknown)
+}
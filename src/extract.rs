@@ -0,0 +1,432 @@
+use crate::{
+    error::Error, Beatmap, BeatmapType, HashingWriter, Playlist, Result, ZipCache, ZipReader,
+};
+use blister_format::values::Sha1;
+use flate2::Compression;
+use serde_json::Value as Json;
+use std::{
+    fs,
+    io::{self, Cursor, Read, Seek, Write},
+    path::{Component, Path, PathBuf},
+};
+use zip::{write::FileOptions, CompressionMethod, ZipArchive, ZipWriter};
+
+// Formats that are already compressed and don't gain anything from deflate
+const STORED_EXTENSIONS: &[&str] = &["egg", "ogg", "wav", "mp3", "png", "jpg", "jpeg"];
+
+const MAX_NAME_LEN: usize = 128;
+// Far more than any level's audio, entries are buffered in memory while recompressing
+const MAX_ENTRY_LEN: u64 = 256 * 1024 * 1024;
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum NamingPolicy {
+    // `<hash> (<song name>)`, falling back to the key then the map index
+    Hash,
+    // `<key> (<song name>)`, falling back to the hash then the map index
+    Key,
+    // `<song name>`, falling back to the map index
+    SongName,
+}
+
+impl Default for NamingPolicy {
+    #[inline]
+    fn default() -> Self {
+        Self::Hash
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct ExtractReport {
+    pub extracted: Vec<(usize, PathBuf)>,
+    pub failed: Vec<(usize, Error)>,
+}
+
+impl ExtractReport {
+    #[inline]
+    pub fn is_complete(&self) -> bool {
+        self.failed.is_empty()
+    }
+}
+
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq, Hash)]
+pub struct ZipRecompression {
+    pub maps: usize,
+    pub original: usize,
+    pub recompressed: usize,
+}
+
+impl Playlist {
+    // Zips that don't shrink are kept as is
+    pub fn recompress_zips(&mut self, level: Compression) -> Result<ZipRecompression> {
+        let mut recompression = ZipRecompression::default();
+        for map in &mut self.maps {
+            let zip = match &mut map.zip {
+                Some(zip) => zip,
+                None => continue,
+            };
+            let recompressed = recompress(ZipReader::new(zip)?, level)?;
+            if recompressed.len() < zip.len() {
+                recompression.maps += 1;
+                recompression.original += zip.len();
+                recompression.recompressed += recompressed.len();
+                *zip = recompressed.into();
+            }
+        }
+        Ok(recompression)
+    }
+
+    // Decompresses every entry so the zip crate checks it against its CRC
+    pub fn verify_zips(&self) -> Result<()> {
+        for (i, map) in self.maps.iter().enumerate() {
+            if let Some(zip) = &map.zip {
+                verify(ZipReader::new(zip)?)
+                    .map_err(|source| Error::CorruptZip { map: i, source })?;
+            }
+        }
+        Ok(())
+    }
+
+    // Maps that fail to extract are reported instead of aborting the whole playlist
+    pub fn extract_zips<P>(&self, dir: P, naming: NamingPolicy) -> Result<ExtractReport>
+    where
+        P: AsRef<Path>,
+    {
+        let dir = dir.as_ref();
+        fs::create_dir_all(dir)?;
+
+        let mut report = ExtractReport::default();
+        for (i, map) in self.maps.iter().enumerate() {
+            if map.ty != BeatmapType::Zip {
+                continue;
+            }
+            let zip = map.zip.as_ref().ok_or(Error::NotSelfContained(i));
+            let zip = zip.and_then(|zip| ZipReader::new(zip).map_err(Into::into));
+            match zip.and_then(|zip| extract_zip(map, zip, i, dir, naming)) {
+                Ok(path) => report.extracted.push((i, path)),
+                Err(e) => report.failed.push((i, e)),
+            }
+        }
+        Ok(report)
+    }
+
+    // Like `extract_zips`, but maps that aren't self contained are extracted from the cache
+    pub fn extract_zips_cached<P>(
+        &self,
+        dir: P,
+        naming: NamingPolicy,
+        cache: &ZipCache,
+    ) -> Result<ExtractReport>
+    where
+        P: AsRef<Path>,
+    {
+        let dir = dir.as_ref();
+        fs::create_dir_all(dir)?;
+
+        let mut report = ExtractReport::default();
+        for (i, map) in self.maps.iter().enumerate() {
+            let result = match (&map.zip, &map.hash) {
+                (Some(zip), _) => ZipReader::new(zip)
+                    .map_err(Into::into)
+                    .and_then(|zip| extract_zip(map, zip, i, dir, naming)),
+                (None, Some(hash)) => match cache.get(hash) {
+                    Ok(Some(zip)) => extract_zip(map, Cursor::new(&zip[..]), i, dir, naming),
+                    Ok(None) => Err(Error::NotSelfContained(i)),
+                    Err(e) => Err(e),
+                },
+                (None, None) if map.ty == BeatmapType::Zip => Err(Error::NotSelfContained(i)),
+                (None, None) => continue,
+            };
+            match result {
+                Ok(path) => report.extracted.push((i, path)),
+                Err(e) => report.failed.push((i, e)),
+            }
+        }
+        Ok(report)
+    }
+}
+
+impl Beatmap {
+    // Only Info.dat and the files it references end up in the zip
+    pub fn from_level_dir<P>(path: P) -> Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        let info_name = fs::read_dir(path)?
+            .filter_map(|e| e.ok())
+            .map(|e| e.file_name())
+            .find(|n| {
+                n.to_str()
+                    .is_some_and(|n| n.eq_ignore_ascii_case("info.dat"))
+            })
+            .ok_or(Error::MissingLevelInfo)?;
+        let info_bytes = fs::read(path.join(&info_name))?;
+        let info: Json = serde_json::from_slice(&info_bytes)?;
+
+        let mut files = referenced_files(&info);
+        files.sort_unstable();
+        files.dedup();
+
+        let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+        let options = FileOptions::default();
+        zip.start_file("Info.dat", options)?;
+        zip.write_all(&info_bytes)?;
+        for file in files {
+            if !is_enclosed(file) {
+                return Err(Error::InvalidLevelFile(file.to_owned()));
+            }
+            zip.start_file(file, options)?;
+            zip.write_all(&fs::read(path.join(file))?)?;
+        }
+        let zip = zip.finish()?.into_inner();
+
+        Ok(Self::new_zip(zip))
+    }
+}
+
+pub(crate) fn verify<R>(zip: R) -> zip::result::ZipResult<()>
+where
+    R: Read + Seek,
+{
+    let mut archive = ZipArchive::new(zip)?;
+    for i in 0..archive.len() {
+        io::copy(&mut archive.by_index(i)?, &mut io::sink())?;
+    }
+    Ok(())
+}
+
+fn recompress<R>(zip: R, level: Compression) -> Result<Vec<u8>>
+where
+    R: Read + Seek,
+{
+    let mut archive = ZipArchive::new(zip)?;
+    let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+    let deflated = FileOptions::default()
+        .compression_method(CompressionMethod::Deflated)
+        .compression_level(Some(level.level() as i32));
+    let stored = FileOptions::default().compression_method(CompressionMethod::Stored);
+
+    let mut contents = Vec::new();
+    for i in 0..archive.len() {
+        let mut file = archive.by_index(i)?;
+        if file.is_dir() {
+            writer.add_directory(file.name(), stored)?;
+            continue;
+        }
+        let options = match Path::new(file.name()).extension().and_then(|e| e.to_str()) {
+            Some(e) if STORED_EXTENSIONS.iter().any(|s| e.eq_ignore_ascii_case(s)) => stored,
+            _ => deflated,
+        };
+        // Declared sizes can't be trusted, reading one byte past them catches the lie
+        let name = file.name().to_owned();
+        let limit = file.size();
+        if limit > MAX_ENTRY_LEN {
+            return Err(Error::ZipEntryTooLarge(name));
+        }
+        contents.clear();
+        (&mut file).take(limit + 1).read_to_end(&mut contents)?;
+        if contents.len() as u64 > limit {
+            return Err(Error::ZipEntryTooLarge(name));
+        }
+        writer.start_file(name, options)?;
+        writer.write_all(&contents)?;
+    }
+    Ok(writer.finish()?.into_inner())
+}
+
+fn extract_zip<R>(
+    map: &Beatmap,
+    zip: R,
+    index: usize,
+    dir: &Path,
+    naming: NamingPolicy,
+) -> Result<PathBuf>
+where
+    R: Read + Seek,
+{
+    let mut archive = ZipArchive::new(zip)?;
+
+    let name = folder_name(map, index, song_name(&mut archive).as_deref(), naming);
+    let path = unique_path(dir, &name);
+    fs::create_dir(&path)?;
+
+    // Leave nothing half written behind
+    if let Err(e) = unpack(&mut archive, &path) {
+        let _ = fs::remove_dir_all(&path);
+        return Err(e);
+    }
+    Ok(path)
+}
+
+fn unpack<R>(archive: &mut ZipArchive<R>, path: &Path) -> Result<()>
+where
+    R: Read + Seek,
+{
+    for i in 0..archive.len() {
+        let mut file = archive.by_index(i)?;
+        // Entries escaping the map folder are skipped
+        let target = match file.enclosed_name() {
+            Some(name) => path.join(name),
+            None => continue,
+        };
+        if file.is_dir() {
+            fs::create_dir_all(&target)?;
+            continue;
+        }
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        io::copy(&mut file, &mut fs::File::create(&target)?)?;
+    }
+    Ok(())
+}
+
+// BeatSaver's map hash, the SHA-1 of Info.dat followed by every difficulty file it lists. Only the
+// v2 layout is covered, v4 levels hash their files differently.
+pub(crate) fn level_hash<R>(zip: R) -> Option<Sha1>
+where
+    R: Read + Seek,
+{
+    let mut archive = ZipArchive::new(zip).ok()?;
+    let name = archive
+        .file_names()
+        .find(|n| n.eq_ignore_ascii_case("info.dat"))?
+        .to_owned();
+    let mut info = Vec::new();
+    archive
+        .by_name(&name)
+        .ok()?
+        .take(MAX_ENTRY_LEN)
+        .read_to_end(&mut info)
+        .ok()?;
+    let json: Json = serde_json::from_slice(&info).ok()?;
+
+    let mut hasher = HashingWriter::new(io::sink());
+    hasher.write_all(&info).ok()?;
+    let sets = json.get("_difficultyBeatmapSets")?.as_array()?;
+    for set in sets {
+        for map in set.get("_difficultyBeatmaps")?.as_array()? {
+            let file = map.get("_beatmapFilename")?.as_str()?;
+            io::copy(&mut archive.by_name(file).ok()?, &mut hasher).ok()?;
+        }
+    }
+    Some(hasher.finish().1)
+}
+
+fn song_name<R>(archive: &mut ZipArchive<R>) -> Option<String>
+where
+    R: Read + Seek,
+{
+    let name = archive
+        .file_names()
+        .find(|n| n.eq_ignore_ascii_case("info.dat"))?
+        .to_owned();
+    let mut info = String::new();
+    archive
+        .by_name(&name)
+        .ok()?
+        .read_to_string(&mut info)
+        .ok()?;
+
+    let info: Json = serde_json::from_str(&info).ok()?;
+    let name = info
+        .get("_songName")
+        .or_else(|| info.get("song").and_then(|s| s.get("title")))?
+        .as_str()?;
+    Some(name.to_owned())
+}
+
+// Covers both the v2 (`_songFilename`) and v4 (`audio.songFilename`) layouts
+fn referenced_files<'a>(info: &'a Json) -> Vec<&'a str> {
+    let mut files = Vec::new();
+    let mut push = |v: Option<&'a Json>| {
+        if let Some(f) = v.and_then(Json::as_str).filter(|f| !f.is_empty()) {
+            files.push(f);
+        }
+    };
+
+    push(info.get("_songFilename"));
+    push(info.get("_coverImageFilename"));
+    push(info.get("coverImageFilename"));
+    if let Some(audio) = info.get("audio") {
+        push(audio.get("songFilename"));
+        push(audio.get("audioDataFilename"));
+    }
+
+    let sets = info.get("_difficultyBeatmapSets").and_then(Json::as_array);
+    for set in sets.into_iter().flatten() {
+        let maps = set.get("_difficultyBeatmaps").and_then(Json::as_array);
+        for map in maps.into_iter().flatten() {
+            push(map.get("_beatmapFilename"));
+        }
+    }
+    let maps = info.get("difficultyBeatmaps").and_then(Json::as_array);
+    for map in maps.into_iter().flatten() {
+        push(map.get("beatmapDataFilename"));
+        push(map.get("lightshowDataFilename"));
+    }
+
+    files
+}
+
+fn is_enclosed(file: &str) -> bool {
+    Path::new(file)
+        .components()
+        .all(|c| matches!(c, Component::Normal(_)))
+}
+
+fn folder_name(map: &Beatmap, index: usize, song: Option<&str>, naming: NamingPolicy) -> String {
+    let hash = map.hash.as_ref().map(ToString::to_string);
+    let key = map.key.map(|k| format!("{:x}", k));
+    let id = match naming {
+        NamingPolicy::Hash => hash.or(key),
+        NamingPolicy::Key => key.or(hash),
+        NamingPolicy::SongName => None,
+    };
+
+    let song = song.map(sanitize).filter(|s| !s.is_empty());
+    let name = match (id, song) {
+        (Some(id), Some(song)) => format!("{} ({})", id, song),
+        (Some(id), None) => id,
+        (None, Some(song)) => song,
+        (None, None) => index.to_string(),
+    };
+    truncate(name)
+}
+
+// Strips characters that aren't allowed in folder names on any platform
+fn sanitize(name: &str) -> String {
+    let name: String = name
+        .chars()
+        .map(|c| match c {
+            '<' | '>' | ':' | '"' | '/' | '\\' | '|' | '?' | '*' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+    name.trim_matches(|c: char| c == '.' || c.is_whitespace())
+        .to_owned()
+}
+
+fn truncate(mut name: String) -> String {
+    if name.len() > MAX_NAME_LEN {
+        let mut end = MAX_NAME_LEN;
+        while !name.is_char_boundary(end) {
+            end -= 1;
+        }
+        name.truncate(end);
+        name.truncate(name.trim_end().len());
+    }
+    name
+}
+
+fn unique_path(dir: &Path, name: &str) -> PathBuf {
+    let path = dir.join(name);
+    if !path.exists() {
+        return path;
+    }
+    (2..)
+        .map(|n| dir.join(format!("{} ({})", name, n)))
+        .find(|p| !p.exists())
+        .unwrap()
+}
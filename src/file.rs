@@ -0,0 +1,223 @@
+#[cfg(all(feature = "lock", feature = "gzip"))]
+use crate::FileLock;
+use crate::Result;
+#[cfg(feature = "gzip")]
+use crate::{
+    clock::instant_now, error::Error, history, Playlist, PlaylistEditor, ReadOptions, Snapshot,
+    WriteOptions,
+};
+use std::{
+    ffi::OsString,
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+#[cfg(feature = "gzip")]
+use std::{
+    io::BufReader,
+    time::{Duration, Instant},
+};
+
+#[cfg(feature = "gzip")]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum AutosavePolicy {
+    Never,
+    // Checked whenever the playlist is edited or `autosave` is called
+    Interval(Duration),
+    Changes(u64),
+}
+
+#[cfg(feature = "gzip")]
+impl Default for AutosavePolicy {
+    #[inline]
+    fn default() -> Self {
+        Self::Never
+    }
+}
+
+// A playlist editor bound to the file it was loaded from, locked for as long as it is open when the
+// `lock` feature is enabled
+#[cfg(feature = "gzip")]
+#[derive(Debug)]
+pub struct PlaylistFile {
+    path: PathBuf,
+    editor: PlaylistEditor,
+    pub options: WriteOptions,
+    pub autosave: AutosavePolicy,
+    // Number of previous versions kept in the history folder on save, none by default
+    pub keep_snapshots: usize,
+    saved_revision: Option<u64>,
+    last_save: Option<Instant>,
+    #[cfg(feature = "lock")]
+    lock: FileLock,
+}
+
+#[cfg(feature = "gzip")]
+impl PlaylistFile {
+    pub fn open<P>(path: P, options: &ReadOptions) -> Result<Self>
+    where
+        P: Into<PathBuf>,
+    {
+        let path = path.into();
+        #[cfg(feature = "lock")]
+        let lock = FileLock::acquire(&path)?;
+        let playlist = Playlist::read_with_options(BufReader::new(File::open(&path)?), options)?;
+        Ok(Self {
+            path,
+            editor: PlaylistEditor::new(playlist),
+            options: Default::default(),
+            autosave: Default::default(),
+            keep_snapshots: 0,
+            saved_revision: Some(0),
+            last_save: instant_now(),
+            #[cfg(feature = "lock")]
+            lock,
+        })
+    }
+
+    // Nothing is written until the first save
+    pub fn create<P>(path: P, playlist: Playlist) -> Result<Self>
+    where
+        P: Into<PathBuf>,
+    {
+        let path = path.into();
+        Ok(Self {
+            #[cfg(feature = "lock")]
+            lock: FileLock::acquire(&path)?,
+            path,
+            editor: PlaylistEditor::new(playlist),
+            options: Default::default(),
+            autosave: Default::default(),
+            keep_snapshots: 0,
+            saved_revision: None,
+            last_save: instant_now(),
+        })
+    }
+
+    #[inline]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    #[inline]
+    pub fn playlist(&self) -> &Playlist {
+        self.editor.playlist()
+    }
+
+    #[cfg(feature = "lock")]
+    #[inline]
+    pub fn lock(&self) -> &FileLock {
+        &self.lock
+    }
+
+    #[inline]
+    pub fn editor(&self) -> &PlaylistEditor {
+        &self.editor
+    }
+
+    // Undoing back to the saved state still counts as a change
+    #[inline]
+    pub fn is_dirty(&self) -> bool {
+        self.saved_revision != Some(self.editor.revision())
+    }
+
+    // Autosaves afterwards if the policy says so, even when the edit itself failed. A failed edit
+    // is reported over a failed save, which is retried on the next one since the file stays dirty,
+    // and a failed save after a successful edit is wrapped in `Error::Autosave`
+    pub fn edit<F, T>(&mut self, f: F) -> Result<T>
+    where
+        F: FnOnce(&mut PlaylistEditor) -> Result<T>,
+    {
+        let result = f(&mut self.editor);
+        let saved = self.autosave();
+        let value = result?;
+        saved.map_err(|e| Error::Autosave(Box::new(e)))?;
+        Ok(value)
+    }
+
+    // Returns whether the playlist was saved
+    pub fn autosave(&mut self) -> Result<bool> {
+        if !self.is_dirty() {
+            return Ok(false);
+        }
+        let due = match self.autosave {
+            AutosavePolicy::Never => false,
+            // Never due without a clock to measure the interval with
+            AutosavePolicy::Interval(interval) => {
+                self.last_save.is_some_and(|s| s.elapsed() >= interval)
+            }
+            AutosavePolicy::Changes(changes) => {
+                let saved = self.saved_revision.unwrap_or(0);
+                self.editor.revision() - saved >= changes
+            }
+        };
+        if due {
+            self.save()?;
+        }
+        Ok(due)
+    }
+
+    pub fn save(&mut self) -> Result<()> {
+        history::snapshot(&self.path, self.keep_snapshots)?;
+        let playlist = self.editor.playlist();
+        write_atomic(&self.path, |w| {
+            playlist.write_with_options(w, &self.options).map(|_| ())
+        })?;
+
+        self.saved_revision = Some(self.editor.revision());
+        self.last_save = instant_now();
+        Ok(())
+    }
+
+    // Oldest first
+    #[inline]
+    pub fn history(&self) -> Result<Vec<Snapshot>> {
+        history::snapshots(&self.path)
+    }
+
+    // Loaded as an edit, so it can be undone and is only written on the next save
+    pub fn restore(&mut self, version: u64, options: &ReadOptions) -> Result<()> {
+        let snapshot = history::find(&self.path, version)?;
+        let playlist =
+            Playlist::read_with_options(BufReader::new(File::open(snapshot.path)?), options)?;
+        self.edit(|e| e.replace_playlist(playlist))
+    }
+}
+
+// Written to a sibling file first and renamed over the original, so a crash never leaves a half
+// written file behind. The original's permissions carry over to the new file
+pub fn write_atomic<F>(path: &Path, write: F) -> Result<()>
+where
+    F: FnOnce(&mut BufWriter<File>) -> Result<()>,
+{
+    let tmp = tmp_path(path);
+    let permissions = match fs::metadata(path) {
+        Ok(metadata) => Some(metadata.permissions()),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+        Err(e) => return Err(e.into()),
+    };
+    let result = File::create(&tmp).map_err(Into::into).and_then(|file| {
+        let mut writer = BufWriter::new(file);
+        write(&mut writer)?;
+        writer.flush()?;
+        writer.get_ref().sync_all()?;
+        // Only applied once written, a read-only original would otherwise lock the new file too
+        if let Some(permissions) = permissions {
+            writer.get_ref().set_permissions(permissions)?;
+        }
+        Ok(())
+    });
+    if let Err(e) = result {
+        let _ = fs::remove_file(&tmp);
+        return Err(e);
+    }
+    fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+fn tmp_path(path: &Path) -> PathBuf {
+    let mut name = OsString::from(".");
+    name.push(path.file_name().unwrap_or_default());
+    name.push(".tmp");
+    path.with_file_name(name)
+}
@@ -0,0 +1,136 @@
+use crate::{playlist::canonical_cover, Playlist, Result};
+use blister_format::{Map, MapEncoding};
+use byteorder::{WriteBytesExt, LE};
+use sha2::{Digest, Sha256};
+use std::{
+    convert::TryInto,
+    hash::{Hash, Hasher},
+    ops::Deref,
+};
+
+pub const FINGERPRINT_LEN: usize = 32;
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct FingerprintOptions {
+    pub date_added: bool,
+    pub custom_data: bool,
+}
+
+impl Default for FingerprintOptions {
+    #[inline]
+    fn default() -> Self {
+        Self {
+            date_added: false,
+            custom_data: true,
+        }
+    }
+}
+
+impl Playlist {
+    #[inline]
+    pub fn fingerprint(&self) -> Result<[u8; FINGERPRINT_LEN]> {
+        self.fingerprint_with_options(Default::default())
+    }
+
+    pub fn fingerprint_with_options(
+        &self,
+        options: FingerprintOptions,
+    ) -> Result<[u8; FINGERPRINT_LEN]> {
+        // Fields are hashed straight from the playlist, leaving out custom data means hashing
+        // them over an empty map
+        let empty = Map::new();
+        let custom_data = |map| if options.custom_data { map } else { &empty };
+
+        let mut hasher = Sha256::new();
+
+        let cover = canonical_cover(self.cover.as_deref(), true);
+        let lists = self.encode_lists()?;
+        let header = self.header_overlay(cover, &lists, true)?;
+        custom_data(&self.custom_data).write_overlaid(
+            &header,
+            &mut hasher,
+            MapEncoding::Length,
+            true,
+        )?;
+
+        hasher.write_u32::<LE>(self.maps.len().try_into()?)?;
+        for map in &self.maps {
+            let mut overlay = map.overlay(options.custom_data)?;
+            if !options.date_added {
+                overlay.retain(|(k, _)| **k != 1);
+                overlay.push((1.into(), None));
+            }
+            custom_data(&map.custom_data).write_overlaid(
+                &overlay,
+                &mut hasher,
+                MapEncoding::Length,
+                true,
+            )?;
+        }
+
+        Ok(hasher.finalize().into())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CanonicalPlaylist {
+    playlist: Playlist,
+    hash: [u8; FINGERPRINT_LEN],
+}
+
+impl Playlist {
+    #[inline]
+    pub fn canonical_hash(&self) -> Result<[u8; FINGERPRINT_LEN]> {
+        self.fingerprint_with_options(FingerprintOptions {
+            date_added: true,
+            custom_data: true,
+        })
+    }
+
+    #[inline]
+    pub fn into_canonical(self) -> Result<CanonicalPlaylist> {
+        CanonicalPlaylist::new(self)
+    }
+}
+
+// The hash is computed once so the playlist can't be mutated behind it
+impl CanonicalPlaylist {
+    pub fn new(playlist: Playlist) -> Result<Self> {
+        let hash = playlist.canonical_hash()?;
+        Ok(Self { playlist, hash })
+    }
+
+    #[inline]
+    pub fn digest(&self) -> &[u8; FINGERPRINT_LEN] {
+        &self.hash
+    }
+
+    #[inline]
+    pub fn into_inner(self) -> Playlist {
+        self.playlist
+    }
+}
+
+impl Deref for CanonicalPlaylist {
+    type Target = Playlist;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        &self.playlist
+    }
+}
+
+impl PartialEq for CanonicalPlaylist {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.hash == other.hash
+    }
+}
+impl Eq for CanonicalPlaylist {}
+
+impl Hash for CanonicalPlaylist {
+    #[inline]
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.hash.hash(state)
+    }
+}
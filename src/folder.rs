@@ -0,0 +1,187 @@
+use crate::{error::Error, Result};
+#[cfg(feature = "gzip")]
+use crate::{Playlist, ReadOptions};
+use blister_format::values::Binary;
+use byteorder::{ReadBytesExt, WriteBytesExt, LE};
+use std::{convert::TryInto, io};
+#[cfg(feature = "gzip")]
+use std::{
+    fs::File,
+    io::BufReader,
+    path::{Component, Path, PathBuf},
+};
+
+pub(crate) const CHILDREN_KEY: u32 = 7;
+// Cycles are caught separately, this only bounds legitimately deep trees
+pub const MAX_CHILD_DEPTH: usize = 16;
+
+const PATH_CHILD: u8 = 0;
+const EMBEDDED_CHILD: u8 = 1;
+
+// Embedded children are kept encoded until the tree is walked
+#[derive(Debug, Clone, PartialEq)]
+pub enum ChildPlaylist {
+    Path(String),
+    Embedded(Binary),
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum ChildPlaylistRef<'a> {
+    Path(&'a str),
+    Embedded(&'a [u8]),
+}
+
+#[cfg(feature = "gzip")]
+impl ChildPlaylist {
+    pub fn embed(playlist: Playlist) -> Result<Self> {
+        let mut bytes = Vec::new();
+        playlist.write(&mut bytes)?;
+        Ok(Self::Embedded(bytes.into()))
+    }
+
+    // Paths are relative to the directory of the parent playlist
+    pub fn load(&self, base: Option<&Path>, options: &ReadOptions) -> Result<Playlist> {
+        match self {
+            Self::Path(path) => {
+                let path = resolve(base, path)?;
+                Playlist::read_with_options(BufReader::new(File::open(path)?), options)
+            }
+            Self::Embedded(bytes) => Playlist::read_with_options(bytes.reader()?, options),
+        }
+    }
+}
+
+impl<'a> ChildPlaylistRef<'a> {
+    pub fn to_owned(self) -> ChildPlaylist {
+        match self {
+            Self::Path(path) => ChildPlaylist::Path(path.to_owned()),
+            Self::Embedded(bytes) => ChildPlaylist::Embedded(bytes.into()),
+        }
+    }
+}
+
+#[cfg(feature = "gzip")]
+impl Playlist {
+    // Visits this playlist then every descendant depth first, along with its depth
+    pub fn walk<F>(&self, base: Option<&Path>, options: &ReadOptions, mut f: F) -> Result<()>
+    where
+        F: FnMut(usize, &Playlist),
+    {
+        self.walk_at(base, options, 0, &mut Vec::new(), &mut f)
+    }
+
+    // Child maps are appended after the parent's own, in tree order
+    pub fn flatten(&self, base: Option<&Path>, options: &ReadOptions) -> Result<Playlist> {
+        let mut flat = Playlist {
+            children: Vec::new(),
+            maps: Vec::new(),
+            ..self.clone()
+        };
+        self.walk(base, options, |_, p| {
+            flat.maps.extend(p.maps.iter().cloned())
+        })?;
+        Ok(flat)
+    }
+
+    fn walk_at<F>(
+        &self,
+        base: Option<&Path>,
+        options: &ReadOptions,
+        depth: usize,
+        ancestors: &mut Vec<PathBuf>,
+        f: &mut F,
+    ) -> Result<()>
+    where
+        F: FnMut(usize, &Playlist),
+    {
+        f(depth, self);
+        if self.children.is_empty() {
+            return Ok(());
+        }
+        if depth >= MAX_CHILD_DEPTH {
+            return Err(Error::ChildTooDeep);
+        }
+
+        for child in &self.children {
+            let path = match child {
+                ChildPlaylist::Path(path) => path,
+                ChildPlaylist::Embedded(_) => {
+                    let playlist = child.load(base, options)?;
+                    playlist.walk_at(base, options, depth + 1, ancestors, f)?;
+                    continue;
+                }
+            };
+
+            // Canonical paths catch cycles going through links or different spellings
+            let canonical = resolve(base, path)?.canonicalize()?;
+            if ancestors.contains(&canonical) {
+                return Err(Error::ChildCycle(path.clone()));
+            }
+            let playlist =
+                Playlist::read_with_options(BufReader::new(File::open(&canonical)?), options)?;
+            let child_base = canonical.parent().map(Path::to_path_buf);
+            ancestors.push(canonical);
+            let walked = playlist.walk_at(child_base.as_deref(), options, depth + 1, ancestors, f);
+            ancestors.pop();
+            walked?;
+        }
+        Ok(())
+    }
+}
+
+// Children can't reach outside of the parent's folder
+#[cfg(feature = "gzip")]
+fn resolve(base: Option<&Path>, path: &str) -> Result<PathBuf> {
+    if !Path::new(path)
+        .components()
+        .all(|c| matches!(c, Component::Normal(_)))
+    {
+        return Err(Error::UnsafeChildPath(path.to_owned()));
+    }
+    match base {
+        Some(base) => Ok(base.join(path)),
+        None => Err(Error::UnresolvedChild(path.to_owned())),
+    }
+}
+
+// Each child is a kind byte followed by its length-prefixed contents
+pub(crate) fn encode_children(children: &[ChildPlaylist]) -> Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    for child in children {
+        match child {
+            ChildPlaylist::Path(path) => {
+                bytes.write_u8(PATH_CHILD)?;
+                bytes.write_u32::<LE>(path.len().try_into()?)?;
+                bytes.extend_from_slice(path.as_bytes());
+            }
+            ChildPlaylist::Embedded(playlist) => {
+                bytes.write_u8(EMBEDDED_CHILD)?;
+                bytes.write_u32::<LE>(playlist.len().try_into()?)?;
+                io::copy(&mut playlist.reader()?, &mut bytes)?;
+            }
+        }
+    }
+    Ok(bytes)
+}
+
+pub(crate) fn decode_children(mut bytes: &[u8]) -> Result<Vec<ChildPlaylistRef<'_>>> {
+    let mut children = Vec::new();
+    while !bytes.is_empty() {
+        let invalid = |_| Error::InvalidPlaylistChildren(None);
+        let kind = bytes.read_u8().map_err(invalid)?;
+        let len = bytes.read_u32::<LE>().map_err(invalid)? as usize;
+        if len > bytes.len() {
+            return Err(Error::InvalidPlaylistChildren(None));
+        }
+        let (contents, rest) = bytes.split_at(len);
+        children.push(match kind {
+            PATH_CHILD => ChildPlaylistRef::Path(
+                std::str::from_utf8(contents).map_err(|_| Error::InvalidPlaylistChildren(None))?,
+            ),
+            EMBEDDED_CHILD => ChildPlaylistRef::Embedded(contents),
+            _ => return Err(Error::InvalidPlaylistChildren(None)),
+        });
+        bytes = rest;
+    }
+    Ok(children)
+}
@@ -0,0 +1,54 @@
+use crate::{Beatmap, Playlist, Result, LONG_STRING_LEN, SHORT_STRING_LEN};
+use arbitrary::{Arbitrary, Unstructured};
+use blister_format::{fuzzing::string, Map};
+use chrono::{TimeZone, Utc};
+
+impl<'a> Arbitrary<'a> for Beatmap {
+    fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<Self> {
+        let mut beatmap = match u.int_in_range(0..=3)? {
+            0 => Beatmap::new_key(u.arbitrary()?),
+            1 => Beatmap::new_hash(u.arbitrary()?),
+            2 => Beatmap::new_zip(<&[u8]>::arbitrary(u)?),
+            _ => Beatmap::new_level_id(string(u, SHORT_STRING_LEN)?.into()),
+        };
+        // Dates are stored with a precision of one second
+        beatmap.date_added = Utc.timestamp_opt(u32::arbitrary(u)?.into(), 0).unwrap();
+        beatmap.custom_data = custom_data(u, 6)?;
+        Ok(beatmap)
+    }
+}
+
+impl<'a> Arbitrary<'a> for Playlist {
+    fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<Self> {
+        let mut playlist = Playlist::new(
+            string(u, SHORT_STRING_LEN)?.into(),
+            string(u, SHORT_STRING_LEN)?.into(),
+        );
+        if u.arbitrary()? {
+            playlist.description = Some(string(u, LONG_STRING_LEN)?.into());
+        }
+        if u.arbitrary()? {
+            playlist.cover = Some(<&[u8]>::arbitrary(u)?.into());
+        }
+        playlist.custom_data = custom_data(u, 4)?;
+        playlist.maps = u.arbitrary()?;
+        Ok(playlist)
+    }
+}
+
+// Reserved keys would be overwritten by the core fields when writing
+fn custom_data(u: &mut Unstructured<'_>, reserved: u32) -> arbitrary::Result<Map> {
+    let mut map = Map::arbitrary(u)?;
+    for key in 0..reserved {
+        map.remove(key);
+    }
+    Ok(map)
+}
+
+impl Playlist {
+    pub fn roundtrips(&self) -> Result<bool> {
+        let mut buffer = Vec::new();
+        self.write(&mut buffer)?;
+        Ok(Playlist::read(buffer.as_slice(), true)? == *self)
+    }
+}
@@ -0,0 +1,145 @@
+use blister_format::values::Sha1;
+use sha1::{Digest, Sha1 as Sha1Hasher};
+use std::io::{self, Read, Write};
+
+// Hashes bytes as they pass through, so payloads never need to be buffered whole
+#[derive(Debug, Clone)]
+pub struct HashingReader<R> {
+    inner: R,
+    hasher: Sha1Hasher,
+    len: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct HashingWriter<W> {
+    inner: W,
+    hasher: Sha1Hasher,
+    len: u64,
+}
+
+impl<R> HashingReader<R> {
+    #[inline]
+    pub fn new(inner: R) -> Self {
+        Self {
+            inner,
+            hasher: Sha1Hasher::new(),
+            len: 0,
+        }
+    }
+
+    #[inline]
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    // Digest of everything read so far
+    #[inline]
+    pub fn digest(&self) -> Sha1 {
+        Sha1(self.hasher.clone().finalize().into())
+    }
+
+    #[inline]
+    pub fn finish(self) -> (R, Sha1) {
+        (self.inner, Sha1(self.hasher.finalize().into()))
+    }
+}
+
+impl<W> HashingWriter<W> {
+    #[inline]
+    pub fn new(inner: W) -> Self {
+        Self {
+            inner,
+            hasher: Sha1Hasher::new(),
+            len: 0,
+        }
+    }
+
+    #[inline]
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    // Hashes bytes the inner writer already holds without writing them again, e.g. the start of a
+    // file being appended to
+    pub fn hash_existing<R>(&mut self, mut reader: R) -> io::Result<u64>
+    where
+        R: Read,
+    {
+        let mut buf = [0; 8 * 1024];
+        let mut hashed = 0;
+        loop {
+            let read = match reader.read(&mut buf) {
+                Ok(0) => break,
+                Ok(read) => read,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            };
+            self.hasher.update(&buf[..read]);
+            hashed += read as u64;
+        }
+        self.len += hashed;
+        Ok(hashed)
+    }
+
+    // Digest of everything written so far
+    #[inline]
+    pub fn digest(&self) -> Sha1 {
+        Sha1(self.hasher.clone().finalize().into())
+    }
+
+    #[inline]
+    pub fn finish(self) -> (W, Sha1) {
+        (self.inner, Sha1(self.hasher.finalize().into()))
+    }
+}
+
+impl<R> Read for HashingReader<R>
+where
+    R: Read,
+{
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.hasher.update(&buf[..read]);
+        self.len += read as u64;
+        Ok(read)
+    }
+}
+
+impl<W> Write for HashingWriter<W>
+where
+    W: Write,
+{
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        // Only what the inner writer accepted is hashed
+        let written = self.inner.write(buf)?;
+        self.hasher.update(&buf[..written]);
+        self.len += written as u64;
+        Ok(written)
+    }
+
+    #[inline]
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+// Copies a payload from its source to its destination while hashing it
+pub fn copy_hashed<R, W>(reader: R, writer: &mut W) -> io::Result<(u64, Sha1)>
+where
+    R: Read,
+    W: Write + ?Sized,
+{
+    let mut reader = HashingReader::new(reader);
+    let len = io::copy(&mut reader, writer)?;
+    Ok((len, reader.finish().1))
+}
@@ -0,0 +1,73 @@
+use crate::{error::Error, Result};
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+pub const HISTORY_DIR: &str = ".history";
+
+// A previous version of a saved file, versions only ever go up
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct Snapshot {
+    pub version: u64,
+    pub path: PathBuf,
+}
+
+// Snapshots of `<dir>/<name>` live in `<dir>/.history/<name>/<version>`
+pub(crate) fn history_dir(path: &Path) -> PathBuf {
+    let name = path.file_name().unwrap_or_default();
+    path.with_file_name(HISTORY_DIR).join(name)
+}
+
+// Oldest first
+pub(crate) fn snapshots(path: &Path) -> Result<Vec<Snapshot>> {
+    let entries = match fs::read_dir(history_dir(path)) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+    let mut snapshots = Vec::new();
+    for entry in entries {
+        let entry = entry?;
+        let version = entry.file_name().to_str().and_then(|n| n.parse().ok());
+        if let Some(version) = version {
+            snapshots.push(Snapshot {
+                version,
+                path: entry.path(),
+            });
+        }
+    }
+    snapshots.sort_unstable_by_key(|s| s.version);
+    Ok(snapshots)
+}
+
+pub(crate) fn find(path: &Path, version: u64) -> Result<Snapshot> {
+    snapshots(path)?
+        .into_iter()
+        .find(|s| s.version == version)
+        .ok_or(Error::MissingSnapshot(version))
+}
+
+// Copies the current file to the history before it gets overwritten, keeping the last `keep`
+// versions. Nothing happens if the file doesn't exist yet.
+pub(crate) fn snapshot(path: &Path, keep: usize) -> Result<Option<Snapshot>> {
+    if keep == 0 || !path.is_file() {
+        return Ok(None);
+    }
+    let mut snapshots = snapshots(path)?;
+    let version = snapshots.last().map_or(1, |s| s.version + 1);
+
+    let dir = history_dir(path);
+    fs::create_dir_all(&dir)?;
+    let snapshot_path = dir.join(version.to_string());
+    fs::copy(path, &snapshot_path)?;
+
+    let excess = (snapshots.len() + 1).saturating_sub(keep);
+    for old in snapshots.drain(..excess) {
+        fs::remove_file(old.path)?;
+    }
+    Ok(Some(Snapshot {
+        version,
+        path: snapshot_path,
+    }))
+}
@@ -0,0 +1,328 @@
+use crate::{error::Error, HashingWriter, Result};
+use blister_format::values::Sha1;
+use std::{
+    ffi::OsString,
+    fs::{self, File, OpenOptions},
+    io::{self, Read, Write},
+    path::{Path, PathBuf},
+    sync::{Condvar, Mutex},
+    thread,
+    time::Duration,
+};
+
+pub const DEFAULT_CONCURRENCY: usize = 4;
+pub const DEFAULT_MAX_BODY_LEN: u64 = 256 * 1024 * 1024;
+
+// Transport shared by every network-facing operation, implemented on top of whichever HTTP
+// library the application already depends on
+pub trait HttpClient {
+    // A non-zero `offset` is a resumed download and should be sent as a `Range` header
+    fn get(&self, url: &str, offset: u64) -> io::Result<HttpResponse>;
+}
+
+pub struct HttpResponse {
+    pub status: u16,
+    // Parsed from the `Retry-After` header, if any
+    pub retry_after: Option<Duration>,
+    // Raw `Content-Range` header, which partial responses have to carry
+    pub content_range: Option<String>,
+    pub body: Box<dyn Read + Send>,
+}
+
+impl HttpResponse {
+    #[inline]
+    pub fn new<R>(status: u16, body: R) -> Self
+    where
+        R: Read + Send + 'static,
+    {
+        Self {
+            status,
+            retry_after: None,
+            content_range: None,
+            body: Box::new(body),
+        }
+    }
+
+    #[inline]
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+}
+
+impl<F> HttpClient for F
+where
+    F: Fn(&str, u64) -> io::Result<HttpResponse>,
+{
+    #[inline]
+    fn get(&self, url: &str, offset: u64) -> io::Result<HttpResponse> {
+        self(url, offset)
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct RetryPolicy {
+    pub max_retries: u32,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    #[inline]
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(30),
+        }
+    }
+}
+
+impl RetryPolicy {
+    #[inline]
+    pub fn none() -> Self {
+        Self {
+            max_retries: 0,
+            ..Default::default()
+        }
+    }
+
+    // Doubles with every attempt, starting from zero
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let factor = 1u32.checked_shl(attempt).unwrap_or(u32::MAX);
+        self.initial_backoff
+            .checked_mul(factor)
+            .map_or(self.max_backoff, |b| b.min(self.max_backoff))
+    }
+}
+
+// Retries transient failures with exponential backoff and caps the number of requests in flight
+// across all the threads sharing it
+#[derive(Debug)]
+pub struct HttpDownloader<C> {
+    client: C,
+    retry: RetryPolicy,
+    concurrency: usize,
+    max_body_len: u64,
+    in_flight: Mutex<usize>,
+    available: Condvar,
+}
+
+impl<C> HttpDownloader<C>
+where
+    C: HttpClient,
+{
+    #[inline]
+    pub fn new(client: C) -> Self {
+        Self {
+            client,
+            retry: RetryPolicy::default(),
+            concurrency: DEFAULT_CONCURRENCY,
+            max_body_len: DEFAULT_MAX_BODY_LEN,
+            in_flight: Mutex::new(0),
+            available: Condvar::new(),
+        }
+    }
+
+    #[inline]
+    pub fn with_retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    #[inline]
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    // Applies to fetched bodies and downloaded files alike
+    #[inline]
+    pub fn with_max_body_len(mut self, max_body_len: u64) -> Self {
+        self.max_body_len = max_body_len;
+        self
+    }
+
+    #[inline]
+    pub fn client(&self) -> &C {
+        &self.client
+    }
+
+    #[inline]
+    pub fn retry_policy(&self) -> &RetryPolicy {
+        &self.retry
+    }
+
+    pub fn fetch(&self, url: &str) -> Result<Vec<u8>> {
+        self.with_retries(|| {
+            let mut response = self.request(url, 0)?;
+            let mut body = Vec::new();
+            (&mut response.body)
+                .take(self.max_body_len.saturating_add(1))
+                .read_to_end(&mut body)?;
+            if body.len() as u64 > self.max_body_len {
+                return Err(self.too_large(url));
+            }
+            Ok(body)
+        })
+    }
+
+    #[inline]
+    pub fn download(&self, url: &str, dest: &Path) -> Result<(u64, Sha1)> {
+        self.download_verified(url, dest, |_| Ok(()))
+    }
+
+    // Bytes are written to a sibling `.part` file that later attempts and runs resume from, and
+    // which is only renamed to `dest` once complete and accepted by `verify`. Returns the size and
+    // SHA-1 digest of the downloaded file, hashed as it streams in.
+    pub fn download_verified<F>(&self, url: &str, dest: &Path, verify: F) -> Result<(u64, Sha1)>
+    where
+        F: Fn(&Path) -> Result<()>,
+    {
+        let part = part_path(dest);
+        let (len, digest) = self.with_retries(|| {
+            let mut offset = fs::metadata(&part).map_or(0, |m| m.len());
+            let mut response = match self.request(url, offset) {
+                // A `.part` already holding the whole file, or more, can't be resumed and its
+                // length alone doesn't tell which, so it is thrown away and fetched again
+                Err(Error::Http { status: 416, .. }) if offset > 0 => {
+                    fs::remove_file(&part)?;
+                    offset = 0;
+                    self.request(url, offset)?
+                }
+                response => response?,
+            };
+            // Servers ignoring the range send the whole body back
+            let resumed = response.status == 206;
+            if resumed && response.content_range.as_deref().and_then(range_start) != Some(offset) {
+                // The body can't be lined up with what's on disk, so the next attempt restarts
+                let _ = fs::remove_file(&part);
+                return Err(Error::UnexpectedContentRange {
+                    url: url.to_owned(),
+                    offset,
+                });
+            }
+            let file = OpenOptions::new()
+                .create(true)
+                .write(true)
+                .append(resumed)
+                .truncate(!resumed)
+                .open(&part)?;
+            let mut writer = HashingWriter::new(file);
+            if resumed {
+                writer.hash_existing(io::BufReader::new(File::open(&part)?))?;
+            }
+            let remaining = self.max_body_len.saturating_sub(writer.len());
+            io::copy(
+                &mut (&mut response.body).take(remaining.saturating_add(1)),
+                &mut writer,
+            )?;
+            if writer.len() > self.max_body_len {
+                drop(writer);
+                let _ = fs::remove_file(&part);
+                return Err(self.too_large(url));
+            }
+            writer.flush()?;
+            let len = writer.len();
+            let (file, digest) = writer.finish();
+            file.sync_all()?;
+            Ok((len, digest))
+        })?;
+
+        if let Err(e) = verify(&part) {
+            let _ = fs::remove_file(&part);
+            return Err(e);
+        }
+        fs::rename(&part, dest)?;
+        Ok((len, digest))
+    }
+
+    #[inline]
+    fn too_large(&self, url: &str) -> Error {
+        Error::ResponseTooLarge {
+            url: url.to_owned(),
+            max: self.max_body_len,
+        }
+    }
+
+    fn request(&self, url: &str, offset: u64) -> Result<HttpResponse> {
+        let response = self.client.get(url, offset)?;
+        if !response.is_success() {
+            return Err(Error::Http {
+                url: url.to_owned(),
+                status: response.status,
+                retry_after: response.retry_after,
+            });
+        }
+        Ok(response)
+    }
+
+    fn with_retries<T, F>(&self, mut attempt: F) -> Result<T>
+    where
+        F: FnMut() -> Result<T>,
+    {
+        let mut retries = 0;
+        loop {
+            let result = {
+                let _permit = self.acquire();
+                attempt()
+            };
+            match result {
+                Err(e) if retries < self.retry.max_retries && is_transient(&e) => {
+                    let backoff = match e {
+                        Error::Http {
+                            retry_after: Some(after),
+                            ..
+                        } => after.min(self.retry.max_backoff),
+                        _ => self.retry.backoff(retries),
+                    };
+                    thread::sleep(backoff);
+                    retries += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    fn acquire(&self) -> Permit<'_, C> {
+        let mut in_flight = self.in_flight.lock().unwrap_or_else(|e| e.into_inner());
+        while *in_flight >= self.concurrency {
+            in_flight = self
+                .available
+                .wait(in_flight)
+                .unwrap_or_else(|e| e.into_inner());
+        }
+        *in_flight += 1;
+        Permit(self)
+    }
+}
+
+struct Permit<'a, C>(&'a HttpDownloader<C>);
+
+impl<C> Drop for Permit<'_, C> {
+    fn drop(&mut self) {
+        let mut in_flight = self.0.in_flight.lock().unwrap_or_else(|e| e.into_inner());
+        *in_flight -= 1;
+        self.0.available.notify_one();
+    }
+}
+
+// Network errors, rate limiting and server errors are worth retrying, anything else won't change
+fn is_transient(error: &Error) -> bool {
+    match error {
+        Error::IO(_) | Error::UnexpectedContentRange { .. } => true,
+        Error::Http { status, .. } => *status == 429 || *status >= 500,
+        _ => false,
+    }
+}
+
+// Start of a `bytes start-end/total` range
+fn range_start(content_range: &str) -> Option<u64> {
+    let range = content_range.trim().strip_prefix("bytes ")?;
+    range.split('-').next()?.trim().parse().ok()
+}
+
+fn part_path(path: &Path) -> PathBuf {
+    let mut name = OsString::from(path.file_name().unwrap_or_default());
+    name.push(".part");
+    path.with_file_name(name)
+}
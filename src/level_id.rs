@@ -0,0 +1,77 @@
+use crate::Beatmap;
+use blister_format::values::Sha1;
+
+const CUSTOM_PREFIX: &str = "custom_level_";
+const WIP_SUFFIX: &str = " WIP";
+
+// The original soundtrack ships with the game, every other official level belongs to a music pack
+const OST_LEVEL_IDS: &[&str] = &[
+    "100Bills",
+    "AngelVoices",
+    "BalearicPumping",
+    "BeatSaber",
+    "Breezer",
+    "CommercialPumping",
+    "CountryRounds",
+    "Crystallized",
+    "CycleHit",
+    "Elixia",
+    "Escape",
+    "FitBeat",
+    "IntoTheDream",
+    "ItTakesMe",
+    "Legend",
+    "LvlInsane",
+    "Origins",
+    "RumNBass",
+    "TurnMeOn",
+    "UnlimitedPower",
+    "WhatTheCat",
+];
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum LevelIdKind {
+    Ost,
+    Dlc,
+    Custom { hash: Sha1, wip: bool },
+    // `custom_level_` IDs without a valid hash
+    InvalidCustom,
+}
+
+impl LevelIdKind {
+    pub fn parse(level_id: &str) -> Self {
+        let custom = match strip_prefix_ignore_case(level_id, CUSTOM_PREFIX) {
+            Some(custom) => custom,
+            None if OST_LEVEL_IDS.contains(&level_id) => return Self::Ost,
+            None => return Self::Dlc,
+        };
+
+        let (hash, wip) = match custom.strip_suffix(WIP_SUFFIX) {
+            Some(hash) => (hash, true),
+            None => (custom, false),
+        };
+        match Sha1::from_hex(hash) {
+            Some(hash) => Self::Custom { hash, wip },
+            None => Self::InvalidCustom,
+        }
+    }
+
+    #[inline]
+    pub fn is_built_in(self) -> bool {
+        matches!(self, Self::Ost | Self::Dlc)
+    }
+}
+
+impl Beatmap {
+    #[inline]
+    pub fn level_id_kind(&self) -> Option<LevelIdKind> {
+        self.level_id.as_deref().map(LevelIdKind::parse)
+    }
+}
+
+fn strip_prefix_ignore_case<'a>(s: &'a str, prefix: &str) -> Option<&'a str> {
+    match s.get(..prefix.len()) {
+        Some(p) if p.eq_ignore_ascii_case(prefix) => Some(&s[prefix.len()..]),
+        _ => None,
+    }
+}
@@ -1,40 +1,240 @@
+#[cfg(feature = "hmac")]
+mod auth;
 mod beatmap;
+#[cfg(feature = "beatsaver")]
+mod beatsaver;
+mod borrowed;
+#[cfg(feature = "bplist")]
+mod bplist;
+mod builder;
+mod clock;
+#[cfg(feature = "bplist")]
+mod convert;
+#[cfg(feature = "image")]
+mod cover;
+mod csv;
+mod diff;
+#[cfg(feature = "discovery")]
+mod discovery;
+mod display;
+mod editor;
+#[cfg(feature = "encryption")]
+mod encryption;
+mod enrich;
+mod equality;
 pub mod error;
+mod extension;
+#[cfg(feature = "zip")]
+mod extract;
+mod file;
+mod fingerprint;
+mod folder;
+#[cfg(feature = "arbitrary")]
+mod fuzzing;
+mod hashing;
+#[cfg(feature = "gzip")]
+mod history;
+mod http;
+mod level_id;
+mod library;
+mod localized;
+#[cfg(feature = "lock")]
+mod lock;
+#[cfg(feature = "gzip")]
+mod migrate;
+mod nested;
+mod normalize;
+mod options;
 mod playlist;
+mod report;
+mod resolver;
+mod rules;
+mod sanitize;
+mod scrape;
+mod size;
+mod sniff;
+#[cfg(feature = "songcore")]
+mod song_cache;
+mod source_map;
+#[cfg(any(feature = "sqlx", feature = "diesel"))]
+mod sql;
+#[cfg(feature = "proptest")]
+pub mod strategy;
+#[cfg(feature = "stream")]
+mod stream;
+mod summary;
+mod validate;
+mod warning;
+mod zip_cache;
+mod zip_reader;
 
 pub use crate::{
     beatmap::{Beatmap, BeatmapType},
+    borrowed::{BeatmapRef, PlaylistRef},
+    builder::PlaylistBuilder,
+    csv::Columns,
+    diff::PlaylistDiff,
+    display::Redacted,
+    editor::{ChangeEvent, Edit, ObserverId, PlaylistEditor, DEFAULT_HISTORY_LEN},
+    enrich::EnrichReport,
+    equality::IgnoreFields,
+    extension::{BeatmapExtension, ExtensionRegistry},
+    file::write_atomic,
+    fingerprint::{CanonicalPlaylist, FingerprintOptions, FINGERPRINT_LEN},
+    folder::{ChildPlaylist, ChildPlaylistRef, MAX_CHILD_DEPTH},
+    hashing::{copy_hashed, HashingReader, HashingWriter},
+    http::{
+        HttpClient, HttpDownloader, HttpResponse, RetryPolicy, DEFAULT_CONCURRENCY,
+        DEFAULT_MAX_BODY_LEN,
+    },
+    level_id::LevelIdKind,
+    library::{Library, SharedLibrary},
+    localized::LocalizedStrings,
+    normalize::NormalizeReport,
+    options::{DuplicatePolicy, ReadOptions, WriteOptions},
     playlist::Playlist,
+    report::{CoverRecompression, WriteReport},
+    resolver::{Match, Resolution, Resolver, SongInfo},
+    rules::{Rule, RuleReport, RuleSet, Violation},
+    sanitize::SanitizeOptions,
+    scrape::scrape,
+    size::{EncodedSize, MemoryUsage},
+    sniff::{is_blist, sniff, Version},
+    source_map::SourceMap,
+    summary::{PlaylistSummary, COVER_DIGEST_LEN},
+    validate::{Problem, ValidationReport},
+    warning::{Warning, MAX_RECOMMENDED_COVER_LEN},
+    zip_cache::{FreezeReport, ThinReport, ZipCache, BEATSAVER_CDN_URL},
+    zip_reader::ZipReader,
 };
 
+#[cfg(feature = "chrono")]
+pub use crate::{
+    clock::{Clock, FixedClock, SystemClock},
+    scrape::scrape_with_clock,
+};
+#[cfg(feature = "gzip")]
+pub use crate::{
+    file::{AutosavePolicy, PlaylistFile},
+    history::{Snapshot, HISTORY_DIR},
+    migrate::{
+        migrate, migrate_file, migrate_file_with_options, migrate_with_options, v1_to_v2, v2_to_v3,
+        v3_to_v4, v4_to_v5, MigrateOptions,
+    },
+    options::GzipHeader,
+};
+
+#[cfg(feature = "beatsaver")]
+pub use crate::beatsaver::{BeatSaverResolver, BEATSAVER_API_URL};
+#[cfg(feature = "bplist")]
+pub use crate::bplist::UnknownFieldPolicy;
+#[cfg(feature = "bplist")]
+pub use crate::convert::{convert_dir, ConvertReport, FormatOptions, PlaylistFormat};
+#[cfg(feature = "image")]
+pub use crate::cover::CoverOptions;
+#[cfg(feature = "discovery")]
+pub use crate::discovery::{playlist_dirs, InstallSource, PlaylistDir};
+#[cfg(feature = "encryption")]
+pub use crate::encryption::{Cipher, KEY_LEN};
+#[cfg(feature = "zip")]
+pub use crate::extract::{ExtractReport, NamingPolicy, ZipRecompression};
+#[cfg(feature = "lock")]
+pub use crate::lock::FileLock;
+#[cfg(feature = "songcore")]
+pub use crate::song_cache::{InstalledSong, SongCache};
+
 use crate::error::Error;
+use blister_format::MapEncoding;
+use std::io::Read;
 
 pub type Result<T> = std::result::Result<T, Error>;
 
 const MAGIC_NUMBER_LEN: usize = 8;
+// Only ever read by migrations
+#[cfg(feature = "gzip")]
+const MAGIC_NUMBER_V1: &[u8; MAGIC_NUMBER_LEN] = b"Blist.v1";
+#[cfg(feature = "gzip")]
+const MAGIC_NUMBER_V2: &[u8; MAGIC_NUMBER_LEN] = b"Blist.v2";
 const MAGIC_NUMBER: &[u8; MAGIC_NUMBER_LEN] = b"Blist.v3";
+const MAGIC_NUMBER_V4: &[u8; MAGIC_NUMBER_LEN] = b"Blist.v4";
+const MAGIC_NUMBER_V5: &[u8; MAGIC_NUMBER_LEN] = b"Blist.v5";
+// Kept apart from the version numbers so encrypted files never sniff as plaintext playlists
+#[cfg(feature = "encryption")]
+const MAGIC_NUMBER_ENCRYPTED: &[u8; MAGIC_NUMBER_LEN] = b"Blist.e1";
+
+const PREALLOCATION_LIMIT: usize = 1024;
+
+const SHORT_STRING_LEN: usize = blister_format::values::ShortString::MAX_LEN;
+const LONG_STRING_LEN: usize = blister_format::values::LongString::MAX_LEN;
+
+// Magic numbers compare in constant time unless `constant-time-eq` is left out of the build
+#[inline]
+fn magic_eq(a: &[u8; MAGIC_NUMBER_LEN], b: &[u8; MAGIC_NUMBER_LEN]) -> bool {
+    #[cfg(feature = "constant-time-eq")]
+    return constant_time_eq::constant_time_eq(&a[..], &b[..]);
+    #[cfg(not(feature = "constant-time-eq"))]
+    return a == b;
+}
+
+// v4 and v5 are identical to v3 except for count-prefixed and checksummed maps
+fn read_map_encoding<R>(mut reader: R) -> Result<MapEncoding>
+where
+    R: Read,
+{
+    let mut magic_number = [0; MAGIC_NUMBER_LEN];
+    reader.read_exact(&mut magic_number)?;
+    if magic_eq(&magic_number, MAGIC_NUMBER) {
+        Ok(MapEncoding::Length)
+    } else if magic_eq(&magic_number, MAGIC_NUMBER_V4) {
+        Ok(MapEncoding::Counted)
+    } else if magic_eq(&magic_number, MAGIC_NUMBER_V5) {
+        Ok(MapEncoding::Checksummed)
+    } else {
+        Err(Error::InvalidMagicNumber(magic_number))
+    }
+}
+
+#[inline]
+fn magic_number(encoding: MapEncoding) -> &'static [u8; MAGIC_NUMBER_LEN] {
+    match encoding {
+        MapEncoding::Length => MAGIC_NUMBER,
+        MapEncoding::Counted => MAGIC_NUMBER_V4,
+        MapEncoding::Checksummed => MAGIC_NUMBER_V5,
+    }
+}
 
-#[cfg(test)]
+// Most tests build playlists with dates and read them back from gzipped files
+#[cfg(all(test, feature = "chrono", feature = "gzip"))]
 mod tests {
-    use crate::{Beatmap, Playlist};
+    use crate::{Beatmap, FingerprintOptions, Playlist, PlaylistRef, ReadOptions};
+    use blister_format::{values::LongString, Map};
     use chrono::{TimeZone, Utc};
+    use std::{
+        convert::TryFrom,
+        io::{Read, Write},
+    };
 
     #[test]
     fn write_and_read() {
+        // Dates are stored with second precision
+        let clock = crate::FixedClock(Utc.timestamp_opt(1_600_000_000, 0).unwrap());
+
         let mut old = Playlist::new("test playlist".to_owned(), "me".to_owned());
         old.description = Some("description".to_owned());
-        old.cover = Some(vec![2, 1, 1, 2]);
+        old.cover = Some(vec![2, 1, 1, 2].into());
         old.custom_data.insert(2112, 1.234);
 
-        old.maps.push(Beatmap::new_key(2112));
-        old.maps.push(Beatmap::new_hash([4; 20].into()));
+        old.maps.push(Beatmap::new_key_with_clock(2112, &clock));
         old.maps
-            .push(Beatmap::new_zip(vec![0, 1, 2, 3, 4, 5, 6, 7, 8, 9]));
-        old.maps.push(Beatmap::new_level_id("level ID".to_owned()));
-
-        for m in old.maps.iter_mut() {
-            m.date_added = Utc.timestamp(m.date_added.timestamp(), 0);
-        }
+            .push(Beatmap::new_hash_with_clock([4; 20].into(), &clock));
+        old.maps.push(Beatmap::new_zip_with_clock(
+            vec![0, 1, 2, 3, 4, 5, 6, 7, 8, 9],
+            &clock,
+        ));
+        old.maps.push(Beatmap::new_level_id_with_clock(
+            "level ID".to_owned(),
+            &clock,
+        ));
 
         let mut buffer = Vec::new();
         old.clone().write(&mut buffer).unwrap();
@@ -42,5 +242,2858 @@ mod tests {
         let new = Playlist::read(buffer.as_slice(), true).unwrap();
 
         assert_eq!(old, new);
+        assert_eq!(old, Playlist::try_from(buffer.as_slice()).unwrap());
+
+        let (body, _) = PlaylistRef::decompress(buffer.as_slice()).unwrap();
+        assert_eq!(old.encoded_size().uncompressed, body.len());
+
+        let borrowed = PlaylistRef::parse(&body, true).unwrap();
+        assert_eq!(borrowed.title, "test playlist");
+        assert_eq!(old, borrowed.to_owned());
+
+        let mut uncompressed = Vec::new();
+        let report = old
+            .write_uncompressed(&mut uncompressed, &Default::default())
+            .unwrap();
+        assert_eq!(
+            &uncompressed[..crate::MAGIC_NUMBER_LEN],
+            crate::MAGIC_NUMBER
+        );
+        assert_eq!(&uncompressed[crate::MAGIC_NUMBER_LEN..], body.as_slice());
+        assert_eq!(report.compressed, uncompressed.len());
+        let (new, warnings) =
+            Playlist::read_uncompressed(uncompressed.as_slice(), &Default::default()).unwrap();
+        assert!(warnings.is_empty());
+        assert_eq!(old, new);
+    }
+
+    #[test]
+    fn counted() {
+        let mut old = Playlist::new("test playlist".to_owned(), "me".to_owned());
+        old.maps.push(Beatmap::new_key(2112));
+        old.maps.push(Beatmap::new_level_id("level ID".to_owned()));
+        for m in &mut old {
+            m.date_added = Utc.timestamp_opt(m.date_added.timestamp(), 0).unwrap();
+        }
+
+        let mut buffer = Vec::new();
+        old.clone()
+            .write_with_encoding(&mut buffer, blister_format::MapEncoding::Counted)
+            .unwrap();
+
+        assert_eq!(crate::sniff(buffer.as_slice()), Some(crate::Version::V4));
+        assert!(crate::is_blist(&buffer));
+        assert_eq!(old, Playlist::read(buffer.as_slice(), true).unwrap());
+
+        let (body, encoding) = PlaylistRef::decompress(buffer.as_slice()).unwrap();
+        assert_eq!(encoding, blister_format::MapEncoding::Counted);
+        let borrowed = PlaylistRef::parse_with_encoding(&body, encoding, true).unwrap();
+        assert_eq!(old, borrowed.to_owned());
+
+        let mut buffer = Vec::new();
+        old.clone()
+            .write_with_encoding(&mut buffer, blister_format::MapEncoding::Checksummed)
+            .unwrap();
+        assert_eq!(crate::sniff(buffer.as_slice()), Some(crate::Version::V5));
+        assert_eq!(old, Playlist::read(buffer.as_slice(), true).unwrap());
+
+        let (body, encoding) = PlaylistRef::decompress(buffer.as_slice()).unwrap();
+        assert_eq!(encoding, blister_format::MapEncoding::Checksummed);
+        let borrowed = PlaylistRef::parse_with_encoding(&body, encoding, true).unwrap();
+        assert_eq!(old, borrowed.to_owned());
+    }
+
+    #[test]
+    fn migrate() {
+        use crate::{error::ErrorKind, GzipHeader, MigrateOptions, ReadOptions, Version};
+        use flate2::{Compression, GzBuilder};
+        use std::{convert::TryInto, io::Write};
+
+        let mut old = Playlist::new("test playlist".to_owned(), "me".to_owned());
+        old.custom_data.insert(2112, 1.234);
+        let mut map = Beatmap::new_key(2112);
+        map.date_added = Utc.timestamp_opt(1_600_000_000, 0).unwrap();
+        map.custom_data.insert(4096, b"unknown".to_vec());
+        old.maps.push(map.clone());
+        map.key = Some(2113);
+        old.maps.push(map);
+
+        let options = crate::WriteOptions {
+            compression: Compression::best(),
+            gzip: GzipHeader {
+                filename: Some("old.blist".into()),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut v3 = Vec::new();
+        old.clone().write_with_options(&mut v3, &options).unwrap();
+
+        // v1 and v2 are the v3 body without the beatmap count, v2 being gzipped
+        let (body, _) = PlaylistRef::decompress(v3.as_slice()).unwrap();
+        let header_len = 4 + u32::from_le_bytes(body[..4].try_into().unwrap()) as usize;
+        let mut legacy = body[..header_len].to_vec();
+        legacy.extend_from_slice(&body[header_len + 4..]);
+        let v1 = [&b"Blist.v1"[..], &legacy].concat();
+        let mut encoder = GzBuilder::new()
+            .filename("old.blist")
+            .write(b"Blist.v2".to_vec(), Compression::best());
+        encoder.write_all(&legacy).unwrap();
+        let v2 = encoder.finish().unwrap();
+        assert_eq!(crate::sniff(v1.as_slice()), Some(Version::V1));
+        assert_eq!(crate::sniff(v2.as_slice()), Some(Version::V2));
+        assert!(Playlist::read(v2.as_slice(), true).is_err());
+
+        let mut from_v1 = Vec::new();
+        crate::v1_to_v2(v1.as_slice(), &mut from_v1, &Default::default()).unwrap();
+        assert_eq!(crate::sniff(from_v1.as_slice()), Some(Version::V2));
+        assert!(crate::v1_to_v2(v2.as_slice(), Vec::new(), &Default::default()).is_err());
+
+        // The gzip header and compression level are kept
+        let gzip_header_len = 8 + 10 + b"old.blist\0".len();
+        let mut from_v2 = Vec::new();
+        crate::v2_to_v3(v2.as_slice(), &mut from_v2, &Default::default()).unwrap();
+        assert_eq!(from_v2[..gzip_header_len], v3[..gzip_header_len]);
+        assert_eq!(old, Playlist::read(from_v2.as_slice(), true).unwrap());
+        let mut from_v1_v2 = Vec::new();
+        crate::v2_to_v3(from_v1.as_slice(), &mut from_v1_v2, &Default::default()).unwrap();
+        assert_eq!(old, Playlist::read(from_v1_v2.as_slice(), true).unwrap());
+
+        // Anything after the gzip stream, like an HMAC tag, is carried over
+        let mut trailed = v3.clone();
+        trailed.extend_from_slice(b"trailer");
+        let mut v4 = Vec::new();
+        crate::v3_to_v4(trailed.as_slice(), &mut v4, &Default::default()).unwrap();
+        assert_eq!(crate::sniff(v4.as_slice()), Some(Version::V4));
+        assert!(v4.ends_with(b"trailer"));
+        assert_eq!(v4[8..gzip_header_len], v3[8..gzip_header_len]);
+        assert_eq!(old, Playlist::read(v4.as_slice(), true).unwrap());
+        assert!(crate::v4_to_v5(v3.as_slice(), Vec::new(), &Default::default()).is_err());
+
+        let mut v5 = Vec::new();
+        crate::v4_to_v5(v4.as_slice(), &mut v5, &Default::default()).unwrap();
+        assert_eq!(crate::sniff(v5.as_slice()), Some(Version::V5));
+        assert!(v5.ends_with(b"trailer"));
+        assert_eq!(old, Playlist::read(v5.as_slice(), true).unwrap());
+
+        // Hops apply the same limits as reads
+        let limited = ReadOptions {
+            max_maps: Some(1),
+            ..Default::default()
+        };
+        for e in [
+            crate::v1_to_v2(v1.as_slice(), Vec::new(), &limited).unwrap_err(),
+            crate::v2_to_v3(v2.as_slice(), Vec::new(), &limited).unwrap_err(),
+            crate::v3_to_v4(v3.as_slice(), Vec::new(), &limited).unwrap_err(),
+        ] {
+            assert_eq!(e.kind(), ErrorKind::TooManyMaps);
+        }
+
+        // Migrations stop at v3 unless asked to go further
+        let mut migrated = Vec::new();
+        assert_eq!(
+            crate::migrate(v2.as_slice(), &mut migrated).unwrap(),
+            Version::V2
+        );
+        assert_eq!(migrated, from_v2);
+        let mut migrated = Vec::new();
+        assert_eq!(
+            crate::migrate(v1.as_slice(), &mut migrated).unwrap(),
+            Version::V1
+        );
+        assert_eq!(crate::sniff(migrated.as_slice()), Some(Version::V3));
+        assert_eq!(old, Playlist::read(migrated.as_slice(), true).unwrap());
+        for data in [&v3, &v4, &v5] {
+            let mut migrated = Vec::new();
+            crate::migrate(data.as_slice(), &mut migrated).unwrap();
+            assert_eq!(&migrated, data);
+        }
+        let options = MigrateOptions {
+            target: Version::V5,
+            ..Default::default()
+        };
+        let mut migrated = Vec::new();
+        assert_eq!(
+            crate::migrate_with_options(v1.as_slice(), &mut migrated, &options).unwrap(),
+            Version::V1
+        );
+        assert_eq!(crate::sniff(migrated.as_slice()), Some(Version::V5));
+        assert_eq!(old, Playlist::read(migrated.as_slice(), true).unwrap());
+        let e = crate::migrate(&b"Blist.v9"[..], Vec::new()).unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::UnsupportedVersion);
+
+        let temp = tempfile::tempdir().unwrap();
+        let path = temp.path().join("migrate.blist");
+        std::fs::write(&path, &v2).unwrap();
+        assert_eq!(crate::migrate_file(&path).unwrap(), Version::V2);
+        assert_eq!(std::fs::read(&path).unwrap(), from_v2);
+        assert_eq!(crate::migrate_file(&path).unwrap(), Version::V3);
+        assert_eq!(std::fs::read(&path).unwrap(), from_v2);
+        assert_eq!(
+            crate::migrate_file_with_options(&path, &options).unwrap(),
+            Version::V3
+        );
+        let data = std::fs::read(&path).unwrap();
+        assert_eq!(crate::sniff(data.as_slice()), Some(Version::V5));
+        assert_eq!(old, Playlist::read(data.as_slice(), true).unwrap());
+    }
+
+    #[test]
+    fn level_id_kind() {
+        use crate::LevelIdKind;
+
+        assert_eq!(LevelIdKind::parse("BeatSaber"), LevelIdKind::Ost);
+        assert_eq!(LevelIdKind::parse("SomePackSong"), LevelIdKind::Dlc);
+        let hash = blister_format::values::Sha1([0xab; 20]);
+        assert_eq!(
+            LevelIdKind::parse(&format!("custom_level_{}", "AB".repeat(20))),
+            LevelIdKind::Custom { hash, wip: false }
+        );
+        assert_eq!(
+            LevelIdKind::parse(&format!("custom_level_{} WIP", "ab".repeat(20))),
+            LevelIdKind::Custom { hash, wip: true }
+        );
+        assert_eq!(
+            LevelIdKind::parse("custom_level_nope"),
+            LevelIdKind::InvalidCustom
+        );
+        assert!(!LevelIdKind::InvalidCustom.is_built_in());
+        assert_eq!(Beatmap::new_key(1).level_id_kind(), None);
+    }
+
+    #[test]
+    fn summary() {
+        let mut playlist = Playlist::new("test playlist".to_owned(), "me".to_owned());
+        playlist.cover = Some(b"cover".to_vec().into());
+        playlist.maps.push(Beatmap::new_key(1));
+        playlist.maps.push(Beatmap::new_key(2));
+        playlist
+            .maps
+            .push(Beatmap::new_level_id("BeatSaber".to_owned()));
+
+        let summary = playlist.summary().unwrap();
+        assert_eq!(summary.maps, 3);
+        assert_eq!(summary.key_maps, 2);
+        assert_eq!(summary.level_id_maps, 1);
+        assert_eq!(summary.fingerprint, playlist.fingerprint().unwrap());
+        assert_eq!(summary.encoded_len, playlist.encoded_size().uncompressed);
+
+        #[cfg(feature = "bplist")]
+        {
+            let json = serde_json::to_value(&summary).unwrap();
+            assert_eq!(
+                json["coverDigest"],
+                "3fa405a8301ace34d11cf44a816080b8f0e49a48fbd048b8aef1543a8c58bdb6"
+            );
+            assert_eq!(
+                serde_json::from_value::<crate::PlaylistSummary>(json).unwrap(),
+                summary
+            );
+        }
+    }
+
+    #[test]
+    fn memory_usage() {
+        let mut playlist = Playlist::new("test playlist".to_owned(), "me".to_owned());
+        let empty = playlist.memory_usage();
+        assert_eq!(empty.strings, "test playlist".len() + "me".len());
+        assert_eq!(empty.cover, 0);
+        assert_eq!(empty.zips, 0);
+        assert_eq!(empty.children, 0);
+
+        playlist.description = Some("a description".to_owned());
+        playlist.tags.push("tech".to_owned());
+        let usage = playlist.memory_usage();
+        assert!(usage.strings >= empty.strings + "a description".len() + "tech".len());
+
+        playlist.cover = Some(vec![0; 1024].into());
+        assert!(playlist.memory_usage().cover >= 1024);
+
+        playlist.maps.push(Beatmap::new_zip(vec![0; 4096]));
+        let usage = playlist.memory_usage();
+        assert!(usage.zips >= 4096);
+        assert!(usage.maps >= std::mem::size_of::<Beatmap>());
+
+        let before = usage.custom_data;
+        playlist.custom_data.insert(2112, vec![0u8; 256]);
+        playlist.maps[0].custom_data.insert(16, vec![0u8; 512]);
+        assert!(playlist.memory_usage().custom_data >= before + 256 + 512);
+
+        let child = Playlist::new("child".to_owned(), "me".to_owned());
+        playlist
+            .children
+            .push(crate::ChildPlaylist::Path("sub/leaf.blist".to_owned()));
+        playlist
+            .children
+            .push(crate::ChildPlaylist::embed(child).unwrap());
+        let usage = playlist.memory_usage();
+        let embedded = match &playlist.children[1] {
+            crate::ChildPlaylist::Embedded(b) => b.len(),
+            _ => unreachable!(),
+        };
+        assert!(usage.children >= "sub/leaf.blist".len() + embedded);
+        assert_eq!(
+            usage.total(),
+            usage.strings
+                + usage.cover
+                + usage.zips
+                + usage.custom_data
+                + usage.maps
+                + usage.children
+        );
+    }
+
+    #[test]
+    fn to_csv() {
+        let mut playlist = Playlist::new("test playlist".to_owned(), "me".to_owned());
+        let mut map = Beatmap::new_key(0xff);
+        map.date_added = Utc.timestamp_opt(0, 0).unwrap();
+        map.custom_data
+            .insert(16, LongString::new("Song, \"Remix\"").unwrap());
+        map.set_note("tech").unwrap();
+        playlist.maps.push(map);
+        let mut map = Beatmap::new_level_id("BeatSaber".to_owned());
+        map.date_added = Utc.timestamp_opt(60, 0).unwrap();
+        map.custom_data.insert(16, 3u8);
+        playlist.maps.push(map);
+
+        let columns = crate::Columns {
+            hash: false,
+            custom_data: vec![16, 17],
+            ..Default::default()
+        };
+        let mut csv = Vec::new();
+        playlist.to_csv(&mut csv, &columns).unwrap();
+        assert_eq!(
+            String::from_utf8(csv).unwrap(),
+            "type,key,level_id,date_added,note,custom_16,custom_17\r\n\
+             key,ff,,1970-01-01T00:00:00Z,tech,\"Song, \"\"Remix\"\"\",\r\n\
+             level_id,,BeatSaber,1970-01-01T00:01:00Z,,3,\r\n"
+        );
+    }
+
+    #[test]
+    fn localized() {
+        let mut playlist = Playlist::new("test playlist".to_owned(), "me".to_owned());
+        playlist.set_title_localized("fr", "liste de test").unwrap();
+        playlist
+            .set_title_localized("pt-BR", "lista de teste")
+            .unwrap();
+        playlist
+            .set_description_localized("fr", "une description")
+            .unwrap();
+
+        let mut buffer = Vec::new();
+        playlist.write(&mut buffer).unwrap();
+        let (playlist, warnings) =
+            Playlist::read_with_warnings(buffer.as_slice(), &Default::default()).unwrap();
+        assert!(warnings.is_empty());
+        playlist.check_reserved_keys().unwrap();
+
+        assert_eq!(playlist.title_localized("fr-CA"), "liste de test");
+        assert_eq!(playlist.title_localized("PT-br"), "lista de teste");
+        assert_eq!(playlist.title_localized("de"), "test playlist");
+        assert_eq!(
+            playlist.description_localized("fr").as_deref(),
+            Some("une description")
+        );
+        assert_eq!(playlist.description_localized("de"), None);
+        assert_eq!(playlist.localized_titles.len(), 2);
+        assert!(playlist.custom_data.is_empty());
+
+        let (body, encoding) = PlaylistRef::decompress(buffer.as_slice()).unwrap();
+        assert_eq!(playlist.encoded_size().uncompressed, body.len());
+        let borrowed = PlaylistRef::parse_with_encoding(&body, encoding, true).unwrap();
+        assert_eq!(borrowed.localized_descriptions, [("fr", "une description")]);
+        assert_eq!(borrowed.to_owned(), playlist);
+    }
+
+    #[test]
+    fn note() {
+        let mut playlist = Playlist::new("test playlist".to_owned(), "me".to_owned());
+        let mut map = Beatmap::new_key(1);
+        map.set_note("great for warmups").unwrap();
+        playlist.maps.push(map);
+        playlist.maps.push(Beatmap::new_key(2));
+
+        let mut buffer = Vec::new();
+        playlist.clone().write(&mut buffer).unwrap();
+        let (read, warnings) =
+            Playlist::read_with_warnings(buffer.as_slice(), &Default::default()).unwrap();
+        assert!(warnings.is_empty());
+        assert_eq!(read.maps[0].note.as_deref(), Some("great for warmups"));
+        assert!(read.maps[0].custom_data.is_empty());
+        assert_eq!(read.maps[1].note, None);
+        assert!(read.maps[0]
+            .to_string()
+            .ends_with("(\"great for warmups\")"));
+
+        let (body, _) = PlaylistRef::decompress(buffer.as_slice()).unwrap();
+        let borrowed = PlaylistRef::parse(&body, false).unwrap();
+        assert_eq!(borrowed.maps[0].note, Some("great for warmups"));
+        assert_eq!(borrowed.to_owned(), read);
+        assert_eq!(playlist.encoded_size().uncompressed, body.len());
+    }
+
+    #[test]
+    fn tags() {
+        let playlist = Playlist::builder()
+            .title("test playlist")
+            .author("me")
+            .tag("tech")
+            .tag("Speed")
+            .map(Beatmap::new_key(1))
+            .build()
+            .unwrap();
+
+        let mut buffer = Vec::new();
+        playlist.clone().write(&mut buffer).unwrap();
+        let (read, warnings) =
+            Playlist::read_with_warnings(buffer.as_slice(), &Default::default()).unwrap();
+        assert!(warnings.is_empty());
+        assert_eq!(read.tags, vec!["tech", "Speed"]);
+        assert!(read.custom_data.is_empty());
+
+        let (body, _) = PlaylistRef::decompress(buffer.as_slice()).unwrap();
+        let borrowed = PlaylistRef::parse(&body, false).unwrap();
+        assert_eq!(borrowed.tags, vec!["tech", "Speed"]);
+        assert_eq!(borrowed.to_owned(), read);
+        assert_eq!(playlist.encoded_size().uncompressed, body.len());
+
+        let mut library = crate::Library::new();
+        library.insert(Playlist::new("untagged".to_owned(), "me".to_owned()));
+        library.insert(read);
+        let tagged: Vec<_> = library.tagged("speed").map(|(i, _)| i).collect();
+        assert_eq!(tagged, vec![1]);
+        assert_eq!(library.tagged("dance").count(), 0);
+    }
+
+    #[test]
+    fn children() {
+        use crate::{error::ErrorKind, ChildPlaylist};
+
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        std::fs::create_dir_all(dir.join("sub")).unwrap();
+
+        let mut leaf = Playlist::new("leaf".to_owned(), "me".to_owned());
+        leaf.maps.push(Beatmap::new_key(3));
+        leaf.write(std::fs::File::create(dir.join("sub/leaf.blist")).unwrap())
+            .unwrap();
+
+        let mut embedded = Playlist::new("embedded".to_owned(), "me".to_owned());
+        embedded.maps.push(Beatmap::new_key(2));
+        embedded
+            .children
+            .push(ChildPlaylist::Path("sub/leaf.blist".to_owned()));
+
+        let mut root = Playlist::new("root".to_owned(), "me".to_owned());
+        root.maps.push(Beatmap::new_key(1));
+        root.children.push(ChildPlaylist::embed(embedded).unwrap());
+
+        let mut buffer = Vec::new();
+        root.clone().write(&mut buffer).unwrap();
+        let read = Playlist::read(buffer.as_slice(), false).unwrap();
+        assert_eq!(read.children, root.children);
+        assert!(read.custom_data.is_empty());
+        let (body, _) = PlaylistRef::decompress(buffer.as_slice()).unwrap();
+        assert_eq!(PlaylistRef::parse(&body, false).unwrap().to_owned(), read);
+        assert_eq!(root.encoded_size().uncompressed, body.len());
+
+        let options = ReadOptions::default();
+        let mut visited = Vec::new();
+        read.walk(Some(dir), &options, |depth, p| {
+            visited.push((depth, p.title.clone()))
+        })
+        .unwrap();
+        assert_eq!(
+            visited,
+            vec![
+                (0, "root".to_owned()),
+                (1, "embedded".to_owned()),
+                (2, "leaf".to_owned()),
+            ]
+        );
+        let flat = read.flatten(Some(dir), &options).unwrap();
+        let keys: Vec<_> = flat.maps.iter().map(|m| m.key.unwrap()).collect();
+        assert_eq!(keys, vec![1, 2, 3]);
+        assert!(flat.children.is_empty());
+        assert_eq!(
+            read.flatten(None, &options).unwrap_err().kind(),
+            ErrorKind::UnresolvedChild
+        );
+
+        let mut cycle = Playlist::new("cycle".to_owned(), "me".to_owned());
+        cycle
+            .children
+            .push(ChildPlaylist::Path("cycle.blist".to_owned()));
+        cycle
+            .clone()
+            .write(std::fs::File::create(dir.join("cycle.blist")).unwrap())
+            .unwrap();
+        assert_eq!(
+            cycle
+                .walk(Some(dir), &options, |_, _| ())
+                .unwrap_err()
+                .kind(),
+            ErrorKind::ChildCycle
+        );
+
+        for path in &["../leaf.blist", "/tmp/leaf.blist", "sub/../sub/leaf.blist"] {
+            let mut escaping = Playlist::new("escaping".to_owned(), "me".to_owned());
+            escaping
+                .children
+                .push(ChildPlaylist::Path(path.to_string()));
+            assert_eq!(
+                escaping
+                    .walk(Some(&dir.join("sub")), &options, |_, _| ())
+                    .unwrap_err()
+                    .kind(),
+                ErrorKind::UnsafeChildPath
+            );
+        }
+    }
+
+    #[test]
+    fn spill() {
+        let dir = tempfile::tempdir().unwrap();
+        let clock = crate::FixedClock(Utc.timestamp_opt(1_600_000_000, 0).unwrap());
+
+        let mut playlist = Playlist::new("test playlist".to_owned(), "me".to_owned());
+        playlist.cover = Some(vec![1; 16].into());
+        playlist.tags = vec!["x".repeat(1100)];
+        playlist.maps.push(Beatmap::new_key_with_clock(1, &clock));
+        playlist
+            .maps
+            .push(Beatmap::new_zip_with_clock(vec![2; 4096], &clock));
+        let mut buffer = Vec::new();
+        playlist.write(&mut buffer).unwrap();
+
+        let options = ReadOptions {
+            spill: Some(blister_format::Spill::new(1024, dir.path())),
+            ..Default::default()
+        };
+        let read = Playlist::read_with_options(buffer.as_slice(), &options).unwrap();
+        assert_eq!(read.cover.as_deref(), Some(&[1; 16][..]));
+        assert_eq!(read.tags, playlist.tags);
+
+        let zip = read.maps[1].zip.as_ref().unwrap();
+        let spilled = zip.spilled().unwrap();
+        assert_eq!(spilled.scope, Some(1));
+        assert_eq!(*spilled.key, 4);
+        assert_eq!(zip.len(), 4096);
+        assert!(zip.as_bytes().is_none());
+        assert_eq!(zip.read_to_vec().unwrap(), vec![2; 4096]);
+        assert_eq!(read, playlist);
+
+        // Spilled values hash like their contents
+        let hash = |b: &blister_format::values::Binary| {
+            let mut hasher = std::collections::hash_map::DefaultHasher::new();
+            std::hash::Hash::hash(b, &mut hasher);
+            std::hash::Hasher::finish(&hasher)
+        };
+        assert_eq!(hash(zip), hash(&vec![2; 4096].into()));
+
+        let mut bytes = Vec::new();
+        read.maps[1]
+            .zip_reader()
+            .unwrap()
+            .unwrap()
+            .read_to_end(&mut bytes)
+            .unwrap();
+        assert_eq!(bytes, vec![2; 4096]);
+        assert!(read.maps[0].zip_reader().unwrap().is_none());
+
+        // Writing streams the spilled zip back out instead of losing it
+        let mut rewritten = Vec::new();
+        read.write(&mut rewritten).unwrap();
+        assert_eq!(
+            Playlist::read(rewritten.as_slice(), true).unwrap(),
+            playlist
+        );
+
+        let path = spilled.path().to_owned();
+        drop(read);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn long_binary_zip() {
+        let clock = crate::FixedClock(Utc.timestamp_opt(1_600_000_000, 0).unwrap());
+        let zip = vec![0, 1, 2, 3, 4, 5, 6, 7, 8, 9];
+
+        // Stored as a long binary, the way archives past 4 GB are written
+        let mut map = Beatmap::new_zip_with_clock(zip.clone(), &clock);
+        map.zip = None;
+        map.custom_data
+            .insert(4, blister_format::values::LongBinary::from(zip.clone()));
+        let mut playlist = Playlist::new("test playlist".to_owned(), "me".to_owned());
+        playlist.maps.push(map);
+
+        let mut buffer = Vec::new();
+        playlist.write(&mut buffer).unwrap();
+        let read = Playlist::read(buffer.as_slice(), true).unwrap();
+        assert_eq!(
+            read.maps[0].zip.as_ref().and_then(|z| z.as_bytes()),
+            Some(&zip[..])
+        );
+        assert!(read.maps[0].custom_data.is_empty());
+
+        let (body, _) = PlaylistRef::decompress(buffer.as_slice()).unwrap();
+        let borrowed = PlaylistRef::parse(&body, true).unwrap();
+        assert_eq!(borrowed.maps[0].zip, Some(&zip[..]));
+    }
+
+    #[test]
+    fn read_partial() {
+        let mut playlist = Playlist::new("test playlist".to_owned(), "me".to_owned());
+        for i in 0..50 {
+            playlist.maps.push(Beatmap::new_key(i));
+        }
+        let mut buffer = Vec::new();
+        playlist
+            .clone()
+            .write_with_compression(&mut buffer, flate2::Compression::none())
+            .unwrap();
+
+        let options = ReadOptions::default();
+        let (read, warnings) = Playlist::read_partial(buffer.as_slice(), &options).unwrap();
+        assert_eq!(read.maps.len(), 50);
+        assert!(warnings.is_empty());
+
+        let truncated = &buffer[..buffer.len() * 2 / 3];
+        assert!(Playlist::read(truncated, false).is_err());
+        let (read, warnings) = Playlist::read_partial(truncated, &options).unwrap();
+        assert_eq!(read.title, "test playlist");
+        assert!(!read.maps.is_empty() && read.maps.len() < 50);
+        assert_eq!(
+            read.maps.last().unwrap().key,
+            Some(read.maps.len() as u32 - 1)
+        );
+        assert_eq!(
+            warnings,
+            vec![crate::Warning::Truncated {
+                maps_lost_estimate: 50 - read.maps.len()
+            }]
+        );
+
+        // Corruption in the middle of the stream isn't mistaken for the file ending early
+        let mut compressed = Vec::new();
+        playlist.write(&mut compressed).unwrap();
+        let truncated = &compressed[..compressed.len() / 2];
+        assert!(Playlist::read_partial(truncated, &options).is_ok());
+        let mut corrupted = compressed.clone();
+        let middle = corrupted.len() / 2;
+        for b in &mut corrupted[middle..middle + 8] {
+            *b = 0xff;
+        }
+        assert!(Playlist::read_partial(corrupted.as_slice(), &options).is_err());
+    }
+
+    #[test]
+    fn hashing() {
+        use crate::{HashingReader, HashingWriter};
+
+        let expected = "a9993e364706816aba3e25717850c26c9cd0d89d";
+        let mut written = Vec::new();
+        let (len, hash) = crate::copy_hashed(&b"abc"[..], &mut written).unwrap();
+        assert_eq!((len, hash.to_string()), (3, expected.to_owned()));
+        assert_eq!(written, b"abc");
+
+        let mut reader = HashingReader::new(&b"abc"[..]);
+        let mut byte = [0; 1];
+        reader.read_exact(&mut byte).unwrap();
+        assert_eq!(reader.len(), 1);
+        reader.read_to_end(&mut Vec::new()).unwrap();
+        assert_eq!(reader.digest().to_string(), expected);
+
+        let mut writer = HashingWriter::new(Vec::new());
+        writer.write_all(b"ab").unwrap();
+        writer.write_all(b"c").unwrap();
+        let (inner, hash) = writer.finish();
+        assert_eq!(
+            (inner, hash.to_string()),
+            (b"abc".to_vec(), expected.to_owned())
+        );
+    }
+
+    #[test]
+    fn editor() {
+        use crate::{error::ErrorKind, PlaylistEditor};
+
+        let playlist = Playlist::new("test playlist".to_owned(), "me".to_owned());
+        let mut editor = PlaylistEditor::with_history_len(playlist.clone(), 3);
+        assert!(!editor.undo());
+
+        editor.push_map(Beatmap::new_key(1)).unwrap();
+        editor.push_map(Beatmap::new_key(2)).unwrap();
+        editor.move_map(1, 0).unwrap();
+        editor.set_title("renamed").unwrap();
+        let keys = |e: &PlaylistEditor| -> Vec<_> {
+            e.playlist().maps.iter().map(|m| m.key.unwrap()).collect()
+        };
+        assert_eq!(keys(&editor), vec![2, 1]);
+        assert_eq!(
+            editor.remove_map(2).unwrap_err().kind(),
+            ErrorKind::MapIndexOutOfBounds
+        );
+
+        assert!(editor.undo());
+        assert_eq!(editor.playlist().title, "test playlist");
+        assert!(editor.undo());
+        assert_eq!(keys(&editor), vec![1, 2]);
+        assert!(editor.redo());
+        assert_eq!(keys(&editor), vec![2, 1]);
+        assert!(editor.undo());
+        assert!(editor.undo());
+        // The first insertion fell out of the bounded history
+        assert!(!editor.undo());
+        assert_eq!(keys(&editor), vec![1]);
+
+        let events = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let observed = events.clone();
+        let id = editor.subscribe(move |e| observed.lock().unwrap().push(*e));
+        editor.remove_map(0).unwrap();
+        assert!(editor.undo());
+        assert!(editor.unsubscribe(id));
+        assert!(editor.redo());
+        assert_eq!(
+            *events.lock().unwrap(),
+            vec![
+                crate::ChangeEvent::MapRemoved { index: 0 },
+                crate::ChangeEvent::MapInserted { index: 0 },
+            ]
+        );
+        assert!(!editor.can_redo());
+        assert!(editor.playlist().maps.is_empty());
+
+        // Playlists read from elsewhere can hold values the editor itself wouldn't accept
+        let mut long_title = playlist;
+        long_title.title = "a".repeat(300);
+        let mut editor = PlaylistEditor::new(long_title);
+        editor.set_title("short").unwrap();
+        assert!(editor.undo());
+        assert_eq!(editor.playlist().title.len(), 300);
+        assert!(editor.redo());
+        assert_eq!(editor.playlist().title, "short");
+    }
+
+    #[test]
+    fn reorder() {
+        use crate::error::ErrorKind;
+
+        let mut playlist = Playlist::new("test playlist".to_owned(), "me".to_owned());
+        playlist.maps = (1..=4).map(Beatmap::new_key).collect();
+        let keys = |p: &Playlist| -> Vec<_> { p.maps.iter().map(|m| m.key.unwrap()).collect() };
+
+        playlist.move_map(0, 2).unwrap();
+        assert_eq!(keys(&playlist), vec![2, 3, 1, 4]);
+        playlist.swap(0, 3).unwrap();
+        assert_eq!(keys(&playlist), vec![4, 3, 1, 2]);
+        playlist.sort_manual(&[2, 3, 1, 0]).unwrap();
+        assert_eq!(keys(&playlist), vec![1, 2, 3, 4]);
+
+        assert_eq!(
+            playlist.swap(1, 4).unwrap_err().kind(),
+            ErrorKind::MapIndexOutOfBounds
+        );
+        assert_eq!(
+            playlist.sort_manual(&[0, 1, 1, 3]).unwrap_err().kind(),
+            ErrorKind::InvalidMapOrder
+        );
+        assert_eq!(
+            playlist.sort_manual(&[0, 1, 2]).unwrap_err().kind(),
+            ErrorKind::InvalidMapOrder
+        );
+        assert_eq!(keys(&playlist), vec![1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn playlist_file() {
+        use crate::{AutosavePolicy, PlaylistFile};
+
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        let path = dir.join("test.blist");
+
+        let playlist = Playlist::new("test playlist".to_owned(), "me".to_owned());
+        let mut file = PlaylistFile::create(&path, playlist).unwrap();
+        assert!(file.is_dirty());
+        file.save().unwrap();
+        assert!(!file.is_dirty());
+
+        drop(file);
+
+        let mut file = PlaylistFile::open(&path, &ReadOptions::default()).unwrap();
+        #[cfg(feature = "lock")]
+        match PlaylistFile::open(&path, &ReadOptions::default()).unwrap_err() {
+            crate::error::Error::Locked { holder, .. } => {
+                assert_eq!(holder, Some(std::process::id()))
+            }
+            e => panic!("{}", e),
+        }
+        file.autosave = AutosavePolicy::Changes(2);
+        file.edit(|e| e.push_map(Beatmap::new_key(1))).unwrap();
+        assert!(file.is_dirty());
+        file.edit(|e| e.push_map(Beatmap::new_key(2))).unwrap();
+        assert!(!file.is_dirty());
+
+        let read = Playlist::read(std::fs::File::open(&path).unwrap(), false).unwrap();
+        assert_eq!(read.maps.len(), 2);
+        assert!(!dir.join(".test.blist.tmp").exists());
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+
+            let mode = std::fs::Permissions::from_mode(0o600);
+            std::fs::set_permissions(&path, mode).unwrap();
+            file.edit(|e| e.push_map(Beatmap::new_key(3))).unwrap();
+            file.save().unwrap();
+            let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+
+        // A directory in the way of the temporary file makes the save fail but not the edit
+        file.autosave = AutosavePolicy::Changes(1);
+        std::fs::create_dir(dir.join(".test.blist.tmp")).unwrap();
+        assert_eq!(
+            file.edit(|e| e.push_map(Beatmap::new_key(4)))
+                .unwrap_err()
+                .kind(),
+            crate::error::ErrorKind::Autosave
+        );
+        assert_eq!(file.playlist().maps.len(), 4);
+        assert!(file.is_dirty());
+        assert_eq!(
+            file.edit(|e| e.remove_map(99)).unwrap_err().kind(),
+            crate::error::ErrorKind::MapIndexOutOfBounds
+        );
+    }
+
+    #[test]
+    fn history() {
+        use crate::{Library, PlaylistFile};
+
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        let path = dir.join("test.blist");
+
+        let playlist = Playlist::new("test playlist".to_owned(), "me".to_owned());
+        let mut file = PlaylistFile::create(&path, playlist).unwrap();
+        file.keep_snapshots = 2;
+        file.save().unwrap();
+        assert!(file.history().unwrap().is_empty());
+        for key in 1..=3 {
+            file.edit(|e| e.push_map(Beatmap::new_key(key))).unwrap();
+            file.save().unwrap();
+        }
+
+        let versions: Vec<_> = file.history().unwrap().iter().map(|s| s.version).collect();
+        assert_eq!(versions, [2, 3]);
+        assert!(dir.join(".history/test.blist/3").is_file());
+        assert_eq!(
+            file.restore(1, &ReadOptions::default()).unwrap_err().kind(),
+            crate::error::ErrorKind::MissingSnapshot
+        );
+
+        file.restore(2, &ReadOptions::default()).unwrap();
+        assert_eq!(file.playlist().maps.len(), 1);
+        assert!(file.is_dirty());
+        assert!(file.edit(|e| Ok(e.undo())).unwrap());
+        assert_eq!(file.playlist().maps.len(), 3);
+        drop(file);
+
+        let archive = dir.join("library.blib");
+        let mut library = Library::new();
+        library.insert(Playlist::new("first".to_owned(), "me".to_owned()));
+        library.save_archive_with_history(&archive, 1).unwrap();
+        library.insert(Playlist::new("second".to_owned(), "me".to_owned()));
+        library.save_archive_with_history(&archive, 1).unwrap();
+        assert_eq!(Library::archive_history(&archive).unwrap().len(), 1);
+        let restored = Library::restore_archive(&archive, 1, &ReadOptions::default()).unwrap();
+        assert_eq!(restored.len(), 1);
+    }
+
+    #[cfg(feature = "discovery")]
+    #[test]
+    fn playlist_dirs() {
+        use crate::discovery::{parse_library_folders, playlist_dirs_with};
+        use crate::InstallSource;
+
+        let vdf = r#"
+            "libraryfolders"
+            {
+                "0"
+                {
+                    "path"		"D:\\Games\\Steam"
+                    "label"		""
+                }
+            }
+        "#;
+        assert_eq!(
+            parse_library_folders(vdf),
+            vec![std::path::PathBuf::from("D:\\Games\\Steam")]
+        );
+
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        let game = dir.join("steamapps/common/Beat Saber");
+        std::fs::create_dir_all(game.join("Playlists")).unwrap();
+
+        let env = |var: &str| match var {
+            "STEAM_DIR" => Some(dir.as_os_str().to_owned()),
+            _ => None,
+        };
+        let dirs = playlist_dirs_with(env);
+        assert_eq!(dirs.len(), 1);
+        assert_eq!(dirs[0].source, InstallSource::Steam);
+        assert_eq!(dirs[0].path, game.join("Playlists"));
+        assert!(dirs[0].exists);
+    }
+
+    #[test]
+    fn source_map() {
+        let mut playlist = Playlist::new("test playlist".to_owned(), "me".to_owned());
+        playlist.maps.push(Beatmap::new_key(1));
+        playlist.maps.push(Beatmap::new_zip(vec![0; 64]));
+        let mut buffer = Vec::new();
+        playlist.write(&mut buffer).unwrap();
+
+        let (read, _, source_map) =
+            Playlist::read_with_source_map(buffer.as_slice(), &ReadOptions::default()).unwrap();
+        assert_eq!(read.maps.len(), source_map.maps.len());
+        assert_eq!(source_map.header.end, source_map.map_count.start);
+        assert_eq!(source_map.map_count.len(), 4);
+        assert_eq!(source_map.maps[0].start, source_map.map_count.end);
+        assert_eq!(source_map.maps[0].end, source_map.maps[1].start);
+
+        let (body, _) = PlaylistRef::decompress(buffer.as_slice()).unwrap();
+        assert_eq!(source_map.maps[1].end, body.len());
+        let mut span = &body[source_map.maps[1].clone()];
+        blister_format::MapRef::parse(&mut span).unwrap();
+        assert!(span.is_empty());
+        assert_eq!(source_map.map_at(source_map.maps[1].start), Some(1));
+        assert_eq!(source_map.map_at(0), None);
+    }
+
+    #[test]
+    fn extensions() {
+        use crate::{error::ErrorKind, BeatmapExtension, ExtensionRegistry};
+
+        struct Generated;
+        impl BeatmapExtension for Generated {
+            fn discriminant(&self) -> u8 {
+                42
+            }
+            fn name(&self) -> &str {
+                "generated"
+            }
+            fn validate(&self, map: &Beatmap) -> crate::Result<()> {
+                match map.custom_data.get(100) {
+                    Some(_) => Ok(()),
+                    None => Err(crate::error::Error::MissingField("seed")),
+                }
+            }
+        }
+
+        let mut registry = ExtensionRegistry::new();
+        registry.register(Generated).unwrap();
+        struct Reserved;
+        impl BeatmapExtension for Reserved {
+            fn discriminant(&self) -> u8 {
+                2
+            }
+            fn name(&self) -> &str {
+                "reserved"
+            }
+        }
+        assert_eq!(
+            registry.register(Reserved).unwrap_err().kind(),
+            ErrorKind::InvalidExtensionType
+        );
+
+        let mut playlist = Playlist::new("test playlist".to_owned(), "me".to_owned());
+        let mut map = Beatmap::new_extension(42);
+        map.custom_data.insert(100, 7u8);
+        playlist.maps.push(map);
+        playlist.maps.push(Beatmap::new_extension(42));
+        let mut buffer = Vec::new();
+        playlist.write(&mut buffer).unwrap();
+
+        let (read, warnings) =
+            Playlist::read_with_warnings(buffer.as_slice(), &ReadOptions::default()).unwrap();
+        assert_eq!(read.maps[0].extension_type(), Some(42));
+        assert!(!warnings
+            .iter()
+            .any(|w| matches!(w, crate::Warning::ReservedKey { .. })));
+        let (body, _) = PlaylistRef::decompress(buffer.as_slice()).unwrap();
+        let borrowed = PlaylistRef::parse(&body, false).unwrap();
+        assert_eq!(borrowed.maps[1].to_owned().extension_type(), Some(42));
+        assert_eq!(
+            registry.describe(&read.maps[0]).as_deref(),
+            Some("generated beatmap")
+        );
+
+        let options = ReadOptions {
+            strict: true,
+            extensions: Some(registry),
+            ..Default::default()
+        };
+        assert_eq!(
+            Playlist::read_with_options(buffer.as_slice(), &options)
+                .unwrap_err()
+                .kind(),
+            ErrorKind::MissingField
+        );
+        assert_eq!(
+            Playlist::read(buffer.as_slice(), true).unwrap_err().kind(),
+            ErrorKind::StrictModeUnknownBeatmapType
+        );
+
+        let problems = read.validate_strict(options.extensions.as_ref()).problems;
+        assert!(matches!(
+            problems.as_slice(),
+            [crate::Problem::InvalidExtension { map: 1, .. }]
+        ));
+        let write_options = crate::WriteOptions {
+            strict: true,
+            ..Default::default()
+        };
+        match read.clone().write_with_options(Vec::new(), &write_options) {
+            Err(crate::error::Error::Validation(problems)) => assert_eq!(
+                problems,
+                vec![
+                    crate::Problem::UnknownType { map: 0 },
+                    crate::Problem::UnknownType { map: 1 },
+                ]
+            ),
+            r => panic!("{:?}", r.map(|_| ())),
+        }
+        let mut valid = read;
+        valid.maps.truncate(1);
+        let write_options = crate::WriteOptions {
+            extensions: options.extensions,
+            ..write_options
+        };
+        valid
+            .write_with_options(Vec::new(), &write_options)
+            .unwrap();
+    }
+
+    #[test]
+    fn shared_library() {
+        use crate::{Library, SharedLibrary};
+
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<Library>();
+        assert_send_sync::<SharedLibrary>();
+
+        let shared = SharedLibrary::default();
+        let readers: Vec<_> = (0..4)
+            .map(|_| {
+                let shared = shared.clone();
+                std::thread::spawn(move || {
+                    for _ in 0..100 {
+                        let len = shared.read().len();
+                        assert!(len == 0 || len == 2);
+                    }
+                })
+            })
+            .collect();
+
+        let mut refreshed = Library::new();
+        refreshed.insert(Playlist::new("a".to_owned(), "me".to_owned()));
+        refreshed.insert(Playlist::new("b".to_owned(), "me".to_owned()));
+        assert!(shared.replace(refreshed).is_empty());
+        for reader in readers {
+            reader.join().unwrap();
+        }
+
+        shared.write().remove(0);
+        assert_eq!(shared.read().get(0).unwrap().title, "b");
+
+        let poisoner = shared.clone();
+        assert!(std::thread::spawn(move || {
+            let _guard = poisoner.write();
+            panic!("poisoning the lock");
+        })
+        .join()
+        .is_err());
+        assert_eq!(shared.read().len(), 1);
+    }
+
+    #[test]
+    fn widen_integers() {
+        let mut playlist = Playlist::new("test playlist".to_owned(), "me".to_owned());
+        let mut narrow = Beatmap::new_key(0);
+        narrow.key = None;
+        narrow.custom_data.insert(2, 2112u16);
+        playlist.maps.push(narrow);
+
+        let mut buffer = Vec::new();
+        playlist.write(&mut buffer).unwrap();
+        assert!(Playlist::read(buffer.as_slice(), true).is_err());
+
+        let options = ReadOptions {
+            widen_integers: true,
+            ..Default::default()
+        };
+        let playlist = Playlist::read_with_options(buffer.as_slice(), &options).unwrap();
+        assert_eq!(playlist.maps[0].key, Some(2112));
+    }
+
+    #[test]
+    fn duplicates() {
+        let mut playlist = Playlist::new("test playlist".to_owned(), "me".to_owned());
+        playlist.maps.push(Beatmap::new_key(2112));
+        playlist.maps.push(Beatmap::new_key(1));
+        // Key maps are the same map whatever else they carry
+        let mut again = Beatmap::new_key(2112);
+        again.hash = Some(blister_format::values::Sha1([1; 20]));
+        playlist.maps.push(again);
+        playlist.maps.push(Beatmap::new_key(1));
+
+        let mut buffer = Vec::new();
+        playlist.write(&mut buffer).unwrap();
+        let read = |duplicates| {
+            let options = ReadOptions {
+                duplicates,
+                ..Default::default()
+            };
+            Playlist::read_with_warnings(buffer.as_slice(), &options)
+        };
+
+        let (playlist, warnings) = read(crate::DuplicatePolicy::Warn).unwrap();
+        assert_eq!(playlist.maps.len(), 4);
+        assert_eq!(
+            warnings,
+            vec![
+                crate::Warning::DuplicateMap { map: 2, first: 0 },
+                crate::Warning::DuplicateMap { map: 3, first: 1 }
+            ]
+        );
+        let (dropped, _) = read(crate::DuplicatePolicy::Drop).unwrap();
+        assert_eq!(dropped.maps.len(), 2);
+        assert!(matches!(
+            read(crate::DuplicatePolicy::Error),
+            Err(crate::error::Error::DuplicateMap { map: 2, first: 0 })
+        ));
+
+        let mut deduped = playlist;
+        assert_eq!(deduped.remove_duplicates(), [(2, 0), (3, 1)]);
+        assert_eq!(deduped.maps, dropped.maps);
+    }
+
+    #[test]
+    fn hostile_lengths() {
+        let mut buffer = Vec::new();
+        Playlist::new("test playlist".to_owned(), "me".to_owned())
+            .write(&mut buffer)
+            .unwrap();
+        let mut body = Vec::new();
+        flate2::read::GzDecoder::new(&buffer[crate::MAGIC_NUMBER_LEN..])
+            .read_to_end(&mut body)
+            .unwrap();
+        let len = body.len();
+        body[len - 4..].copy_from_slice(&u32::MAX.to_le_bytes());
+
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), Default::default());
+        encoder.write_all(&body).unwrap();
+        let mut hostile = crate::MAGIC_NUMBER.to_vec();
+        hostile.extend(encoder.finish().unwrap());
+
+        assert!(Playlist::read(hostile.as_slice(), true).is_err());
+
+        let options = ReadOptions {
+            max_maps: Some(1024),
+            ..Default::default()
+        };
+        assert!(matches!(
+            Playlist::read_with_options(hostile.as_slice(), &options),
+            Err(crate::error::Error::TooManyMaps(_))
+        ));
+    }
+
+    #[test]
+    fn sniff() {
+        let mut buffer = Vec::new();
+        Playlist::new("test playlist".to_owned(), "me".to_owned())
+            .write(&mut buffer)
+            .unwrap();
+
+        assert!(crate::is_blist(&buffer));
+        assert_eq!(crate::sniff(&b"Blist.v2"[..]), Some(crate::Version::V2));
+        assert_eq!(
+            crate::sniff(&b"Blist.v7"[..]),
+            Some(crate::Version::Unsupported(7))
+        );
+        assert_eq!(crate::sniff(&b"Blist"[..]), None);
+    }
+
+    #[test]
+    fn field_budgets() {
+        let mut playlist = Playlist::new("test playlist".to_owned(), "me".to_owned());
+        playlist.cover = Some(vec![0; 16].into());
+        playlist.maps.push(Beatmap::new_zip(vec![0; 16]));
+
+        let mut buffer = Vec::new();
+        playlist.write(&mut buffer).unwrap();
+
+        let options = ReadOptions {
+            max_cover_len: Some(8),
+            ..Default::default()
+        };
+        assert!(matches!(
+            Playlist::read_with_options(buffer.as_slice(), &options),
+            Err(crate::error::Error::CoverTooLarge(16))
+        ));
+
+        let options = ReadOptions {
+            max_zip_len: Some(0),
+            ..Default::default()
+        };
+        assert!(matches!(
+            Playlist::read_with_options(buffer.as_slice(), &options),
+            Err(crate::error::Error::ZipTooLarge(16))
+        ));
+    }
+
+    #[test]
+    fn builder() {
+        let mut playlist = Playlist::builder()
+            .title("test playlist")
+            .author("me")
+            .map(Beatmap::new_key(2112))
+            .custom_data(2112, true)
+            .build()
+            .unwrap();
+        assert_eq!(playlist.maps.len(), 1);
+
+        assert!(matches!(
+            Playlist::builder().title("test playlist").build(),
+            Err(crate::error::Error::MissingField("author"))
+        ));
+        assert!(matches!(
+            Playlist::builder()
+                .title("a".repeat(256))
+                .author("me")
+                .build(),
+            Err(crate::error::Error::FieldTooLong { len: 256, .. })
+        ));
+
+        assert!(playlist.set_author("a".repeat(256)).is_err());
+        assert_eq!(playlist.author, "me");
+        assert!(playlist.set_author("a".repeat(255)).is_ok());
+    }
+
+    #[test]
+    fn display() {
+        let mut playlist = Playlist::new("Tech Acc".to_owned(), "raft".to_owned());
+        playlist.cover = Some(vec![0; 45 * 1024].into());
+        for i in 0..3 {
+            playlist.maps.push(Beatmap::new_hash([i; 20].into()));
+        }
+        playlist.maps.push(Beatmap::new_key(2112));
+
+        assert_eq!(
+            playlist.to_string(),
+            "'Tech Acc' by raft — 4 maps (3 hash, 1 key), cover 45 KB"
+        );
+
+        let mut map = Beatmap::new_zip(vec![0; 3 * 1024 * 1024 + 256 * 1024]);
+        map.date_added = Utc.timestamp_opt(1577836800, 0).unwrap();
+        assert_eq!(map.to_string(), "embedded zip (3.2 MB), added 2020-01-01");
+        map = Beatmap::new_key(0x83a);
+        map.date_added = Utc.timestamp_opt(1577836800, 0).unwrap();
+        assert_eq!(map.to_string(), "key 83a, added 2020-01-01");
+    }
+
+    #[test]
+    fn redacted() {
+        let mut playlist = Playlist::new("test playlist".to_owned(), "me".to_owned());
+        playlist.cover = Some(b"abc"[..].into());
+        playlist.maps.push(Beatmap::new_zip(vec![0; 1024 * 1024]));
+        playlist.maps[0].custom_data.insert(16, vec![1; 4096]);
+
+        let debug = format!("{:?}", playlist.redacted());
+        assert!(debug.contains("<3 B, sha1 a9993e364706816aba3e25717850c26c9cd0d89d>"));
+        assert!(debug.contains("zip: Some(<1.0 MB, sha1 "));
+        assert!(debug.contains("Key(16): Binary(<4 KB, sha1 "));
+        assert!(debug.len() < 1024);
+    }
+
+    #[test]
+    fn validate() {
+        let mut playlist = Playlist::new("a".repeat(256), "me".to_owned());
+        playlist.maps.push(Beatmap::new_key(2112));
+        playlist.maps.push(Beatmap::new_key(2112));
+        playlist.maps.push(Beatmap::new_hash([4; 20].into()));
+        playlist.maps[2].hash = None;
+
+        let report = playlist.validate();
+        assert_eq!(
+            report.problems,
+            vec![
+                crate::Problem::FieldTooLong {
+                    map: None,
+                    field: "title",
+                    len: 256,
+                    max: 255
+                },
+                crate::Problem::MissingIdentifier {
+                    map: 2,
+                    ty: crate::BeatmapType::Hash
+                },
+            ]
+        );
+        assert_eq!(
+            report.warnings,
+            vec![crate::Warning::DuplicateMap { map: 1, first: 0 }]
+        );
+        assert!(playlist.check_reserved_keys().is_ok());
+
+        playlist.maps[0].custom_data.insert(3, 1u8);
+        playlist.maps[1].date_added = Utc::now() + chrono::Duration::days(1);
+        let report = playlist.validate();
+        assert!(report.warnings.contains(&crate::Warning::ReservedKey {
+            map: Some(0),
+            key: 3.into()
+        }));
+        assert!(report
+            .warnings
+            .contains(&crate::Warning::FutureDate { map: 1 }));
+        assert!(matches!(
+            playlist.check_reserved_keys(),
+            Err(crate::error::Error::ReservedKey { map: Some(0), .. })
+        ));
+    }
+
+    #[test]
+    fn normalize() {
+        let mut playlist = Playlist::new(" test playlist\n".to_owned(), "me".to_owned());
+        playlist.description = Some("  ".to_owned());
+        playlist
+            .custom_data
+            .insert(16, LongString::new("").unwrap());
+        playlist.maps.push(Beatmap::new_key(2112));
+        playlist.maps[0].date_added = Utc::now() + chrono::Duration::days(1);
+
+        let report = playlist.normalize();
+        assert_eq!(
+            report,
+            crate::NormalizeReport {
+                title_trimmed: true,
+                description_dropped: true,
+                custom_data_removed: 1,
+                dates_clamped: vec![0],
+                ..Default::default()
+            }
+        );
+        assert_eq!(playlist.title, "test playlist");
+        assert!(playlist.normalize().is_unchanged());
+    }
+
+    #[test]
+    fn sanitize() {
+        let mut playlist = Playlist::new("evil\u{202e}txt.exe".to_owned(), "me\0".to_owned());
+        playlist.description = Some("line\nline\u{7}".to_owned());
+        playlist.cover = Some(vec![1].into());
+        playlist.maps.push(Beatmap::new_zip(vec![0; 4]));
+        playlist.maps.push(Beatmap::new_key(2112));
+
+        let options = crate::SanitizeOptions {
+            max_name_len: 6,
+            drop_cover: true,
+            drop_zips: true,
+            ..Default::default()
+        };
+        assert!(playlist.sanitize(options));
+        assert_eq!(playlist.title, "eviltx");
+        assert_eq!(playlist.author, "me");
+        assert_eq!(playlist.description.as_deref(), Some("line\nline"));
+        assert_eq!(playlist.cover, None);
+        assert_eq!(playlist.maps.len(), 1);
+        assert!(!playlist.sanitize(options));
+    }
+
+    #[test]
+    fn eq_ignoring() {
+        let mut a = Playlist::new("test playlist".to_owned(), "me".to_owned());
+        a.maps.push(Beatmap::new_key(2112));
+        a.custom_data.insert(16, 1u8);
+        let mut b = a.clone();
+        b.maps[0].date_added = Utc.timestamp_opt(0, 0).unwrap();
+        b.custom_data.insert(16, 2u8);
+
+        assert!(a.maps[0].same_map(&b.maps[0]));
+        assert!(!a.eq_ignoring(&b, &Default::default()));
+
+        let ignore = crate::IgnoreFields {
+            custom_data_keys: vec![16.into()],
+            ..Default::default()
+        };
+        assert!(a.eq_ignoring(&b, &ignore));
+    }
+
+    #[test]
+    fn matches() {
+        struct Index;
+        impl crate::Resolver for Index {
+            fn resolve(&self, map: &Beatmap) -> crate::Resolution {
+                match map.key {
+                    Some(2112) => crate::Resolution::Found(crate::SongInfo {
+                        key: Some(2112),
+                        hash: Some([4; 20].into()),
+                        ..Default::default()
+                    }),
+                    Some(_) => crate::Resolution::NotFound,
+                    None => crate::Resolution::Unknown,
+                }
+            }
+        }
+
+        let hash = Beatmap::new_hash([4; 20].into());
+        assert_eq!(
+            Beatmap::new_key(2112).matches(&hash, &Index),
+            crate::Match::Match
+        );
+        assert_eq!(
+            Beatmap::new_key(2112).matches(&Beatmap::new_key(2113), &Index),
+            crate::Match::NoMatch
+        );
+        assert_eq!(
+            Beatmap::new_key(2113).matches(&hash, &Index),
+            crate::Match::Unknown
+        );
+    }
+
+    #[test]
+    fn enrich() {
+        struct Index;
+        impl crate::Resolver for Index {
+            fn resolve(&self, map: &Beatmap) -> crate::Resolution {
+                match map.key {
+                    Some(2112) => crate::Resolution::Found(crate::SongInfo {
+                        name: Some("Tom Sawyer".to_owned()),
+                        author: Some("Rush".to_owned()),
+                        difficulties: vec!["Hard".to_owned(), "Expert".to_owned()],
+                        ..Default::default()
+                    }),
+                    Some(_) => crate::Resolution::NotFound,
+                    None => crate::Resolution::Unknown,
+                }
+            }
+        }
+
+        let mut playlist = Playlist::new("test playlist".to_owned(), "me".to_owned());
+        playlist.maps.push(Beatmap::new_key(2112));
+        playlist.maps.push(Beatmap::new_key(2113));
+        playlist.maps.push(Beatmap::new_hash([4; 20].into()));
+        let report = playlist.enrich(&Index);
+        assert_eq!(report.enriched, [0]);
+        assert_eq!(report.not_found, [1]);
+        assert_eq!(report.unknown, [2]);
+
+        let mut buffer = Vec::new();
+        let options = crate::WriteOptions {
+            strict: true,
+            ..Default::default()
+        };
+        playlist.write_with_options(&mut buffer, &options).unwrap();
+        let (mut playlist, warnings) =
+            Playlist::read_with_warnings(buffer.as_slice(), &Default::default()).unwrap();
+        assert!(warnings.is_empty());
+
+        let map = &mut playlist.maps[0];
+        assert_eq!(map.song_name(), Some("Tom Sawyer"));
+        assert_eq!(map.song_author(), Some("Rush"));
+        assert_eq!(map.mapper(), None);
+        assert_eq!(map.difficulties(), ["Hard", "Expert"]);
+        assert!(playlist.maps[1].difficulties().is_empty());
+
+        playlist.maps[0].clear_song_info();
+        assert!(playlist.maps[0].custom_data.is_empty());
+    }
+
+    #[test]
+    fn dead_maps() {
+        struct Index;
+        impl crate::Resolver for Index {
+            fn resolve(&self, map: &Beatmap) -> crate::Resolution {
+                match map.key {
+                    Some(2112) => crate::Resolution::Found(Default::default()),
+                    Some(_) => crate::Resolution::NotFound,
+                    None => crate::Resolution::Unknown,
+                }
+            }
+        }
+
+        let mut playlist = Playlist::new("test playlist".to_owned(), "me".to_owned());
+        playlist.maps.push(Beatmap::new_key(2112));
+        playlist.maps.push(Beatmap::new_key(2113));
+        playlist.maps.push(Beatmap::new_hash([4; 20].into()));
+        playlist.maps[0].set_dead(true);
+        assert_eq!(playlist.find_unresolvable(&Index), [1]);
+
+        assert_eq!(playlist.mark_unresolvable(&Index), [1]);
+        assert!(!playlist.maps[0].is_dead());
+        assert!(playlist.maps[1].is_dead());
+        assert!(playlist.check_reserved_keys().is_ok());
+
+        assert_eq!(playlist.remove_dead(), 1);
+        assert_eq!(playlist.maps.len(), 2);
+        assert_eq!(playlist.maps[1].ty, crate::BeatmapType::Hash);
+    }
+
+    #[test]
+    fn http_downloader() {
+        use crate::{error::Error, HttpDownloader, HttpResponse, RetryPolicy};
+        use std::{io::Cursor, sync::Mutex, time::Duration};
+
+        // Cuts the connection after a few bytes on the first request
+        struct Flaky(Cursor<Vec<u8>>, usize);
+        impl Read for Flaky {
+            fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+                if self.1 == 0 {
+                    return Err(std::io::ErrorKind::ConnectionReset.into());
+                }
+                let len = buf.len().min(self.1);
+                let read = self.0.read(&mut buf[..len])?;
+                self.1 -= read;
+                Ok(read)
+            }
+        }
+
+        let retry = RetryPolicy {
+            max_retries: 2,
+            initial_backoff: Duration::ZERO,
+            max_backoff: Duration::ZERO,
+        };
+        let body = b"zipped map contents".to_vec();
+        let requests = Mutex::new(Vec::new());
+        let client = |url: &str, offset: u64| {
+            let mut requests = requests.lock().unwrap();
+            requests.push((url.to_owned(), offset));
+            let partial = |start: u64| HttpResponse {
+                content_range: Some(format!("bytes {}-{}/{}", start, body.len() - 1, body.len())),
+                ..HttpResponse::new(206, Cursor::new(body[start as usize..].to_vec()))
+            };
+            let rest = body[offset as usize..].to_vec();
+            Ok(match (url, requests.len()) {
+                ("busy", 1) => HttpResponse::new(503, std::io::empty()),
+                ("missing", _) => HttpResponse::new(404, std::io::empty()),
+                ("map" | "shifted", 1) => HttpResponse::new(200, Flaky(Cursor::new(rest), 6)),
+                // Resumes from the wrong place
+                ("shifted", 2) => partial(2),
+                (_, _) if offset == body.len() as u64 => HttpResponse::new(416, std::io::empty()),
+                (_, _) if offset > 0 => partial(offset),
+                (_, _) => HttpResponse::new(200, Cursor::new(rest)),
+            })
+        };
+        let downloader = HttpDownloader::new(&client)
+            .with_retry(retry)
+            .with_concurrency(0);
+
+        assert_eq!(downloader.fetch("busy").unwrap(), body);
+        assert_eq!(requests.lock().unwrap().len(), 2);
+
+        requests.lock().unwrap().clear();
+        match downloader.fetch("missing").unwrap_err() {
+            Error::Http { status, .. } => assert_eq!(status, 404),
+            e => panic!("{}", e),
+        }
+        assert_eq!(requests.lock().unwrap().len(), 1);
+
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        let dest = dir.join("map.zip");
+
+        requests.lock().unwrap().clear();
+        // The digest covers the bytes kept from the interrupted attempt too
+        let (_, digest) = crate::copy_hashed(body.as_slice(), &mut std::io::sink()).unwrap();
+        assert_eq!(
+            downloader.download("map", &dest).unwrap(),
+            (body.len() as u64, digest)
+        );
+        assert_eq!(
+            *requests.lock().unwrap(),
+            [("map".to_owned(), 0), ("map".to_owned(), 6)]
+        );
+        assert_eq!(std::fs::read(&dest).unwrap(), body);
+        assert!(!dir.join("map.zip.part").exists());
+
+        // The misplaced range throws the partial file away and starts over
+        requests.lock().unwrap().clear();
+        let dest = dir.join("shifted.zip");
+        assert_eq!(
+            downloader.download("shifted", &dest).unwrap().0,
+            body.len() as u64
+        );
+        assert_eq!(
+            *requests.lock().unwrap(),
+            [
+                ("shifted".to_owned(), 0),
+                ("shifted".to_owned(), 6),
+                ("shifted".to_owned(), 0)
+            ]
+        );
+        assert_eq!(std::fs::read(&dest).unwrap(), body);
+
+        // A complete leftover `.part` is past the end of the file and gets downloaded again
+        requests.lock().unwrap().clear();
+        let dest = dir.join("complete.zip");
+        std::fs::write(dir.join("complete.zip.part"), &body).unwrap();
+        assert_eq!(
+            downloader.download("complete", &dest).unwrap(),
+            (body.len() as u64, digest)
+        );
+        assert_eq!(
+            *requests.lock().unwrap(),
+            [
+                ("complete".to_owned(), body.len() as u64),
+                ("complete".to_owned(), 0)
+            ]
+        );
+        assert_eq!(std::fs::read(&dest).unwrap(), body);
+
+        let dest = dir.join("rejected.zip");
+        assert!(matches!(
+            downloader.download_verified("ok", &dest, |_| Err(Error::CorruptDownload(
+                "ok".to_owned()
+            ))),
+            Err(Error::CorruptDownload(_))
+        ));
+        assert!(!dest.exists());
+        assert!(!dir.join("rejected.zip.part").exists());
+
+        let capped = HttpDownloader::new(&client).with_max_body_len(4);
+        assert!(matches!(
+            capped.fetch("ok"),
+            Err(Error::ResponseTooLarge { max: 4, .. })
+        ));
+        assert!(matches!(
+            capped.download("ok", &dest),
+            Err(Error::ResponseTooLarge { max: 4, .. })
+        ));
+        assert!(!dir.join("rejected.zip.part").exists());
+
+        assert_eq!(RetryPolicy::default().backoff(2), Duration::from_secs(2));
+        assert_eq!(RetryPolicy::default().backoff(64), Duration::from_secs(30));
+    }
+
+    #[test]
+    fn zip_cache() {
+        use crate::{HttpDownloader, HttpResponse, Resolution, Resolver, SongInfo, ZipCache};
+        use std::{io::Cursor, sync::Mutex};
+
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        let cache = ZipCache::new(dir).unwrap();
+        let digest = |b: &[u8]| crate::copy_hashed(b, &mut std::io::sink()).unwrap().1;
+
+        // An empty archive, which is only its end of central directory record
+        let mut downloaded = b"PK\x05\x06".to_vec();
+        downloaded.resize(22, 0);
+        let requests = Mutex::new(Vec::new());
+        let client = |url: &str, _: u64| {
+            requests.lock().unwrap().push(url.to_owned());
+            let body = match url.ends_with(&format!("{}.zip", "03".repeat(20))) {
+                true => b"not a zip".to_vec(),
+                false => downloaded.clone(),
+            };
+            Ok(HttpResponse::new(200, Cursor::new(body)))
+        };
+        let downloader = HttpDownloader::new(client);
+
+        // Zips are only cached under a hash they match
+        let embedded = digest(b"embedded zip");
+        let mut frozen = Playlist::new("frozen playlist".to_owned(), "me".to_owned());
+        let mut map = Beatmap::new_zip(b"embedded zip".to_vec());
+        map.hash = Some(embedded);
+        frozen.maps.push(map);
+        let mut map = Beatmap::new_zip(b"wrong zip".to_vec());
+        map.hash = Some([1; 20].into());
+        frozen.maps.push(map);
+        assert_eq!(frozen.cache_zips(&cache).unwrap(), 1);
+        assert_eq!(frozen.cache_zips(&cache).unwrap(), 0);
+        assert!(!cache.contains(&[1; 20].into()));
+        assert_eq!(
+            cache.get(&embedded).unwrap().as_deref(),
+            Some(&b"embedded zip"[..])
+        );
+        assert!(cache
+            .path(&[1; 20].into())
+            .ends_with("01/0101010101010101010101010101010101010101.zip"));
+
+        // Key maps are cached under the hash the resolver finds for them
+        struct Keys;
+        impl Resolver for Keys {
+            fn resolve(&self, map: &Beatmap) -> Resolution {
+                match map.key {
+                    Some(3) => Resolution::Found(SongInfo {
+                        key: Some(3),
+                        hash: Some([2; 20].into()),
+                        ..Default::default()
+                    }),
+                    _ => Resolution::Unknown,
+                }
+            }
+        }
+
+        let mut playlist = Playlist::new("test playlist".to_owned(), "me".to_owned());
+        playlist.maps.push(Beatmap::new_hash(embedded));
+        playlist.maps.push(Beatmap::new_hash([2; 20].into()));
+        playlist.maps.push(Beatmap::new_key(3));
+        playlist.maps.push(Beatmap::new_key(4));
+        let mut again = playlist.clone();
+
+        let report = playlist.freeze(&cache, &downloader, &Keys);
+        assert_eq!(report.frozen, [0, 1, 2]);
+        assert_eq!(report.downloaded, [1]);
+        assert_eq!(report.failed.len(), 1);
+        assert_eq!(report.failed[0].0, 3);
+        assert_eq!(
+            *requests.lock().unwrap(),
+            [format!(
+                "{}/{}.zip",
+                crate::BEATSAVER_CDN_URL,
+                "02".repeat(20)
+            )]
+        );
+        assert_eq!(playlist.maps[0].ty, crate::BeatmapType::Zip);
+        assert_eq!(
+            playlist.maps[0].zip.as_ref().and_then(|z| z.as_bytes()),
+            Some(&b"embedded zip"[..])
+        );
+        assert_eq!(
+            playlist.maps[1].zip.as_ref().and_then(|z| z.as_bytes()),
+            Some(&downloaded[..])
+        );
+        assert_eq!(playlist.maps[2].hash, Some([2; 20].into()));
+        assert_eq!(playlist.maps[2].zip, playlist.maps[1].zip);
+
+        // Later runs are served from the cache
+        let report = again.freeze(&cache, &downloader, &Keys);
+        assert!(report.downloaded.is_empty());
+        assert_eq!(requests.lock().unwrap().len(), 1);
+        assert_eq!(again.maps, playlist.maps);
+
+        // Thinning undoes the freeze, maps that can't be identified or whose zip doesn't match
+        // their hash keep their zip
+        playlist
+            .maps
+            .push(Beatmap::new_zip(b"anonymous zip".to_vec()));
+        let mut map = Beatmap::new_zip(b"wrong zip".to_vec());
+        map.hash = Some([1; 20].into());
+        playlist.maps.push(map);
+        let report = playlist.thin(Some(&cache), &Keys).unwrap();
+        assert_eq!(report.thinned, [0, 1, 2]);
+        assert_eq!(report.kept, [4]);
+        assert_eq!(report.mismatched, [5]);
+        assert!(!report.is_complete());
+        for map in &playlist.maps[..3] {
+            assert_eq!(map.ty, crate::BeatmapType::Hash);
+            assert_eq!(map.zip, None);
+        }
+        assert_eq!(playlist.maps[4].ty, crate::BeatmapType::Zip);
+        assert_eq!(playlist.maps[5].ty, crate::BeatmapType::Zip);
+        assert!(!cache.contains(&[1; 20].into()));
+
+        // Spilled zips are streamed into the cache
+        let zip = vec![5; 4096];
+        let mut spilled = Playlist::new("spilled playlist".to_owned(), "me".to_owned());
+        let mut map = Beatmap::new_zip(zip.clone());
+        map.hash = Some(digest(&zip));
+        spilled.maps.push(map);
+        let mut buffer = Vec::new();
+        spilled.write(&mut buffer).unwrap();
+        let options = ReadOptions {
+            spill: Some(blister_format::Spill::new(1024, dir)),
+            ..Default::default()
+        };
+        let mut spilled = Playlist::read_with_options(buffer.as_slice(), &options).unwrap();
+        assert!(spilled.maps[0].zip.as_ref().unwrap().spilled().is_some());
+        let report = spilled.thin(Some(&cache), &Keys).unwrap();
+        assert_eq!(report.thinned, [0]);
+        assert_eq!(cache.get(&digest(&zip)).unwrap(), Some(zip));
+
+        assert!(cache.remove(&[2; 20].into()).unwrap());
+        assert_eq!(cache.get(&[2; 20].into()).unwrap(), None);
+        assert_eq!(
+            cache.fetch(&[2; 20].into(), &downloader).unwrap(),
+            Some(digest(&downloaded))
+        );
+        assert_eq!(cache.fetch(&[2; 20].into(), &downloader).unwrap(), None);
+
+        // Downloads that aren't zips never make it into the cache
+        assert!(matches!(
+            cache.fetch(&[3; 20].into(), &downloader),
+            Err(crate::error::Error::CorruptDownload(_))
+        ));
+        assert!(!cache.contains(&[3; 20].into()));
+    }
+
+    #[test]
+    fn rules() {
+        use crate::{Rule, RuleSet};
+
+        let rules = RuleSet::new()
+            .with(Rule::max_maps(2))
+            .with(Rule::no_duplicates())
+            .with(Rule::require_cover())
+            .with(Rule::for_each_map("hash only".to_owned(), |map| {
+                map.hash.is_none().then(|| "map has no hash".to_owned())
+            }));
+
+        let mut playlist = Playlist::new("test playlist".to_owned(), "me".to_owned());
+        playlist.maps.push(Beatmap::new_hash([1; 20].into()));
+        playlist.cover = Some(vec![1, 2, 3].into());
+        assert!(playlist.check(&rules).is_ok());
+
+        playlist.maps.push(Beatmap::new_key(2112));
+        playlist.maps.push(Beatmap::new_hash([1; 20].into()));
+        playlist.cover = None;
+        let report = playlist.check(&rules);
+        let broken: Vec<_> = report
+            .violations
+            .iter()
+            .map(|v| (v.rule.as_str(), v.map))
+            .collect();
+        assert_eq!(
+            broken,
+            [
+                ("max maps", None),
+                ("no duplicates", Some(2)),
+                ("require cover", None),
+                ("hash only", Some(1)),
+            ]
+        );
+        assert_eq!(
+            report.violations[0].message,
+            "playlist has 3 maps, at most 2 allowed"
+        );
+    }
+
+    #[test]
+    fn changelog() {
+        let clock = crate::FixedClock(Utc.timestamp_opt(1_600_000_000, 0).unwrap());
+
+        let mut old = Playlist::new("weekly".to_owned(), "me".to_owned());
+        old.maps.push(Beatmap::new_key_with_clock(0x2112, &clock));
+        old.maps.push(Beatmap::new_key_with_clock(0x2113, &clock));
+        assert!(old.diff(&old).is_empty());
+        assert_eq!(
+            old.diff(&old).to_changelog(),
+            "## Changes to weekly\n\nNo changes\n"
+        );
+
+        let mut new = old.clone();
+        new.title = "weekly_picks".to_owned();
+        new.maps.remove(0);
+        let mut map = Beatmap::new_hash_with_clock([4; 20].into(), &clock);
+        map.cache_song_info(&crate::SongInfo {
+            name: Some("Song*".to_owned()),
+            author: Some("artist".to_owned()),
+            mapper: Some("mapper".to_owned()),
+            ..Default::default()
+        });
+        new.maps.push(map);
+
+        let diff = old.diff(&new);
+        assert_eq!(diff.added.len(), 1);
+        assert_eq!(diff.removed[0].key, Some(0x2112));
+        assert_eq!(
+            diff.to_changelog(),
+            format!(
+                "## Changes to weekly\\_picks\n\
+                 \nRenamed from **weekly** to **weekly\\_picks**\n\
+                 \n### Added (1)\n\
+                 \n- **Song\\*** by artist, mapped by mapper (`{}`), added 2020-09-13\n\
+                 \n### Removed (1)\n\
+                 \n- *`2112`*, added 2020-09-13\n",
+                "04".repeat(20)
+            )
+        );
+    }
+
+    #[test]
+    fn scrape() {
+        let hash = "0123456789abcdef0123456789ABCDEF01234567";
+        let text = format!(
+            "new pack! <https://beatsaver.com/maps/2112>, also !bsr 25f and\n\
+             (https://bsaber.com/songs/1a2b/?ref=x) or beatsaver://2112 but not add or cafe.\n\
+             hash {} / https://r2cdn.beatsaver.com/{}.zip",
+            hash,
+            "ab".repeat(20)
+        );
+        let maps = crate::scrape(&text);
+        let found: Vec<_> = maps.iter().map(|m| (m.key, m.hash)).collect();
+        let mut bytes = [0; 20];
+        for (i, b) in bytes.iter_mut().enumerate() {
+            *b = u8::from_str_radix(&hash[i * 2..i * 2 + 2], 16).unwrap();
+        }
+        assert_eq!(
+            found,
+            [
+                (Some(0x2112), None),
+                (Some(0x25f), None),
+                (Some(0x1a2b), None),
+                (None, Some(bytes.into())),
+                (None, Some([0xab; 20].into())),
+            ]
+        );
+        assert!(crate::scrape("nothing to see here, deadbeef").is_empty());
+    }
+
+    #[test]
+    fn canonical() {
+        let mut a = Playlist::new("test playlist".to_owned(), "me".to_owned());
+        a.description = Some(String::new());
+        let mut b = a.clone();
+        b.description = None;
+        b.custom_data = Map::with_capacity(1024);
+        for i in 0..64 {
+            a.custom_data.insert(i, i);
+            b.custom_data.insert(63 - i, 63 - i);
+        }
+
+        let mut a_buffer = Vec::new();
+        a.write_canonical(&mut a_buffer).unwrap();
+        let mut b_buffer = Vec::new();
+        b.write_canonical(&mut b_buffer).unwrap();
+
+        assert_eq!(a_buffer, b_buffer);
+    }
+
+    #[test]
+    fn library() {
+        let mut a = Playlist::new("a".to_owned(), "me".to_owned());
+        a.cover = Some(vec![2, 1, 1, 2].into());
+        a.maps.push(Beatmap::new_key(2112));
+        a.maps[0].date_added = Utc.timestamp_opt(0, 0).unwrap();
+        let mut b = Playlist::new("b".to_owned(), "me".to_owned());
+        b.cover = Some(vec![2, 1, 1, 2].into());
+        let c = Playlist::new("c".to_owned(), "me".to_owned());
+
+        let mut library = crate::Library::new();
+        library.insert(a);
+        library.insert(b);
+        library.insert(c);
+        assert_eq!(library.cover_count(), 1);
+        let (a, b) = (library.get(0).unwrap(), library.get(1).unwrap());
+        assert!(std::sync::Arc::ptr_eq(
+            a.cover.as_ref().unwrap(),
+            b.cover.as_ref().unwrap()
+        ));
+
+        let mut buffer = Vec::new();
+        library.write_archive(&mut buffer).unwrap();
+        let mut read =
+            crate::Library::read_archive(buffer.as_slice(), &Default::default()).unwrap();
+        assert_eq!(read.cover_count(), 1);
+        assert!(read.playlists().eq(library.playlists()));
+
+        let held = read.get(0).unwrap().cover.clone();
+        read.remove(0);
+        assert_eq!(read.cover_count(), 1);
+        read.remove(0);
+        assert_eq!(read.cover_count(), 0);
+        assert!(held.is_some());
+    }
+
+    #[test]
+    fn write_options() {
+        let mut playlist = Playlist::new("test playlist".to_owned(), "me".to_owned());
+        playlist.maps.push(Beatmap::new_key(2112));
+        playlist.maps[0].key = None;
+
+        let options = crate::WriteOptions {
+            version: crate::Version::V4,
+            canonical: true,
+            ..Default::default()
+        };
+        let mut buffer = Vec::new();
+        let report = playlist
+            .clone()
+            .write_with_options(&mut buffer, &options)
+            .unwrap();
+        assert_eq!(crate::sniff(buffer.as_slice()), Some(crate::Version::V4));
+        assert_eq!(report.compressed, buffer.len());
+        let mut body = Vec::new();
+        flate2::read::GzDecoder::new(&buffer[8..])
+            .read_to_end(&mut body)
+            .unwrap();
+        assert_eq!(report.uncompressed, body.len());
+        assert_eq!(report.uncompressed, report.header + 4 + report.maps);
+
+        let mut gzip = crate::GzipHeader {
+            mtime: 1_600_000_000,
+            filename: Some("test.blist".into()),
+            comment: Some("comment".into()),
+        };
+        let header_options = crate::WriteOptions {
+            canonical: false,
+            gzip: gzip.clone(),
+            ..options.clone()
+        };
+        let mut buffer = Vec::new();
+        playlist
+            .clone()
+            .write_with_options(&mut buffer, &header_options)
+            .unwrap();
+        let decoder = flate2::read::GzDecoder::new(&buffer[8..]);
+        let header = decoder.header().unwrap();
+        assert_eq!(header.mtime(), 1_600_000_000);
+        assert_eq!(header.filename(), Some(&b"test.blist"[..]));
+        assert_eq!(header.comment(), Some(&b"comment"[..]));
+
+        let canonical_options = crate::WriteOptions {
+            canonical: true,
+            ..header_options.clone()
+        };
+        let mut buffer = Vec::new();
+        playlist
+            .write_with_options(&mut buffer, &canonical_options)
+            .unwrap();
+        let decoder = flate2::read::GzDecoder::new(&buffer[8..]);
+        let header = decoder.header().unwrap();
+        assert_eq!(header.mtime(), 0);
+        assert_eq!(header.filename(), None);
+        assert_eq!(header.comment(), None);
+
+        gzip.comment = Some("nul\0".into());
+        let header_options = crate::WriteOptions {
+            gzip,
+            ..header_options
+        };
+        assert!(matches!(
+            playlist
+                .clone()
+                .write_with_options(Vec::new(), &header_options),
+            Err(crate::error::Error::InvalidGzipHeader("comment"))
+        ));
+
+        let options = crate::WriteOptions {
+            validate: true,
+            ..options
+        };
+        assert!(matches!(
+            playlist.clone().write_with_options(Vec::new(), &options),
+            Err(crate::error::Error::Validation(_))
+        ));
+        let options = crate::WriteOptions {
+            version: crate::Version::Unsupported(2),
+            ..Default::default()
+        };
+        assert!(matches!(
+            playlist.write_with_options(Vec::new(), &options),
+            Err(crate::error::Error::UnsupportedVersion(2))
+        ));
+    }
+
+    #[test]
+    fn fingerprint() {
+        let mut old = Playlist::new("test playlist".to_owned(), "me".to_owned());
+        for i in 0..16 {
+            old.custom_data.insert(i + 16, i);
+            old.maps.push(Beatmap::new_key(i));
+        }
+
+        let mut new = old.clone();
+        for m in new.maps.iter_mut() {
+            m.date_added = Utc.timestamp_opt(0, 0).unwrap();
+        }
+        assert_eq!(old.fingerprint().unwrap(), new.fingerprint().unwrap());
+
+        let options = FingerprintOptions {
+            date_added: true,
+            custom_data: false,
+        };
+        assert_ne!(
+            old.fingerprint_with_options(options).unwrap(),
+            new.fingerprint_with_options(options).unwrap()
+        );
+
+        new.custom_data.insert(2112, true);
+        assert_ne!(old.fingerprint().unwrap(), new.fingerprint().unwrap());
+
+        let mut set = std::collections::HashSet::new();
+        assert!(set.insert(old.clone().into_canonical().unwrap()));
+        assert!(!set.insert(old.into_canonical().unwrap()));
+        assert!(set.insert(new.into_canonical().unwrap()));
+    }
+
+    #[test]
+    fn canonical_hash() {
+        let mut playlist = Playlist::new("test playlist".to_owned(), "me".to_owned());
+        playlist.description = Some("a description".to_owned());
+        playlist.cover = Some(b"cover".to_vec().into());
+        playlist.tags.push("tech".to_owned());
+        playlist
+            .localized_titles
+            .insert("fr".to_owned(), "une playlist".to_owned());
+        playlist
+            .children
+            .push(crate::ChildPlaylist::Path("sub/leaf.blist".to_owned()));
+        for i in 0..16 {
+            playlist.custom_data.insert(i + 16, i);
+            let mut map = Beatmap::new_key(i);
+            map.note = Some("note".to_owned());
+            map.custom_data.insert(i + 16, i);
+            playlist.maps.push(map);
+        }
+        let hash = playlist.canonical_hash().unwrap();
+
+        // Custom data inserted in reverse into a larger table iterates in another order
+        let mut reordered = playlist.clone();
+        reordered.custom_data = Map::with_capacity(1024);
+        for i in (0..16).rev() {
+            reordered.custom_data.insert(i + 16, i);
+        }
+        for map in &mut reordered.maps {
+            let mut custom_data = Map::with_capacity(1024);
+            for (k, v) in map.custom_data.iter().collect::<Vec<_>>().into_iter().rev() {
+                custom_data.insert(*k, v.clone());
+            }
+            map.custom_data = custom_data;
+        }
+        assert_eq!(reordered.canonical_hash().unwrap(), hash);
+
+        for level in [flate2::Compression::none(), flate2::Compression::best()] {
+            let mut buffer = Vec::new();
+            playlist.write_with_compression(&mut buffer, level).unwrap();
+            let read = Playlist::read(buffer.as_slice(), true).unwrap();
+            assert_eq!(read.canonical_hash().unwrap(), hash);
+        }
+
+        let changes: [fn(&mut Playlist); 12] = [
+            |p| p.title.push('!'),
+            |p| p.author.push('!'),
+            |p| p.description = None,
+            |p| p.cover = Some(b"other".to_vec().into()),
+            |p| p.tags.push("speed".to_owned()),
+            |p| {
+                p.localized_titles
+                    .insert("de".to_owned(), "eine Playlist".to_owned());
+            },
+            |p| p.children.clear(),
+            |p| {
+                p.custom_data.insert(16, 1u8);
+            },
+            |p| p.maps[0].key = Some(2112),
+            |p| p.maps[0].date_added = Utc.timestamp_opt(0, 0).unwrap(),
+            |p| p.maps[0].note = None,
+            |p| p.maps.swap(0, 1),
+        ];
+        for change in changes.iter() {
+            let mut changed = playlist.clone();
+            change(&mut changed);
+            assert_ne!(changed.canonical_hash().unwrap(), hash);
+            assert_ne!(
+                changed.into_canonical().unwrap(),
+                playlist.clone().into_canonical().unwrap()
+            );
+        }
+    }
+
+    #[cfg(feature = "hmac")]
+    #[test]
+    fn authenticated() {
+        let mut playlist = Playlist::new("test playlist".to_owned(), "me".to_owned());
+        playlist.maps.push(Beatmap::new_key(2112));
+
+        let mut buffer = Vec::new();
+        playlist
+            .write_authenticated(&mut buffer, b"secret")
+            .unwrap();
+
+        assert!(Playlist::read_authenticated(buffer.as_slice(), b"secret", true).is_ok());
+        assert!(Playlist::read_authenticated(buffer.as_slice(), b"other", true).is_err());
+
+        let options = crate::ReadOptions {
+            max_total_len: Some(buffer.len() - 1),
+            ..Default::default()
+        };
+        assert_eq!(
+            Playlist::read_authenticated_with_options(buffer.as_slice(), b"secret", &options)
+                .unwrap_err()
+                .kind(),
+            crate::error::ErrorKind::PlaylistTooLarge
+        );
+        let options = crate::ReadOptions {
+            max_total_len: Some(buffer.len()),
+            ..Default::default()
+        };
+        assert!(
+            Playlist::read_authenticated_with_options(buffer.as_slice(), b"secret", &options)
+                .is_ok()
+        );
+
+        let last = buffer.len() - 1;
+        buffer[last] ^= 1;
+        assert!(Playlist::read_authenticated(buffer.as_slice(), b"secret", true).is_err());
+    }
+
+    #[cfg(feature = "encryption")]
+    #[test]
+    fn encrypted() {
+        let mut old = Playlist::new("test playlist".to_owned(), "me".to_owned());
+        old.description = Some("private".to_owned());
+
+        let key = [7; crate::KEY_LEN];
+        let mut buffer = Vec::new();
+        old.clone().write_encrypted(&mut buffer, &key).unwrap();
+
+        assert!(Playlist::read(buffer.as_slice(), true).is_err());
+        assert!(!crate::is_blist(&buffer));
+        assert_eq!(crate::sniff(buffer.as_slice()), None);
+        assert!(Playlist::read_encrypted(buffer.as_slice(), &[8; crate::KEY_LEN], true).is_err());
+
+        // Unknown cipher IDs are rejected before anything is decrypted
+        let mut tampered = buffer.clone();
+        tampered[crate::MAGIC_NUMBER_LEN] = 2;
+        assert!(Playlist::read_encrypted(tampered.as_slice(), &key, true).is_err());
+
+        let new = Playlist::read_encrypted(buffer.as_slice(), &key, true).unwrap();
+        assert_eq!(old, new);
+    }
+
+    #[cfg(feature = "bplist")]
+    #[test]
+    fn bplist() {
+        let json = r#"{
+            "playlistTitle": "test playlist",
+            "playlistAuthor": "me",
+            "image": "data:image/png;base64,AgEBAg==",
+            "songs": [
+                { "key": "83a", "songName": "ignored" },
+                { "hash": "0404040404040404040404040404040404040404", "key": "1" },
+                { "levelid": "level ID", "dateAdded": "2020-01-01T00:00:00Z" }
+            ]
+        }"#;
+
+        let old = Playlist::read_bplist(json.as_bytes()).unwrap();
+        assert_eq!(old.cover.as_deref(), Some(&[2, 1, 1, 2][..]));
+        assert_eq!(old.maps[0].key, Some(0x83a));
+        assert_eq!(old.maps[1].hash, Some([4; 20].into()));
+        assert_eq!(old.maps[2].date_added.timestamp(), 1577836800);
+
+        let mut buffer = Vec::new();
+        old.write_bplist(&mut buffer).unwrap();
+        let new = Playlist::read_bplist(buffer.as_slice()).unwrap();
+        assert_eq!(old, new);
+    }
+
+    #[cfg(feature = "bplist")]
+    #[test]
+    fn bplist_unknown_fields() {
+        use crate::{error::ErrorKind, UnknownFieldPolicy};
+
+        let json = r#"{
+            "playlistTitle": "test playlist",
+            "syncURL": "https://example.com/playlist.bplist",
+            "songs": [
+                { "key": "83a", "songName": "song", "difficulties": [{ "name": "Expert" }] }
+            ]
+        }"#;
+
+        let dropped = Playlist::read_bplist(json.as_bytes()).unwrap();
+        assert!(dropped.custom_data.is_empty());
+        assert!(dropped.maps[0].custom_data.is_empty());
+
+        let error = Playlist::read_bplist_with_policy(json.as_bytes(), UnknownFieldPolicy::Error)
+            .unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::UnknownBplistField);
+
+        let preserved =
+            Playlist::read_bplist_with_policy(json.as_bytes(), UnknownFieldPolicy::Preserve)
+                .unwrap();
+        assert!(preserved.check_reserved_keys().is_ok());
+        let mut blist = Vec::new();
+        preserved.clone().write(&mut blist).unwrap();
+        let preserved = Playlist::read(blist.as_slice(), true).unwrap();
+
+        let mut buffer = Vec::new();
+        preserved
+            .write_bplist_with_policy(&mut buffer, UnknownFieldPolicy::Error)
+            .unwrap();
+        let written: serde_json::Value = serde_json::from_slice(&buffer).unwrap();
+        assert_eq!(written["syncURL"], "https://example.com/playlist.bplist");
+        assert_eq!(written["songs"][0]["songName"], "song");
+        assert_eq!(written["songs"][0]["difficulties"][0]["name"], "Expert");
+
+        let mut buffer = Vec::new();
+        preserved
+            .write_bplist_with_policy(&mut buffer, UnknownFieldPolicy::Drop)
+            .unwrap();
+        let written: serde_json::Value = serde_json::from_slice(&buffer).unwrap();
+        assert!(written.get("syncURL").is_none());
+
+        let mut custom = preserved;
+        custom.maps[0].custom_data.insert(16, 1u8);
+        let error = custom
+            .write_bplist_with_policy(std::io::sink(), UnknownFieldPolicy::Error)
+            .unwrap_err();
+        match error {
+            crate::error::Error::UnknownBplistField { map, .. } => assert_eq!(map, Some(0)),
+            e => panic!("{}", e),
+        }
+    }
+
+    #[cfg(feature = "bplist")]
+    #[test]
+    fn convert_dir() {
+        use crate::FormatOptions;
+
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        let (src, dst) = (dir.join("src"), dir.join("dst"));
+        std::fs::create_dir_all(src.join("sub")).unwrap();
+
+        let mut playlist = Playlist::new("test playlist".to_owned(), "me".to_owned());
+        playlist.maps.push(Beatmap::new_key(0x83a));
+        let mut file = std::fs::File::create(src.join("a.blist")).unwrap();
+        playlist.clone().write(&mut file).unwrap();
+        let file = std::fs::File::create(src.join("sub/b.bplist")).unwrap();
+        playlist.write_bplist(file).unwrap();
+        std::fs::write(src.join("sub/c.json"), "not a playlist").unwrap();
+        std::fs::write(src.join("readme.txt"), "ignored").unwrap();
+
+        let options = FormatOptions {
+            threads: 2,
+            ..Default::default()
+        };
+        let report = crate::convert_dir(&src, &dst, &options).unwrap();
+        assert_eq!(
+            report.converted,
+            [(src.join("a.blist"), dst.join("a.bplist"))]
+        );
+        assert!(report.is_complete());
+
+        let options = FormatOptions {
+            recursive: true,
+            ..options
+        };
+        let report = crate::convert_dir(&src, &dst, &options).unwrap();
+        assert_eq!(report.converted.len(), 2);
+        assert_eq!(report.failed.len(), 1);
+        assert_eq!(report.failed[0].0, src.join("sub/c.json"));
+
+        let file = std::fs::File::open(dst.join("a.bplist")).unwrap();
+        let a = Playlist::read_bplist(file).unwrap();
+        let file = std::fs::File::open(dst.join("sub/b.blist")).unwrap();
+        let b = Playlist::read(file, true).unwrap();
+        assert_eq!(a.maps[0].key, Some(0x83a));
+        assert_eq!(a.maps, b.maps);
+
+        // In place, x.blist and x.bplist would overwrite each other while being read, and both
+        // y.bplist and y.json would be written to y.blist
+        let same = dir.join("same");
+        std::fs::create_dir_all(&same).unwrap();
+        for name in ["x.blist", "z.blist"] {
+            let file = std::fs::File::create(same.join(name)).unwrap();
+            playlist.write(file).unwrap();
+        }
+        for name in ["x.bplist", "y.bplist", "y.json"] {
+            let file = std::fs::File::create(same.join(name)).unwrap();
+            playlist.write_bplist(file).unwrap();
+        }
+        let report = crate::convert_dir(&same, &same, &options).unwrap();
+        assert_eq!(
+            report.converted,
+            [(same.join("z.blist"), same.join("z.bplist"))]
+        );
+        let failed: Vec<_> = report
+            .failed
+            .iter()
+            .map(|(path, e)| (path.file_name().unwrap(), e.kind()))
+            .collect();
+        let collision = crate::error::ErrorKind::ConvertCollision;
+        assert_eq!(
+            failed,
+            [
+                ("x.blist".as_ref(), collision),
+                ("x.bplist".as_ref(), collision),
+                ("y.bplist".as_ref(), collision),
+                ("y.json".as_ref(), collision)
+            ]
+        );
+    }
+
+    #[cfg(feature = "beatsaver")]
+    #[test]
+    fn beatsaver_resolver() {
+        use crate::{BeatSaverResolver, HttpDownloader, HttpResponse, Resolution, Resolver};
+        use std::io::Cursor;
+
+        let client = |url: &str, _: u64| {
+            let json = r#"{
+                "id": "2112",
+                "metadata": {
+                    "songName": "song",
+                    "songAuthorName": "artist",
+                    "levelAuthorName": "mapper"
+                },
+                "versions": [{
+                    "hash": "0404040404040404040404040404040404040404",
+                    "diffs": [
+                        { "difficulty": "Expert", "characteristic": "Standard" },
+                        { "difficulty": "Expert", "characteristic": "OneSaber" },
+                        { "difficulty": "ExpertPlus", "characteristic": "Standard" }
+                    ]
+                }]
+            }"#;
+            Ok(match url {
+                "http://beatsaver.test/maps/id/2112" => HttpResponse::new(200, Cursor::new(json)),
+                _ => HttpResponse::new(404, std::io::empty()),
+            })
+        };
+        let resolver = BeatSaverResolver::new(HttpDownloader::new(client))
+            .with_base_url("http://beatsaver.test/".to_owned());
+
+        match resolver.resolve(&Beatmap::new_key(0x2112)) {
+            Resolution::Found(info) => {
+                assert_eq!(info.key, Some(0x2112));
+                assert_eq!(info.hash, Some([4; 20].into()));
+                assert_eq!(info.name.as_deref(), Some("song"));
+                assert_eq!(info.mapper.as_deref(), Some("mapper"));
+                assert_eq!(info.difficulties, ["Expert", "ExpertPlus"]);
+            }
+            r => panic!("{:?}", r),
+        }
+        assert_eq!(
+            resolver.resolve(&Beatmap::new_hash([5; 20].into())),
+            Resolution::NotFound
+        );
+        assert_eq!(
+            resolver.resolve(&Beatmap::new_level_id("custom_level_x".to_owned())),
+            Resolution::Unknown
+        );
+    }
+
+    #[cfg(feature = "songcore")]
+    #[test]
+    fn song_cache() {
+        use crate::SongCache;
+
+        let data = format!(
+            r#"{{
+                "C:\\Beat Saber\\Beat Saber_Data\\CustomLevels\\b": {{
+                    "directoryHash": -42,
+                    "songHash": "{0}"
+                }},
+                "C:\\Beat Saber\\Beat Saber_Data\\CustomLevels\\a": {{
+                    "directoryHash": 7,
+                    "songHash": "{0}"
+                }},
+                "C:\\Beat Saber\\Beat Saber_Data\\CustomWIPLevels\\c": {{
+                    "directoryHash": 1,
+                    "songHash": "not a hash"
+                }}
+            }}"#,
+            "AB".repeat(20)
+        );
+        let cache = SongCache::read(data.as_bytes()).unwrap();
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.songs[0].directory_hash, 7);
+        assert!(cache.songs[0].path.to_str().unwrap().ends_with("\\a"));
+        assert_eq!(cache.skipped.len(), 1);
+
+        let playlist = cache.to_playlist("installed".to_owned(), "me".to_owned());
+        assert_eq!(playlist.maps.len(), 1);
+        assert_eq!(playlist.maps[0].hash, Some([0xab; 20].into()));
+
+        assert!(SongCache::path(std::path::Path::new("game"))
+            .ends_with("UserData/SongCore/SongHashData.dat"));
+    }
+
+    #[cfg(feature = "image")]
+    #[test]
+    fn recompress_cover() {
+        let noise = image::RgbImage::from_fn(1024, 1024, |x, y| {
+            let n = (x.wrapping_mul(2654435761) ^ y.wrapping_mul(40503)) >> 7;
+            image::Rgb([n as u8, (n >> 8) as u8, (n >> 16) as u8])
+        });
+        let mut cover = Vec::new();
+        image::DynamicImage::ImageRgb8(noise)
+            .write_to(
+                &mut std::io::Cursor::new(&mut cover),
+                image::ImageOutputFormat::Png,
+            )
+            .unwrap();
+
+        let mut playlist = Playlist::new("test playlist".to_owned(), "me".to_owned());
+        playlist.cover = Some(cover.into());
+
+        let options = crate::WriteOptions {
+            cover: Some(Default::default()),
+            ..Default::default()
+        };
+        let report = playlist
+            .write_with_options(&mut Vec::new(), &options)
+            .unwrap();
+        let written = report.cover_recompression.unwrap();
+        assert_eq!(written.original, playlist.cover.as_ref().unwrap().len());
+        assert_eq!(written.recompressed, report.cover);
+        assert!(written.recompressed < written.original);
+        let report = playlist.write_with_options(&mut Vec::new(), &Default::default());
+        assert_eq!(report.unwrap().cover_recompression, None);
+
+        let recompression = playlist
+            .recompress_cover(&Default::default())
+            .unwrap()
+            .unwrap();
+        assert!(recompression.recompressed < recompression.original);
+
+        let recompressed = image::load_from_memory(playlist.cover.as_deref().unwrap()).unwrap();
+        assert_eq!((recompressed.width(), recompressed.height()), (512, 512));
+        assert_eq!(
+            playlist.recompress_cover(&Default::default()).unwrap(),
+            None
+        );
+    }
+
+    #[cfg(feature = "zip")]
+    fn build_zip(entries: &[(&str, &[u8])], method: zip::CompressionMethod) -> Vec<u8> {
+        use std::io::Write;
+
+        let options = zip::write::FileOptions::default().compression_method(method);
+        let mut zip = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+        for (name, data) in entries {
+            zip.start_file(*name, options).unwrap();
+            zip.write_all(data).unwrap();
+        }
+        zip.finish().unwrap().into_inner()
+    }
+
+    #[cfg(feature = "zip")]
+    #[test]
+    fn extract_zips() {
+        let zip = build_zip(
+            &[
+                ("Info.dat", br#"{"_songName": "Song: Remix"}"#),
+                ("../escape.dat", b"nope"),
+            ],
+            zip::CompressionMethod::Deflated,
+        );
+
+        let mut playlist = Playlist::new("test playlist".to_owned(), "me".to_owned());
+        let mut map = Beatmap::new_zip(zip.clone());
+        map.key = Some(0x1a2b);
+        playlist.maps.push(map);
+        playlist.maps.push(Beatmap::new_zip(zip));
+        playlist.maps.push(Beatmap::new_zip(b"not a zip".to_vec()));
+        playlist.maps.push(Beatmap::new_key(1));
+
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        let report = playlist
+            .extract_zips(dir, crate::NamingPolicy::Key)
+            .unwrap();
+        let names: Vec<_> = report
+            .extracted
+            .iter()
+            .map(|(i, p)| (*i, p.file_name().unwrap().to_str().unwrap().to_owned()))
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                (0, "1a2b (Song_ Remix)".to_owned()),
+                (1, "Song_ Remix".to_owned()),
+            ]
+        );
+        assert!(report.extracted[0].1.join("Info.dat").is_file());
+        assert!(!dir.join("escape.dat").exists());
+        assert_eq!(report.failed.len(), 1);
+        assert_eq!(report.failed[0].0, 2);
+
+        let again = playlist
+            .extract_zips(dir, crate::NamingPolicy::SongName)
+            .unwrap();
+        assert!(again.extracted[0].1.ends_with("Song_ Remix (2)"));
+
+        let cache = crate::ZipCache::new(dir.join("cache")).unwrap();
+        let mut map = Beatmap::new_hash([7; 20].into());
+        cache
+            .insert(
+                &[7; 20].into(),
+                playlist.maps[1].zip.as_ref().unwrap().as_bytes().unwrap(),
+            )
+            .unwrap();
+        playlist.maps.truncate(1);
+        playlist.maps.push(map.clone());
+        map.hash = Some([8; 20].into());
+        playlist.maps.push(map);
+        let cached = playlist
+            .extract_zips_cached(dir.join("cached"), crate::NamingPolicy::Hash, &cache)
+            .unwrap();
+        assert_eq!(cached.extracted.len(), 2);
+        assert!(cached.extracted[1]
+            .1
+            .ends_with(format!("{} (Song_ Remix)", "07".repeat(20))));
+        assert_eq!(cached.failed.len(), 1);
+        assert_eq!(cached.failed[0].0, 2);
+    }
+
+    #[cfg(feature = "zip")]
+    #[test]
+    fn from_level_dir() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        std::fs::write(
+            dir.join("Info.dat"),
+            br#"{
+                "_songName": "Song",
+                "_songFilename": "song.egg",
+                "_difficultyBeatmapSets": [
+                    {"_difficultyBeatmaps": [{"_beatmapFilename": "Expert.dat"}]}
+                ]
+            }"#,
+        )
+        .unwrap();
+        std::fs::write(dir.join("song.egg"), b"audio").unwrap();
+        std::fs::write(dir.join("Expert.dat"), b"{}").unwrap();
+        std::fs::write(dir.join("autosave.dat"), b"{}").unwrap();
+
+        let map = Beatmap::from_level_dir(dir).unwrap();
+        assert_eq!(map.ty, crate::BeatmapType::Zip);
+        let mut archive = zip::ZipArchive::new(std::io::Cursor::new(
+            map.zip.as_ref().and_then(|z| z.as_bytes()).unwrap(),
+        ))
+        .unwrap();
+        let mut names: Vec<_> = archive.file_names().collect();
+        names.sort_unstable();
+        assert_eq!(names, vec!["Expert.dat", "Info.dat", "song.egg"]);
+        assert!(archive.by_name("song.egg").is_ok());
+
+        std::fs::write(dir.join("Info.dat"), br#"{"_songFilename": "../song.egg"}"#).unwrap();
+        assert_eq!(
+            Beatmap::from_level_dir(dir).unwrap_err().kind(),
+            crate::error::ErrorKind::InvalidLevelFile
+        );
+    }
+
+    #[cfg(feature = "zip")]
+    #[test]
+    fn recompress_zips() {
+        let zip = build_zip(
+            &[
+                ("Expert.dat", &br#"{"_notes": []}"#.repeat(256)),
+                ("song.egg", b"audio"),
+            ],
+            zip::CompressionMethod::Stored,
+        );
+
+        let mut playlist = Playlist::new("test playlist".to_owned(), "me".to_owned());
+        playlist.maps.push(Beatmap::new_zip(zip.clone()));
+        playlist.maps.push(Beatmap::new_key(1));
+        let recompression = playlist
+            .recompress_zips(flate2::Compression::best())
+            .unwrap();
+        assert_eq!(recompression.maps, 1);
+        assert_eq!(recompression.original, zip.len());
+        assert!(recompression.recompressed < recompression.original);
+
+        let mut archive = zip::ZipArchive::new(std::io::Cursor::new(
+            playlist.maps[0]
+                .zip
+                .as_ref()
+                .and_then(|z| z.as_bytes())
+                .unwrap(),
+        ))
+        .unwrap();
+        assert_eq!(
+            archive.by_name("song.egg").unwrap().compression(),
+            zip::CompressionMethod::Stored
+        );
+        let mut notes = Vec::new();
+        std::io::Read::read_to_end(&mut archive.by_name("Expert.dat").unwrap(), &mut notes)
+            .unwrap();
+        assert_eq!(notes, br#"{"_notes": []}"#.repeat(256));
+
+        let again = playlist
+            .recompress_zips(flate2::Compression::best())
+            .unwrap();
+        assert_eq!(again.maps, 0);
+
+        // An entry decompressing past its declared size is rejected rather than buffered
+        let mut lying = zip;
+        let central = lying.windows(4).rposition(|w| w == b"PK\x01\x02").unwrap();
+        lying[central + 24..central + 28].copy_from_slice(&2u32.to_le_bytes());
+        let mut playlist = Playlist::new("test playlist".to_owned(), "me".to_owned());
+        playlist.maps.push(Beatmap::new_zip(lying));
+        assert_eq!(
+            playlist
+                .recompress_zips(flate2::Compression::best())
+                .unwrap_err()
+                .kind(),
+            crate::error::ErrorKind::ZipEntryTooLarge
+        );
+    }
+
+    #[cfg(feature = "zip")]
+    #[test]
+    fn verify_zips() {
+        let mut zip = build_zip(&[("song.egg", b"audio")], zip::CompressionMethod::Stored);
+        let pos = zip.windows(5).position(|w| w == b"audio").unwrap();
+        zip[pos] ^= 0xff;
+
+        let mut playlist = Playlist::new("test playlist".to_owned(), "me".to_owned());
+        playlist.maps.push(Beatmap::new_key(1));
+        playlist.maps.push(Beatmap::new_zip(zip));
+        let mut buffer = Vec::new();
+        playlist.write(&mut buffer).unwrap();
+
+        assert!(Playlist::read(buffer.as_slice(), false).is_ok());
+        let options = ReadOptions {
+            verify_zips: true,
+            ..Default::default()
+        };
+        match Playlist::read_with_options(buffer.as_slice(), &options) {
+            Err(crate::error::Error::CorruptZip { map: 1, .. }) => (),
+            r => panic!("expected a corrupt zip, got {:?}", r.map(|_| ())),
+        }
+    }
+
+    #[cfg(feature = "stream")]
+    #[test]
+    fn read_stream() {
+        use std::{
+            future::Future,
+            task::{Context, Poll, Waker},
+        };
+
+        fn poll<F: Future>(future: F) -> F::Output {
+            let mut future = std::pin::pin!(future);
+            match future
+                .as_mut()
+                .poll(&mut Context::from_waker(Waker::noop()))
+            {
+                Poll::Ready(output) => output,
+                Poll::Pending => panic!("stream shouldn't be pending"),
+            }
+        }
+        fn chunks(
+            buffer: &[u8],
+        ) -> impl futures_util::Stream<Item = std::io::Result<bytes::Bytes>> {
+            let chunks: Vec<_> = buffer
+                .chunks(7)
+                .map(|c| Ok(bytes::Bytes::copy_from_slice(c)))
+                .collect();
+            futures_util::stream::iter(chunks)
+        }
+
+        let mut playlist = Playlist::new("test playlist".to_owned(), "me".to_owned());
+        playlist.maps.push(Beatmap::new_key(1));
+        let mut buffer = Vec::new();
+        playlist.write(&mut buffer).unwrap();
+        let options = ReadOptions::default();
+
+        let read = poll(Playlist::read_stream(chunks(&buffer), &options, 4096)).unwrap();
+        assert_eq!(read, Playlist::read(buffer.as_slice(), false).unwrap());
+        assert_eq!(
+            poll(Playlist::read_stream(chunks(&buffer), &options, 16))
+                .unwrap_err()
+                .kind(),
+            crate::error::ErrorKind::StreamTooLarge
+        );
+
+        // Nothing past the bad magic number is ever pulled from the stream
+        let bogus = futures_util::StreamExt::chain(
+            chunks(b"NotBlist"),
+            futures_util::stream::iter(vec![Err(std::io::Error::other("stream kept going"))]),
+        );
+        assert_eq!(
+            poll(Playlist::read_stream(bogus, &options, 4096))
+                .unwrap_err()
+                .kind(),
+            crate::error::ErrorKind::InvalidMagicNumber
+        );
+    }
+
+    #[cfg(feature = "sqlx")]
+    #[test]
+    fn sqlx_roundtrip() {
+        use sqlx::{
+            any::{AnyArguments, AnyValue},
+            encode::IsNull,
+            Decode, Encode, Value,
+        };
+
+        let mut old = Playlist::new("test playlist".to_owned(), "me".to_owned());
+        let mut map = Beatmap::new_key(2112);
+        map.date_added = Utc.timestamp_opt(1_600_000_000, 0).unwrap();
+        old.maps.push(map);
+
+        let mut arguments = AnyArguments::default();
+        assert!(matches!(
+            Encode::<sqlx::Any>::encode_by_ref(&old, &mut arguments.values),
+            Ok(IsNull::No)
+        ));
+        let value = AnyValue {
+            kind: arguments.values.0.pop().unwrap(),
+        };
+        let new: Playlist = Decode::<sqlx::Any>::decode(value.as_ref()).unwrap();
+        assert_eq!(old, new);
+    }
+
+    #[cfg(feature = "diesel")]
+    #[test]
+    fn diesel_roundtrip() {
+        use diesel::{
+            deserialize::FromSql,
+            pg::{Pg, PgMetadataLookup, PgTypeMetadata, PgValue},
+            query_builder::bind_collector::{BindCollector, RawBytesBindCollector},
+            sql_types::Binary,
+        };
+        use std::num::NonZeroU32;
+
+        // Binary has a static OID, so nothing is ever looked up
+        struct NoLookup;
+        impl PgMetadataLookup for NoLookup {
+            fn lookup_type(&mut self, _: &str, _: Option<&str>) -> PgTypeMetadata {
+                unreachable!()
+            }
+        }
+
+        let mut old = Playlist::new("test playlist".to_owned(), "me".to_owned());
+        let mut map = Beatmap::new_key(2112);
+        map.date_added = Utc.timestamp_opt(1_600_000_000, 0).unwrap();
+        old.maps.push(map);
+
+        let mut collector = RawBytesBindCollector::<Pg>::new();
+        collector
+            .push_bound_value::<Binary, _>(&old, &mut NoLookup)
+            .unwrap();
+        let bytes = collector.binds.pop().unwrap().unwrap();
+        let oid = NonZeroU32::new(17).unwrap();
+        let new: Playlist = FromSql::<Binary, Pg>::from_sql(PgValue::new(&bytes, &oid)).unwrap();
+        assert_eq!(old, new);
+    }
+
+    #[cfg(feature = "arbitrary")]
+    #[test]
+    fn arbitrary() {
+        use arbitrary::{Arbitrary, Unstructured};
+
+        let data: Vec<u8> = (0..4096u32)
+            .map(|i| (i.wrapping_mul(2654435761) >> 13) as u8)
+            .collect();
+        let mut u = Unstructured::new(&data);
+        while !u.is_empty() {
+            let playlist = Playlist::arbitrary(&mut u).unwrap();
+            assert!(playlist.roundtrips().unwrap());
+        }
+    }
+
+    #[cfg(feature = "proptest")]
+    proptest::proptest! {
+        #[test]
+        fn strategy(playlist in crate::strategy::playlist(0..8)) {
+            let mut buffer = Vec::new();
+            playlist.clone().write(&mut buffer).unwrap();
+            proptest::prop_assert_eq!(Playlist::read(buffer.as_slice(), true).unwrap(), playlist);
+        }
     }
 }
@@ -0,0 +1,285 @@
+use crate::Playlist;
+#[cfg(feature = "gzip")]
+use crate::{
+    error::Error, file::write_atomic, history, options::exceeds, ReadOptions, Result, Snapshot,
+    MAGIC_NUMBER_LEN, PREALLOCATION_LIMIT,
+};
+#[cfg(feature = "gzip")]
+use blister_format::MapEncoding;
+#[cfg(feature = "gzip")]
+use byteorder::{ReadBytesExt, WriteBytesExt, LE};
+#[cfg(feature = "gzip")]
+use flate2::{bufread::GzDecoder, write::GzEncoder, Compression};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::HashMap,
+    slice,
+    sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard},
+};
+#[cfg(feature = "gzip")]
+use std::{
+    convert::TryInto,
+    io::{self, BufReader, Read, Write},
+    path::Path,
+};
+
+#[cfg(feature = "gzip")]
+const ARCHIVE_MAGIC_NUMBER: &[u8; MAGIC_NUMBER_LEN] = b"Blist.l1";
+#[cfg(feature = "gzip")]
+const NO_COVER: u32 = u32::MAX;
+
+type CoverDigest = [u8; 32];
+
+// Identical covers are stored once and shared between playlists
+#[derive(Debug, Clone, Default)]
+pub struct Library {
+    playlists: Vec<Playlist>,
+    // Counted here rather than through `Arc::strong_count`, which clones held outside the library
+    // would inflate
+    covers: HashMap<CoverDigest, (Arc<[u8]>, usize)>,
+}
+
+// Cheap to clone handle for concurrent use, readers are only blocked while a writer holds the lock
+#[derive(Debug, Clone, Default)]
+pub struct SharedLibrary(Arc<RwLock<Library>>);
+
+impl SharedLibrary {
+    #[inline]
+    pub fn new(library: Library) -> Self {
+        Self(Arc::new(RwLock::new(library)))
+    }
+
+    // A panicking writer can't leave the library half updated in a way later readers would trip
+    // on, so poisoning is ignored rather than spreading the panic to every other thread
+    #[inline]
+    pub fn read(&self) -> RwLockReadGuard<'_, Library> {
+        self.0.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    #[inline]
+    pub fn write(&self) -> RwLockWriteGuard<'_, Library> {
+        self.0.write().unwrap_or_else(|e| e.into_inner())
+    }
+
+    // Refreshes can build the new library without the lock and only hold it for the swap
+    #[inline]
+    pub fn replace(&self, library: Library) -> Library {
+        std::mem::replace(&mut *self.write(), library)
+    }
+}
+
+impl From<Library> for SharedLibrary {
+    #[inline]
+    fn from(library: Library) -> Self {
+        Self::new(library)
+    }
+}
+
+impl Library {
+    #[inline]
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn insert(&mut self, mut playlist: Playlist) -> usize {
+        if let Some(cover) = playlist.cover.take() {
+            playlist.cover = Some(self.share_cover(cover));
+        }
+        self.playlists.push(playlist);
+        self.playlists.len() - 1
+    }
+
+    pub fn remove(&mut self, index: usize) -> Option<Playlist> {
+        if index >= self.playlists.len() {
+            return None;
+        }
+        let playlist = self.playlists.remove(index);
+        self.covers.retain(|_, (c, count)| {
+            if is_cover_of(c, &playlist) {
+                *count -= 1;
+            }
+            *count > 0
+        });
+        Some(playlist)
+    }
+
+    #[inline]
+    pub fn get(&self, index: usize) -> Option<&Playlist> {
+        self.playlists.get(index)
+    }
+
+    #[inline]
+    pub fn playlists(&self) -> slice::Iter<'_, Playlist> {
+        self.playlists.iter()
+    }
+
+    // Tags are matched case-insensitively
+    pub fn tagged<'a>(&'a self, tag: &'a str) -> impl Iterator<Item = (usize, &'a Playlist)> + 'a {
+        self.playlists
+            .iter()
+            .enumerate()
+            .filter(move |(_, p)| p.has_tag(tag))
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.playlists.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.playlists.is_empty()
+    }
+
+    #[inline]
+    pub fn cover_count(&self) -> usize {
+        self.covers.len()
+    }
+
+    fn share_cover(&mut self, cover: Arc<[u8]>) -> Arc<[u8]> {
+        let digest: CoverDigest = Sha256::digest(&cover).into();
+        let (cover, count) = self.covers.entry(digest).or_insert((cover, 0));
+        *count += 1;
+        cover.clone()
+    }
+}
+
+// Archives are gzipped like playlists
+#[cfg(feature = "gzip")]
+impl Library {
+    pub fn read_archive<R>(mut reader: R, options: &ReadOptions) -> Result<Self>
+    where
+        R: Read,
+    {
+        let mut magic_number = [0; MAGIC_NUMBER_LEN];
+        reader.read_exact(&mut magic_number)?;
+        if &magic_number != ARCHIVE_MAGIC_NUMBER {
+            return Err(Error::InvalidArchiveMagicNumber(magic_number));
+        }
+        let mut decoder = GzDecoder::new(BufReader::new(reader));
+
+        let cover_count = decoder.read_u32::<LE>()? as usize;
+        let mut covers = Vec::with_capacity(cover_count.min(PREALLOCATION_LIMIT));
+        for _ in 0..cover_count {
+            let len = decoder.read_u32::<LE>()? as usize;
+            if exceeds(options.max_cover_len, len) {
+                return Err(Error::CoverTooLarge(len));
+            }
+            let mut cover = Vec::with_capacity(len.min(PREALLOCATION_LIMIT));
+            (&mut decoder).take(len as u64).read_to_end(&mut cover)?;
+            if cover.len() != len {
+                return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+            }
+            covers.push(Arc::<[u8]>::from(cover));
+        }
+
+        let playlist_count = decoder.read_u32::<LE>()? as usize;
+        let mut library = Self::new();
+        library
+            .playlists
+            .reserve(playlist_count.min(PREALLOCATION_LIMIT));
+        for _ in 0..playlist_count {
+            let cover = match decoder.read_u32::<LE>()? {
+                NO_COVER => None,
+                i => match covers.get(i as usize) {
+                    Some(cover) => Some(cover.clone()),
+                    None => return Err(Error::InvalidArchiveCover(i)),
+                },
+            };
+            let mut playlist = Playlist::read_decoded(
+                &mut decoder,
+                options,
+                MapEncoding::Length,
+                &mut Vec::new(),
+            )?;
+            playlist.cover = cover;
+            library.insert(playlist);
+        }
+        Ok(library)
+    }
+
+    // Replaces the archive atomically, holding its lock while writing when the `lock` feature is
+    // enabled
+    pub fn save_archive<P>(&self, path: P) -> Result<()>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        #[cfg(feature = "lock")]
+        let _lock = crate::FileLock::acquire(path)?;
+        write_atomic(path, |w| self.write_archive(w))
+    }
+
+    // Like `save_archive`, but keeps the last `keep` versions of the archive in its history folder
+    pub fn save_archive_with_history<P>(&self, path: P, keep: usize) -> Result<()>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        #[cfg(feature = "lock")]
+        let _lock = crate::FileLock::acquire(path)?;
+        history::snapshot(path, keep)?;
+        write_atomic(path, |w| self.write_archive(w))
+    }
+
+    // Oldest first
+    #[inline]
+    pub fn archive_history<P>(path: P) -> Result<Vec<Snapshot>>
+    where
+        P: AsRef<Path>,
+    {
+        history::snapshots(path.as_ref())
+    }
+
+    // The archive itself is left alone until the restored library is saved over it
+    pub fn restore_archive<P>(path: P, version: u64, options: &ReadOptions) -> Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        let snapshot = history::find(path.as_ref(), version)?;
+        Self::read_archive(BufReader::new(std::fs::File::open(snapshot.path)?), options)
+    }
+
+    pub fn write_archive<W>(&self, mut writer: W) -> Result<()>
+    where
+        W: Write,
+    {
+        writer.write_all(ARCHIVE_MAGIC_NUMBER)?;
+        let mut encoder = GzEncoder::new(writer, Compression::default());
+
+        // Shared covers are the same allocation so pointers are enough to tell them apart
+        let mut covers = Vec::new();
+        let mut indices = HashMap::new();
+        for cover in self.playlists.iter().filter_map(|p| p.cover.as_ref()) {
+            indices.entry(Arc::as_ptr(cover)).or_insert_with(|| {
+                covers.push(cover);
+                covers.len() - 1
+            });
+        }
+
+        encoder.write_u32::<LE>(covers.len().try_into()?)?;
+        for cover in &covers {
+            encoder.write_u32::<LE>(cover.len().try_into()?)?;
+            encoder.write_all(cover)?;
+        }
+
+        encoder.write_u32::<LE>(self.playlists.len().try_into()?)?;
+        for playlist in &self.playlists {
+            let index = match &playlist.cover {
+                Some(cover) => indices[&Arc::as_ptr(cover)].try_into()?,
+                None => NO_COVER,
+            };
+            encoder.write_u32::<LE>(index)?;
+
+            playlist.write_maps(&mut encoder, None, false, MapEncoding::Length)?;
+        }
+
+        encoder.finish()?;
+        Ok(())
+    }
+}
+
+#[inline]
+fn is_cover_of(cover: &Arc<[u8]>, playlist: &Playlist) -> bool {
+    matches!(&playlist.cover, Some(c) if Arc::ptr_eq(c, cover))
+}
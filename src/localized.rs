@@ -0,0 +1,87 @@
+use crate::{
+    builder::check_len,
+    error::Error,
+    nested::{decode_str_refs, encode_strings},
+    Playlist, Result, LONG_STRING_LEN, SHORT_STRING_LEN,
+};
+use std::{borrow::Cow, collections::BTreeMap};
+
+pub(crate) const LOCALIZED_TITLES_KEY: u32 = 4;
+pub(crate) const LOCALIZED_DESCRIPTIONS_KEY: u32 = 5;
+
+// Keyed by lowercase language tag
+pub type LocalizedStrings = BTreeMap<String, String>;
+
+impl Playlist {
+    // Falls back to the primary language subtag, then to the default title
+    pub fn title_localized(&self, language: &str) -> Cow<'_, str> {
+        Cow::Borrowed(lookup(&self.localized_titles, language).unwrap_or(&self.title))
+    }
+
+    pub fn description_localized(&self, language: &str) -> Option<Cow<'_, str>> {
+        lookup(&self.localized_descriptions, language)
+            .or(self.description.as_deref())
+            .map(Cow::Borrowed)
+    }
+
+    pub fn set_title_localized<L, S>(&mut self, language: L, title: S) -> Result<()>
+    where
+        L: Into<String>,
+        S: Into<String>,
+    {
+        let title = title.into();
+        check_len("localized title", &title, SHORT_STRING_LEN)?;
+        insert(&mut self.localized_titles, language.into(), title)
+    }
+
+    pub fn set_description_localized<L, S>(&mut self, language: L, description: S) -> Result<()>
+    where
+        L: Into<String>,
+        S: Into<String>,
+    {
+        let description = description.into();
+        check_len("localized description", &description, LONG_STRING_LEN)?;
+        insert(
+            &mut self.localized_descriptions,
+            language.into(),
+            description,
+        )
+    }
+}
+
+fn lookup<'a>(localized: &'a LocalizedStrings, language: &str) -> Option<&'a str> {
+    let language = language.to_ascii_lowercase();
+    let primary = language.split('-').next().unwrap_or_default();
+    localized
+        .get(&language)
+        .or_else(|| localized.get(primary))
+        .map(String::as_str)
+}
+
+fn insert(localized: &mut LocalizedStrings, language: String, s: String) -> Result<()> {
+    check_len("language tag", &language, SHORT_STRING_LEN)?;
+    localized.insert(language.to_ascii_lowercase(), s);
+    Ok(())
+}
+
+// Stored as a string list alternating between language tags and strings
+#[inline]
+pub(crate) fn encode_localized(localized: &LocalizedStrings) -> Result<Vec<u8>> {
+    encode_strings(localized.iter().flat_map(|(l, s)| [l.as_str(), s.as_str()]))
+}
+
+pub(crate) fn decode_localized_refs(bytes: &[u8]) -> Result<Vec<(&str, &str)>> {
+    let strings = decode_str_refs(bytes)?;
+    if strings.len() % 2 != 0 {
+        return Err(Error::InvalidStringList);
+    }
+    Ok(strings.chunks(2).map(|p| (p[0], p[1])).collect())
+}
+
+#[inline]
+pub(crate) fn decode_localized(bytes: &[u8]) -> Result<LocalizedStrings> {
+    Ok(decode_localized_refs(bytes)?
+        .into_iter()
+        .map(|(l, s)| (l.to_owned(), s.to_owned()))
+        .collect())
+}
@@ -0,0 +1,399 @@
+use crate::{
+    beatmap::ZIP_KEY,
+    error::Error,
+    file::write_atomic,
+    magic_number,
+    options::exceeds,
+    playlist::{field_len, COVER_KEY},
+    sniff, GzipHeader, ReadOptions, Result, Version, WriteOptions, MAGIC_NUMBER_LEN,
+    MAGIC_NUMBER_V1, MAGIC_NUMBER_V2,
+};
+use blister_format::{error::Error as FormatError, Map, MapEncoding};
+use byteorder::{ReadBytesExt, WriteBytesExt, LE};
+use flate2::{bufread::GzDecoder, Compression, GzBuilder, GzHeader};
+use std::{
+    fs,
+    io::{self, BufRead, BufReader, Read, Write},
+    path::Path,
+};
+
+// Length of the fixed part of a gzip header, which ends with the extra flags and OS bytes
+const GZIP_HEADER_LEN: usize = 10;
+
+// v1 and v2 are the layouts v3 grew out of. Both store the playlist map followed by every beatmap
+// map until the end of the data, v1 as is and v2 gzipped. v3 counts the beatmaps instead.
+//
+// Every hop only re-encodes the maps, so custom data and keys this crate doesn't know about are
+// carried over as stored, and so are the gzip header, its compression level where it tells and
+// anything following the gzip stream. Trailers such as HMAC tags are copied as they are, so ones
+// covering the whole file have to be computed again.
+#[derive(Debug, Clone)]
+pub struct MigrateOptions {
+    // v3 by default, which every reader understands. Going on to v4 and v5 changes how maps are
+    // framed and is left for the caller to ask for.
+    pub target: Version,
+    // Limits checked while the maps are read
+    pub read: ReadOptions,
+}
+
+impl Default for MigrateOptions {
+    #[inline]
+    fn default() -> Self {
+        Self {
+            target: Version::V3,
+            read: Default::default(),
+        }
+    }
+}
+
+pub fn v1_to_v2<R, W>(reader: R, mut writer: W, options: &ReadOptions) -> Result<()>
+where
+    R: Read,
+    W: Write,
+{
+    let mut reader = BufReader::new(reader);
+    check_magic_number(&mut reader, MAGIC_NUMBER_V1)?;
+    let limits = Limits::new(options, MapEncoding::Length);
+
+    let mut map = Map::new();
+    limits.read_header(&mut map, &mut reader)?;
+    writer.write_all(MAGIC_NUMBER_V2)?;
+    // There is no previous level to keep, so this is the one playlists are written with
+    let mut encoder = GzipHeader::default()
+        .builder()?
+        .write(writer, WriteOptions::default().compression);
+    map.write_with_encoding(&mut encoder, MapEncoding::Length)?;
+
+    let mut count = 0;
+    while !reader.fill_buf()?.is_empty() {
+        limits.read_map(&mut map, &mut reader, count)?;
+        map.write_with_encoding(&mut encoder, MapEncoding::Length)?;
+        count += 1;
+    }
+
+    encoder.finish()?;
+    Ok(())
+}
+
+pub fn v2_to_v3<R, W>(reader: R, mut writer: W, options: &ReadOptions) -> Result<()>
+where
+    R: Read,
+    W: Write,
+{
+    let mut source = Source::open(reader, MAGIC_NUMBER_V2)?;
+    let limits = Limits::new(options, MapEncoding::Length);
+    let mut decoder = BufReader::new(&mut source.decoder);
+
+    let mut map = Map::new();
+    limits.read_header(&mut map, &mut decoder)?;
+    writer.write_all(magic_number(MapEncoding::Length))?;
+    let mut encoder = builder(decoder.get_ref().header()).write(writer, source.level);
+    map.write_with_encoding(&mut encoder, MapEncoding::Length)?;
+
+    // The count goes before the beatmaps, which are only counted once they've all been read
+    let mut maps = Vec::new();
+    let mut count = 0;
+    while !decoder.fill_buf()?.is_empty() {
+        limits.read_map(&mut map, &mut decoder, count)?;
+        map.write_with_encoding(&mut maps, MapEncoding::Length)?;
+        count += 1;
+    }
+    encoder.write_u32::<LE>(count as u32)?;
+    encoder.write_all(&maps)?;
+
+    // Nothing is left buffered, the beatmaps were read up to the end of the body
+    drop(decoder);
+    source.finish(encoder.finish()?)
+}
+
+#[inline]
+pub fn v3_to_v4<R, W>(reader: R, writer: W, options: &ReadOptions) -> Result<()>
+where
+    R: Read,
+    W: Write,
+{
+    hop(
+        reader,
+        writer,
+        options,
+        MapEncoding::Length,
+        MapEncoding::Counted,
+    )
+}
+
+#[inline]
+pub fn v4_to_v5<R, W>(reader: R, writer: W, options: &ReadOptions) -> Result<()>
+where
+    R: Read,
+    W: Write,
+{
+    hop(
+        reader,
+        writer,
+        options,
+        MapEncoding::Counted,
+        MapEncoding::Checksummed,
+    )
+}
+
+// Upgrades v1 and v2 data to v3 and returns the version it started at. Later versions are copied
+// as they are.
+#[inline]
+pub fn migrate<R, W>(reader: R, writer: W) -> Result<Version>
+where
+    R: Read,
+    W: Write,
+{
+    migrate_with_options(reader, writer, &Default::default())
+}
+
+// Goes through every hop up to the target version and returns the version the data started at.
+// Data already at or past the target is copied as it is, nothing is ever downgraded.
+pub fn migrate_with_options<R, W>(
+    mut reader: R,
+    mut writer: W,
+    options: &MigrateOptions,
+) -> Result<Version>
+where
+    R: Read,
+    W: Write,
+{
+    let mut number = [0; MAGIC_NUMBER_LEN];
+    reader.read_exact(&mut number)?;
+    let version = sniff(&number[..]).ok_or(Error::InvalidMagicNumber(number))?;
+    let from = step(version)?;
+    let to = step(options.target)?;
+    let mut reader = (&number[..]).chain(reader);
+    if from >= to {
+        io::copy(&mut reader, &mut writer)?;
+        return Ok(version);
+    }
+
+    // Every hop but the last one goes through memory
+    let mut data = Vec::new();
+    reader.read_to_end(&mut data)?;
+    for hop in &HOPS[from..to - 1] {
+        let mut next = Vec::new();
+        hop(data.as_slice(), &mut next, &options.read)?;
+        data = next;
+    }
+    HOPS[to - 1](data.as_slice(), &mut writer, &options.read)?;
+    Ok(version)
+}
+
+#[inline]
+pub fn migrate_file<P>(path: P) -> Result<Version>
+where
+    P: AsRef<Path>,
+{
+    migrate_file_with_options(path, &Default::default())
+}
+
+// Files already at or past the target version are left untouched
+pub fn migrate_file_with_options<P>(path: P, options: &MigrateOptions) -> Result<Version>
+where
+    P: AsRef<Path>,
+{
+    let path = path.as_ref();
+    let data = fs::read(path)?;
+    let version = sniff(data.as_slice()).ok_or_else(|| {
+        let mut number = [0; MAGIC_NUMBER_LEN];
+        let len = data.len().min(MAGIC_NUMBER_LEN);
+        number[..len].copy_from_slice(&data[..len]);
+        Error::InvalidMagicNumber(number)
+    })?;
+    if step(version)? >= step(options.target)? {
+        return Ok(version);
+    }
+
+    write_atomic(path, |w| {
+        migrate_with_options(data.as_slice(), w, options)?;
+        Ok(())
+    })?;
+    Ok(version)
+}
+
+type Hop = fn(&[u8], &mut dyn Write, &ReadOptions) -> Result<()>;
+
+// Indexed by the step a hop starts from
+const HOPS: [Hop; 4] = [
+    |r, w, o| v1_to_v2(r, w, o),
+    |r, w, o| v2_to_v3(r, w, o),
+    |r, w, o| v3_to_v4(r, w, o),
+    |r, w, o| v4_to_v5(r, w, o),
+];
+
+#[inline]
+fn step(version: Version) -> Result<usize> {
+    match version {
+        Version::V1 => Ok(0),
+        Version::V2 => Ok(1),
+        Version::V3 => Ok(2),
+        Version::V4 => Ok(3),
+        Version::V5 => Ok(4),
+        Version::Unsupported(v) => Err(Error::UnsupportedVersion(v)),
+    }
+}
+
+fn hop<R, W>(
+    reader: R,
+    mut writer: W,
+    options: &ReadOptions,
+    from: MapEncoding,
+    to: MapEncoding,
+) -> Result<()>
+where
+    R: Read,
+    W: Write,
+{
+    let mut source = Source::open(reader, magic_number(from))?;
+    let limits = Limits::new(options, from);
+
+    let mut map = Map::new();
+    limits.read_header(&mut map, &mut source.decoder)?;
+    writer.write_all(magic_number(to))?;
+    let mut encoder = builder(source.decoder.header()).write(writer, source.level);
+    map.write_with_encoding(&mut encoder, to)?;
+
+    let count = source.decoder.read_u32::<LE>()? as usize;
+    if exceeds(options.max_maps, count) {
+        return Err(Error::TooManyMaps(count));
+    }
+    encoder.write_u32::<LE>(count as u32)?;
+    for i in 0..count {
+        limits.read_map(&mut map, &mut source.decoder, i)?;
+        map.write_with_encoding(&mut encoder, to)?;
+    }
+    // Nothing reads past the beatmaps, but whatever is there is kept
+    io::copy(&mut source.decoder, &mut encoder)?;
+
+    source.finish(encoder.finish()?)
+}
+
+struct Limits {
+    header: blister_format::ReadOptions,
+    map: blister_format::ReadOptions,
+    max_maps: Option<usize>,
+}
+
+impl Limits {
+    // The same limits `Playlist::read_with_options` applies, values are never spilled since
+    // they're written right back
+    fn new(options: &ReadOptions, encoding: MapEncoding) -> Self {
+        let format = blister_format::ReadOptions {
+            encoding,
+            spill: None,
+            ..options.format()
+        };
+        Self {
+            header: blister_format::ReadOptions {
+                max_field_lens: field_len(COVER_KEY, options.max_cover_len),
+                ..format.clone()
+            },
+            map: blister_format::ReadOptions {
+                max_field_lens: field_len(ZIP_KEY, options.max_zip_len),
+                ..format
+            },
+            max_maps: options.max_maps,
+        }
+    }
+
+    #[inline]
+    fn read_header<R>(&self, map: &mut Map, reader: R) -> Result<()>
+    where
+        R: Read,
+    {
+        map.read_with_options(reader, &self.header, &mut Vec::new())
+            .map_err(|e| match e {
+                FormatError::FieldTooLarge { len, .. } => Error::CoverTooLarge(len),
+                e => e.into(),
+            })
+    }
+
+    // Also checks the beatmap count, for layouts that only know it once every map is read
+    fn read_map<R>(&self, map: &mut Map, reader: R, index: usize) -> Result<()>
+    where
+        R: Read,
+    {
+        if exceeds(self.max_maps, index + 1) {
+            return Err(Error::TooManyMaps(index + 1));
+        }
+        map.clear();
+        map.read_with_options(reader, &self.map, &mut Vec::new())
+            .map_err(|e| match e {
+                FormatError::FieldTooLarge { len, .. } => Error::ZipTooLarge(len),
+                e => e.into(),
+            })
+    }
+}
+
+// The gzipped body of a v2 or later file, along with what's needed to write it back alike
+struct Source<R> {
+    decoder: GzDecoder<BufReader<io::Chain<io::Cursor<[u8; GZIP_HEADER_LEN]>, R>>>,
+    level: Compression,
+}
+
+impl<R> Source<R>
+where
+    R: Read,
+{
+    fn open(mut reader: R, magic_number: &[u8; MAGIC_NUMBER_LEN]) -> Result<Self> {
+        check_magic_number(&mut reader, magic_number)?;
+        let mut header = [0; GZIP_HEADER_LEN];
+        reader.read_exact(&mut header)?;
+        // The extra flags only tell the fastest and slowest levels apart from the rest, which
+        // are all written back at the default one
+        let level = match header[8] {
+            2 => Compression::best(),
+            4 => Compression::fast(),
+            _ => Compression::default(),
+        };
+        let reader = BufReader::new(io::Cursor::new(header).chain(reader));
+        Ok(Self {
+            decoder: GzDecoder::new(reader),
+            level,
+        })
+    }
+
+    // Copies whatever follows the gzip stream
+    fn finish<W>(mut self, mut writer: W) -> Result<()>
+    where
+        W: Write,
+    {
+        io::copy(&mut self.decoder, &mut io::sink())?;
+        io::copy(&mut self.decoder.into_inner(), &mut writer)?;
+        Ok(())
+    }
+}
+
+// The header is only known once the decoder has read past it
+fn builder(header: Option<&GzHeader>) -> GzBuilder {
+    let mut builder = GzBuilder::new();
+    if let Some(header) = header {
+        builder = builder
+            .mtime(header.mtime())
+            .operating_system(header.operating_system());
+        if let Some(filename) = header.filename() {
+            builder = builder.filename(filename);
+        }
+        if let Some(comment) = header.comment() {
+            builder = builder.comment(comment);
+        }
+        if let Some(extra) = header.extra() {
+            builder = builder.extra(extra);
+        }
+    }
+    builder
+}
+
+fn check_magic_number<R>(mut reader: R, expected: &[u8; MAGIC_NUMBER_LEN]) -> Result<()>
+where
+    R: Read,
+{
+    let mut number = [0; MAGIC_NUMBER_LEN];
+    reader.read_exact(&mut number)?;
+    match &number == expected {
+        true => Ok(()),
+        false => Err(Error::InvalidMagicNumber(number)),
+    }
+}
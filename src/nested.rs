@@ -0,0 +1,42 @@
+use crate::{error::Error, Result};
+use byteorder::{ReadBytesExt, WriteBytesExt, LE};
+use std::convert::TryInto;
+
+// The format has no array type, so string lists are packed into binary values
+// as a sequence of length-prefixed UTF-8 strings
+pub(crate) fn encode_strings<'a, I>(strings: I) -> Result<Vec<u8>>
+where
+    I: IntoIterator<Item = &'a str>,
+{
+    let mut bytes = Vec::new();
+    for s in strings {
+        bytes.write_u32::<LE>(s.len().try_into()?)?;
+        bytes.extend_from_slice(s.as_bytes());
+    }
+    Ok(bytes)
+}
+
+#[inline]
+pub(crate) fn decode_strings(bytes: &[u8]) -> Result<Vec<String>> {
+    Ok(decode_str_refs(bytes)?
+        .into_iter()
+        .map(ToOwned::to_owned)
+        .collect())
+}
+
+pub(crate) fn decode_str_refs(mut bytes: &[u8]) -> Result<Vec<&str>> {
+    let mut strings = Vec::new();
+    while !bytes.is_empty() {
+        let len = bytes
+            .read_u32::<LE>()
+            .map_err(|_| Error::InvalidStringList)? as usize;
+        if len > bytes.len() {
+            return Err(Error::InvalidStringList);
+        }
+        let (s, rest) = bytes.split_at(len);
+        let s = std::str::from_utf8(s).map_err(|_| Error::InvalidStringList)?;
+        strings.push(s);
+        bytes = rest;
+    }
+    Ok(strings)
+}
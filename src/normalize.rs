@@ -0,0 +1,77 @@
+use crate::Playlist;
+use blister_format::{Map, Value};
+#[cfg(feature = "chrono")]
+use chrono::{TimeZone, Utc};
+
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct NormalizeReport {
+    pub title_trimmed: bool,
+    pub author_trimmed: bool,
+    pub description_dropped: bool,
+    pub cover_dropped: bool,
+    pub custom_data_removed: usize,
+    pub dates_clamped: Vec<usize>,
+}
+
+impl NormalizeReport {
+    #[inline]
+    pub fn is_unchanged(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+impl Playlist {
+    pub fn normalize(&mut self) -> NormalizeReport {
+        let mut report = NormalizeReport {
+            title_trimmed: trim(&mut self.title),
+            author_trimmed: trim(&mut self.author),
+            description_dropped: matches!(&self.description, Some(s) if s.trim().is_empty()),
+            cover_dropped: matches!(&self.cover, Some(b) if b.is_empty()),
+            ..Default::default()
+        };
+        if report.description_dropped {
+            self.description = None;
+        }
+        if report.cover_dropped {
+            self.cover = None;
+        }
+
+        report.custom_data_removed += strip_empty(&mut self.custom_data);
+
+        // Dates are stored as unsigned timestamps and can't be in the future. Raw dates are left
+        // alone without `chrono`.
+        #[cfg(feature = "chrono")]
+        let (epoch, now) = (Utc.timestamp_opt(0, 0).unwrap(), Utc::now());
+        #[allow(unused_variables)]
+        for (i, map) in self.maps.iter_mut().enumerate() {
+            report.custom_data_removed += strip_empty(&mut map.custom_data);
+            #[cfg(feature = "chrono")]
+            if map.date_added < epoch || map.date_added > now {
+                map.date_added = map.date_added.clamp(epoch, now);
+                report.dates_clamped.push(i);
+            }
+        }
+
+        report
+    }
+}
+
+fn trim(s: &mut String) -> bool {
+    let trimmed = s.trim();
+    if trimmed.len() == s.len() {
+        return false;
+    }
+    *s = trimmed.to_owned();
+    true
+}
+
+fn strip_empty(data: &mut Map) -> usize {
+    let len = data.len();
+    data.retain(|_, v| match v {
+        Value::ShortString(s) => !s.is_empty(),
+        Value::LongString(s) => !s.is_empty(),
+        Value::Binary(b) => !b.is_empty(),
+        _ => true,
+    });
+    len - data.len()
+}
@@ -0,0 +1,136 @@
+#[cfg(feature = "gzip")]
+use crate::{error::Error, Result};
+use crate::{ExtensionRegistry, Version};
+use blister_format::{Interner, Spill};
+#[cfg(feature = "gzip")]
+use flate2::{Compression, GzBuilder};
+#[cfg(feature = "gzip")]
+use std::sync::Arc;
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum DuplicatePolicy {
+    Ignore,
+    Warn,
+    Drop,
+    Error,
+}
+
+impl Default for DuplicatePolicy {
+    #[inline]
+    fn default() -> Self {
+        Self::Ignore
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ReadOptions {
+    pub strict: bool,
+    pub max_maps: Option<usize>,
+    pub max_binary_len: Option<usize>,
+    pub max_cover_len: Option<usize>,
+    pub max_zip_len: Option<usize>,
+    // Bounds readers that buffer the whole file before decoding it
+    pub max_total_len: Option<usize>,
+    pub lossy_utf8: bool,
+    pub interner: Option<Interner>,
+    pub resync: bool,
+    // Keeps the bytes thrown away by lossy and resynchronizing reads in their warnings
+    pub capture_discarded: bool,
+    pub widen_integers: bool,
+    pub duplicates: DuplicatePolicy,
+    #[cfg(feature = "zip")]
+    pub verify_zips: bool,
+    // Spilled values are scoped to the index of the map they were read from, or `None` for the
+    // playlist itself, and left empty in the playlist
+    pub spill: Option<Spill>,
+    // Registered types pass strict reads and are validated by their extension
+    pub extensions: Option<ExtensionRegistry>,
+}
+
+// Not `Copy`, the extension registry and gzip header strings are shared when cloned instead
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct WriteOptions {
+    #[cfg(feature = "gzip")]
+    pub compression: Compression,
+    pub canonical: bool,
+    pub version: Version,
+    pub validate: bool,
+    // Validates with `Playlist::validate_strict` instead, using `extensions` for custom types
+    pub strict: bool,
+    pub extensions: Option<ExtensionRegistry>,
+    #[cfg(feature = "gzip")]
+    pub gzip: GzipHeader,
+    #[cfg(feature = "image")]
+    pub cover: Option<crate::CoverOptions>,
+}
+
+// The defaults leave out everything that could make identical playlists differ byte for byte
+#[cfg(feature = "gzip")]
+#[derive(Debug, Clone, Default, Eq, PartialEq, Hash)]
+pub struct GzipHeader {
+    // Seconds since the Unix epoch, zero meaning unknown. Canonical writes ignore the whole header.
+    pub mtime: u32,
+    pub filename: Option<Arc<str>>,
+    pub comment: Option<Arc<str>>,
+}
+
+impl Default for WriteOptions {
+    #[inline]
+    fn default() -> Self {
+        Self {
+            #[cfg(feature = "gzip")]
+            compression: Default::default(),
+            canonical: false,
+            version: Version::V3,
+            validate: false,
+            strict: false,
+            extensions: None,
+            #[cfg(feature = "gzip")]
+            gzip: Default::default(),
+            #[cfg(feature = "image")]
+            cover: None,
+        }
+    }
+}
+
+impl ReadOptions {
+    #[inline]
+    pub(crate) fn format(&self) -> blister_format::ReadOptions {
+        blister_format::ReadOptions {
+            max_binary_len: self.max_binary_len,
+            max_field_lens: Vec::new(),
+            lossy_utf8: self.lossy_utf8,
+            interner: self.interner.clone(),
+            encoding: Default::default(),
+            resync: self.resync,
+            spill: self.spill.clone(),
+            capture_discarded: self.capture_discarded,
+        }
+    }
+}
+
+#[cfg(feature = "gzip")]
+impl GzipHeader {
+    // Header strings are nul terminated so they can't contain any
+    pub(crate) fn builder(&self) -> Result<GzBuilder> {
+        let mut builder = GzBuilder::new().mtime(self.mtime);
+        if let Some(filename) = &self.filename {
+            if filename.contains('\0') {
+                return Err(Error::InvalidGzipHeader("filename"));
+            }
+            builder = builder.filename(filename.as_bytes());
+        }
+        if let Some(comment) = &self.comment {
+            if comment.contains('\0') {
+                return Err(Error::InvalidGzipHeader("comment"));
+            }
+            builder = builder.comment(comment.as_bytes());
+        }
+        Ok(builder)
+    }
+}
+
+#[inline]
+pub(crate) fn exceeds(limit: Option<usize>, len: usize) -> bool {
+    matches!(limit, Some(max) if len > max)
+}
@@ -1,18 +1,60 @@
-use crate::{error::Error, Beatmap, Result, MAGIC_NUMBER, MAGIC_NUMBER_LEN};
-use blister_format::{Map, Value};
+#[cfg(feature = "gzip")]
+use crate::Version;
+use crate::{
+    beatmap::ZIP_KEY,
+    builder::check_len,
+    clock::instant_now,
+    error::Error,
+    folder::{decode_children, encode_children, ChildPlaylistRef, CHILDREN_KEY},
+    localized::{
+        decode_localized, encode_localized, LOCALIZED_DESCRIPTIONS_KEY, LOCALIZED_TITLES_KEY,
+    },
+    magic_number,
+    nested::{decode_strings, encode_strings},
+    options::{exceeds, DuplicatePolicy, WriteOptions},
+    read_map_encoding,
+    report::CountingWriter,
+    source_map::{CountingReader, SourceMap},
+    validate::{find_duplicates, DuplicateFinder},
+    Beatmap, ChildPlaylist, CoverRecompression, LocalizedStrings, ReadOptions, Result, Warning,
+    WriteReport, PREALLOCATION_LIMIT, SHORT_STRING_LEN,
+};
+use blister_format::{
+    error::Error as FormatError, values::Binary, Key, Map, MapEncoding, Value, ValueRef,
+};
 use byteorder::{ReadBytesExt, WriteBytesExt, LE};
-use flate2::{bufread::GzDecoder, write::GzEncoder, Compression};
+#[cfg(feature = "gzip")]
+use flate2::{bufread::GzDecoder, Compression, GzBuilder};
+#[cfg(feature = "gzip")]
+use std::{convert::TryFrom, fs::File, path::Path};
 use std::{
     convert::TryInto,
-    io::{BufReader, Read, Write},
+    io::{self, Read, Write},
+    slice,
+    sync::Arc,
+    vec,
 };
 
+pub(crate) const COVER_KEY: u32 = 3;
+pub(crate) const TAGS_KEY: u32 = 6;
+// JSON fields preserved from a bplist
+pub(crate) const UNKNOWN_FIELDS_KEY: u32 = 8;
+
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(
+    feature = "diesel",
+    derive(diesel::expression::AsExpression, diesel::deserialize::FromSqlRow),
+    diesel(sql_type = diesel::sql_types::Binary)
+)]
 pub struct Playlist {
     pub title: String,
     pub author: String,
     pub description: Option<String>,
-    pub cover: Option<Vec<u8>>,
+    pub cover: Option<Arc<[u8]>>,
+    pub localized_titles: LocalizedStrings,
+    pub localized_descriptions: LocalizedStrings,
+    pub tags: Vec<String>,
+    pub children: Vec<ChildPlaylist>,
 
     pub maps: Vec<Beatmap>,
 
@@ -26,106 +68,722 @@ impl Playlist {
             author,
             description: None,
             cover: None,
+            localized_titles: LocalizedStrings::new(),
+            localized_descriptions: LocalizedStrings::new(),
+            tags: Vec::new(),
+            children: Vec::new(),
             maps: Default::default(),
             custom_data: Default::default(),
         }
     }
 
-    pub fn read<R>(mut reader: R, strict: bool) -> Result<Self>
+    #[inline]
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|t| t.eq_ignore_ascii_case(tag))
+    }
+
+    #[inline]
+    pub fn maps(&self) -> slice::Iter<'_, Beatmap> {
+        self.maps.iter()
+    }
+
+    #[inline]
+    pub fn maps_mut(&mut self) -> slice::IterMut<'_, Beatmap> {
+        self.maps.iter_mut()
+    }
+
+    // Keeps the first occurrence of every map, like reads with `DuplicatePolicy::Drop`. Returns
+    // `(duplicate, first occurrence)` pairs, indexed from before the removal.
+    pub fn remove_duplicates(&mut self) -> Vec<(usize, usize)> {
+        let duplicates = find_duplicates(&self.maps);
+        drop_duplicates(&mut self.maps, &duplicates);
+        duplicates
+    }
+
+    pub fn set_title<S>(&mut self, title: S) -> Result<()>
+    where
+        S: Into<String>,
+    {
+        let title = title.into();
+        check_len("title", &title, SHORT_STRING_LEN)?;
+        self.title = title;
+        Ok(())
+    }
+
+    pub fn set_author<S>(&mut self, author: S) -> Result<()>
+    where
+        S: Into<String>,
+    {
+        let author = author.into();
+        check_len("author", &author, SHORT_STRING_LEN)?;
+        self.author = author;
+        Ok(())
+    }
+
+    #[cfg(feature = "gzip")]
+    #[inline]
+    pub fn read<R>(reader: R, strict: bool) -> Result<Self>
     where
         R: Read,
     {
-        let mut magic_number = [0; MAGIC_NUMBER_LEN];
-        reader.read_exact(&mut magic_number)?;
-        if !constant_time_eq::constant_time_eq(&magic_number[..], &MAGIC_NUMBER[..]) {
-            return Err(Error::InvalidMagicNumber(magic_number));
-        }
+        Self::read_with_options(
+            reader,
+            &ReadOptions {
+                strict,
+                ..Default::default()
+            },
+        )
+    }
+
+    #[cfg(feature = "gzip")]
+    #[inline]
+    pub fn read_with_options<R>(reader: R, options: &ReadOptions) -> Result<Self>
+    where
+        R: Read,
+    {
+        Self::read_with_warnings(reader, options).map(|(playlist, _)| playlist)
+    }
+
+    #[cfg(feature = "gzip")]
+    pub fn read_with_warnings<R>(
+        mut reader: R,
+        options: &ReadOptions,
+    ) -> Result<(Self, Vec<Warning>)>
+    where
+        R: Read,
+    {
+        let encoding = read_map_encoding(&mut reader)?;
+
+        let mut warnings = Vec::new();
+        let playlist = Self::read_body(reader, options, encoding, &mut warnings)?;
+        Ok((playlist, warnings))
+    }
+
+    // Reads the magic number followed by a body the caller already decompressed, which is all
+    // builds without `gzip` can do
+    pub fn read_uncompressed<R>(
+        mut reader: R,
+        options: &ReadOptions,
+    ) -> Result<(Self, Vec<Warning>)>
+    where
+        R: Read,
+    {
+        let encoding = read_map_encoding(&mut reader)?;
+
+        let mut warnings = Vec::new();
+        let playlist = Self::read_decoded(reader, options, encoding, &mut warnings)?;
+        Ok((playlist, warnings))
+    }
+
+    // Files cut off in the map section, e.g. by an interrupted upload, yield every map read in
+    // full instead of an error
+    #[cfg(feature = "gzip")]
+    pub fn read_partial<R>(mut reader: R, options: &ReadOptions) -> Result<(Self, Vec<Warning>)>
+    where
+        R: Read,
+    {
+        let encoding = read_map_encoding(&mut reader)?;
 
-        let mut decoder = GzDecoder::new(BufReader::new(reader));
+        let mut warnings = Vec::new();
+        let decoder = GzDecoder::new(io::BufReader::new(reader));
+        let playlist =
+            Self::read_decoded_with(decoder, options, encoding, &mut warnings, true, None)?;
+        Ok((playlist, warnings))
+    }
+
+    // Spans are recorded before duplicates are handled, so they follow the maps as stored
+    #[cfg(feature = "gzip")]
+    pub fn read_with_source_map<R>(
+        mut reader: R,
+        options: &ReadOptions,
+    ) -> Result<(Self, Vec<Warning>, SourceMap)>
+    where
+        R: Read,
+    {
+        let encoding = read_map_encoding(&mut reader)?;
+
+        let mut warnings = Vec::new();
+        let mut source_map = SourceMap::default();
+        let decoder = GzDecoder::new(io::BufReader::new(reader));
+        let playlist = Self::read_decoded_with(
+            decoder,
+            options,
+            encoding,
+            &mut warnings,
+            false,
+            Some(&mut source_map),
+        )?;
+        Ok((playlist, warnings, source_map))
+    }
+
+    #[cfg(feature = "gzip")]
+    #[inline]
+    pub(crate) fn read_body<R>(
+        reader: R,
+        options: &ReadOptions,
+        encoding: MapEncoding,
+        warnings: &mut Vec<Warning>,
+    ) -> Result<Self>
+    where
+        R: Read,
+    {
+        let decoder = GzDecoder::new(io::BufReader::new(reader));
+        Self::read_decoded(decoder, options, encoding, warnings)
+    }
 
-        let mut data = Map::with_capacity(2);
-        data.read(&mut decoder)?;
+    #[inline]
+    pub(crate) fn read_decoded<R>(
+        decoder: R,
+        options: &ReadOptions,
+        encoding: MapEncoding,
+        warnings: &mut Vec<Warning>,
+    ) -> Result<Self>
+    where
+        R: Read,
+    {
+        Self::read_decoded_with(decoder, options, encoding, warnings, false, None)
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, err, fields(maps, warnings))
+    )]
+    fn read_decoded_with<R>(
+        decoder: R,
+        options: &ReadOptions,
+        encoding: MapEncoding,
+        warnings: &mut Vec<Warning>,
+        partial: bool,
+        mut source_map: Option<&mut SourceMap>,
+    ) -> Result<Self>
+    where
+        R: Read,
+    {
+        let mut decoder = CountingReader::new(decoder);
+        let mut format_warnings = Vec::new();
+        let format = blister_format::ReadOptions {
+            encoding,
+            ..options.format()
+        };
+        // Field limits are checked against the length prefix, before the value is read
+        let header_format = blister_format::ReadOptions {
+            max_field_lens: field_len(COVER_KEY, options.max_cover_len),
+            ..format.clone()
+        };
+        let format = blister_format::ReadOptions {
+            max_field_lens: field_len(ZIP_KEY, options.max_zip_len),
+            ..format
+        };
+
+        let spill = options.spill.as_ref();
+        if let Some(spill) = spill {
+            spill.set_scope(None);
+        }
+
+        let mut data = Map::new();
+        data.read_with_options(&mut decoder, &header_format, &mut format_warnings)
+            .map_err(|e| match e {
+                FormatError::FieldTooLarge { len, .. } => Error::CoverTooLarge(len),
+                e => e.into(),
+            })?;
+        if let Some(source_map) = source_map.as_deref_mut() {
+            source_map.header = 0..decoder.position();
+        }
+        warnings.extend(
+            format_warnings
+                .drain(..)
+                .map(|w| Warning::from_format(w, None)),
+        );
 
         let title = match data.remove(0) {
-            Some(Value::ShortString(s)) => s,
+            Some(Value::ShortString(s)) => s.into(),
             v => return Err(Error::InvalidPlaylistTitle(v)),
         };
         let author = match data.remove(1) {
-            Some(Value::ShortString(s)) => s,
+            Some(Value::ShortString(s)) => s.into(),
             v => return Err(Error::InvalidPlaylistAuthor(v)),
         };
         let description = match data.remove(2) {
-            Some(Value::LongString(s)) => Some(s),
+            Some(Value::LongString(s)) => Some(s.into()),
             None => None,
             v => return Err(Error::InvalidPlaylistDescription(v)),
         };
-        let cover = match data.remove(3) {
-            Some(Value::Binary(b)) => Some(b),
+        let cover = match data.remove(COVER_KEY) {
+            Some(Value::Binary(b)) => Some(load(b)?.into()),
             None => None,
             v => return Err(Error::InvalidPlaylistCover(v)),
         };
+        let localized_titles = match data.remove(LOCALIZED_TITLES_KEY) {
+            Some(Value::Binary(b)) => decode_localized(&load(b)?)?,
+            None => LocalizedStrings::new(),
+            v => return Err(Error::InvalidPlaylistLocalizedTitles(v)),
+        };
+        let localized_descriptions = match data.remove(LOCALIZED_DESCRIPTIONS_KEY) {
+            Some(Value::Binary(b)) => decode_localized(&load(b)?)?,
+            None => LocalizedStrings::new(),
+            v => return Err(Error::InvalidPlaylistLocalizedDescriptions(v)),
+        };
+        let tags = match data.remove(TAGS_KEY) {
+            Some(Value::Binary(b)) => decode_strings(&load(b)?)?,
+            None => Vec::new(),
+            v => return Err(Error::InvalidPlaylistTags(v)),
+        };
+        let children = match data.remove(CHILDREN_KEY) {
+            Some(Value::Binary(b)) => decode_children(&load(b)?)?
+                .into_iter()
+                .map(ChildPlaylistRef::to_owned)
+                .collect(),
+            None => Vec::new(),
+            v => return Err(Error::InvalidPlaylistChildren(v)),
+        };
 
-        let map_count = decoder.read_u32::<LE>()? as usize;
-        let mut maps = Vec::with_capacity(map_count);
-        for _ in 0..map_count {
-            maps.push(Beatmap::read(&mut decoder, strict)?);
+        let map_count_start = decoder.position();
+        let map_count = match decoder.read_u32::<LE>() {
+            Ok(count) => count as usize,
+            // Nothing is known about the maps at this point
+            Err(e) if partial && is_truncation(&e) => {
+                warnings.push(Warning::Truncated {
+                    maps_lost_estimate: 0,
+                });
+                0
+            }
+            Err(e) => return Err(e.into()),
+        };
+        if exceeds(options.max_maps, map_count) {
+            return Err(Error::TooManyMaps(map_count));
+        }
+        // Even a count under the configured limit can outgrow the actual data
+        let capacity = map_count.min(PREALLOCATION_LIMIT);
+        if let Some(source_map) = source_map.as_deref_mut() {
+            source_map.map_count = map_count_start..decoder.position();
+            source_map.maps.reserve(capacity);
+        }
+        let mut maps = Vec::with_capacity(capacity);
+        let mut duplicates = DuplicateFinder::default();
+        for i in 0..map_count {
+            let start = decoder.position();
+            if let Some(spill) = spill {
+                spill.set_scope(Some(i));
+            }
+            match Beatmap::read(&mut decoder, options, &format, &mut format_warnings) {
+                Ok(map) => {
+                    let first = match options.duplicates {
+                        DuplicatePolicy::Ignore => None,
+                        _ => duplicates.check(&maps, &map, i),
+                    };
+                    let keep = match (first, options.duplicates) {
+                        (Some(first), DuplicatePolicy::Error) => {
+                            return Err(Error::DuplicateMap { map: i, first })
+                        }
+                        (Some(first), DuplicatePolicy::Warn) => {
+                            warnings.push(Warning::DuplicateMap { map: i, first });
+                            true
+                        }
+                        (Some(_), DuplicatePolicy::Drop) => false,
+                        _ => true,
+                    };
+                    if keep {
+                        maps.push(map);
+                        if let Some(source_map) = source_map.as_deref_mut() {
+                            source_map.maps.push(start..decoder.position());
+                        }
+                    }
+                }
+                Err(Error::IO(e) | Error::Format(FormatError::IO(e)))
+                    if partial && is_truncation(&e) =>
+                {
+                    format_warnings.clear();
+                    warnings.push(Warning::Truncated {
+                        maps_lost_estimate: map_count - i,
+                    });
+                    break;
+                }
+                Err(e) => return Err(e),
+            }
+            warnings.extend(
+                format_warnings
+                    .drain(..)
+                    .map(|w| Warning::from_format(w, Some(i))),
+            );
         }
 
-        Ok(Self {
+        let playlist = Self {
             title,
             author,
             description,
             cover,
+            localized_titles,
+            localized_descriptions,
+            tags,
+            children,
             maps,
             custom_data: data,
-        })
+        };
+        playlist.collect_warnings(warnings);
+        #[cfg(feature = "zip")]
+        if options.verify_zips {
+            playlist.verify_zips()?;
+        }
+
+        #[cfg(feature = "tracing")]
+        tracing::Span::current()
+            .record("maps", map_count)
+            .record("warnings", warnings.len());
+
+        Ok(playlist)
     }
 
+    #[cfg(feature = "gzip")]
     #[inline]
-    pub fn write<W>(self, writer: W) -> Result<()>
+    pub fn write<W>(&self, writer: W) -> Result<()>
     where
         W: Write,
     {
-        self.write_with_compression(writer, Default::default())
+        self.write_with_options(writer, &Default::default())
+            .map(|_| ())
     }
 
-    pub fn write_with_compression<W>(self, mut writer: W, level: Compression) -> Result<()>
+    #[cfg(feature = "gzip")]
+    #[inline]
+    pub fn write_with_compression<W>(&self, writer: W, level: Compression) -> Result<()>
     where
         W: Write,
     {
-        writer.write_all(MAGIC_NUMBER)?;
+        let options = WriteOptions {
+            compression: level,
+            ..Default::default()
+        };
+        self.write_with_options(writer, &options).map(|_| ())
+    }
 
-        let mut encoder = GzEncoder::new(
-            Vec::with_capacity((4 + 1 + 1 + 1) + (4 + 1 + 1 + 1) + 4),
-            level,
-        );
+    #[cfg(feature = "gzip")]
+    #[inline]
+    pub fn write_with_encoding<W>(&self, writer: W, encoding: MapEncoding) -> Result<()>
+    where
+        W: Write,
+    {
+        let options = WriteOptions {
+            version: Version::from_encoding(encoding),
+            ..Default::default()
+        };
+        self.write_with_options(writer, &options).map(|_| ())
+    }
 
-        let Self {
-            title,
-            author,
-            description,
-            cover,
-            maps,
-            custom_data: mut data,
-        } = self;
+    #[cfg(feature = "gzip")]
+    #[inline]
+    pub fn write_canonical<W>(&self, writer: W) -> Result<()>
+    where
+        W: Write,
+    {
+        let options = WriteOptions {
+            compression: Compression::best(),
+            canonical: true,
+            ..Default::default()
+        };
+        self.write_with_options(writer, &options).map(|_| ())
+    }
+
+    #[cfg(feature = "gzip")]
+    pub fn write_with_options<W>(&self, writer: W, options: &WriteOptions) -> Result<WriteReport>
+    where
+        W: Write,
+    {
+        let start = instant_now();
+        let encoding = options.version.encoding()?;
+        let recompressed = self.prepare_write(options)?;
+        let cover = recompressed.as_deref().or(self.cover.as_deref());
+
+        // Canonical output only depends on the playlist, so the configured header is left out
+        let gzip = if options.canonical {
+            GzBuilder::new().operating_system(255)
+        } else {
+            options.gzip.builder()?
+        };
+
+        let mut writer = CountingWriter::new(writer);
+        writer.write_all(magic_number(encoding))?;
+        let mut encoder = gzip.write(&mut writer, options.compression);
+        let mut report = self.write_maps(&mut encoder, cover, options.canonical, encoding)?;
+        encoder.finish()?;
+        report.cover_recompression = self.recompression(recompressed.as_deref());
+        report.compressed = writer.written();
+        report.duration = start.map(|s| s.elapsed());
+        Ok(report)
+    }
+
+    // Writes the magic number followed by the body, which the caller has to gzip for the output
+    // to be a valid file. This is all builds without `gzip` can do.
+    pub fn write_uncompressed<W>(&self, writer: W, options: &WriteOptions) -> Result<WriteReport>
+    where
+        W: Write,
+    {
+        let start = instant_now();
+        let encoding = options.version.encoding()?;
+        let recompressed = self.prepare_write(options)?;
+        let cover = recompressed.as_deref().or(self.cover.as_deref());
+
+        let mut writer = CountingWriter::new(writer);
+        writer.write_all(magic_number(encoding))?;
+        let mut report = self.write_maps(&mut writer, cover, options.canonical, encoding)?;
+        report.cover_recompression = self.recompression(recompressed.as_deref());
+        report.compressed = writer.written();
+        report.duration = start.map(|s| s.elapsed());
+        Ok(report)
+    }
+
+    // Validates as configured and returns the recompressed cover, if any
+    fn prepare_write(&self, options: &WriteOptions) -> Result<Option<Vec<u8>>> {
+        let report = if options.strict {
+            Some(self.validate_strict(options.extensions.as_ref()))
+        } else if options.validate {
+            Some(self.validate())
+        } else {
+            None
+        };
+        if let Some(report) = report.filter(|r| !r.is_valid()) {
+            return Err(Error::Validation(report.problems));
+        }
+        #[cfg(feature = "image")]
+        return match (&options.cover, &self.cover) {
+            (Some(options), Some(cover)) => crate::cover::recompress(cover, options),
+            _ => Ok(None),
+        };
+        #[cfg(not(feature = "image"))]
+        return Ok(None);
+    }
+
+    #[inline]
+    fn recompression(&self, recompressed: Option<&[u8]>) -> Option<CoverRecompression> {
+        recompressed.map(|c| CoverRecompression {
+            original: self.cover.as_ref().map_or(0, |c| c.len()),
+            recompressed: c.len(),
+        })
+    }
+
+    #[cfg(feature = "encryption")]
+    pub(crate) fn write_body<W>(
+        &self,
+        writer: W,
+        level: Compression,
+        encoding: MapEncoding,
+    ) -> Result<WriteReport>
+    where
+        W: Write,
+    {
+        let mut encoder = flate2::write::GzEncoder::new(writer, level);
+        let report = self.write_maps(&mut encoder, self.cover.as_deref(), false, encoding)?;
+        encoder.finish()?;
+        Ok(report)
+    }
+
+    // Only fills in the section sizes. `cover` replaces the playlist's own, and canonical bodies
+    // are sorted and leave out empty optional fields.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip(self, writer, cover), err, fields(maps, bytes))
+    )]
+    pub(crate) fn write_maps<W>(
+        &self,
+        mut writer: W,
+        cover: Option<&[u8]>,
+        canonical: bool,
+        encoding: MapEncoding,
+    ) -> Result<WriteReport>
+    where
+        W: Write,
+    {
+        let cover = canonical_cover(cover, canonical);
+        let zips = self
+            .maps
+            .iter()
+            .filter_map(|m| m.zip.as_ref())
+            .map(|z| z.len())
+            .sum();
+
+        let lists = self.encode_lists()?;
+        let header = self.header_overlay(cover, &lists, canonical)?;
+        let maps = self
+            .maps
+            .iter()
+            .map(|m| m.overlay(true))
+            .collect::<Result<Vec<_>>>()?;
+        let mut report = WriteReport {
+            header: self.custom_data.encoded_len_overlaid(&header, encoding),
+            cover: cover.map_or(0, |c| c.len()),
+            maps: self
+                .maps
+                .iter()
+                .zip(&maps)
+                .map(|(m, o)| m.custom_data.encoded_len_overlaid(o, encoding))
+                .sum(),
+            zips,
+            ..Default::default()
+        };
+        report.uncompressed = report.header + 4 + report.maps;
+        #[cfg(feature = "tracing")]
+        tracing::Span::current()
+            .record("maps", maps.len())
+            .record("bytes", report.uncompressed);
+
+        self.custom_data
+            .write_overlaid(&header, &mut writer, encoding, canonical)?;
+        writer.write_u32::<LE>(maps.len().try_into()?)?;
+        for (map, overlay) in self.maps.iter().zip(&maps) {
+            map.custom_data
+                .write_overlaid(overlay, &mut writer, encoding, canonical)?;
+        }
+        Ok(report)
+    }
 
-        data.insert(0, Value::ShortString(title));
-        data.insert(1, Value::ShortString(author));
-        if let Some(s) = description {
-            data.insert(2, Value::LongString(s));
+    // Localized strings, tags and children are stored as a single binary value each
+    pub(crate) fn encode_lists(&self) -> Result<EncodedLists> {
+        let localized_titles = match self.localized_titles.is_empty() {
+            true => None,
+            false => Some(encode_localized(&self.localized_titles)?),
+        };
+        let localized_descriptions = match self.localized_descriptions.is_empty() {
+            true => None,
+            false => Some(encode_localized(&self.localized_descriptions)?),
+        };
+        let tags = match self.tags.is_empty() {
+            true => None,
+            false => Some(encode_strings(self.tags.iter().map(String::as_str))?),
+        };
+        let children = match self.children.is_empty() {
+            true => None,
+            false => Some(encode_children(&self.children)?),
+        };
+        Ok(EncodedLists {
+            localized_titles,
+            localized_descriptions,
+            tags,
+            children,
+        })
+    }
+
+    // Core fields written over the custom data, which keeps its entries for fields left unset
+    pub(crate) fn header_overlay<'a>(
+        &'a self,
+        cover: Option<&'a [u8]>,
+        lists: &'a EncodedLists,
+        canonical: bool,
+    ) -> Result<Vec<(Key, Option<ValueRef<'a>>)>> {
+        let mut overlay = vec![
+            (0.into(), Some(ValueRef::short_string(&self.title)?)),
+            (1.into(), Some(ValueRef::short_string(&self.author)?)),
+        ];
+        match &self.description {
+            Some(s) if canonical && s.is_empty() => (),
+            Some(s) => overlay.push((2.into(), Some(ValueRef::long_string(s)?))),
+            None => (),
         }
         if let Some(b) = cover {
-            data.insert(3, Value::Binary(b));
+            overlay.push((3.into(), Some(ValueRef::Binary(b))));
+        }
+        if let Some(b) = &lists.localized_titles {
+            overlay.push((LOCALIZED_TITLES_KEY.into(), Some(ValueRef::Binary(b))));
+        }
+        if let Some(b) = &lists.localized_descriptions {
+            overlay.push((LOCALIZED_DESCRIPTIONS_KEY.into(), Some(ValueRef::Binary(b))));
+        }
+        if let Some(b) = &lists.tags {
+            overlay.push((TAGS_KEY.into(), Some(ValueRef::Binary(b))));
+        }
+        if let Some(b) = &lists.children {
+            overlay.push((CHILDREN_KEY.into(), Some(ValueRef::Binary(b))));
         }
-        data.write(&mut encoder)?;
+        Ok(overlay)
+    }
+}
+
+// Canonical bodies leave out empty covers like they do empty descriptions
+#[inline]
+pub(crate) fn canonical_cover(cover: Option<&[u8]>, canonical: bool) -> Option<&[u8]> {
+    cover.filter(|c| !(canonical && c.is_empty()))
+}
+
+pub(crate) struct EncodedLists {
+    localized_titles: Option<Vec<u8>>,
+    localized_descriptions: Option<Vec<u8>>,
+    tags: Option<Vec<u8>>,
+    children: Option<Vec<u8>>,
+}
 
-        let map_count = maps.len();
-        encoder.write_u32::<LE>(map_count.try_into()?)?;
-        for map in maps {
-            map.write(&mut encoder)?;
+#[inline]
+pub(crate) fn field_len(key: u32, max: Option<usize>) -> Vec<(Key, usize)> {
+    max.map(|max| (key.into(), max)).into_iter().collect()
+}
+
+fn drop_duplicates(maps: &mut Vec<Beatmap>, duplicates: &[(usize, usize)]) {
+    let mut i = 0;
+    let mut duplicates = duplicates.iter().map(|&(map, _)| map).peekable();
+    maps.retain(|_| {
+        let keep = duplicates.peek() != Some(&i);
+        if !keep {
+            duplicates.next();
         }
+        i += 1;
+        keep
+    });
+}
 
-        writer.write_all(&encoder.finish()?)?;
-        Ok(())
+#[cfg(feature = "gzip")]
+impl TryFrom<&[u8]> for Playlist {
+    type Error = Error;
+
+    #[inline]
+    fn try_from(data: &[u8]) -> Result<Self> {
+        Self::read_with_options(data, &Default::default())
     }
 }
+
+#[cfg(feature = "gzip")]
+impl TryFrom<&Path> for Playlist {
+    type Error = Error;
+
+    #[inline]
+    fn try_from(path: &Path) -> Result<Self> {
+        Self::read_with_options(File::open(path)?, &Default::default())
+    }
+}
+
+impl IntoIterator for Playlist {
+    type Item = Beatmap;
+    type IntoIter = vec::IntoIter<Beatmap>;
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        self.maps.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a Playlist {
+    type Item = &'a Beatmap;
+    type IntoIter = slice::Iter<'a, Beatmap>;
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        self.maps()
+    }
+}
+
+impl<'a> IntoIterator for &'a mut Playlist {
+    type Item = &'a mut Beatmap;
+    type IntoIter = slice::IterMut<'a, Beatmap>;
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        self.maps_mut()
+    }
+}
+
+// Spilled values that have to be decoded are read back into memory
+#[inline]
+fn load(b: Binary) -> Result<Vec<u8>> {
+    Ok(b.into_vec()?)
+}
+
+// Corrupted deflate blocks fail with other kinds and stay errors
+fn is_truncation(e: &io::Error) -> bool {
+    e.kind() == io::ErrorKind::UnexpectedEof
+}
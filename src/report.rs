@@ -0,0 +1,72 @@
+use std::{
+    io::{self, Write},
+    time::Duration,
+};
+
+// Section sizes are uncompressed, the body being the header, the map count and the maps
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq, Hash)]
+pub struct WriteReport {
+    pub uncompressed: usize,
+    // Everything written, magic number included
+    pub compressed: usize,
+    pub header: usize,
+    pub cover: usize,
+    // Set when the cover was recompressed, `cover` then being the recompressed size
+    pub cover_recompression: Option<CoverRecompression>,
+    pub maps: usize,
+    pub zips: usize,
+    // Missing on targets without a clock
+    pub duration: Option<Duration>,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct CoverRecompression {
+    pub original: usize,
+    pub recompressed: usize,
+}
+
+impl WriteReport {
+    // Compressed size over uncompressed size, lower is better
+    #[inline]
+    pub fn ratio(&self) -> f64 {
+        if self.uncompressed == 0 {
+            return 1.0;
+        }
+        self.compressed as f64 / self.uncompressed as f64
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct CountingWriter<W> {
+    inner: W,
+    written: usize,
+}
+
+impl<W> CountingWriter<W> {
+    #[inline]
+    pub(crate) fn new(inner: W) -> Self {
+        Self { inner, written: 0 }
+    }
+
+    #[inline]
+    pub(crate) fn written(&self) -> usize {
+        self.written
+    }
+}
+
+impl<W> Write for CountingWriter<W>
+where
+    W: Write,
+{
+    #[inline]
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.written += written;
+        Ok(written)
+    }
+
+    #[inline]
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
@@ -0,0 +1,93 @@
+use crate::{Beatmap, BeatmapType};
+use blister_format::values::Sha1;
+
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct SongInfo {
+    pub key: Option<u32>,
+    pub hash: Option<Sha1>,
+    pub name: Option<String>,
+    pub author: Option<String>,
+    pub mapper: Option<String>,
+    pub difficulties: Vec<String>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Resolution {
+    Found(SongInfo),
+    NotFound,
+    // The resolver couldn't tell, for instance because it is offline
+    Unknown,
+}
+
+pub trait Resolver {
+    fn resolve(&self, map: &Beatmap) -> Resolution;
+}
+
+impl<R> Resolver for &R
+where
+    R: Resolver + ?Sized,
+{
+    #[inline]
+    fn resolve(&self, map: &Beatmap) -> Resolution {
+        (**self).resolve(map)
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum Match {
+    Match,
+    NoMatch,
+    Unknown,
+}
+
+impl From<bool> for Match {
+    #[inline]
+    fn from(b: bool) -> Self {
+        if b {
+            Self::Match
+        } else {
+            Self::NoMatch
+        }
+    }
+}
+
+impl Beatmap {
+    pub fn matches<R>(&self, other: &Self, resolver: &R) -> Match
+    where
+        R: Resolver + ?Sized,
+    {
+        if self.same_map(other) {
+            return Match::Match;
+        }
+        if self.ty == other.ty && self.ty != BeatmapType::Unknown {
+            return Match::NoMatch;
+        }
+
+        let a = identifiers(self, resolver);
+        let b = identifiers(other, resolver);
+        match (a, b) {
+            (SongInfo { hash: Some(a), .. }, SongInfo { hash: Some(b), .. }) => (a == b).into(),
+            (SongInfo { key: Some(a), .. }, SongInfo { key: Some(b), .. }) => (a == b).into(),
+            _ => Match::Unknown,
+        }
+    }
+}
+
+// Identifiers already present on the map are trusted over resolved ones
+fn identifiers<R>(map: &Beatmap, resolver: &R) -> SongInfo
+where
+    R: Resolver + ?Sized,
+{
+    let mut info = SongInfo {
+        key: map.key,
+        hash: map.hash,
+        ..Default::default()
+    };
+    if info.key.is_none() || info.hash.is_none() {
+        if let Resolution::Found(resolved) = resolver.resolve(map) {
+            info.key = info.key.or(resolved.key);
+            info.hash = info.hash.or(resolved.hash);
+        }
+    }
+    info
+}
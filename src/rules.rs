@@ -0,0 +1,175 @@
+use crate::{validate::find_duplicates, Beatmap, Playlist};
+use std::{fmt, iter::FromIterator, sync::Arc};
+
+type Check = dyn Fn(&Playlist, &mut Vec<Violation>) + Send + Sync;
+
+// A named curation requirement, checked against a whole playlist
+#[derive(Clone)]
+pub struct Rule {
+    name: String,
+    check: Arc<Check>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct RuleSet {
+    rules: Vec<Rule>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Violation {
+    pub rule: String,
+    // `None` when the playlist as a whole breaks the rule
+    pub map: Option<usize>,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct RuleReport {
+    pub violations: Vec<Violation>,
+}
+
+impl RuleReport {
+    #[inline]
+    pub fn is_ok(&self) -> bool {
+        self.violations.is_empty()
+    }
+}
+
+impl Rule {
+    // The closure returns the reason the playlist breaks the rule, if it does
+    pub fn new<F>(name: String, check: F) -> Self
+    where
+        F: Fn(&Playlist) -> Option<String> + Send + Sync + 'static,
+    {
+        Self::with_check(name, move |playlist, violations| {
+            if let Some(message) = check(playlist) {
+                violations.push((None, message));
+            }
+        })
+    }
+
+    // Checked against every map on its own
+    pub fn for_each_map<F>(name: String, check: F) -> Self
+    where
+        F: Fn(&Beatmap) -> Option<String> + Send + Sync + 'static,
+    {
+        Self::with_check(name, move |playlist, violations| {
+            for (i, map) in playlist.maps.iter().enumerate() {
+                if let Some(message) = check(map) {
+                    violations.push((Some(i), message));
+                }
+            }
+        })
+    }
+
+    pub fn max_maps(max: usize) -> Self {
+        Self::new("max maps".to_owned(), move |playlist| {
+            let len = playlist.maps.len();
+            (len > max).then(|| format!("playlist has {} maps, at most {} allowed", len, max))
+        })
+    }
+
+    pub fn no_duplicates() -> Self {
+        Self::with_check("no duplicates".to_owned(), |playlist, violations| {
+            for (map, first) in find_duplicates(&playlist.maps) {
+                violations.push((Some(map), format!("duplicate of map {}", first)));
+            }
+        })
+    }
+
+    pub fn require_cover() -> Self {
+        Self::new("require cover".to_owned(), |playlist| {
+            match &playlist.cover {
+                Some(cover) if !cover.is_empty() => None,
+                _ => Some("playlist has no cover".to_owned()),
+            }
+        })
+    }
+
+    #[inline]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn with_check<F>(name: String, check: F) -> Self
+    where
+        F: Fn(&Playlist, &mut Vec<(Option<usize>, String)>) + Send + Sync + 'static,
+    {
+        let rule = name.clone();
+        let check = move |playlist: &Playlist, violations: &mut Vec<Violation>| {
+            let mut found = Vec::new();
+            check(playlist, &mut found);
+            violations.extend(found.into_iter().map(|(map, message)| Violation {
+                rule: rule.clone(),
+                map,
+                message,
+            }));
+        };
+        Self {
+            name,
+            check: Arc::new(check),
+        }
+    }
+}
+
+impl fmt::Debug for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Rule").field(&self.name).finish()
+    }
+}
+
+impl RuleSet {
+    #[inline]
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    #[inline]
+    pub fn with(mut self, rule: Rule) -> Self {
+        self.push(rule);
+        self
+    }
+
+    #[inline]
+    pub fn push(&mut self, rule: Rule) {
+        self.rules.push(rule);
+    }
+
+    #[inline]
+    pub fn iter(&self) -> impl Iterator<Item = &Rule> {
+        self.rules.iter()
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.rules.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+}
+
+impl FromIterator<Rule> for RuleSet {
+    #[inline]
+    fn from_iter<I>(iter: I) -> Self
+    where
+        I: IntoIterator<Item = Rule>,
+    {
+        Self {
+            rules: iter.into_iter().collect(),
+        }
+    }
+}
+
+impl Playlist {
+    // Violations are listed in rule order
+    pub fn check(&self, rules: &RuleSet) -> RuleReport {
+        let mut violations = Vec::new();
+        for rule in &rules.rules {
+            (rule.check)(self, &mut violations);
+        }
+        RuleReport { violations }
+    }
+}
@@ -0,0 +1,113 @@
+use crate::{BeatmapType, Playlist, LONG_STRING_LEN, SHORT_STRING_LEN};
+use blister_format::{
+    values::{LongString, ShortString},
+    Value,
+};
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct SanitizeOptions {
+    pub max_name_len: usize,
+    pub max_description_len: usize,
+    pub drop_cover: bool,
+    pub drop_zips: bool,
+}
+
+impl Default for SanitizeOptions {
+    #[inline]
+    fn default() -> Self {
+        Self {
+            max_name_len: SHORT_STRING_LEN,
+            max_description_len: LONG_STRING_LEN,
+            drop_cover: false,
+            drop_zips: false,
+        }
+    }
+}
+
+impl Playlist {
+    // Returns whether anything was changed
+    pub fn sanitize(&mut self, options: SanitizeOptions) -> bool {
+        let mut changed = false;
+        changed |= sanitize(&mut self.title, options.max_name_len, false);
+        changed |= sanitize(&mut self.author, options.max_name_len, false);
+        if let Some(description) = &mut self.description {
+            changed |= sanitize(description, options.max_description_len, true);
+        }
+        if options.drop_cover && self.cover.is_some() {
+            self.cover = None;
+            changed = true;
+        }
+
+        if options.drop_zips {
+            let len = self.maps.len();
+            self.maps.retain(|m| m.ty != BeatmapType::Zip);
+            changed |= self.maps.len() != len;
+        }
+        for tag in self.tags.iter_mut() {
+            changed |= sanitize(tag, options.max_name_len, false);
+        }
+        let len = self.tags.len();
+        self.tags.retain(|t| !t.is_empty());
+        changed |= self.tags.len() != len;
+
+        for map in self.maps.iter_mut() {
+            if options.drop_zips && map.zip.is_some() {
+                map.zip = None;
+                changed = true;
+            }
+            if let Some(level_id) = &mut map.level_id {
+                changed |= sanitize(level_id, options.max_name_len, false);
+            }
+            if let Some(note) = &mut map.note {
+                changed |= sanitize(note, options.max_description_len, true);
+            }
+            for (_, value) in map.custom_data.iter_mut() {
+                changed |= sanitize_value(value);
+            }
+        }
+        for (_, value) in self.custom_data.iter_mut() {
+            changed |= sanitize_value(value);
+        }
+
+        changed
+    }
+}
+
+fn sanitize_value(value: &mut Value) -> bool {
+    let (mut owned, max) = match value {
+        Value::ShortString(s) => (s.to_string(), SHORT_STRING_LEN),
+        Value::LongString(s) => (s.to_string(), LONG_STRING_LEN),
+        _ => return false,
+    };
+    if !sanitize(&mut owned, max, true) {
+        return false;
+    }
+    // Sanitized strings are truncated to fit, so they can't be rejected
+    *value = match value {
+        Value::ShortString(_) => ShortString::new(owned).unwrap().into(),
+        _ => LongString::new(owned).unwrap().into(),
+    };
+    true
+}
+
+fn sanitize(s: &mut String, max_len: usize, multiline: bool) -> bool {
+    let len = s.len();
+    s.retain(|c| !is_spoofing(c) && !(c.is_control() && !(multiline && (c == '\n' || c == '\t'))));
+
+    if s.len() > max_len {
+        let mut end = max_len;
+        while !s.is_char_boundary(end) {
+            end -= 1;
+        }
+        s.truncate(end);
+    }
+    s.len() != len
+}
+
+// Bidirectional overrides, embeddings, isolates and marks
+fn is_spoofing(c: char) -> bool {
+    matches!(
+        c,
+        '\u{061c}' | '\u{200e}' | '\u{200f}' | '\u{202a}'..='\u{202e}' | '\u{2066}'..='\u{2069}'
+    )
+}
@@ -0,0 +1,123 @@
+use crate::Beatmap;
+#[cfg(feature = "chrono")]
+use crate::Clock;
+use blister_format::values::Sha1;
+
+// Messages wrap links in all sorts of brackets and quotes and end sentences right after them
+const PUNCTUATION: &[char] = &[
+    '<', '>', '(', ')', '[', ']', '{', '}', '"', '\'', '`', ',', '.', ';', ':', '!', '?', '*', '_',
+    '|',
+];
+
+// Bare hexadecimal words are too common to be taken as keys, so keys need one of these around.
+// The `!` of `!bsr` is trimmed along with the rest of the punctuation.
+const BSR_COMMAND: &str = "bsr";
+const KEY_URL_PATHS: &[(&str, &str)] = &[
+    ("beatsaver.com", "maps"),
+    ("beatsaver.com", "beatmap"),
+    ("bsaber.com", "songs"),
+];
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum Found {
+    Key(u32),
+    Hash(Sha1),
+}
+
+// Picks up `!bsr` requests, BeatSaver and BeastSaber links, `beatsaver://` links and bare hashes,
+// in order of appearance and without duplicates
+pub fn scrape(text: &str) -> Vec<Beatmap> {
+    find(text)
+        .into_iter()
+        .map(|f| match f {
+            Found::Key(key) => Beatmap::new_key(key),
+            Found::Hash(hash) => Beatmap::new_hash(hash),
+        })
+        .collect()
+}
+
+#[cfg(feature = "chrono")]
+pub fn scrape_with_clock<C>(text: &str, clock: &C) -> Vec<Beatmap>
+where
+    C: Clock + ?Sized,
+{
+    find(text)
+        .into_iter()
+        .map(|f| match f {
+            Found::Key(key) => Beatmap::new_key_with_clock(key, clock),
+            Found::Hash(hash) => Beatmap::new_hash_with_clock(hash, clock),
+        })
+        .collect()
+}
+
+fn find(text: &str) -> Vec<Found> {
+    let mut found = Vec::new();
+    let mut words = text.split_whitespace().map(trim_punctuation).peekable();
+    while let Some(word) = words.next() {
+        let identifier = if word.eq_ignore_ascii_case(BSR_COMMAND) {
+            words
+                .next_if(|w| parse_key(w).is_some())
+                .and_then(parse_key)
+                .map(Found::Key)
+        } else {
+            parse_word(word)
+        };
+        if let Some(identifier) = identifier {
+            if !found.contains(&identifier) {
+                found.push(identifier);
+            }
+        }
+    }
+
+    found
+}
+
+fn parse_word(word: &str) -> Option<Found> {
+    if let Some(hash) = Sha1::from_hex(word) {
+        return Some(Found::Hash(hash));
+    }
+
+    let lower = word.to_ascii_lowercase();
+    let (scheme, rest) = match lower.split_once("://") {
+        Some((scheme, rest)) => (Some(scheme), rest),
+        None => (None, lower.as_str()),
+    };
+    // Queries and fragments never hold the identifier
+    let rest = rest.split(['?', '#']).next()?;
+    if scheme == Some("beatsaver") {
+        return parse_key(rest.trim_matches('/')).map(Found::Key);
+    }
+
+    let mut segments = rest.split('/').filter(|s| !s.is_empty());
+    let host = segments.next()?;
+    let host = host.strip_prefix("www.").unwrap_or(host);
+    let segments: Vec<_> = segments.collect();
+
+    // CDN links are named after the hash of the map they download
+    if host.ends_with("beatsaver.com") {
+        let zip = segments.last().and_then(|s| s.strip_suffix(".zip"));
+        if let Some(hash) = zip.and_then(Sha1::from_hex) {
+            return Some(Found::Hash(hash));
+        }
+    }
+    match segments.as_slice() {
+        [path, key, ..] if is_key_url(host, path) => parse_key(key).map(Found::Key),
+        _ => None,
+    }
+}
+
+#[inline]
+fn is_key_url(host: &str, path: &str) -> bool {
+    KEY_URL_PATHS.iter().any(|&(h, p)| h == host && p == path)
+}
+
+fn parse_key(s: &str) -> Option<u32> {
+    if s.is_empty() || s.len() > 8 || !s.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
+    u32::from_str_radix(s, 16).ok()
+}
+
+fn trim_punctuation(word: &str) -> &str {
+    word.trim_matches(PUNCTUATION)
+}
@@ -0,0 +1,206 @@
+use crate::{Beatmap, ChildPlaylist, LocalizedStrings, Playlist, MAGIC_NUMBER_LEN};
+use blister_format::{values::Binary, Map, Value};
+use std::mem;
+
+const GZIP_OVERHEAD: usize = 10 + 8;
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Default)]
+pub struct EncodedSize {
+    pub uncompressed: usize,
+    pub compressed_estimate: usize,
+}
+
+#[derive(Default)]
+struct Sizes {
+    total: usize,
+    incompressible: usize,
+}
+
+impl Sizes {
+    fn add_map(&mut self, map: &Map, overridden: impl Fn(u32) -> bool) {
+        self.total += 4;
+        for (k, v) in map.iter() {
+            if overridden(**k) {
+                continue;
+            }
+            self.total += v.encoded_len();
+            match v {
+                Value::Binary(b) => self.incompressible += b.len(),
+                Value::Sha1(_) => self.incompressible += 20,
+                Value::LongBinary(b) => self.incompressible += b.len() as usize,
+                _ => (),
+            }
+        }
+    }
+
+    #[inline]
+    fn add_short_string(&mut self, s: &str) {
+        self.total += 4 + 1 + 1 + s.len();
+    }
+
+    #[inline]
+    fn add_binary(&mut self, len: usize) {
+        self.total += 4 + 1 + 4 + len;
+        self.incompressible += len;
+    }
+}
+
+impl Playlist {
+    pub fn encoded_size(&self) -> EncodedSize {
+        let mut sizes = Sizes::default();
+
+        sizes.add_map(&self.custom_data, |k| match k {
+            0 | 1 => true,
+            2 => self.description.is_some(),
+            3 => self.cover.is_some(),
+            4 => !self.localized_titles.is_empty(),
+            5 => !self.localized_descriptions.is_empty(),
+            6 => !self.tags.is_empty(),
+            7 => !self.children.is_empty(),
+            _ => false,
+        });
+        sizes.add_short_string(&self.title);
+        sizes.add_short_string(&self.author);
+        if let Some(s) = &self.description {
+            sizes.total += 4 + 1 + 2 + s.len();
+        }
+        if let Some(b) = &self.cover {
+            sizes.add_binary(b.len());
+        }
+        for localized in [&self.localized_titles, &self.localized_descriptions] {
+            if !localized.is_empty() {
+                sizes.total += 4 + 1 + 4;
+                sizes.total += localized
+                    .iter()
+                    .map(|(l, s)| 4 + l.len() + 4 + s.len())
+                    .sum::<usize>();
+            }
+        }
+        if !self.tags.is_empty() {
+            sizes.total += 4 + 1 + 4 + self.tags.iter().map(|t| 4 + t.len()).sum::<usize>();
+        }
+        if !self.children.is_empty() {
+            sizes.total += 4 + 1 + 4;
+            for child in &self.children {
+                sizes.total += 1 + 4;
+                match child {
+                    ChildPlaylist::Path(path) => sizes.total += path.len(),
+                    ChildPlaylist::Embedded(playlist) => {
+                        sizes.total += playlist.len();
+                        sizes.incompressible += playlist.len();
+                    }
+                }
+            }
+        }
+
+        sizes.total += 4;
+        for map in &self.maps {
+            map.add_encoded_size(&mut sizes);
+        }
+
+        let compressible = sizes.total - sizes.incompressible;
+        EncodedSize {
+            uncompressed: sizes.total,
+            compressed_estimate: MAGIC_NUMBER_LEN
+                + GZIP_OVERHEAD
+                + sizes.incompressible
+                + compressible / 2,
+        }
+    }
+}
+
+impl Beatmap {
+    fn add_encoded_size(&self, sizes: &mut Sizes) {
+        sizes.add_map(&self.custom_data, |k| match k {
+            0 | 1 => true,
+            2 => self.key.is_some(),
+            3 => self.hash.is_some(),
+            4 => self.zip.is_some(),
+            5 => self.level_id.is_some(),
+            6 => self.note.is_some(),
+            _ => false,
+        });
+        sizes.total += (4 + 1 + 1) + (4 + 1 + 8);
+        if self.key.is_some() {
+            sizes.total += 4 + 1 + 4;
+        }
+        if self.hash.is_some() {
+            sizes.total += 4 + 1 + 20;
+            sizes.incompressible += 20;
+        }
+        if let Some(b) = &self.zip {
+            sizes.add_binary(b.len());
+        }
+        if let Some(s) = &self.level_id {
+            sizes.add_short_string(s);
+        }
+        if let Some(s) = &self.note {
+            sizes.total += 4 + 1 + 2 + s.len();
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Default)]
+pub struct MemoryUsage {
+    pub strings: usize,
+    pub cover: usize,
+    pub zips: usize,
+    pub custom_data: usize,
+    pub maps: usize,
+    // Embedded children are kept encoded, so they count as their bytes
+    pub children: usize,
+}
+
+impl MemoryUsage {
+    #[inline]
+    pub fn total(&self) -> usize {
+        self.strings + self.cover + self.zips + self.custom_data + self.maps + self.children
+    }
+}
+
+impl Playlist {
+    pub fn memory_usage(&self) -> MemoryUsage {
+        let mut usage = MemoryUsage {
+            strings: self.title.capacity()
+                + self.author.capacity()
+                + self.description.as_ref().map_or(0, String::capacity)
+                + self.tags.capacity() * mem::size_of::<String>()
+                + self.tags.iter().map(String::capacity).sum::<usize>()
+                + localized_heap_size(&self.localized_titles)
+                + localized_heap_size(&self.localized_descriptions),
+            cover: self
+                .cover
+                .as_ref()
+                .map_or(0, |b| 2 * mem::size_of::<usize>() + b.len()),
+            zips: 0,
+            custom_data: self.custom_data.heap_size(),
+            maps: self.maps.capacity() * mem::size_of::<Beatmap>(),
+            children: self.children.capacity() * mem::size_of::<ChildPlaylist>(),
+        };
+
+        for child in &self.children {
+            usage.children += match child {
+                ChildPlaylist::Path(path) => path.capacity(),
+                ChildPlaylist::Embedded(playlist) => playlist.heap_size(),
+            };
+        }
+
+        for map in &self.maps {
+            usage.strings += map.level_id.as_ref().map_or(0, String::capacity);
+            usage.strings += map.note.as_ref().map_or(0, String::capacity);
+            usage.zips += map.zip.as_ref().map_or(0, Binary::heap_size);
+            usage.custom_data += map.custom_data.heap_size();
+        }
+
+        usage
+    }
+}
+
+// Node overhead isn't counted
+#[inline]
+fn localized_heap_size(localized: &LocalizedStrings) -> usize {
+    localized
+        .iter()
+        .map(|(l, s)| l.capacity() + s.capacity())
+        .sum()
+}
@@ -0,0 +1,73 @@
+use crate::{
+    error::Error, Result, MAGIC_NUMBER, MAGIC_NUMBER_LEN, MAGIC_NUMBER_V4, MAGIC_NUMBER_V5,
+};
+use blister_format::MapEncoding;
+use std::io::Read;
+
+const VERSION_PREFIX: &[u8] = b"Blist.v";
+
+#[non_exhaustive]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum Version {
+    // Only read by migrations, which upgrade them to v3
+    V1,
+    V2,
+    V3,
+    V4,
+    V5,
+    Unsupported(u8),
+}
+
+impl Version {
+    pub(crate) fn encoding(self) -> Result<MapEncoding> {
+        match self {
+            Version::V3 => Ok(MapEncoding::Length),
+            Version::V4 => Ok(MapEncoding::Counted),
+            Version::V5 => Ok(MapEncoding::Checksummed),
+            Version::V1 => Err(Error::UnsupportedVersion(1)),
+            Version::V2 => Err(Error::UnsupportedVersion(2)),
+            Version::Unsupported(v) => Err(Error::UnsupportedVersion(v)),
+        }
+    }
+
+    #[cfg(feature = "gzip")]
+    pub(crate) fn from_encoding(encoding: MapEncoding) -> Self {
+        match encoding {
+            MapEncoding::Length => Version::V3,
+            MapEncoding::Counted => Version::V4,
+            MapEncoding::Checksummed => Version::V5,
+        }
+    }
+}
+
+pub fn sniff<R>(mut reader: R) -> Option<Version>
+where
+    R: Read,
+{
+    let mut magic_number = [0; MAGIC_NUMBER_LEN];
+    reader.read_exact(&mut magic_number).ok()?;
+
+    if &magic_number == MAGIC_NUMBER {
+        return Some(Version::V3);
+    }
+    if &magic_number == MAGIC_NUMBER_V4 {
+        return Some(Version::V4);
+    }
+    if &magic_number == MAGIC_NUMBER_V5 {
+        return Some(Version::V5);
+    }
+    match magic_number.split_at(VERSION_PREFIX.len()) {
+        (VERSION_PREFIX, b"1") => Some(Version::V1),
+        (VERSION_PREFIX, b"2") => Some(Version::V2),
+        (VERSION_PREFIX, &[v]) if v.is_ascii_digit() => Some(Version::Unsupported(v - b'0')),
+        _ => None,
+    }
+}
+
+#[inline]
+pub fn is_blist(data: &[u8]) -> bool {
+    matches!(
+        sniff(data),
+        Some(Version::V3) | Some(Version::V4) | Some(Version::V5)
+    )
+}
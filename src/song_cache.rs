@@ -0,0 +1,97 @@
+use crate::{Beatmap, Playlist, Result};
+use blister_format::values::Sha1;
+use serde_json::Value as Json;
+use std::{
+    collections::HashSet,
+    fs::File,
+    io::{BufReader, Read},
+    path::{Path, PathBuf},
+};
+
+const SONG_CORE_DIR: &str = "SongCore";
+const USER_DATA_DIR: &str = "UserData";
+const SONG_HASH_DATA: &str = "SongHashData.dat";
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct InstalledSong {
+    // Level folder the hash was computed from
+    pub path: PathBuf,
+    pub hash: Sha1,
+    // SongCore's cheap fingerprint of the folder, used to tell when the hash is stale
+    pub directory_hash: i64,
+}
+
+// The hash cache SongCore keeps of every installed custom level, so they don't need to be hashed
+// again
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct SongCache {
+    pub songs: Vec<InstalledSong>,
+    // Entries whose hash couldn't be read
+    pub skipped: Vec<PathBuf>,
+}
+
+impl SongCache {
+    // Entries are sorted by path
+    pub fn read<R>(reader: R) -> Result<Self>
+    where
+        R: Read,
+    {
+        let json: Json = serde_json::from_reader(reader)?;
+        let mut cache = Self::default();
+        for (path, entry) in json.as_object().into_iter().flatten() {
+            let path = PathBuf::from(path);
+            let hash = entry.get("songHash").and_then(Json::as_str);
+            match hash.and_then(Sha1::from_hex) {
+                Some(hash) => cache.songs.push(InstalledSong {
+                    path,
+                    hash,
+                    directory_hash: entry
+                        .get("directoryHash")
+                        .and_then(Json::as_i64)
+                        .unwrap_or_default(),
+                }),
+                None => cache.skipped.push(path),
+            }
+        }
+        Ok(cache)
+    }
+
+    // Reads `UserData/SongCore/SongHashData.dat` from a game folder
+    pub fn open<P>(game_dir: P) -> Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        let path = Self::path(game_dir.as_ref());
+        Self::read(BufReader::new(File::open(path)?))
+    }
+
+    #[inline]
+    pub fn path(game_dir: &Path) -> PathBuf {
+        game_dir
+            .join(USER_DATA_DIR)
+            .join(SONG_CORE_DIR)
+            .join(SONG_HASH_DATA)
+    }
+
+    // Levels installed more than once only appear once
+    pub fn to_playlist(&self, title: String, author: String) -> Playlist {
+        let mut playlist = Playlist::new(title, author);
+        let mut seen = HashSet::new();
+        for song in &self.songs {
+            if seen.insert(song.hash.0) {
+                playlist.maps.push(Beatmap::new_hash(song.hash));
+            }
+        }
+        playlist
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.songs.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.songs.is_empty()
+    }
+}
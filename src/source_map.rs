@@ -0,0 +1,50 @@
+use std::{
+    io::{self, Read},
+    ops::Range,
+};
+
+// Byte ranges within the decompressed body, which starts right after the magic number
+#[derive(Debug, Clone, Default, Eq, PartialEq, Hash)]
+pub struct SourceMap {
+    pub header: Range<usize>,
+    pub map_count: Range<usize>,
+    pub maps: Vec<Range<usize>>,
+}
+
+impl SourceMap {
+    // Index of the map containing the given offset, if any
+    pub fn map_at(&self, offset: usize) -> Option<usize> {
+        let i = self.maps.partition_point(|m| m.end <= offset);
+        self.maps.get(i).filter(|m| m.contains(&offset)).map(|_| i)
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct CountingReader<R> {
+    inner: R,
+    position: usize,
+}
+
+impl<R> CountingReader<R> {
+    #[inline]
+    pub(crate) fn new(inner: R) -> Self {
+        Self { inner, position: 0 }
+    }
+
+    #[inline]
+    pub(crate) fn position(&self) -> usize {
+        self.position
+    }
+}
+
+impl<R> Read for CountingReader<R>
+where
+    R: Read,
+{
+    #[inline]
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.position += read;
+        Ok(read)
+    }
+}
@@ -0,0 +1,82 @@
+// Playlists are stored as blobs in their regular encoding
+
+#[cfg(feature = "sqlx")]
+mod sqlx_impls {
+    use crate::Playlist;
+    use sqlx::{encode::IsNull, error::BoxDynError, Database, Decode, Encode, Type};
+
+    impl<DB: Database> Type<DB> for Playlist
+    where
+        Vec<u8>: Type<DB>,
+    {
+        #[inline]
+        fn type_info() -> DB::TypeInfo {
+            <Vec<u8> as Type<DB>>::type_info()
+        }
+
+        #[inline]
+        fn compatible(ty: &DB::TypeInfo) -> bool {
+            <Vec<u8> as Type<DB>>::compatible(ty)
+        }
+    }
+
+    impl<'q, DB: Database> Encode<'q, DB> for Playlist
+    where
+        Vec<u8>: Encode<'q, DB>,
+    {
+        fn encode_by_ref(
+            &self,
+            buf: &mut <DB as Database>::ArgumentBuffer<'q>,
+        ) -> Result<IsNull, BoxDynError> {
+            let mut bytes = Vec::new();
+            self.write(&mut bytes)?;
+            bytes.encode(buf)
+        }
+    }
+
+    impl<'r, DB: Database> Decode<'r, DB> for Playlist
+    where
+        &'r [u8]: Decode<'r, DB>,
+    {
+        #[inline]
+        fn decode(value: <DB as Database>::ValueRef<'r>) -> Result<Self, BoxDynError> {
+            Ok(Playlist::read(<&[u8]>::decode(value)?, false)?)
+        }
+    }
+}
+
+#[cfg(feature = "diesel")]
+mod diesel_impls {
+    use crate::Playlist;
+    use diesel::{
+        backend::Backend,
+        deserialize::{self, FromSql},
+        query_builder::bind_collector::RawBytesBindCollector,
+        serialize::{self, Output, ToSql},
+        sql_types::Binary,
+    };
+
+    // Bound through a temporary like Key, with the same backend restriction
+    impl<DB> ToSql<Binary, DB> for Playlist
+    where
+        DB: for<'c> Backend<BindCollector<'c> = RawBytesBindCollector<DB>>,
+        [u8]: ToSql<Binary, DB>,
+    {
+        fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, DB>) -> serialize::Result {
+            let mut bytes = Vec::new();
+            self.write(&mut bytes)?;
+            <[u8] as ToSql<Binary, DB>>::to_sql(&bytes[..], &mut out.reborrow())
+        }
+    }
+
+    impl<DB: Backend> FromSql<Binary, DB> for Playlist
+    where
+        Vec<u8>: FromSql<Binary, DB>,
+    {
+        #[inline]
+        fn from_sql(bytes: DB::RawValue<'_>) -> deserialize::Result<Self> {
+            let bytes = <Vec<u8> as FromSql<Binary, DB>>::from_sql(bytes)?;
+            Ok(Playlist::read(bytes.as_slice(), false)?)
+        }
+    }
+}
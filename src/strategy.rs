@@ -0,0 +1,57 @@
+use crate::{Beatmap, Playlist};
+use blister_format::{
+    strategy::{map, sha1, string},
+    Map,
+};
+use chrono::{TimeZone, Utc};
+use proptest::{collection::SizeRange, prelude::*};
+
+const CUSTOM_DATA_LEN: usize = 8;
+const BINARY_LEN: usize = 256;
+
+pub fn beatmap() -> impl Strategy<Value = Beatmap> {
+    let beatmap = prop_oneof![
+        any::<u32>().prop_map(Beatmap::new_key),
+        sha1().prop_map(Beatmap::new_hash),
+        proptest::collection::vec(any::<u8>(), 0..BINARY_LEN).prop_map(Beatmap::new_zip),
+        string().prop_map(Beatmap::new_level_id),
+    ];
+    // Dates are stored with a precision of one second
+    (beatmap, any::<u32>(), custom_data(6)).prop_map(|(mut beatmap, date_added, data)| {
+        beatmap.date_added = Utc.timestamp_opt(date_added.into(), 0).unwrap();
+        beatmap.custom_data = data;
+        beatmap
+    })
+}
+
+pub fn playlist<S>(maps: S) -> impl Strategy<Value = Playlist>
+where
+    S: Into<SizeRange>,
+{
+    (
+        string(),
+        string(),
+        proptest::option::of(string()),
+        proptest::option::of(proptest::collection::vec(any::<u8>(), 0..BINARY_LEN)),
+        proptest::collection::vec(beatmap(), maps),
+        custom_data(4),
+    )
+        .prop_map(|(title, author, description, cover, maps, data)| {
+            let mut playlist = Playlist::new(title, author);
+            playlist.description = description;
+            playlist.cover = cover.map(Into::into);
+            playlist.maps = maps;
+            playlist.custom_data = data;
+            playlist
+        })
+}
+
+// Reserved keys would be overwritten by the core fields when writing
+fn custom_data(reserved: u32) -> impl Strategy<Value = Map> {
+    map(0..CUSTOM_DATA_LEN).prop_map(move |mut map| {
+        for key in 0..reserved {
+            map.remove(key);
+        }
+        map
+    })
+}
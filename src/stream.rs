@@ -0,0 +1,40 @@
+use crate::{error::Error, read_map_encoding, Playlist, ReadOptions, Result, MAGIC_NUMBER_LEN};
+use bytes::Bytes;
+use futures_util::{Stream, StreamExt};
+
+impl Playlist {
+    // The magic number is checked as soon as it arrives so bogus uploads are rejected early
+    pub async fn read_stream<S, E>(
+        mut stream: S,
+        options: &ReadOptions,
+        max_len: usize,
+    ) -> Result<Self>
+    where
+        S: Stream<Item = std::result::Result<Bytes, E>> + Unpin,
+        E: Into<Box<dyn std::error::Error + Send + Sync>>,
+    {
+        let mut buffer = Vec::new();
+        let mut encoding = None;
+        while let Some(chunk) = stream.next().await {
+            let chunk = chunk.map_err(|e| Error::Stream(e.into()))?;
+            if buffer.len() + chunk.len() > max_len {
+                return Err(Error::StreamTooLarge(max_len));
+            }
+            buffer.extend_from_slice(&chunk);
+            if encoding.is_none() && buffer.len() >= MAGIC_NUMBER_LEN {
+                encoding = Some(read_map_encoding(&buffer[..])?);
+            }
+        }
+
+        let encoding = match encoding {
+            Some(encoding) => encoding,
+            None => read_map_encoding(&buffer[..])?,
+        };
+        Self::read_body(
+            &buffer[MAGIC_NUMBER_LEN..],
+            options,
+            encoding,
+            &mut Vec::new(),
+        )
+    }
+}
@@ -0,0 +1,103 @@
+use crate::{BeatmapType, Playlist, Result, FINGERPRINT_LEN};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+pub const COVER_DIGEST_LEN: usize = 32;
+
+// Everything a listing needs without shipping the maps themselves
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
+pub struct PlaylistSummary {
+    pub title: String,
+    pub author: String,
+    pub has_description: bool,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub tags: Vec<String>,
+
+    pub maps: usize,
+    pub key_maps: usize,
+    pub hash_maps: usize,
+    pub zip_maps: usize,
+    pub level_id_maps: usize,
+
+    #[cfg_attr(feature = "serde", serde(default, with = "hex::option"))]
+    pub cover_digest: Option<[u8; COVER_DIGEST_LEN]>,
+    #[cfg_attr(feature = "serde", serde(with = "hex"))]
+    pub fingerprint: [u8; FINGERPRINT_LEN],
+    pub encoded_len: usize,
+}
+
+impl Playlist {
+    pub fn summary(&self) -> Result<PlaylistSummary> {
+        let count = |ty| self.maps.iter().filter(|m| m.ty == ty).count();
+        Ok(PlaylistSummary {
+            title: self.title.clone(),
+            author: self.author.clone(),
+            has_description: self.description.is_some(),
+            tags: self.tags.clone(),
+
+            maps: self.maps.len(),
+            key_maps: count(BeatmapType::Key),
+            hash_maps: count(BeatmapType::Hash),
+            zip_maps: count(BeatmapType::Zip),
+            level_id_maps: count(BeatmapType::LevelId),
+
+            cover_digest: self.cover.as_ref().map(|c| Sha256::digest(c).into()),
+            fingerprint: self.fingerprint()?,
+            encoded_len: self.encoded_size().uncompressed,
+        })
+    }
+}
+
+// Digests are exposed as lowercase hex strings
+#[cfg(feature = "serde")]
+mod hex {
+    use blister_format::values::decode_hex;
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S>(bytes: &[u8; 32], serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let hex: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+        serializer.serialize_str(&hex)
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<[u8; 32], D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let hex = String::deserialize(deserializer)?;
+        let mut bytes = [0; 32];
+        if !decode_hex(&hex, &mut bytes) {
+            return Err(D::Error::custom(format!("invalid digest `{}`", hex)));
+        }
+        Ok(bytes)
+    }
+
+    pub mod option {
+        use serde::{Deserialize, Deserializer, Serializer};
+
+        pub fn serialize<S>(bytes: &Option<[u8; 32]>, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+        {
+            match bytes {
+                Some(bytes) => super::serialize(bytes, serializer),
+                None => serializer.serialize_none(),
+            }
+        }
+
+        pub fn deserialize<'de, D>(deserializer: D) -> Result<Option<[u8; 32]>, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            #[derive(Deserialize)]
+            struct Hex(#[serde(with = "super")] [u8; 32]);
+
+            Ok(Option::<Hex>::deserialize(deserializer)?.map(|Hex(bytes)| bytes))
+        }
+    }
+}
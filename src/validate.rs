@@ -0,0 +1,312 @@
+use crate::{
+    error::Error, Beatmap, BeatmapType, ExtensionRegistry, Playlist, Result, Warning,
+    LONG_STRING_LEN, SHORT_STRING_LEN,
+};
+use blister_format::{Key, Map, Value};
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap},
+    hash::{Hash, Hasher},
+};
+
+const BINARY_LEN: usize = u32::MAX as usize;
+
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct ValidationReport {
+    pub problems: Vec<Problem>,
+    pub warnings: Vec<Warning>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Problem {
+    FieldTooLong {
+        map: Option<usize>,
+        field: &'static str,
+        len: usize,
+        max: usize,
+    },
+    CustomDataTooLong {
+        map: Option<usize>,
+        key: Key,
+        len: usize,
+        max: usize,
+    },
+    EmptyCover,
+    MissingIdentifier {
+        map: usize,
+        ty: BeatmapType,
+    },
+    DateBeforeEpoch {
+        map: usize,
+    },
+    // Only reported by strict validation, along with the problems below
+    ReservedKey {
+        map: Option<usize>,
+        key: Key,
+    },
+    UnknownType {
+        map: usize,
+    },
+    InvalidExtension {
+        map: usize,
+        message: String,
+    },
+}
+
+impl ValidationReport {
+    #[inline]
+    pub fn is_valid(&self) -> bool {
+        self.problems.is_empty()
+    }
+}
+
+impl Playlist {
+    pub fn validate(&self) -> ValidationReport {
+        let mut problems = Vec::new();
+
+        check_len(
+            &mut problems,
+            None,
+            "title",
+            self.title.len(),
+            SHORT_STRING_LEN,
+        );
+        check_len(
+            &mut problems,
+            None,
+            "author",
+            self.author.len(),
+            SHORT_STRING_LEN,
+        );
+        if let Some(description) = &self.description {
+            check_len(
+                &mut problems,
+                None,
+                "description",
+                description.len(),
+                LONG_STRING_LEN,
+            );
+        }
+        match &self.cover {
+            Some(cover) if cover.is_empty() => problems.push(Problem::EmptyCover),
+            Some(cover) => check_len(&mut problems, None, "cover", cover.len(), BINARY_LEN),
+            None => (),
+        }
+        for (language, title) in &self.localized_titles {
+            check_len(
+                &mut problems,
+                None,
+                "language tag",
+                language.len(),
+                SHORT_STRING_LEN,
+            );
+            check_len(
+                &mut problems,
+                None,
+                "localized title",
+                title.len(),
+                SHORT_STRING_LEN,
+            );
+        }
+        for (language, description) in &self.localized_descriptions {
+            check_len(
+                &mut problems,
+                None,
+                "language tag",
+                language.len(),
+                SHORT_STRING_LEN,
+            );
+            check_len(
+                &mut problems,
+                None,
+                "localized description",
+                description.len(),
+                LONG_STRING_LEN,
+            );
+        }
+        for tag in &self.tags {
+            check_len(&mut problems, None, "tag", tag.len(), SHORT_STRING_LEN);
+        }
+        check_custom_data(&mut problems, None, &self.custom_data);
+
+        let mut warnings = Vec::new();
+        self.collect_warnings(&mut warnings);
+
+        for (i, map) in self.maps.iter().enumerate() {
+            check_map(&mut problems, i, map);
+        }
+        warnings.extend(
+            find_duplicates(&self.maps)
+                .into_iter()
+                .map(|(map, first)| Warning::DuplicateMap { map, first }),
+        );
+
+        ValidationReport { problems, warnings }
+    }
+
+    // Also rejects reserved keys in custom data and maps of types not handled by an extension
+    pub fn validate_strict(&self, extensions: Option<&ExtensionRegistry>) -> ValidationReport {
+        let mut report = self.validate();
+        let problems = &mut report.problems;
+
+        for key in self.reserved_custom_keys() {
+            problems.push(Problem::ReservedKey { map: None, key });
+        }
+        for (i, map) in self.maps.iter().enumerate() {
+            for key in map.reserved_custom_keys() {
+                problems.push(Problem::ReservedKey { map: Some(i), key });
+            }
+            if map.ty != BeatmapType::Unknown {
+                continue;
+            }
+            match extensions.and_then(|e| e.extension_for(map)) {
+                Some(extension) => {
+                    if let Err(e) = extension.validate(map) {
+                        problems.push(Problem::InvalidExtension {
+                            map: i,
+                            message: e.to_string(),
+                        });
+                    }
+                }
+                None => problems.push(Problem::UnknownType { map: i }),
+            }
+        }
+        // Strict problems supersede their warnings
+        report.warnings.retain(|w| {
+            !matches!(
+                w,
+                Warning::ReservedKey { .. } | Warning::UnknownBeatmapType { .. }
+            )
+        });
+        report
+    }
+
+    pub fn check_reserved_keys(&self) -> Result<()> {
+        if let Some(key) = self.reserved_custom_keys().next() {
+            return Err(Error::ReservedKey { map: None, key });
+        }
+        for (i, map) in self.maps.iter().enumerate() {
+            if let Some(key) = map.reserved_custom_keys().next() {
+                return Err(Error::ReservedKey { map: Some(i), key });
+            }
+        }
+        Ok(())
+    }
+}
+
+fn check_map(problems: &mut Vec<Problem>, i: usize, map: &Beatmap) {
+    let missing = match map.ty {
+        BeatmapType::Key => map.key.is_none(),
+        BeatmapType::Hash => map.hash.is_none(),
+        BeatmapType::Zip => map.zip.is_none(),
+        BeatmapType::LevelId => map.level_id.is_none(),
+        BeatmapType::Unknown => false,
+    };
+    if missing {
+        problems.push(Problem::MissingIdentifier { map: i, ty: map.ty });
+    }
+
+    #[cfg(feature = "chrono")]
+    if map.date_added.timestamp() < 0 {
+        problems.push(Problem::DateBeforeEpoch { map: i });
+    }
+    if let Some(zip) = &map.zip {
+        check_len(problems, Some(i), "zip", zip.len(), BINARY_LEN);
+    }
+    if let Some(level_id) = &map.level_id {
+        check_len(
+            problems,
+            Some(i),
+            "level ID",
+            level_id.len(),
+            SHORT_STRING_LEN,
+        );
+    }
+    if let Some(note) = &map.note {
+        check_len(problems, Some(i), "note", note.len(), LONG_STRING_LEN);
+    }
+    check_custom_data(problems, Some(i), &map.custom_data);
+}
+
+fn check_custom_data(problems: &mut Vec<Problem>, map: Option<usize>, data: &Map) {
+    for (&key, value) in data.iter() {
+        let (len, max) = match value {
+            Value::ShortString(s) => (s.len(), SHORT_STRING_LEN),
+            Value::LongString(s) => (s.len(), LONG_STRING_LEN),
+            Value::Binary(b) => (b.len(), BINARY_LEN),
+            _ => continue,
+        };
+        if len > max {
+            problems.push(Problem::CustomDataTooLong { map, key, len, max });
+        }
+    }
+}
+
+fn check_len(
+    problems: &mut Vec<Problem>,
+    map: Option<usize>,
+    field: &'static str,
+    len: usize,
+    max: usize,
+) {
+    if len > max {
+        problems.push(Problem::FieldTooLong {
+            map,
+            field,
+            len,
+            max,
+        });
+    }
+}
+
+// Returns `(duplicate, first occurrence)` pairs in order
+pub(crate) fn find_duplicates(maps: &[Beatmap]) -> Vec<(usize, usize)> {
+    let mut finder = DuplicateFinder::default();
+    maps.iter()
+        .enumerate()
+        .filter_map(|(i, map)| finder.check(&maps[..i], map, i).map(|first| (i, first)))
+        .collect()
+}
+
+// Remembers maps by the identifier `Beatmap::same_map` compares, so duplicates can be caught while
+// the maps are still being read
+#[derive(Debug, Default)]
+pub(crate) struct DuplicateFinder {
+    // Identifier hashes to `(position in the kept maps, index the map was read at)`
+    seen: HashMap<u64, Vec<(usize, usize)>>,
+}
+
+impl DuplicateFinder {
+    // `maps` are the maps kept so far, which `map` is expected to be pushed onto if it isn't a
+    // duplicate. Returns the index its first occurrence was read at.
+    pub(crate) fn check(&mut self, maps: &[Beatmap], map: &Beatmap, i: usize) -> Option<usize> {
+        let candidates = self.seen.entry(identifier_hash(map)).or_default();
+        match candidates
+            .iter()
+            .find(|&&(kept, _)| maps[kept].same_map(map))
+        {
+            Some(&(_, first)) => Some(first),
+            None => {
+                candidates.push((maps.len(), i));
+                None
+            }
+        }
+    }
+}
+
+fn identifier_hash(map: &Beatmap) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    map.ty.hash(&mut hasher);
+    match map.ty {
+        BeatmapType::Key => map.key.hash(&mut hasher),
+        BeatmapType::Hash => map.hash.map(|h| h.0).hash(&mut hasher),
+        BeatmapType::Zip => map.zip.hash(&mut hasher),
+        BeatmapType::LevelId => map.level_id.hash(&mut hasher),
+        BeatmapType::Unknown => {
+            map.key.hash(&mut hasher);
+            map.hash.map(|h| h.0).hash(&mut hasher);
+            map.zip.hash(&mut hasher);
+            map.level_id.hash(&mut hasher);
+        }
+    }
+    hasher.finish()
+}
@@ -0,0 +1,103 @@
+use crate::{playlist::UNKNOWN_FIELDS_KEY, BeatmapType, Playlist};
+use blister_format::{Key, PLAYLIST_RESERVED_KEYS};
+#[cfg(feature = "chrono")]
+use chrono::Utc;
+
+pub const MAX_RECOMMENDED_COVER_LEN: usize = 1024 * 1024;
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Warning {
+    // `raw` holds the discarded bytes when `ReadOptions::capture_discarded` is set
+    InvalidUtf8 {
+        map: Option<usize>,
+        key: Key,
+        raw: Option<Vec<u8>>,
+    },
+    TruncatedString {
+        map: Option<usize>,
+        key: Key,
+        len: usize,
+    },
+    CorruptEntries {
+        map: Option<usize>,
+        offset: usize,
+        len: usize,
+        raw: Option<Vec<u8>>,
+    },
+    UnknownBeatmapType {
+        map: usize,
+    },
+    DuplicateMap {
+        map: usize,
+        first: usize,
+    },
+    OversizedCover {
+        len: usize,
+    },
+    ReservedKey {
+        map: Option<usize>,
+        key: Key,
+    },
+    FutureDate {
+        map: usize,
+    },
+    // Only produced by partial reads, counts the map cut off midway
+    Truncated {
+        maps_lost_estimate: usize,
+    },
+}
+
+impl Warning {
+    pub(crate) fn from_format(warning: blister_format::Warning, map: Option<usize>) -> Self {
+        match warning {
+            blister_format::Warning::InvalidUtf8 { key, raw } => {
+                Self::InvalidUtf8 { map, key, raw }
+            }
+            blister_format::Warning::TruncatedString { key, len } => {
+                Self::TruncatedString { map, key, len }
+            }
+            blister_format::Warning::CorruptEntries { offset, len, raw } => Self::CorruptEntries {
+                map,
+                offset,
+                len,
+                raw,
+            },
+        }
+    }
+}
+
+impl Playlist {
+    // Fields preserved from a bplist are the only data kept under a reserved key on purpose
+    pub(crate) fn reserved_custom_keys(&self) -> impl Iterator<Item = Key> + '_ {
+        self.custom_data
+            .reserved_keys(&PLAYLIST_RESERVED_KEYS)
+            .filter(|k| **k != UNKNOWN_FIELDS_KEY)
+    }
+
+    // Duplicates are left out since finding them isn't free
+    pub(crate) fn collect_warnings(&self, warnings: &mut Vec<Warning>) {
+        if let Some(cover) = &self.cover {
+            if cover.len() > MAX_RECOMMENDED_COVER_LEN {
+                warnings.push(Warning::OversizedCover { len: cover.len() });
+            }
+        }
+        for key in self.reserved_custom_keys() {
+            warnings.push(Warning::ReservedKey { map: None, key });
+        }
+
+        #[cfg(feature = "chrono")]
+        let now = Utc::now();
+        for (i, map) in self.maps.iter().enumerate() {
+            if map.ty == BeatmapType::Unknown {
+                warnings.push(Warning::UnknownBeatmapType { map: i });
+            }
+            #[cfg(feature = "chrono")]
+            if map.date_added > now {
+                warnings.push(Warning::FutureDate { map: i });
+            }
+            for key in map.reserved_custom_keys() {
+                warnings.push(Warning::ReservedKey { map: Some(i), key });
+            }
+        }
+    }
+}
@@ -0,0 +1,298 @@
+use crate::{
+    error::Error, file::write_atomic, BeatmapType, HashingWriter, HttpClient, HttpDownloader,
+    Playlist, Resolution, Resolver, Result,
+};
+use blister_format::values::{Binary, Sha1};
+#[cfg(not(feature = "zip"))]
+use std::io::Read;
+use std::{
+    fs::{self, File},
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+pub const BEATSAVER_CDN_URL: &str = "https://r2cdn.beatsaver.com";
+
+// Local file header and end of central directory, the latter starting empty archives
+#[cfg(not(feature = "zip"))]
+const ZIP_SIGNATURES: [[u8; 4]; 2] = [*b"PK\x03\x04", *b"PK\x05\x06"];
+
+// Map zips stored on disk under their map hash, shared between playlists and runs
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct ZipCache {
+    dir: PathBuf,
+}
+
+#[derive(Debug, Default)]
+pub struct FreezeReport {
+    pub frozen: Vec<usize>,
+    // Subset of `frozen` that wasn't cached yet
+    pub downloaded: Vec<usize>,
+    pub failed: Vec<(usize, Error)>,
+}
+
+impl FreezeReport {
+    #[inline]
+    pub fn is_complete(&self) -> bool {
+        self.failed.is_empty()
+    }
+}
+
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct ThinReport {
+    pub thinned: Vec<usize>,
+    // Embedded maps with neither a hash nor a key, even after resolving, which keep their zip
+    pub kept: Vec<usize>,
+    // Embedded maps whose zip doesn't match their hash, which keep their zip instead of having it
+    // cached under the wrong hash
+    pub mismatched: Vec<usize>,
+}
+
+impl ThinReport {
+    #[inline]
+    pub fn is_complete(&self) -> bool {
+        self.kept.is_empty() && self.mismatched.is_empty()
+    }
+}
+
+impl ZipCache {
+    pub fn new<P>(dir: P) -> Result<Self>
+    where
+        P: Into<PathBuf>,
+    {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+        Ok(Self { dir })
+    }
+
+    #[inline]
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    // Sharded on the first byte of the hash to keep directories small
+    pub fn path(&self, hash: &Sha1) -> PathBuf {
+        let hash = hash.to_string();
+        self.dir.join(&hash[..2]).join(hash + ".zip")
+    }
+
+    #[inline]
+    pub fn contains(&self, hash: &Sha1) -> bool {
+        self.path(hash).is_file()
+    }
+
+    pub fn get(&self, hash: &Sha1) -> Result<Option<Vec<u8>>> {
+        match fs::read(self.path(hash)) {
+            Ok(zip) => Ok(Some(zip)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub fn insert(&self, hash: &Sha1, zip: &[u8]) -> Result<PathBuf> {
+        let path = self.path(hash);
+        create_parent(&path)?;
+        write_atomic(&path, |w| w.write_all(zip).map_err(Into::into))?;
+        Ok(path)
+    }
+
+    // Streams the zip into the cache, only keeping it if it matches the hash. That's either the
+    // digest of the zip itself or, with the `zip` feature, the BeatSaver hash of the level it
+    // holds. Returns whether the zip was stored.
+    pub fn insert_verified(&self, hash: &Sha1, zip: &Binary) -> Result<bool> {
+        let path = self.path(hash);
+        create_parent(&path)?;
+        let result = write_atomic(&path, |w| {
+            let mut w = HashingWriter::new(w);
+            io::copy(&mut zip.reader()?, &mut w)?;
+            match w.digest() == *hash || is_level(zip, hash)? {
+                true => Ok(()),
+                false => Err(Error::ZipHashMismatch(*hash)),
+            }
+        });
+        match result {
+            Ok(()) => Ok(true),
+            Err(Error::ZipHashMismatch(_)) => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    // Returns whether there was anything to remove
+    pub fn remove(&self, hash: &Sha1) -> Result<bool> {
+        match fs::remove_file(self.path(hash)) {
+            Ok(()) => Ok(true),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(false),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    // Downloads the zip from the BeatSaver CDN unless it is already cached, resuming a previous
+    // partial download if there is one. Returns the SHA-1 digest of the zip file, hashed while it
+    // was downloaded, or `None` if it was already cached.
+    pub fn fetch<C>(&self, hash: &Sha1, downloader: &HttpDownloader<C>) -> Result<Option<Sha1>>
+    where
+        C: HttpClient,
+    {
+        let path = self.path(hash);
+        if path.is_file() {
+            return Ok(None);
+        }
+        create_parent(&path)?;
+        let url = format!("{}/{}.zip", BEATSAVER_CDN_URL, hash);
+        let (_, digest) =
+            downloader.download_verified(&url, &path, |part| match is_zip(part)? {
+                true => Ok(()),
+                false => Err(Error::CorruptDownload(url.clone())),
+            })?;
+        Ok(Some(digest))
+    }
+}
+
+impl Playlist {
+    // Embeds the zip of every hash and key map, fetching the ones missing from the cache. The zips
+    // are stored under their hash, so the resolver looks up the hash of maps only known by their
+    // key, and those it can't resolve fail with `MissingBeatmapHash`.
+    pub fn freeze<C, R>(
+        &mut self,
+        cache: &ZipCache,
+        downloader: &HttpDownloader<C>,
+        resolver: &R,
+    ) -> FreezeReport
+    where
+        C: HttpClient,
+        R: Resolver + ?Sized,
+    {
+        let mut report = FreezeReport::default();
+        for (i, map) in self.maps.iter_mut().enumerate() {
+            if !matches!(map.ty, BeatmapType::Key | BeatmapType::Hash) {
+                continue;
+            }
+            let hash = match map.hash {
+                Some(hash) => hash,
+                None => match resolver.resolve(map) {
+                    Resolution::Found(info) if info.hash.is_some() => {
+                        map.hash = info.hash;
+                        info.hash.unwrap()
+                    }
+                    _ => {
+                        report.failed.push((i, Error::MissingBeatmapHash));
+                        continue;
+                    }
+                },
+            };
+
+            let zip = cache.fetch(&hash, downloader).and_then(|digest| {
+                if digest.is_some() {
+                    report.downloaded.push(i);
+                }
+                Ok(fs::read(cache.path(&hash))?)
+            });
+            match zip {
+                Ok(zip) => {
+                    map.zip = Some(zip.into());
+                    map.ty = BeatmapType::Zip;
+                    report.frozen.push(i);
+                }
+                Err(e) => report.failed.push((i, e)),
+            }
+        }
+        report
+    }
+
+    // Turns embedded maps back into hash references, or key references for maps without a hash,
+    // which is what `freeze` undoes. Maps with neither are looked up with the resolver. Zips are
+    // stored in the cache first, if any, so freezing again doesn't download them, and maps keep
+    // their zip unless it made it into the cache.
+    pub fn thin<R>(&mut self, cache: Option<&ZipCache>, resolver: &R) -> Result<ThinReport>
+    where
+        R: Resolver + ?Sized,
+    {
+        let mut report = ThinReport::default();
+        for (i, map) in self.maps.iter_mut().enumerate() {
+            if map.ty != BeatmapType::Zip {
+                continue;
+            }
+            let (hash, key) = match (map.hash, map.key) {
+                (None, None) => match resolver.resolve(map) {
+                    Resolution::Found(info) => (info.hash, info.key),
+                    Resolution::NotFound | Resolution::Unknown => (None, None),
+                },
+                identifiers => identifiers,
+            };
+            let ty = match (hash, key) {
+                (Some(_), _) => BeatmapType::Hash,
+                (None, Some(_)) => BeatmapType::Key,
+                (None, None) => {
+                    report.kept.push(i);
+                    continue;
+                }
+            };
+
+            if let (Some(cache), Some(hash), Some(zip)) = (cache, &hash, &map.zip) {
+                if !cache.contains(hash) && !cache.insert_verified(hash, zip)? {
+                    report.mismatched.push(i);
+                    continue;
+                }
+            }
+            map.ty = ty;
+            map.hash = hash;
+            map.key = key;
+            map.zip = None;
+            report.thinned.push(i);
+        }
+        Ok(report)
+    }
+
+    // Stores the embedded zips of maps with a known hash, so later freezes don't download them
+    // again. Zips that don't match their hash are left out. Returns the number of zips added to
+    // the cache.
+    pub fn cache_zips(&self, cache: &ZipCache) -> Result<usize> {
+        let mut cached = 0;
+        for map in &self.maps {
+            if let (Some(hash), Some(zip)) = (&map.hash, &map.zip) {
+                if !cache.contains(hash) && cache.insert_verified(hash, zip)? {
+                    cached += 1;
+                }
+            }
+        }
+        Ok(cached)
+    }
+}
+
+fn create_parent(path: &Path) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    Ok(())
+}
+
+// Every entry is decompressed and checked against its CRC
+#[cfg(feature = "zip")]
+fn is_zip(path: &Path) -> io::Result<bool> {
+    let file = io::BufReader::new(File::open(path)?);
+    Ok(crate::extract::verify(file).is_ok())
+}
+
+// Only the signature of the first record can be checked without the zip crate
+#[cfg(not(feature = "zip"))]
+fn is_zip(path: &Path) -> io::Result<bool> {
+    let mut signature = [0; 4];
+    match File::open(path)?.read_exact(&mut signature) {
+        Ok(()) => Ok(ZIP_SIGNATURES.contains(&signature)),
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
+#[cfg(feature = "zip")]
+#[inline]
+fn is_level(zip: &Binary, hash: &Sha1) -> Result<bool> {
+    let zip = crate::ZipReader::new(zip)?;
+    Ok(crate::extract::level_hash(zip).as_ref() == Some(hash))
+}
+
+#[cfg(not(feature = "zip"))]
+#[inline]
+fn is_level(_zip: &Binary, _hash: &Sha1) -> Result<bool> {
+    Ok(false)
+}
@@ -0,0 +1,50 @@
+use crate::Beatmap;
+use blister_format::values::Binary;
+use std::{
+    fs::File,
+    io::{self, Cursor, Read, Seek, SeekFrom},
+};
+
+// Implements `Seek` so it can be handed to zip readers directly
+#[derive(Debug)]
+pub enum ZipReader<'a> {
+    Memory(Cursor<&'a [u8]>),
+    Spilled(File),
+}
+
+impl Beatmap {
+    #[inline]
+    pub fn zip_reader(&self) -> io::Result<Option<ZipReader<'_>>> {
+        self.zip.as_ref().map(ZipReader::new).transpose()
+    }
+}
+
+impl<'a> ZipReader<'a> {
+    // Zips spilled while reading are opened from their file
+    pub fn new(zip: &'a Binary) -> io::Result<Self> {
+        Ok(match zip.spilled() {
+            Some(spilled) => Self::Spilled(spilled.open()?),
+            None => Self::Memory(Cursor::new(zip.as_bytes().unwrap_or_default())),
+        })
+    }
+}
+
+impl Read for ZipReader<'_> {
+    #[inline]
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Self::Memory(c) => c.read(buf),
+            Self::Spilled(f) => f.read(buf),
+        }
+    }
+}
+
+impl Seek for ZipReader<'_> {
+    #[inline]
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        match self {
+            Self::Memory(c) => c.seek(pos),
+            Self::Spilled(f) => f.seek(pos),
+        }
+    }
+}
@@ -0,0 +1,181 @@
+use std::convert::TryInto;
+use wasm_bindgen::prelude::*;
+
+#[wasm_bindgen]
+#[derive(Debug, Clone)]
+pub struct Playlist(blister::Playlist);
+
+#[wasm_bindgen]
+#[derive(Debug, Clone)]
+pub struct Beatmap(blister::Beatmap);
+
+#[wasm_bindgen]
+impl Playlist {
+    #[wasm_bindgen(constructor)]
+    pub fn new(title: String, author: String) -> Self {
+        Self(blister::Playlist::new(title, author))
+    }
+
+    #[cfg(feature = "gzip")]
+    pub fn read(data: &[u8], strict: bool) -> Result<Playlist, JsError> {
+        Ok(Self(blister::Playlist::read(data, strict)?))
+    }
+
+    #[cfg(feature = "gzip")]
+    pub fn write(&self) -> Result<Vec<u8>, JsError> {
+        let mut buffer = Vec::new();
+        self.0.write(&mut buffer)?;
+        Ok(buffer)
+    }
+
+    // The magic number followed by a body decompressed by the caller
+    #[wasm_bindgen(js_name = readUncompressed)]
+    pub fn read_uncompressed(data: &[u8], strict: bool) -> Result<Playlist, JsError> {
+        let options = blister::ReadOptions {
+            strict,
+            ..Default::default()
+        };
+        let (playlist, _) = blister::Playlist::read_uncompressed(data, &options)?;
+        Ok(Self(playlist))
+    }
+
+    #[wasm_bindgen(js_name = writeUncompressed)]
+    pub fn write_uncompressed(&self) -> Result<Vec<u8>, JsError> {
+        let mut buffer = Vec::new();
+        self.0
+            .write_uncompressed(&mut buffer, &Default::default())?;
+        Ok(buffer)
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn title(&self) -> String {
+        self.0.title.clone()
+    }
+
+    #[wasm_bindgen(setter)]
+    pub fn set_title(&mut self, title: String) {
+        self.0.title = title;
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn author(&self) -> String {
+        self.0.author.clone()
+    }
+
+    #[wasm_bindgen(setter)]
+    pub fn set_author(&mut self, author: String) {
+        self.0.author = author;
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn description(&self) -> Option<String> {
+        self.0.description.clone()
+    }
+
+    #[wasm_bindgen(setter)]
+    pub fn set_description(&mut self, description: Option<String>) {
+        self.0.description = description;
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn cover(&self) -> Option<Vec<u8>> {
+        self.0.cover.as_deref().map(ToOwned::to_owned)
+    }
+
+    #[wasm_bindgen(setter)]
+    pub fn set_cover(&mut self, cover: Option<Vec<u8>>) {
+        self.0.cover = cover.map(Into::into);
+    }
+
+    #[wasm_bindgen(getter, js_name = mapCount)]
+    pub fn map_count(&self) -> usize {
+        self.0.maps.len()
+    }
+
+    pub fn map(&self, index: usize) -> Option<Beatmap> {
+        self.0.maps.get(index).cloned().map(Beatmap)
+    }
+
+    #[wasm_bindgen(js_name = addKey)]
+    pub fn add_key(&mut self, key: u32) {
+        self.0.maps.push(blister::Beatmap::new_key(key));
+    }
+
+    #[wasm_bindgen(js_name = addHash)]
+    pub fn add_hash(&mut self, hash: &[u8]) -> Result<(), JsError> {
+        let hash: [u8; 20] = hash
+            .try_into()
+            .map_err(|_| JsError::new("SHA1 hashes must be 20 bytes long"))?;
+        self.0.maps.push(blister::Beatmap::new_hash(hash.into()));
+        Ok(())
+    }
+
+    #[wasm_bindgen(js_name = addZip)]
+    pub fn add_zip(&mut self, zip: Vec<u8>) {
+        self.0.maps.push(blister::Beatmap::new_zip(zip));
+    }
+
+    #[wasm_bindgen(js_name = addLevelId)]
+    pub fn add_level_id(&mut self, level_id: String) {
+        self.0.maps.push(blister::Beatmap::new_level_id(level_id));
+    }
+
+    #[wasm_bindgen(js_name = removeMap)]
+    pub fn remove_map(&mut self, index: usize) -> Option<Beatmap> {
+        if index < self.0.maps.len() {
+            Some(Beatmap(self.0.maps.remove(index)))
+        } else {
+            None
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl Beatmap {
+    #[wasm_bindgen(getter, js_name = type)]
+    pub fn ty(&self) -> u8 {
+        self.0.ty.into()
+    }
+
+    // Milliseconds since the Unix epoch, as expected by `Date`
+    #[wasm_bindgen(getter, js_name = dateAdded)]
+    pub fn date_added(&self) -> f64 {
+        #[cfg(feature = "chrono")]
+        return self.0.date_added.timestamp_millis() as f64;
+        #[cfg(not(feature = "chrono"))]
+        return self
+            .0
+            .date_added_secs()
+            .map_or(f64::NAN, |s| (s * 1000) as f64);
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn key(&self) -> Option<u32> {
+        self.0.key
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn hash(&self) -> Option<Vec<u8>> {
+        self.0.hash.map(|h| h.to_vec())
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn zip(&self) -> Option<Vec<u8>> {
+        // There's no filesystem to spill to, so zips are always in memory
+        self.0
+            .zip
+            .as_ref()
+            .and_then(|z| z.as_bytes())
+            .map(<[u8]>::to_vec)
+    }
+
+    #[wasm_bindgen(getter, js_name = levelId)]
+    pub fn level_id(&self) -> Option<String> {
+        self.0.level_id.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn note(&self) -> Option<String> {
+        self.0.note.clone()
+    }
+}